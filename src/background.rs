@@ -0,0 +1,197 @@
+//! Backgrounds that move to the music, drawn behind everything and never in the way.
+
+use std::{fmt::Display, str::FromStr, f32::consts::TAU};
+
+use macroquad::{prelude::*, rand::gen_range};
+
+use crate::utils::{acmul, screen_center, screen_width, screen_height};
+
+/// Most particles a drift field has
+pub const MAX_DRIFT_PARTICLES: usize = 96;
+/// How quickly the intensity follows its target, per beat
+const INTENSITY_RATE: f32 = 4.0;
+
+fn wrap(pos: Vec2, size: Vec2) -> Vec2 {
+    vec2(pos.x.rem_euclid(size.x), pos.y.rem_euclid(size.y))
+}
+
+/// Where the music is, from the same clock obstacles use.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BeatClock {
+    /// Beats into the song
+    pub beat: f32,
+    /// How far into the beat, from 0 to 1
+    pub beat_phase: f32,
+    /// How far into the bar, from 0 to 1. Bars restart at tempo changes.
+    pub bar_phase: f32,
+}
+impl BeatClock {
+    pub fn new(beat: f32, bar_phase: f32) -> Self {
+        BeatClock { beat, beat_phase: beat.rem_euclid(1.0), bar_phase }
+    }
+}
+
+/// A background visual. Draws before the obstacles and doesn't collide.
+pub trait Background {
+    /// `dbeat` is the beats since the last update, `clock` where the music is now.
+    fn update(&mut self, clock: BeatClock, dbeat: f32);
+    /// `intensity` 1 is the usual, charts raise it for drops.
+    fn draw(&self, color: Color, intensity: f32);
+}
+
+/// A radial gradient from the middle of the screen, brightening on each downbeat.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RadialPulse {
+    flash: f32,
+    bar_phase: f32,
+}
+impl Background for RadialPulse {
+    fn update(&mut self, clock: BeatClock, dbeat: f32) {
+        // the bar phase wrapping around is a downbeat
+        if clock.bar_phase < self.bar_phase { self.flash = 1.0; }
+        self.bar_phase = clock.bar_phase;
+        self.flash *= (-dbeat * 3.0).exp();
+    }
+    fn draw(&self, color: Color, intensity: f32) {
+        let center = screen_center();
+        let max_rad = center.length();
+        let rings = 12;
+        let brightness = (0.15 + 0.35 * self.flash) * intensity;
+        for i in 0..rings {
+            // outermost first, each smaller circle adding to the middle
+            let t = 1.0 - i as f32 / rings as f32;
+            draw_circle(center.x, center.y, max_rad * t * (1.0 + 0.1 * self.flash), acmul(color, brightness / rings as f32 * 2.0));
+        }
+    }
+}
+
+/// A slowly scrolling grid, its lines flashing on each beat.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ScrollGrid {
+    scroll: Vec2,
+    beat_phase: f32,
+}
+impl ScrollGrid {
+    pub const SPACING: f32 = 80.0;
+    /// Pixels per beat
+    pub const SPEED: Vec2 = Vec2::new(6.0, 4.0);
+}
+impl Background for ScrollGrid {
+    fn update(&mut self, clock: BeatClock, dbeat: f32) {
+        self.scroll = wrap(self.scroll + Self::SPEED * dbeat, Vec2::splat(Self::SPACING));
+        self.beat_phase = clock.beat_phase;
+    }
+    fn draw(&self, color: Color, intensity: f32) {
+        let glow = (1.0 - self.beat_phase).powi(2);
+        let color = acmul(color, ((0.08 + 0.25 * glow) * intensity).min(1.0));
+        let (w, h) = (screen_width(), screen_height());
+        let mut x = self.scroll.x - Self::SPACING;
+        while x < w {
+            draw_line(x, 0.0, x, h, 2.0, color);
+            x += Self::SPACING;
+        }
+        let mut y = self.scroll.y - Self::SPACING;
+        while y < h {
+            draw_line(0.0, y, w, y, 2.0, color);
+            y += Self::SPACING;
+        }
+    }
+}
+
+/// Specks drifting across the screen, pushed along on each beat.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriftField {
+    /// (position, velocity in pixels per beat)
+    particles: Vec<(Vec2, Vec2)>,
+    beat_phase: f32,
+}
+impl Default for DriftField {
+    fn default() -> Self { Self::new(64) }
+}
+impl DriftField {
+    /// Up to `MAX_DRIFT_PARTICLES`, made once so nothing is allocated while playing.
+    pub fn new(count: usize) -> Self {
+        let particles = (0..count.min(MAX_DRIFT_PARTICLES)).map(|_| {
+            let pos = vec2(gen_range(0.0, screen_width()), gen_range(0.0, screen_height()));
+            let angle = gen_range(0.0, TAU);
+            (pos, vec2(angle.cos(), angle.sin()) * gen_range(5.0, 20.0))
+        }).collect();
+        DriftField { particles, beat_phase: 0.0 }
+    }
+}
+impl Background for DriftField {
+    fn update(&mut self, clock: BeatClock, dbeat: f32) {
+        // a push at the start of each beat, easing off until the next
+        let push = 1.0 + 3.0 * (1.0 - clock.beat_phase).powi(3);
+        let size = vec2(screen_width(), screen_height());
+        for (pos, vel) in &mut self.particles {
+            *pos = wrap(*pos + *vel * dbeat * push, size);
+        }
+        self.beat_phase = clock.beat_phase;
+    }
+    fn draw(&self, color: Color, intensity: f32) {
+        let alpha = ((0.2 + 0.3 * (1.0 - self.beat_phase).powi(2)) * intensity).min(1.0);
+        for (pos, _) in &self.particles {
+            draw_circle(pos.x, pos.y, 3.0, acmul(color, alpha));
+        }
+    }
+}
+
+/// The backgrounds charts can name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundKind {
+    Pulse,
+    Grid,
+    Drift,
+}
+impl BackgroundKind {
+    pub fn create(self) -> Box<dyn Background> {
+        match self {
+            BackgroundKind::Pulse => Box::new(RadialPulse::default()),
+            BackgroundKind::Grid => Box::new(ScrollGrid::default()),
+            BackgroundKind::Drift => Box::new(DriftField::default()),
+        }
+    }
+}
+impl Display for BackgroundKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            BackgroundKind::Pulse => "pulse",
+            BackgroundKind::Grid => "grid",
+            BackgroundKind::Drift => "drift",
+        })
+    }
+}
+impl FromStr for BackgroundKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pulse" => Ok(BackgroundKind::Pulse),
+            "grid" => Ok(BackgroundKind::Grid),
+            "drift" => Ok(BackgroundKind::Drift),
+            _ => Err(format!("unknown background `{s}`, expected `pulse`, `grid` or `drift`")),
+        }
+    }
+}
+
+/// The background of a level, with its color and intensity.
+pub struct BackgroundLayer {
+    pub visual: Box<dyn Background>,
+    /// `None` uses a dim foreground color
+    pub color: Option<Color>,
+    pub intensity: f32,
+    /// The intensity eases towards this
+    pub target_intensity: f32,
+}
+impl BackgroundLayer {
+    pub fn new(visual: Box<dyn Background>, color: Option<Color>) -> Self {
+        BackgroundLayer { visual, color, intensity: 1.0, target_intensity: 1.0 }
+    }
+    pub fn update(&mut self, clock: BeatClock, dbeat: f32) {
+        self.intensity += (self.target_intensity - self.intensity) * (1.0 - (-dbeat * INTENSITY_RATE).exp());
+        self.visual.update(clock, dbeat);
+    }
+    pub fn draw(&self, fg: Color) {
+        self.visual.draw(self.color.unwrap_or(fg), self.intensity);
+    }
+}
@@ -0,0 +1,165 @@
+use std::rc::Rc;
+
+use crate::{game::UpdateAccumulator, timeline::EventCategory};
+
+/// Rounds `beats` to the nearest 1/`division` of a beat.
+pub fn snap(beats: f32, division: f32) -> f32 {
+    (beats * division).round() / division
+}
+
+/// The first downbeat at or after `time`.
+pub fn next_downbeat(time: f32, beats_per_bar: f32) -> f32 {
+    (time / beats_per_bar).ceil() * beats_per_bar
+}
+
+pub type ScheduledFn = Box<dyn FnOnce(&mut UpdateAccumulator)>;
+
+/// A sequence of one-off calls on beats, for levels written in code.\
+/// Built up with the chaining methods, then `run` each frame. Entries already due when added fire on the next `run`.
+pub struct Schedule {
+    /// Sorted by beat, ties in the order they were added
+    entries: Vec<(f32, ScheduledFn)>,
+    /// The beat bar 1 starts on
+    origin: f32,
+    beats_per_bar: f32,
+    /// Where `every` starts
+    cursor: f32,
+}
+impl Default for Schedule {
+    fn default() -> Self { Self::new(0.0, 4.0) }
+}
+impl Schedule {
+    pub fn new(origin: f32, beats_per_bar: f32) -> Self {
+        Schedule { entries: vec![], origin, beats_per_bar, cursor: origin }
+    }
+    /// Adds a call at `beat`, keeping calls on the same beat in order.
+    pub fn add(&mut self, beat: f32, f: impl FnOnce(&mut UpdateAccumulator) + 'static) {
+        let idx = self.entries.partition_point(|e| e.0 <= beat);
+        self.entries.insert(idx, (beat, Box::new(f)));
+    }
+    pub fn at(mut self, beat: f32, f: impl FnOnce(&mut UpdateAccumulator) + 'static) -> Self {
+        self.add(beat, f);
+        self
+    }
+    /// Moves the cursor `every` starts from.
+    pub fn starting_at(mut self, beat: f32) -> Self {
+        self.cursor = beat;
+        self
+    }
+    /// Moves the cursor `beats` further.
+    pub fn rest(mut self, beats: f32) -> Self {
+        self.cursor += beats;
+        self
+    }
+    /// Calls `f` with the repetition `count` times, `interval` beats apart from the cursor, then moves the cursor past them.
+    pub fn every(mut self, interval: f32, count: usize, f: impl Fn(usize, &mut UpdateAccumulator) + 'static) -> Self {
+        let f = Rc::new(f);
+        for i in 0..count {
+            let f = f.clone();
+            self.add(self.cursor + i as f32 * interval, move |ac| f(i, ac));
+        }
+        self.cursor += count as f32 * interval;
+        self
+    }
+    /// Calls `f` with the index at each of `bars`, counted from 1 like sheet music. `2.5` is halfway through bar 2.
+    pub fn at_bars(mut self, bars: &[f32], f: impl Fn(usize, &mut UpdateAccumulator) + 'static) -> Self {
+        let f = Rc::new(f);
+        for (i, &bar) in bars.iter().enumerate() {
+            let f = f.clone();
+            self.add(self.origin + (bar - 1.0) * self.beats_per_bar, move |ac| f(i, ac));
+        }
+        self
+    }
+    /// Fires and removes every entry due at `beat`, in order, with the accumulator's time set to the entry's beat.
+    pub fn run(&mut self, beat: f32, accum: &mut UpdateAccumulator) {
+        let due = self.entries.partition_point(|e| e.0 <= beat);
+        for (time, f) in self.entries.drain(..due) {
+            accum.set_time(time);
+            f(accum);
+        }
+    }
+    /// (beat, category) of the calls still to come. What a call does can't be looked into, so they're all `Other`.
+    pub fn timeline(&self) -> impl Iterator<Item = (f32, EventCategory)> + '_ {
+        self.entries.iter().map(|e| (e.0, EventCategory::Other))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    /// (name, beat) of each call, in the order they fired.
+    type Log = Rc<RefCell<Vec<(&'static str, f32)>>>;
+
+    fn record(log: &Log, name: &'static str) -> impl Fn(usize, &mut UpdateAccumulator) + 'static {
+        let log = log.clone();
+        move |_, ac| log.borrow_mut().push((name, ac.time()))
+    }
+
+    #[test]
+    fn snaps_to_the_grid() {
+        assert_eq!(snap(1.13, 4.0), 1.25);
+        assert_eq!(snap(1.1, 4.0), 1.0);
+        assert_eq!(snap(-0.3, 2.0), -0.5);
+        assert_eq!(next_downbeat(5.0, 4.0), 8.0);
+        assert_eq!(next_downbeat(8.0, 4.0), 8.0);
+        assert_eq!(next_downbeat(0.1, 3.0), 3.0);
+    }
+
+    #[test]
+    fn fires_in_order() {
+        let log = Log::default();
+        let mut accum = UpdateAccumulator::new();
+        let once = record(&log, "at");
+        let mut schedule = Schedule::new(0.0, 4.0)
+            .at(4.0, move |ac| once(0, ac))
+            .every(1.0, 3, record(&log, "every"))
+            .at_bars(&[1.0, 2.5], record(&log, "bar"));
+        assert_eq!(schedule.len(), 6);
+        schedule.run(0.5, &mut accum);
+        // ties go in the order they were added
+        assert_eq!(*log.borrow(), [("every", 0.0), ("bar", 0.0)]);
+        schedule.run(2.0, &mut accum);
+        schedule.run(2.0, &mut accum);
+        assert_eq!(log.borrow()[2..], [("every", 1.0), ("every", 2.0)]);
+        // a long frame fires everything it skipped over, each at its own beat
+        schedule.run(10.0, &mut accum);
+        assert_eq!(log.borrow()[4..], [("at", 4.0), ("bar", 6.0)]);
+        assert!(schedule.is_empty());
+    }
+
+    #[test]
+    fn every_moves_the_cursor() {
+        let log = Log::default();
+        let mut accum = UpdateAccumulator::new();
+        let mut schedule = Schedule::default()
+            .starting_at(8.0)
+            .every(0.5, 2, record(&log, "first"))
+            .rest(1.0)
+            .every(0.5, 2, record(&log, "second"));
+        schedule.run(100.0, &mut accum);
+        assert_eq!(*log.borrow(), [("first", 8.0), ("first", 8.5), ("second", 10.0), ("second", 10.5)]);
+    }
+
+    #[test]
+    fn past_entries_fire_once() {
+        let log = Log::default();
+        let mut accum = UpdateAccumulator::new();
+        let mut schedule = Schedule::default();
+        schedule.run(16.0, &mut accum);
+        // added after its beat went by, e.g. on a hot-reload
+        let late = record(&log, "late");
+        schedule.add(4.0, move |ac| late(0, ac));
+        schedule.run(16.5, &mut accum);
+        schedule.run(17.0, &mut accum);
+        assert_eq!(*log.borrow(), [("late", 4.0)]);
+    }
+}
@@ -0,0 +1,85 @@
+use std::f32::consts::TAU;
+
+use macroquad::{prelude::*, time::get_time};
+use soloud::{Sfxr, SfxrPreset, AudioExt};
+
+use crate::{sound::SfxCreator, utils::{centered_text_draw, acmul, screen_width, screen_height}, palette::Palette};
+
+pub const CALIBRATION_BPM: f32 = 100.0;
+/// Beats the player taps along to
+pub const CALIBRATION_BEATS: usize = 16;
+/// Clicks before taps start counting, to pick up the rhythm
+const LEAD_IN_BEATS: usize = 4;
+/// Taps further than this from the median are discarded
+const OUTLIER_MS: f32 = 80.0;
+/// Fewest taps left after discarding outliers for the result to count
+const MIN_TAPS: usize = 8;
+
+/// A metronome the player taps along to, measuring how late they hear and see the beat.
+pub struct Calibration {
+    click: Sfxr,
+    start: f64,
+    /// When each click actually played, which is up to a frame after it was due
+    clicks: Vec<f64>,
+    taps: Vec<f64>,
+}
+impl Calibration {
+    pub fn new() -> Self {
+        let mut click = Sfxr::default();
+        if let Err(e) = click.load_preset(SfxrPreset::Blip, 0) { println!("couldn't make the metronome click: {e}"); }
+        Calibration { click, start: get_time() + Self::period(), clicks: vec![], taps: vec![] }
+    }
+    fn period() -> f64 { 60.0 / CALIBRATION_BPM as f64 }
+    fn total_beats() -> usize { LEAD_IN_BEATS + CALIBRATION_BEATS }
+    /// Plays the clicks that are due and records a tap if `tapped`.\
+    /// Once the last beat has passed, returns the offset in milliseconds or why there isn't one.
+    pub fn update(&mut self, sfx: &SfxCreator, tapped: bool) -> Option<Result<f32, String>> {
+        let now = get_time();
+        if tapped { self.taps.push(now); }
+        if self.clicks.len() < Self::total_beats() && now >= self.start + self.clicks.len() as f64 * Self::period() {
+            sfx.spawn_sfx(&self.click);
+            self.clicks.push(now);
+        }
+        let done = self.clicks.len() == Self::total_beats() && now > self.clicks[self.clicks.len() - 1] + Self::period() / 2.0;
+        done.then(|| tap_offset_ms(&self.clicks[LEAD_IN_BEATS..], &self.taps))
+    }
+    pub fn draw(&self, palette: &Palette) {
+        clear_background(palette.background);
+        let center = vec2(screen_width(), screen_height()) / 2.0;
+        let since_click = self.clicks.last().map_or(f32::INFINITY, |&c| (get_time() - c) as f32);
+        let pulse = (-since_click * 8.0).exp();
+        let counted = self.clicks.len().saturating_sub(LEAD_IN_BEATS);
+        let color = if counted == 0 { acmul(palette.text, 0.5) } else { palette.text };
+        draw_circle(center.x, center.y, 60.0 + 40.0 * pulse, acmul(color, 0.2 + 0.8 * pulse));
+        for i in 0..CALIBRATION_BEATS {
+            let angle = i as f32 / CALIBRATION_BEATS as f32 * TAU;
+            let pos = center + 160.0 * vec2(angle.sin(), -angle.cos());
+            draw_circle(pos.x, pos.y, 6.0, if i < counted { palette.text } else { acmul(palette.text, 0.2) });
+        }
+        let text = if counted == 0 { "Listen..." } else { "Tap Dash on the beat" };
+        centered_text_draw(text, center - vec2(0.0, 240.0), 40.0, palette.text);
+        centered_text_draw("Pause to cancel", center + vec2(0.0, 240.0), 24.0, acmul(palette.text, 0.5));
+    }
+}
+
+/// The median time from each click to the tap nearest it, after discarding taps far from the median.
+fn tap_offset_ms(clicks: &[f64], taps: &[f64]) -> Result<f32, String> {
+    let half = Calibration::period() / 2.0;
+    let mut offsets = taps.iter().filter_map(|&tap| {
+        let nearest = clicks.iter().min_by(|a, b| (*a - tap).abs().total_cmp(&(*b - tap).abs()))?;
+        ((tap - nearest).abs() <= half).then(|| ((tap - nearest) * 1000.0) as f32)
+    }).collect::<Vec<_>>();
+    let Some(middle) = median(&mut offsets) else { return Err("no taps landed near a beat".to_string()) };
+    let mut kept = offsets.into_iter().filter(|o| (o - middle).abs() <= OUTLIER_MS).collect::<Vec<_>>();
+    if kept.len() < MIN_TAPS {
+        return Err(format!("only {} taps were on the beat, at least {MIN_TAPS} are needed", kept.len()));
+    }
+    Ok(median(&mut kept).unwrap_or(middle))
+}
+
+fn median(values: &mut [f32]) -> Option<f32> {
+    if values.is_empty() { return None; }
+    values.sort_by(f32::total_cmp);
+    let mid = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) { (values[mid - 1] + values[mid]) / 2.0 } else { values[mid] })
+}
@@ -0,0 +1,327 @@
+//! What the level is seen through. Shake and jerk offset every `draw`, zoom and rotation turn the whole world around the
+//! center of the screen through macroquad's camera.\
+//! Only drawing goes through the camera, collisions stay in world space.
+
+use macroquad::prelude::*;
+use perlin2d::PerlinNoise2D;
+
+use crate::utils::{virtual_camera, screen_width, screen_height, sq};
+
+/// Most the zoom pulses can add up to
+pub const MAX_ZOOM_PULSE: f32 = 0.5;
+/// Pixels the shake reaches at full trauma. A `shake` of this much is full trauma on its own
+pub const MAX_SHAKE_OFFSET: f32 = 30.0;
+/// Radians the shake turns the view at full trauma, small enough to feel rather than see
+pub const MAX_SHAKE_ROTATION: f32 = 0.03;
+/// Trauma lost per beat, so full trauma settles in a beat
+pub const TRAUMA_DECAY: f32 = 1.0;
+/// How many times a beat the shake's noise changes direction, roughly
+const SHAKE_NOISE_FREQ: f32 = 10.0;
+/// How much of the sideways shake a fully directional shake takes away
+const DIRECTIONAL_SQUASH: f32 = 0.8;
+/// Pull of the jerk's spring back to the middle, per beat squared. About two swings a beat
+pub const JERK_STIFFNESS: f32 = 160.0;
+/// Drag on the jerk's spring per beat, enough to stop it overshooting more than a couple of times
+pub const JERK_DAMPING: f32 = 7.5;
+/// Pixels the jerk can move the view at most, however many land at once
+pub const MAX_JERK: f32 = 120.0;
+/// Beats the jerk's spring is stepped by at most at a time. Well under where it'd blow up, and shorter than frames
+/// up to ~1000 FPS at 120 BPM, so frames of any length step it the same way
+const JERK_STEP: f32 = 1.0 / 480.0;
+/// Frames longer than this many beats leave the jerk settled, there'd be nothing of it left anyway
+const JERK_SETTLE_BEATS: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    /// Offset from knockback-like jolts, springing back to nothing past the middle and back
+    jerk: Vec2,
+    jerk_vel: Vec2,
+    /// Pull of the jerk's spring, per beat squared
+    pub jerk_stiffness: f32,
+    /// Drag on the jerk's spring, per beat
+    pub jerk_damping: f32,
+    /// Pixels the jerk goes to at most
+    pub max_jerk: f32,
+    /// How shaken the view is from 0 to 1. The shake goes with its square, so small bumps barely move it and big ones stand out
+    trauma: f32,
+    /// Direction a directional shake leans in, as long as the trauma that came with it
+    lean: Vec2,
+    /// Trauma lost per beat
+    pub trauma_decay: f32,
+    /// Pixels the shake reaches at full trauma
+    pub max_offset: f32,
+    /// Radians the shake turns at full trauma
+    pub max_rotation: f32,
+    /// Pixels of slow floating motion
+    pub float: f32,
+    /// Extra zoom at the start of the latest pulse
+    pulse: f32,
+    pulse_start: f32,
+    /// Beats the pulse fades over
+    pulse_decay: f32,
+    /// Radians, clockwise on screen
+    pub rotation: f32,
+    rotation_target: f32,
+    /// Radians per beat towards the target
+    rotation_rate: f32,
+}
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            jerk: Vec2::ZERO, jerk_vel: Vec2::ZERO, jerk_stiffness: JERK_STIFFNESS, jerk_damping: JERK_DAMPING, max_jerk: MAX_JERK,
+            trauma: 0.0, lean: Vec2::ZERO,
+            trauma_decay: TRAUMA_DECAY, max_offset: MAX_SHAKE_OFFSET, max_rotation: MAX_SHAKE_ROTATION,
+            float: 0.0, pulse: 0.0, pulse_start: 0.0, pulse_decay: 0.0,
+            rotation: 0.0, rotation_target: 0.0, rotation_rate: 0.0,
+        }
+    }
+}
+/// Smooth noise from -1 to 1 at `time`, a different track for each `axis`.
+fn shake_noise(time: f32, axis: f64) -> f32 {
+    // Perlin construction does zero extra logic; inexpensive
+    let perlin = PerlinNoise2D::new(2, 1.0, 1.0, 0.5, 2.0, (1.0, 1.0), 0.0, 0);
+    // off the lattice, where the noise is always 0
+    (perlin.get_noise((time * SHAKE_NOISE_FREQ) as f64, axis * 3.7 + 0.5) as f32 * 2.0).clamp(-1.0, 1.0)
+}
+impl Camera {
+    /// Adds `amount` pixels' worth of shake to the trauma, one `max_offset` being all of it.
+    pub fn shake(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount / self.max_offset).clamp(0.0, 1.0);
+        self.lean = self.lean.clamp_length_max(self.trauma);
+    }
+    /// Shakes by the length of `amount`, mostly along it, like `shake`.
+    pub fn shake_directional(&mut self, amount: Vec2) {
+        if amount == Vec2::ZERO { return; }
+        self.shake(amount.length());
+        self.lean = (self.lean + amount / self.max_offset).clamp_length_max(self.trauma);
+    }
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+    pub fn clear_shake(&mut self) {
+        self.trauma = 0.0;
+        self.lean = Vec2::ZERO;
+    }
+    /// Takes a frame of `beat_dt` beats off the trauma.
+    pub fn decay_shake(&mut self, beat_dt: f32) {
+        let trauma = (self.trauma - self.trauma_decay * beat_dt.max(0.0)).max(0.0);
+        // the lean fades with the trauma, so the shake stays as directional as it was
+        self.lean = if self.trauma > 0.0 { self.lean * (trauma / self.trauma) } else { Vec2::ZERO };
+        self.trauma = trauma;
+    }
+    /// How far the shake moves the view at `time`, scaled by `scale`.
+    pub fn shake_offset(&self, time: f32, scale: f32) -> Vec2 {
+        let reach = sq(self.trauma) * self.max_offset * scale;
+        if reach <= 0.0 { return Vec2::ZERO; }
+        let noise = vec2(shake_noise(time, 0.0), shake_noise(time, 1.0));
+        let lean = self.lean.length() / self.trauma;
+        let along = self.lean.try_normalize().unwrap_or(Vec2::X);
+        (along * noise.x + along.perp() * noise.y * (1.0 - lean * DIRECTIONAL_SQUASH)) * reach
+    }
+    /// Radians the shake turns the view at `time`, scaled by `scale`.
+    pub fn shake_rotation(&self, time: f32, scale: f32) -> f32 {
+        sq(self.trauma) * self.max_rotation * scale * shake_noise(time, 2.0)
+    }
+    /// Knocks the view `jerk` pixels over at once, from wherever it is. It springs back from there.
+    pub fn jerk(&mut self, jerk: Vec2) {
+        if !jerk.is_finite() { return; }
+        // each way first, huge ones would make the length infinite
+        let jerk = jerk.clamp(Vec2::splat(-self.max_jerk), Vec2::splat(self.max_jerk));
+        self.jerk = (self.jerk + jerk).clamp_length_max(self.max_jerk);
+    }
+    pub fn jerk_offset(&self) -> Vec2 {
+        self.jerk
+    }
+    pub fn clear_jerk(&mut self) {
+        self.jerk = Vec2::ZERO;
+        self.jerk_vel = Vec2::ZERO;
+    }
+    /// Moves the jerk's spring along by `beat_dt` beats, in small steps so it behaves the same at any frame rate.
+    fn spring_jerk(&mut self, beat_dt: f32) {
+        // NaN frames settle it too
+        if beat_dt.is_nan() || beat_dt >= JERK_SETTLE_BEATS { return self.clear_jerk(); }
+        let steps = (beat_dt.max(0.0) / JERK_STEP).ceil();
+        let dt = beat_dt.max(0.0) / steps.max(1.0);
+        for _ in 0..steps as usize {
+            // semi-implicit Euler: the velocity first, then the position with the new velocity
+            self.jerk_vel += (-self.jerk_stiffness * self.jerk - self.jerk_damping * self.jerk_vel) * dt;
+            self.jerk = (self.jerk + self.jerk_vel * dt).clamp_length_max(self.max_jerk);
+        }
+        if !self.jerk.is_finite() || !self.jerk_vel.is_finite() { self.clear_jerk(); }
+    }
+    /// Zooms in by `amount` (0.1 for 10%) at `time`, easing back out over `decay` beats.
+    pub fn zoom_pulse(&mut self, amount: f32, decay: f32, time: f32) {
+        self.pulse = amount.clamp(-MAX_ZOOM_PULSE, MAX_ZOOM_PULSE);
+        self.pulse_start = time;
+        self.pulse_decay = decay;
+    }
+    /// Turns to `target` radians over `ramp_beats`, at once if 0.
+    pub fn rotate_to(&mut self, target: f32, ramp_beats: f32) {
+        self.rotation_target = target;
+        if ramp_beats <= 0.0 {
+            self.rotation = target;
+            self.rotation_rate = 0.0;
+        } else {
+            self.rotation_rate = (target - self.rotation).abs() / ramp_beats;
+        }
+    }
+    /// Eases the shake, jerk and rotation by a frame of `beat_dt` beats.
+    pub fn update(&mut self, beat_dt: f32) {
+        self.spring_jerk(beat_dt);
+        self.decay_shake(beat_dt);
+        // clamping to a NaN or backwards step would panic
+        let step = self.rotation_rate * beat_dt.max(0.0);
+        self.rotation += (self.rotation_target - self.rotation).clamp(-step, step);
+    }
+    pub fn zoom(&self, time: f32) -> f32 {
+        if self.pulse_decay <= 0.0 { return 1.0; }
+        1.0 + self.pulse * (1.0 - (time - self.pulse_start) / self.pulse_decay).clamp(0.0, 1.0)
+    }
+    /// What every `draw` is offset by at `time`, the shake scaled by `shake_scale`. 0 holds it still, e.g. while paused.
+    pub fn offset(&self, time: f32, shake_scale: f32) -> Vec2 {
+        self.jerk
+            + self.shake_offset(time, shake_scale)
+            + vec2(time.sin(), (time * 1.2).sin()) * self.float
+    }
+    /// A box around everything on screen at `time`, whichever way the camera is turned.
+    pub fn visible(&self, time: f32) -> Rect {
+        let reach = vec2(screen_width(), screen_height()).length() / 2.0 / self.zoom(time);
+        Rect::new(screen_width() / 2.0 - reach, screen_height() / 2.0 - reach, reach * 2.0, reach * 2.0)
+    }
+    /// Screen coordinates, zoomed and rotated around the center of the screen, with the shake's turn scaled like in `offset`.
+    pub fn camera2d(&self, time: f32, shake_scale: f32) -> Camera2D {
+        let mut camera = virtual_camera(Rect::new(0.0, 0.0, screen_width(), screen_height()));
+        camera.zoom *= self.zoom(time);
+        camera.rotation = (self.rotation + self.shake_rotation(time, shake_scale)).to_degrees();
+        camera
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trauma_adds_up_to_one() {
+        let mut camera = Camera::default();
+        camera.shake(MAX_SHAKE_OFFSET / 4.0);
+        camera.shake(MAX_SHAKE_OFFSET / 4.0);
+        assert_eq!(camera.trauma(), 0.5);
+        camera.shake(MAX_SHAKE_OFFSET * 10.0);
+        assert_eq!(camera.trauma(), 1.0);
+        // nor below nothing
+        camera.shake(-MAX_SHAKE_OFFSET * 10.0);
+        assert_eq!(camera.trauma(), 0.0);
+    }
+
+    #[test]
+    fn trauma_decays_linearly_at_any_frame_rate() {
+        let (mut once, mut often) = (Camera::default(), Camera::default());
+        once.shake(MAX_SHAKE_OFFSET);
+        often.shake(MAX_SHAKE_OFFSET);
+        once.decay_shake(0.3);
+        (0..30).for_each(|_| often.decay_shake(0.01));
+        assert!((once.trauma() - 0.7).abs() < 1e-5 && (often.trauma() - 0.7).abs() < 1e-5);
+        // to nothing and no further, and never back up
+        once.decay_shake(5.0);
+        assert_eq!(once.trauma(), 0.0);
+        once.decay_shake(-1.0);
+        assert_eq!(once.trauma(), 0.0);
+    }
+
+    #[test]
+    fn shake_reaches_as_far_as_the_trauma_squared() {
+        let mut camera = Camera::default();
+        assert_eq!(camera.shake_offset(1.0, 1.0), Vec2::ZERO);
+        camera.shake(MAX_SHAKE_OFFSET / 2.0);
+        let reach = 0.25 * MAX_SHAKE_OFFSET;
+        let mut furthest: f32 = 0.0;
+        for i in 0..1000 {
+            let offset = camera.shake_offset(i as f32 * 0.013, 1.0);
+            assert!(offset.x.abs() <= reach && offset.y.abs() <= reach, "{offset} past {reach}");
+            assert!(camera.shake_rotation(i as f32 * 0.013, 1.0).abs() <= 0.25 * MAX_SHAKE_ROTATION);
+            furthest = furthest.max(offset.length());
+        }
+        // and does actually move
+        assert!(furthest > reach * 0.2);
+        // the accessibility setting scales it away entirely
+        assert_eq!(camera.shake_offset(0.5, 0.0), Vec2::ZERO);
+        assert_eq!(camera.shake_rotation(0.5, 0.0), 0.0);
+    }
+
+    #[test]
+    fn directional_shakes_lean_along_their_direction() {
+        let mut camera = Camera::default();
+        camera.shake_directional(vec2(MAX_SHAKE_OFFSET, 0.0));
+        assert_eq!(camera.trauma(), 1.0);
+        let sideways = (0..1000).map(|i| camera.shake_offset(i as f32 * 0.013, 1.0).y.abs()).fold(0.0, f32::max);
+        assert!(sideways <= MAX_SHAKE_OFFSET * (1.0 - DIRECTIONAL_SQUASH) + 1e-4);
+        // the lean fades with the trauma, staying as directional as it was
+        camera.decay_shake(0.5);
+        assert!((camera.lean.length() - camera.trauma()).abs() < 1e-6 && camera.lean.y == 0.0);
+        // and is never more than the trauma, even after a plain shake takes it down
+        camera.shake(-MAX_SHAKE_OFFSET * 0.4);
+        assert!(camera.lean.length() <= camera.trauma() + 1e-6);
+        camera.shake_directional(Vec2::ZERO);
+        assert!(camera.trauma() < 0.5);
+    }
+
+
+    #[test]
+    fn jerks_spring_back_to_the_middle() {
+        let mut camera = Camera::default();
+        camera.jerk(vec2(40.0, -30.0));
+        let mut crossed = false;
+        for _ in 0..120 {
+            camera.update(1.0 / 60.0);
+            crossed |= camera.jerk_offset().x < 0.0;
+        }
+        // past the middle on the way back, then settled within two beats
+        assert!(crossed, "never overshot");
+        assert!(camera.jerk_offset().length() < 0.5, "still {} out", camera.jerk_offset());
+        (0..240).for_each(|_| camera.update(1.0 / 60.0));
+        assert!(camera.jerk_offset().length() < 1e-3 && camera.jerk_vel.length() < 1e-2);
+    }
+
+    #[test]
+    fn jerks_spring_the_same_at_any_frame_rate() {
+        let (mut slow, mut fast) = (Camera::default(), Camera::default());
+        slow.jerk(vec2(50.0, 0.0));
+        fast.jerk(vec2(50.0, 0.0));
+        for _ in 0..30 {
+            // 60 and 480 FPS at 120 BPM
+            slow.update(1.0 / 30.0);
+            (0..8).for_each(|_| fast.update(1.0 / 240.0));
+            assert!(slow.jerk_offset().distance(fast.jerk_offset()) < 0.05, "{} against {}", slow.jerk_offset(), fast.jerk_offset());
+        }
+    }
+
+    #[test]
+    fn jerks_in_quick_succession_add_up_to_the_cap() {
+        let mut camera = Camera::default();
+        camera.jerk(vec2(30.0, 0.0));
+        camera.jerk(vec2(30.0, 0.0));
+        assert_eq!(camera.jerk_offset(), vec2(60.0, 0.0));
+        (0..20).for_each(|_| camera.jerk(vec2(30.0, 30.0)));
+        assert!(camera.jerk_offset().length() <= MAX_JERK + 1e-3);
+    }
+
+    #[test]
+    fn extreme_jerks_never_go_nan() {
+        for jerk in [vec2(1e30, -1e30), vec2(f32::MAX, f32::MAX), vec2(f32::INFINITY, 0.0), vec2(f32::NAN, 1.0), vec2(-1e-30, 1e-30)] {
+            let mut camera = Camera::default();
+            camera.jerk(jerk);
+            camera.rotate_to(1.0, 2.0);
+            for dt in [1.0 / 60.0, 0.0, -1.0, 0.5, f32::NAN, 100.0, 1.0 / 1000.0] {
+                camera.update(dt);
+                assert!(camera.jerk_offset().is_finite() && camera.jerk_offset().length() <= MAX_JERK + 1e-3, "{jerk} after {dt}");
+                assert!(camera.rotation.is_finite(), "turned to {} after {dt}", camera.rotation);
+            }
+        }
+        // a spring stiff enough to blow up settles instead
+        let mut camera = Camera { jerk_stiffness: 1e12, ..Camera::default() };
+        camera.jerk(vec2(MAX_JERK, 0.0));
+        (0..60).for_each(|_| camera.update(1.0 / 60.0));
+        assert!(camera.jerk_offset().is_finite() && camera.jerk_vel.is_finite());
+    }
+}
@@ -0,0 +1,1336 @@
+//! Plain-text charts, so levels can be shared and iterated on without recompiling.
+//!
+//! ```text
+//! # comments take up a whole line
+//! title Example
+//! artist Someone
+//! difficulty Hard
+//! duration 95
+//! color #ff0080
+//! palette high_contrast
+//! bpm 140
+//! tempo 30.5 160 3
+//! offset 0.25
+//! audio assets/song.wav
+//! checkpoint 32 First drop
+//! background pulse #401030
+//! intensity 32 2.5
+//! palette_shift 64 deuteranopia 4
+//! flash 64 #ffffff 0.5 1
+//! fade 124 1 4
+//! time_scale 96 0.5 2
+//! zoom 64 0.1 1
+//! tilt 96 0.2 4
+//! 0  Pellet pos=(0.5s, 0s) vel=(0, 200) rad=10
+//! 4  GrowLaser start=(0, 0.5s) end=(1s, 0.5s) thickness=40 warning_time=2 show_time=1 ease=quad
+//! 8  Periodic steps=8 interval=0.5 trail=linear(2, 1, 0.25, (0.1s, 0.5s), (0.1s, 0), (40, 40), 0)
+//! 16 CenterProj show_time=8 events=[(0, Pulse), (1, Lasers(8, 0))]
+//! ```
+//! The headers come before the entries. `title`, `artist`, `difficulty`, `duration` (seconds) and `color`
+//! only show up in the chart list. `palette` plays the chart in a built-in palette instead of the player's.\
+//! `tempo <seconds> <bpm> [beats per bar]` changes the tempo partway through the song, `bpm` sets the starting tempo.\
+//! `background <pulse|grid|drift> [color]` picks the background, `intensity <beat> <value>` eases it towards `value` from `beat` on.\
+//! `palette_shift <beat> <palette> <beats>` eases into another palette over `beats`.\
+//! `flash <beat> <color> <intensity> <beats>` flashes the screen, `fade <beat> <alpha> <beats>` fades it to black and back.\
+//! `time_scale <beat> <scale> <beats>` eases the speed of the whole game, music included, for bullet time.\
+//! `zoom <beat> <amount> <beats>` punches the camera in by `amount` and back out, `tilt <beat> <radians> <beats>` turns it.
+//! `impact_flashes false` stops slam lasers and bombs from flashing, `hitstop <multiplier>` scales how long they freeze the game.\
+//! Each entry is `<beat> <Obstacle> field=value...`, the fields being the obstacle's constructor/builder parameters.\
+//! A number suffixed with `s` is a fraction of the screen size, resolved when the obstacle spawns.\
+//! `ease` is one of `sqrt`, `quad`, `quant16th`, `recip(k)` or `circ(period)`.
+//!
+//! This is its own format rather than RON or JSON: a line per spawn diffs and hand-edits better, and `serde`
+//! stays optional. The `serde` feature derives (de)serialization for `ObstacleSpec` and `ChartEntry` for tools
+//! wanting them, but charts on disk are always this format.
+
+use std::{fmt::{self, Display}, fs, io::{self, BufRead}, path::{Path, PathBuf}, error::Error, time::SystemTime};
+
+use macroquad::prelude::{Vec2, vec2, Color};
+
+use crate::{game::{GSEvent, UpdateAccumulator, Accumulatee, Checkpoint}, utils::{GameRng, screen_width, screen_height, recip_ease_fn, circ_climb_fn}, tempo::{TempoMap, TempoPoint}, background::BackgroundKind, timeline::EventCategory, palette::{Palette, PALETTE_NAMES}, game_objects::{Obstacle, Obst, Pellet, Bomb, GrowLaser, SlamLaser, RotatableRect, RotatingRect, WarningStyle, SpinningArc, CenterProj, CenterEvent, GOLGrid, Periodic, CatchUp, OnEarlyKill, Ease, Easing}};
+
+#[derive(Debug)]
+pub enum ChartError {
+    Io(io::Error),
+    Syntax { line: usize, field: Option<String>, message: String },
+}
+impl Display for ChartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChartError::Io(e) => write!(f, "{e}"),
+            ChartError::Syntax { line, field: Some(field), message } => write!(f, "line {line}, field `{field}`: {message}"),
+            ChartError::Syntax { line, field: None, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+impl Error for ChartError {}
+impl From<io::Error> for ChartError {
+    fn from(e: io::Error) -> Self { ChartError::Io(e) }
+}
+
+/// An error within a line, given its line number by the caller.
+struct FieldError {
+    field: Option<String>,
+    message: String,
+}
+impl FieldError {
+    fn new(field: Option<&str>, message: impl Into<String>) -> Self {
+        FieldError { field: field.map(str::to_string), message: message.into() }
+    }
+    fn at(self, line: usize) -> ChartError {
+        ChartError::Syntax { line, field: self.field, message: self.message }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Num(f32),
+    /// A fraction of the screen size, `0.5s`.
+    Screen(f32),
+    Ident(String),
+    Call(String, Vec<Value>),
+    Tuple(Vec<Value>),
+    List(Vec<Value>),
+}
+fn join(vals: &[Value]) -> String {
+    vals.iter().map(Value::to_string).collect::<Vec<_>>().join(", ")
+}
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Num(n) => write!(f, "{n}"),
+            Value::Screen(n) => write!(f, "{n}s"),
+            Value::Ident(s) => write!(f, "{s}"),
+            Value::Call(name, args) => write!(f, "{name}({})", join(args)),
+            Value::Tuple(vals) => write!(f, "({})", join(vals)),
+            Value::List(vals) => write!(f, "[{}]", join(vals)),
+        }
+    }
+}
+
+/// A tiny recursive descent reader over a single line.
+struct Reader<'a> {
+    src: &'a str,
+    pos: usize,
+}
+impl<'a> Reader<'a> {
+    fn new(src: &'a str) -> Self {
+        Reader { src, pos: 0 }
+    }
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) { self.pos += 1; }
+    }
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_ws();
+        if self.peek() == Some(c) { self.pos += 1; true } else { false }
+    }
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let start = self.pos;
+        while self.peek().is_some_and(&f) { self.pos += self.peek().unwrap().len_utf8(); }
+        &self.src[start..self.pos]
+    }
+    fn ident(&mut self) -> Option<&'a str> {
+        self.skip_ws();
+        if !self.peek().is_some_and(|c| c.is_alphabetic() || c == '_') { return None; }
+        Some(self.take_while(|c| c.is_alphanumeric() || c == '_'))
+    }
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_ws();
+        match self.peek() {
+            Some('(') => { self.pos += 1; Ok(Value::Tuple(self.seq(')')?)) }
+            Some('[') => { self.pos += 1; Ok(Value::List(self.seq(']')?)) }
+            Some(c) if c.is_ascii_digit() || "+-.".contains(c) => {
+                let text = self.take_while(|c| c.is_ascii_digit() || "+-.eE".contains(c));
+                let n = text.parse::<f32>().map_err(|_| format!("`{text}` is not a number"))?;
+                if self.peek() == Some('s') {
+                    self.pos += 1;
+                    Ok(Value::Screen(n))
+                } else {
+                    Ok(Value::Num(n))
+                }
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let name = self.take_while(|c| c.is_alphanumeric() || c == '_').to_string();
+                if self.peek() == Some('(') {
+                    self.pos += 1;
+                    Ok(Value::Call(name, self.seq(')')?))
+                } else {
+                    Ok(Value::Ident(name))
+                }
+            }
+            Some(c) => Err(format!("unexpected `{c}`")),
+            None => Err("expected a value".to_string()),
+        }
+    }
+    /// Reads comma separated values up to `close`, the opening bracket already consumed.
+    fn seq(&mut self, close: char) -> Result<Vec<Value>, String> {
+        let mut vals = vec![];
+        loop {
+            if self.eat(close) { return Ok(vals); }
+            vals.push(self.value()?);
+            if !self.eat(',') {
+                return if self.eat(close) { Ok(vals) } else { Err(format!("expected `,` or `{close}`")) };
+            }
+        }
+    }
+}
+
+/// Conversion between chart values and the types obstacle fields are made of.
+trait ChartValue: Sized {
+    fn from_value(val: &Value) -> Result<Self, String>;
+    fn to_value(&self) -> Value;
+}
+impl ChartValue for f32 {
+    fn from_value(val: &Value) -> Result<Self, String> {
+        match val {
+            Value::Num(n) => Ok(*n),
+            _ => Err(format!("expected a number, got `{val}`")),
+        }
+    }
+    fn to_value(&self) -> Value { Value::Num(*self) }
+}
+impl ChartValue for usize {
+    fn from_value(val: &Value) -> Result<Self, String> {
+        match val {
+            Value::Num(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+            _ => Err(format!("expected a whole number, got `{val}`")),
+        }
+    }
+    fn to_value(&self) -> Value { Value::Num(*self as f32) }
+}
+impl ChartValue for bool {
+    fn from_value(val: &Value) -> Result<Self, String> {
+        match val {
+            Value::Ident(s) if s == "true" => Ok(true),
+            Value::Ident(s) if s == "false" => Ok(false),
+            _ => Err(format!("expected `true` or `false`, got `{val}`")),
+        }
+    }
+    fn to_value(&self) -> Value { Value::Ident(self.to_string()) }
+}
+impl ChartValue for Vec2 {
+    fn from_value(val: &Value) -> Result<Self, String> {
+        match val {
+            Value::Tuple(v) if v.len() == 2 => Ok(vec2(f32::from_value(&v[0])?, f32::from_value(&v[1])?)),
+            _ => Err(format!("expected `(x, y)`, got `{val}`")),
+        }
+    }
+    fn to_value(&self) -> Value { Value::Tuple(vec![self.x.to_value(), self.y.to_value()]) }
+}
+impl ChartValue for (usize, usize) {
+    fn from_value(val: &Value) -> Result<Self, String> {
+        match val {
+            Value::Tuple(v) if v.len() == 2 => Ok((usize::from_value(&v[0])?, usize::from_value(&v[1])?)),
+            _ => Err(format!("expected `(w, h)`, got `{val}`")),
+        }
+    }
+    fn to_value(&self) -> Value { Value::Tuple(vec![self.0.to_value(), self.1.to_value()]) }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Coord {
+    Px(f32),
+    /// A fraction of the screen's width or height.
+    Screen(f32),
+}
+impl Coord {
+    pub fn resolve(self, extent: f32) -> f32 {
+        match self {
+            Coord::Px(px) => px,
+            Coord::Screen(frac) => frac * extent,
+        }
+    }
+}
+impl ChartValue for Coord {
+    fn from_value(val: &Value) -> Result<Self, String> {
+        match val {
+            Value::Num(n) => Ok(Coord::Px(*n)),
+            Value::Screen(n) => Ok(Coord::Screen(*n)),
+            _ => Err(format!("expected a number, got `{val}`")),
+        }
+    }
+    fn to_value(&self) -> Value {
+        match *self {
+            Coord::Px(n) => Value::Num(n),
+            Coord::Screen(n) => Value::Screen(n),
+        }
+    }
+}
+
+/// A vector whose components can each be in pixels or screen fractions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChartVec(pub Coord, pub Coord);
+impl ChartVec {
+    pub fn px(v: Vec2) -> Self {
+        ChartVec(Coord::Px(v.x), Coord::Px(v.y))
+    }
+    pub fn screen(v: Vec2) -> Self {
+        ChartVec(Coord::Screen(v.x), Coord::Screen(v.y))
+    }
+    pub fn resolve(self) -> Vec2 {
+        vec2(self.0.resolve(screen_width()), self.1.resolve(screen_height()))
+    }
+    /// Moved by `delta` pixels, each component staying in its unit.
+    pub fn offset(self, delta: Vec2) -> Self {
+        let shift = |coord: Coord, delta: f32, extent: f32| match coord {
+            Coord::Px(px) => Coord::Px(px + delta),
+            Coord::Screen(frac) => Coord::Screen(frac + delta / extent),
+        };
+        ChartVec(shift(self.0, delta.x, screen_width()), shift(self.1, delta.y, screen_height()))
+    }
+}
+impl ChartValue for ChartVec {
+    fn from_value(val: &Value) -> Result<Self, String> {
+        match val {
+            Value::Tuple(v) if v.len() == 2 => Ok(ChartVec(Coord::from_value(&v[0])?, Coord::from_value(&v[1])?)),
+            _ => Err(format!("expected `(x, y)`, got `{val}`")),
+        }
+    }
+    fn to_value(&self) -> Value { Value::Tuple(vec![self.0.to_value(), self.1.to_value()]) }
+}
+
+impl ChartValue for CenterEvent {
+    fn from_value(val: &Value) -> Result<Self, String> {
+        let args = |args: &[Value], n: usize| if args.len() == n { Ok(()) } else { Err(format!("expected {n} arguments in `{val}`")) };
+        match val {
+            Value::Ident(s) if s == "Pulse" => Ok(CenterEvent::Pulse),
+            Value::Call(name, a) => match name.as_str() {
+                "SPulse" => { args(a, 1)?; Ok(CenterEvent::SPulse(f32::from_value(&a[0])?)) }
+                "Lasers" => { args(a, 2)?; Ok(CenterEvent::Lasers(usize::from_value(&a[0])?, f32::from_value(&a[1])?)) }
+                "Pellets" => {
+                    args(a, 5)?;
+                    Ok(CenterEvent::Pellets(usize::from_value(&a[0])?, f32::from_value(&a[1])?, f32::from_value(&a[2])?, f32::from_value(&a[3])?, bool::from_value(&a[4])?))
+                }
+                "MessyPellets" => {
+                    args(a, 4)?;
+                    Ok(CenterEvent::MessyPellets(usize::from_value(&a[0])?, f32::from_value(&a[1])?, f32::from_value(&a[2])?, f32::from_value(&a[3])?))
+                }
+                "PelletSpinner" => {
+                    args(a, 5)?;
+                    Ok(CenterEvent::PelletSpinner(usize::from_value(&a[0])?, f32::from_value(&a[1])?, f32::from_value(&a[2])?, f32::from_value(&a[3])?, f32::from_value(&a[4])?))
+                }
+                _ => Err(format!("unknown center event `{name}`")),
+            },
+            _ => Err(format!("unknown center event `{val}`")),
+        }
+    }
+    fn to_value(&self) -> Value {
+        let call = |name: &str, args: Vec<Value>| Value::Call(name.to_string(), args);
+        match *self {
+            CenterEvent::Pulse => Value::Ident("Pulse".to_string()),
+            CenterEvent::SPulse(s) => call("SPulse", vec![s.to_value()]),
+            CenterEvent::Lasers(c, p) => call("Lasers", vec![c.to_value(), p.to_value()]),
+            CenterEvent::Pellets(c, s, r, p, st) => call("Pellets", vec![c.to_value(), s.to_value(), r.to_value(), p.to_value(), st.to_value()]),
+            CenterEvent::MessyPellets(c, r, min, max) => call("MessyPellets", vec![c.to_value(), r.to_value(), min.to_value(), max.to_value()]),
+            CenterEvent::PelletSpinner(c, s, r, p, ppb) => call("PelletSpinner", vec![c.to_value(), s.to_value(), r.to_value(), p.to_value(), ppb.to_value()]),
+        }
+    }
+}
+impl ChartValue for Vec<(f32, CenterEvent)> {
+    fn from_value(val: &Value) -> Result<Self, String> {
+        match val {
+            Value::List(evs) => evs.iter().map(|ev| match ev {
+                Value::Tuple(v) if v.len() == 2 => Ok((f32::from_value(&v[0])?, CenterEvent::from_value(&v[1])?)),
+                _ => Err(format!("expected `(beat, event)`, got `{ev}`")),
+            }).collect(),
+            _ => Err(format!("expected a list of events, got `{val}`")),
+        }
+    }
+    fn to_value(&self) -> Value {
+        Value::List(self.iter().map(|(t, ev)| Value::Tuple(vec![t.to_value(), ev.to_value()])).collect())
+    }
+}
+
+/// The built-in `Periodic` trails, with the same parameters as their constructors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TrailSpec {
+    Linear { rect_life: f32, warning_time: f32, grow_time: f32, start: ChartVec, delta: ChartVec, scale: ChartVec, rot: f32 },
+    Chase { rect_life: f32, warning_time: f32, grow_time: f32, size: ChartVec, lag_beats: f32 },
+    Bezier { rect_life: f32, warning_time: f32, grow_time: f32, p0: ChartVec, p1: ChartVec, p2: ChartVec, p3: ChartVec, size: ChartVec, align_to_tangent: bool },
+}
+impl ChartValue for TrailSpec {
+    fn from_value(val: &Value) -> Result<Self, String> {
+        let Value::Call(name, a) = val else { return Err(format!("expected a trail, got `{val}`")) };
+        let args = |n: usize| if a.len() == n { Ok(()) } else { Err(format!("`{name}` takes {n} arguments")) };
+        match name.as_str() {
+            "linear" => {
+                args(7)?;
+                Ok(TrailSpec::Linear {
+                    rect_life: f32::from_value(&a[0])?, warning_time: f32::from_value(&a[1])?, grow_time: f32::from_value(&a[2])?,
+                    start: ChartVec::from_value(&a[3])?, delta: ChartVec::from_value(&a[4])?, scale: ChartVec::from_value(&a[5])?,
+                    rot: f32::from_value(&a[6])?,
+                })
+            }
+            "chase" => {
+                args(5)?;
+                Ok(TrailSpec::Chase {
+                    rect_life: f32::from_value(&a[0])?, warning_time: f32::from_value(&a[1])?, grow_time: f32::from_value(&a[2])?,
+                    size: ChartVec::from_value(&a[3])?, lag_beats: f32::from_value(&a[4])?,
+                })
+            }
+            "bezier" => {
+                args(9)?;
+                Ok(TrailSpec::Bezier {
+                    rect_life: f32::from_value(&a[0])?, warning_time: f32::from_value(&a[1])?, grow_time: f32::from_value(&a[2])?,
+                    p0: ChartVec::from_value(&a[3])?, p1: ChartVec::from_value(&a[4])?, p2: ChartVec::from_value(&a[5])?, p3: ChartVec::from_value(&a[6])?,
+                    size: ChartVec::from_value(&a[7])?, align_to_tangent: bool::from_value(&a[8])?,
+                })
+            }
+            _ => Err(format!("unknown trail `{name}`")),
+        }
+    }
+    fn to_value(&self) -> Value {
+        let call = |name: &str, args: Vec<Value>| Value::Call(name.to_string(), args);
+        match self {
+            TrailSpec::Linear { rect_life, warning_time, grow_time, start, delta, scale, rot } => call("linear", vec![
+                rect_life.to_value(), warning_time.to_value(), grow_time.to_value(), start.to_value(), delta.to_value(), scale.to_value(), rot.to_value()
+            ]),
+            TrailSpec::Chase { rect_life, warning_time, grow_time, size, lag_beats } => call("chase", vec![
+                rect_life.to_value(), warning_time.to_value(), grow_time.to_value(), size.to_value(), lag_beats.to_value()
+            ]),
+            TrailSpec::Bezier { rect_life, warning_time, grow_time, p0, p1, p2, p3, size, align_to_tangent } => call("bezier", vec![
+                rect_life.to_value(), warning_time.to_value(), grow_time.to_value(),
+                p0.to_value(), p1.to_value(), p2.to_value(), p3.to_value(), size.to_value(), align_to_tangent.to_value()
+            ]),
+        }
+    }
+}
+
+impl TrailSpec {
+    /// The trail's modifier, resolving screen fractions against the current screen size.
+    pub fn build(self) -> Box<dyn Accumulatee> {
+        match self {
+            TrailSpec::Linear { rect_life, warning_time, grow_time, start, delta, scale, rot } =>
+                Periodic::linear(rect_life, warning_time, grow_time, start.resolve(), delta.resolve(), scale.resolve(), rot),
+            TrailSpec::Chase { rect_life, warning_time, grow_time, size, lag_beats } =>
+                Periodic::chase(rect_life, warning_time, grow_time, size.resolve(), lag_beats),
+            TrailSpec::Bezier { rect_life, warning_time, grow_time, p0, p1, p2, p3, size, align_to_tangent } =>
+                Periodic::bezier_trail(rect_life, warning_time, grow_time, p0.resolve(), p1.resolve(), p2.resolve(), p3.resolve(), size.resolve(), align_to_tangent),
+        }
+    }
+}
+
+/// A `Periodic` as data, partway through or not, its modifier being one of the built-in trails.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PeriodicSpec {
+    pub trail: TrailSpec,
+    pub time_mod: f32,
+    pub time_div: usize,
+    pub interval: f32,
+    pub max_steps: usize,
+    pub on_early_kill: OnEarlyKill,
+    pub catch_up: CatchUp,
+}
+impl PeriodicSpec {
+    /// The spec of `periodic`, whose modifier is `trail`.
+    pub fn of(periodic: &Periodic, trail: TrailSpec) -> Self {
+        let Periodic { time_mod, time_div, interval, max_steps, on_early_kill, catch_up, .. } = *periodic;
+        PeriodicSpec { trail, time_mod, time_div, interval, max_steps, on_early_kill, catch_up }
+    }
+}
+impl From<PeriodicSpec> for Periodic {
+    fn from(spec: PeriodicSpec) -> Self {
+        Periodic {
+            modifier: spec.trail.build(),
+            time_mod: spec.time_mod,
+            time_div: spec.time_div,
+            interval: spec.interval,
+            max_steps: spec.max_steps,
+            on_early_kill: spec.on_early_kill,
+            catch_up: spec.catch_up,
+        }
+    }
+}
+
+/// The spawners a `BombSpec` can name, as the spawners themselves can't be written down.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BombSpawner {
+    /// `Bomb::pellet_spawner`
+    #[default]
+    Pellets,
+}
+impl BombSpawner {
+    pub fn spawner(self) -> Box<dyn Accumulatee> {
+        match self {
+            BombSpawner::Pellets => Box::new(Bomb::pellet_spawner),
+        }
+    }
+}
+
+/// A `Bomb` as data, partway through or not, with its spawner named.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BombSpec {
+    pub start: Vec2,
+    pub target: Vec2,
+    pub time: f32,
+    pub life: f32,
+    pub pellets: usize,
+    pub pellet_vel: f32,
+    pub pellet_rad: f32,
+    pub snappiness: f32,
+    pub rad: f32,
+    pub spawner: BombSpawner,
+}
+impl BombSpec {
+    /// The spec of `bomb`, whose spawner is `spawner`.
+    pub fn of(bomb: &Bomb, spawner: BombSpawner) -> Self {
+        let Bomb { start, target, time, life, pellets, pellet_vel, pellet_rad, snappiness, rad, .. } = *bomb;
+        BombSpec { start, target, time, life, pellets, pellet_vel, pellet_rad, snappiness, rad, spawner }
+    }
+}
+impl From<BombSpec> for Bomb {
+    fn from(spec: BombSpec) -> Self {
+        Bomb {
+            start: spec.start,
+            target: spec.target,
+            time: spec.time,
+            life: spec.life,
+            pellets: spec.pellets,
+            pellet_vel: spec.pellet_vel,
+            pellet_rad: spec.pellet_rad,
+            snappiness: spec.snappiness,
+            rad: spec.rad,
+            spawner: spec.spawner.spawner(),
+        }
+    }
+}
+
+/// The `field=value` pairs of an entry, taken out as the spec is built so leftovers can be reported.
+struct Fields(Vec<(String, Value)>);
+impl Fields {
+    fn take<T: ChartValue>(&mut self, name: &str, default: Option<T>) -> Result<T, FieldError> {
+        match self.0.iter().position(|(n, _)| n == name) {
+            Some(idx) => T::from_value(&self.0.remove(idx).1).map_err(|e| FieldError::new(Some(name), e)),
+            None => default.ok_or_else(|| FieldError::new(Some(name), "missing")),
+        }
+    }
+}
+
+/// Declares `ObstacleSpec` along with its conversions, fields without a default being required.
+macro_rules! specs {
+    ($($kind:ident { $($field:ident: $type:ty $(= $default:expr)?),* $(,)? })*) => {
+        /// A built-in obstacle and its parameters.
+        #[derive(Debug, Clone, PartialEq)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub enum ObstacleSpec {
+            $($kind { $($field: $type),* }),*
+        }
+        impl ObstacleSpec {
+            pub fn kind(&self) -> &'static str {
+                match self { $(ObstacleSpec::$kind { .. } => stringify!($kind)),* }
+            }
+            fn fields(&self) -> Vec<(&'static str, Value)> {
+                match self { $(ObstacleSpec::$kind { $($field),* } => vec![$((stringify!($field), $field.to_value())),*]),* }
+            }
+            fn from_fields(kind: &str, fields: &mut Fields) -> Result<Self, FieldError> {
+                match kind {
+                    $(stringify!($kind) => Ok(ObstacleSpec::$kind { $($field: fields.take(stringify!($field), specs!(@default $($default)?))?),* }),)*
+                    _ => Err(FieldError::new(None, format!("unknown obstacle `{kind}`"))),
+                }
+            }
+        }
+    };
+    (@default) => { None };
+    (@default $default:expr) => { Some($default) };
+}
+specs! {
+    Pellet { pos: ChartVec, vel: ChartVec, rad: f32 }
+    Bomb { start: ChartVec, target: ChartVec, lifetime: f32, pellets: usize, pellet_vel: f32, pellet_rad: f32 }
+    GrowLaser { start: ChartVec, end: ChartVec, thickness: f32, warning_time: f32, show_time: f32, jerk: Vec2 = Vec2::ZERO, grow_time: f32 = 0.25 }
+    SlamLaser { start: ChartVec, end: ChartVec, thickness: f32, warning_time: f32, show_time: f32, anticipation: f32, jerk: Vec2 = Vec2::ZERO, shake: f32 = 0.0 }
+    RotatableRect { center: ChartVec, size: ChartVec, rot: f32 = 0.0, warning_time: f32, show_time: f32, grow_time: f32 = 0.25 }
+    RotatingRect {
+        center: ChartVec = ChartVec::screen(vec2(0.5, 0.5)), size: ChartVec, rot: f32 = 0.0,
+        warning_time: f32 = 8.0, show_time: f32 = 0.0, grow_time: f32 = 0.25, rpb: f32 = 0.25,
+    }
+    SpinningArc {
+        center: ChartVec = ChartVec::screen(vec2(0.5, 0.5)), inner_rad: f32, outer_rad: f32, left_angle: f32, right_angle: f32,
+        rpb: f32 = 0.0, warning_time: f32 = 0.0, show_time: f32,
+    }
+    CenterProj {
+        disp_amp: f32 = 75.0, disp_freq: Vec2 = Vec2::ONE, disp_phase: Vec2 = Vec2::ZERO,
+        leave_time: f32 = 0.25, warning_time: f32 = 1.0, show_time: f32 = 32.0, events: Vec<(f32, CenterEvent)> = vec![],
+    }
+    GOLGrid {
+        dims: (usize, usize) = (32, 18), max: usize = 32, period: f32 = 1.0,
+        warning_time: f32 = 0.0, first_warning_time: f32 = 1.0, populate: usize = 0,
+    }
+    Periodic { steps: usize, interval: f32, trail: TrailSpec }
+}
+impl ObstacleSpec {
+    /// Creates the obstacle, resolving screen fractions against the current screen size.
+    pub fn build(&self, rng: &mut GameRng) -> Box<dyn Obstacle> {
+        match self.clone() {
+            ObstacleSpec::Pellet { pos, vel, rad } => Box::new(Pellet::new(pos.resolve(), vel.resolve(), rad)),
+            ObstacleSpec::Bomb { start, target, lifetime, pellets, pellet_vel, pellet_rad } => Box::new(Bomb::new(
+                start.resolve(), target.resolve(), lifetime, pellets, pellet_vel, pellet_rad, Box::new(Bomb::pellet_spawner)
+            )),
+            ObstacleSpec::GrowLaser { start, end, thickness, warning_time, show_time, jerk, grow_time } => Box::new(
+                GrowLaser::new(start.resolve(), end.resolve(), thickness, warning_time, show_time, jerk).grow_time(grow_time)
+            ),
+            ObstacleSpec::SlamLaser { start, end, thickness, warning_time, show_time, anticipation, jerk, shake } => Box::new(
+                SlamLaser::new(start.resolve(), end.resolve(), thickness, warning_time, show_time, anticipation, jerk, shake)
+            ),
+            ObstacleSpec::RotatableRect { center, size, rot, warning_time, show_time, grow_time } => Box::new(RotatableRect {
+                center: center.resolve(),
+                size: size.resolve(),
+                rot,
+                warning_time,
+                show_time,
+                current_time: 0.0,
+                grow_time,
+                warning_style: WarningStyle::Fill,
+            }),
+            ObstacleSpec::RotatingRect { center, size, rot, warning_time, show_time, grow_time, rpb } => Box::new(RotatingRect::default()
+                .center(center.resolve())
+                .size(size.resolve())
+                .rot(rot)
+                .warning_time(warning_time)
+                .show_time(show_time)
+                .grow_time(grow_time)
+                .rpb(rpb)
+            ),
+            ObstacleSpec::SpinningArc { center, inner_rad, outer_rad, left_angle, right_angle, rpb, warning_time, show_time } => Box::new(SpinningArc::new()
+                .center(center.resolve())
+                .inner_rad(inner_rad)
+                .outer_rad(outer_rad)
+                .left_angle(left_angle)
+                .right_angle(right_angle)
+                .rpb(rpb)
+                .warning_time(warning_time)
+                .show_time(show_time)
+            ),
+            ObstacleSpec::CenterProj { disp_amp, disp_freq, disp_phase, leave_time, warning_time, show_time, events } => Box::new(CenterProj::new()
+                .disp_amp(disp_amp)
+                .disp_freq(disp_freq)
+                .disp_phase(disp_phase)
+                .leave_time(leave_time)
+                .warning_time(warning_time)
+                .show_time(show_time)
+                .evs(events)
+                .sort()
+            ),
+            ObstacleSpec::GOLGrid { dims: (w, h), max, period, warning_time, first_warning_time, populate } => Box::new(GOLGrid::default()
+                .dims(w, h)
+                .max(max)
+                .period(period)
+                .warning_time(warning_time)
+                .first_warning_time(first_warning_time)
+                .populate(populate, rng)
+            ),
+            ObstacleSpec::Periodic { steps, interval, trail } => Box::new(Periodic::new(steps, interval, trail.build())),
+        }
+    }
+}
+
+/// The editor's handles on a spec: where it is, and moving and turning it.
+impl ObstacleSpec {
+    /// Where the obstacle is, for picking it in the editor. `None` for ones that aren't anywhere in particular.
+    pub fn position(&self) -> Option<Vec2> {
+        match self {
+            ObstacleSpec::Pellet { pos, .. } => Some(pos.resolve()),
+            ObstacleSpec::Bomb { target, .. } => Some(target.resolve()),
+            ObstacleSpec::GrowLaser { start, end, .. } | ObstacleSpec::SlamLaser { start, end, .. } => Some((start.resolve() + end.resolve()) / 2.0),
+            ObstacleSpec::RotatableRect { center, .. } | ObstacleSpec::RotatingRect { center, .. } | ObstacleSpec::SpinningArc { center, .. } => Some(center.resolve()),
+            ObstacleSpec::Periodic { trail: TrailSpec::Linear { start, .. }, .. } => Some(start.resolve()),
+            ObstacleSpec::Periodic { trail: TrailSpec::Bezier { p0, .. }, .. } => Some(p0.resolve()),
+            ObstacleSpec::Periodic { trail: TrailSpec::Chase { .. }, .. } | ObstacleSpec::CenterProj { .. } | ObstacleSpec::GOLGrid { .. } => None,
+        }
+    }
+    /// Moves every point of the obstacle by `delta` pixels.
+    pub fn translate(&mut self, delta: Vec2) {
+        match self {
+            ObstacleSpec::Pellet { pos, .. } => *pos = pos.offset(delta),
+            ObstacleSpec::Bomb { start, target, .. } | ObstacleSpec::GrowLaser { start, end: target, .. } | ObstacleSpec::SlamLaser { start, end: target, .. } => {
+                *start = start.offset(delta);
+                *target = target.offset(delta);
+            }
+            ObstacleSpec::RotatableRect { center, .. } | ObstacleSpec::RotatingRect { center, .. } | ObstacleSpec::SpinningArc { center, .. } => *center = center.offset(delta),
+            ObstacleSpec::Periodic { trail: TrailSpec::Linear { start, .. }, .. } => *start = start.offset(delta),
+            ObstacleSpec::Periodic { trail: TrailSpec::Bezier { p0, p1, p2, p3, .. }, .. } => {
+                for p in [p0, p1, p2, p3] { *p = p.offset(delta); }
+            }
+            ObstacleSpec::Periodic { trail: TrailSpec::Chase { .. }, .. } | ObstacleSpec::CenterProj { .. } | ObstacleSpec::GOLGrid { .. } => {}
+        }
+    }
+    /// Turns the obstacle by `angle` radians: rects and arcs in place, lasers around their middle.
+    pub fn rotate(&mut self, angle: f32) {
+        match self {
+            ObstacleSpec::RotatableRect { rot, .. } | ObstacleSpec::RotatingRect { rot, .. } => *rot += angle,
+            ObstacleSpec::SpinningArc { left_angle, right_angle, .. } => {
+                *left_angle += angle;
+                *right_angle += angle;
+            }
+            ObstacleSpec::GrowLaser { start, end, .. } | ObstacleSpec::SlamLaser { start, end, .. } => {
+                let (a, b) = (start.resolve(), end.resolve());
+                let mid = (a + b) / 2.0;
+                let turn = Vec2::from_angle(angle);
+                *start = start.offset(mid + turn.rotate(a - mid) - a);
+                *end = end.offset(mid + turn.rotate(b - mid) - b);
+            }
+            ObstacleSpec::Periodic { trail: TrailSpec::Linear { rot, .. }, .. } => *rot += angle,
+            _ => {}
+        }
+    }
+}
+
+/// Looks up an easing by the name it has in `Ease`, or a tunable one as `recip(k)` (see `recip_ease_k`) or `circ(period)` (see `circ_climb_period`).
+pub fn easing(name: &str) -> Option<Box<dyn Easing>> {
+    let arg = |call: &str| name.strip_prefix(call)?.strip_prefix('(')?.strip_suffix(')')?.trim().parse::<f32>().ok().filter(|a| a.is_finite());
+    if let Some(k) = arg("recip") { return Some(Box::new(recip_ease_fn(k))); }
+    if let Some(period) = arg("circ").filter(|&p| p > 0.0) { return Some(Box::new(circ_climb_fn(period))); }
+    let ease: fn(f32) -> f32 = match name {
+        "sqrt" => Ease::sqrt_ease,
+        "quad" => Ease::quad_ease,
+        "quant16th" => Ease::quant16th_ease,
+        _ => return None,
+    };
+    Some(Box::new(ease))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChartEntry {
+    pub beat: f32,
+    pub spec: ObstacleSpec,
+    /// The name of an easing from `easing`, with its argument for the tunable ones.
+    pub ease: Option<String>,
+}
+impl ChartEntry {
+    pub fn new(beat: f32, spec: ObstacleSpec) -> Self {
+        ChartEntry { beat, spec, ease: None }
+    }
+    pub fn ease(mut self, name: &str) -> Self {
+        self.ease = Some(name.to_string());
+        self
+    }
+    pub fn build(&self, rng: &mut GameRng) -> Box<dyn Obstacle> {
+        let proj = self.spec.build(rng);
+        match self.ease.as_deref().and_then(easing) {
+            Some(ease) => Box::new(Ease { ease, proj, prev: 0.0 }),
+            None => proj,
+        }
+    }
+    fn parse(line: &str) -> Result<Self, FieldError> {
+        let mut reader = Reader::new(line);
+        let beat = reader.take_while(|c| !c.is_whitespace());
+        let beat = beat.parse::<f32>().map_err(|_| FieldError::new(None, format!("expected a beat, got `{beat}`")))?;
+        let kind = reader.ident().ok_or_else(|| FieldError::new(None, "expected an obstacle"))?;
+        let mut fields = Fields(vec![]);
+        loop {
+            reader.skip_ws();
+            if reader.peek().is_none() { break; }
+            let name = reader.ident().ok_or_else(|| FieldError::new(None, format!("expected a field name at `{}`", &line[reader.pos..])))?;
+            if !reader.eat('=') { return Err(FieldError::new(Some(name), "expected `=`")); }
+            let val = reader.value().map_err(|e| FieldError::new(Some(name), e))?;
+            if fields.0.iter().any(|(n, _)| n == name) { return Err(FieldError::new(Some(name), "given twice")); }
+            fields.0.push((name.to_string(), val));
+        }
+        let ease = match fields.0.iter().position(|(n, _)| n == "ease") {
+            Some(idx) => match fields.0.remove(idx).1 {
+                Value::Ident(name) if easing(&name).is_some() => Some(name),
+                call @ Value::Call(..) if easing(&call.to_string()).is_some() => Some(call.to_string()),
+                val => return Err(FieldError::new(Some("ease"), format!("unknown easing `{val}`"))),
+            },
+            None => None,
+        };
+        let spec = ObstacleSpec::from_fields(kind, &mut fields)?;
+        if let Some((name, _)) = fields.0.first() {
+            return Err(FieldError::new(Some(name), format!("`{kind}` has no such field")));
+        }
+        Ok(ChartEntry { beat, spec, ease })
+    }
+}
+impl Display for ChartEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.beat, self.spec.kind())?;
+        for (name, val) in self.spec.fields() {
+            write!(f, " {name}={val}")?;
+        }
+        if let Some(ease) = &self.ease {
+            write!(f, " ease={ease}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What the chart list shows about a chart.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChartMeta {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub difficulty: Option<String>,
+    /// Length of the song in seconds
+    pub duration: Option<f32>,
+    pub color: Option<Color>,
+    /// Name of the built-in palette the chart is played in
+    pub palette: Option<String>,
+}
+impl ChartMeta {
+    /// Parses a metadata header. Returns false if `head` isn't one.
+    fn parse_line(&mut self, head: &str, rest: &str) -> Result<bool, String> {
+        let rest = rest.trim();
+        match head {
+            "title" => self.title = Some(rest.to_string()),
+            "artist" => self.artist = Some(rest.to_string()),
+            "difficulty" => self.difficulty = Some(rest.to_string()),
+            "duration" => self.duration = Some(rest.parse().map_err(|_| format!("expected a number of seconds, got `{rest}`"))?),
+            "color" => self.color = Some(parse_hex_color(rest).ok_or_else(|| format!("expected a color like `#ff0080`, got `{rest}`"))?),
+            "palette" if Palette::named(rest).is_some() => self.palette = Some(rest.to_string()),
+            "palette" => return Err(format!("unknown palette `{rest}`, expected one of {}", PALETTE_NAMES.join(", "))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+    fn serialize(&self) -> String {
+        let mut text = String::new();
+        let lines = [("title", &self.title), ("artist", &self.artist), ("difficulty", &self.difficulty), ("palette", &self.palette)];
+        for (head, val) in lines {
+            if let Some(val) = val { text += &format!("{head} {val}\n"); }
+        }
+        if let Some(duration) = self.duration { text += &format!("duration {duration}\n"); }
+        if let Some(c) = self.color {
+            text += &format!("color {}\n", hex_color(c));
+        }
+        text
+    }
+}
+
+fn hex_color(c: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", (c.r * 255.0).round() as u8, (c.g * 255.0).round() as u8, (c.b * 255.0).round() as u8)
+}
+
+fn parse_hex_color(text: &str) -> Option<Color> {
+    let hex = text.strip_prefix('#')?;
+    if hex.len() != 6 { return None; }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok().map(|c| c as f32 / 255.0);
+    Some(Color::new(channel(0)?, channel(2)?, channel(4)?, 1.0))
+}
+
+/// A level as data, played back with `GameState::load_chart`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chart {
+    pub meta: ChartMeta,
+    pub tempo: TempoMap,
+    pub offset: f32,
+    pub audio: String,
+    /// Seed of the run's randomness, unless the player chose one
+    pub seed: Option<u64>,
+    pub checkpoints: Vec<Checkpoint>,
+    /// The background and its color, `None` using a dim foreground color
+    pub background: Option<(BackgroundKind, Option<Color>)>,
+    /// (beat, intensity) of the background
+    pub intensity: Vec<(f32, f32)>,
+    /// (beat, palette name, beats to ease over)
+    pub palette_shifts: Vec<(f32, String, f32)>,
+    /// (beat, color, intensity, beats to fade out over)
+    pub flashes: Vec<(f32, Color, f32, f32)>,
+    /// (beat, target alpha, beats to fade over)
+    pub fades: Vec<(f32, f32, f32)>,
+    /// (beat, time scale, beats to ease over)
+    pub time_scales: Vec<(f32, f32, f32)>,
+    /// (beat, zoom amount, beats to ease back over)
+    pub zoom_pulses: Vec<(f32, f32, f32)>,
+    /// (beat, camera rotation in radians, beats to turn over)
+    pub tilts: Vec<(f32, f32, f32)>,
+    /// (beat, beats, harmless) of freezes of every obstacle
+    pub freezes: Vec<(f32, f32, bool)>,
+    /// Whether slam lasers and bombs flash the screen
+    pub impact_flashes: bool,
+    /// Multiplies the hit-stops of slams and the like, 0 turning them off
+    pub hitstop: f32,
+    pub entries: Vec<ChartEntry>,
+}
+impl Default for Chart {
+    fn default() -> Self {
+        Chart { meta: ChartMeta::default(), tempo: TempoMap::default(), offset: 0.0, audio: String::new(), seed: None, checkpoints: vec![], background: None, intensity: vec![], palette_shifts: vec![], flashes: vec![], fades: vec![], time_scales: vec![], zoom_pulses: vec![], tilts: vec![], freezes: vec![], impact_flashes: true, hitstop: 1.0, entries: vec![] }
+    }
+}
+impl Chart {
+    pub fn parse(text: &str) -> Result<Self, ChartError> {
+        Self::parse_lines(text.lines().map(Ok), false)
+    }
+    /// Parses everything but the entries, stopping at the first one.
+    pub fn parse_header(text: &str) -> Result<Self, ChartError> {
+        Self::parse_lines(text.lines().map(Ok), true)
+    }
+    /// `parse_header` on the file at `path`, without reading past the headers.
+    pub fn load_header(path: impl AsRef<Path>) -> Result<Self, ChartError> {
+        Self::parse_lines(io::BufReader::new(fs::File::open(path)?).lines(), true)
+    }
+    fn parse_lines<L: AsRef<str>>(lines: impl Iterator<Item = io::Result<L>>, header_only: bool) -> Result<Self, ChartError> {
+        let mut chart = Self::default();
+        let mut tempo = vec![];
+        for (idx, line) in lines.enumerate() {
+            let line = line?;
+            let line = line.as_ref();
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            let err = |message: String| ChartError::Syntax { line: idx + 1, field: None, message };
+            let num = |rest: &str| rest.trim().parse::<f32>().map_err(|_| err(format!("expected a number, got `{}`", rest.trim())));
+            let (head, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            match head {
+                "bpm" => {
+                    let bpm = num(rest)?;
+                    if bpm <= 0.0 { return Err(err("the tempo must be positive".to_string())); }
+                    tempo.retain(|p: &TempoPoint| p.time != 0.0);
+                    tempo.push(TempoPoint { time: 0.0, bpm, beats_per_bar: 4.0 });
+                }
+                "tempo" => {
+                    let args = rest.split_whitespace().map(num).collect::<Result<Vec<f32>, _>>()?;
+                    let (time, bpm, beats_per_bar) = match args[..] {
+                        [time, bpm] => (time, bpm, 4.0),
+                        [time, bpm, beats_per_bar] => (time, bpm, beats_per_bar),
+                        _ => return Err(err("expected `tempo <seconds> <bpm> [beats per bar]`".to_string()))
+                    };
+                    if bpm <= 0.0 || beats_per_bar <= 0.0 { return Err(err("the tempo and bar length must be positive".to_string())); }
+                    tempo.retain(|p: &TempoPoint| p.time != time);
+                    tempo.push(TempoPoint { time, bpm, beats_per_bar });
+                }
+                "offset" => chart.offset = num(rest)?,
+                "checkpoint" => {
+                    let (beat, name) = rest.trim().split_once(char::is_whitespace).unwrap_or((rest.trim(), ""));
+                    let beat = num(beat)?;
+                    let name = name.trim();
+                    chart.checkpoints.push(if name.is_empty() { Checkpoint::new(beat) } else { Checkpoint::named(beat, name) });
+                }
+                "background" => {
+                    let (kind, color) = rest.trim().split_once(char::is_whitespace).unwrap_or((rest.trim(), ""));
+                    let kind = kind.parse().map_err(err)?;
+                    let color = match color.trim() {
+                        "" => None,
+                        color => Some(parse_hex_color(color).ok_or_else(|| err(format!("expected a color like `#ff0080`, got `{color}`")))?),
+                    };
+                    chart.background = Some((kind, color));
+                }
+                "intensity" => match rest.split_whitespace().map(num).collect::<Result<Vec<f32>, _>>()?[..] {
+                    [beat, intensity] => chart.intensity.push((beat, intensity)),
+                    _ => return Err(err("expected `intensity <beat> <value>`".to_string()))
+                },
+                "palette_shift" => match rest.split_whitespace().collect::<Vec<_>>()[..] {
+                    [beat, name, beats] => {
+                        if Palette::named(name).is_none() { return Err(err(format!("unknown palette `{name}`, expected one of {}", PALETTE_NAMES.join(", ")))); }
+                        chart.palette_shifts.push((num(beat)?, name.to_string(), num(beats)?));
+                    }
+                    _ => return Err(err("expected `palette_shift <beat> <palette> <beats>`".to_string()))
+                },
+                "flash" => match rest.split_whitespace().collect::<Vec<_>>()[..] {
+                    [beat, color, intensity, decay] => {
+                        let color = parse_hex_color(color).ok_or_else(|| err(format!("expected a color like `#ff0080`, got `{color}`")))?;
+                        chart.flashes.push((num(beat)?, color, num(intensity)?.clamp(0.0, 1.0), num(decay)?));
+                    }
+                    _ => return Err(err("expected `flash <beat> <color> <intensity> <beats>`".to_string()))
+                },
+                "fade" => match rest.split_whitespace().map(num).collect::<Result<Vec<f32>, _>>()?[..] {
+                    [beat, alpha, beats] => chart.fades.push((beat, alpha.clamp(0.0, 1.0), beats)),
+                    _ => return Err(err("expected `fade <beat> <alpha> <beats>`".to_string()))
+                },
+                "time_scale" => match rest.split_whitespace().map(num).collect::<Result<Vec<f32>, _>>()?[..] {
+                    [beat, scale, beats] => chart.time_scales.push((beat, scale, beats)),
+                    _ => return Err(err("expected `time_scale <beat> <scale> <beats>`".to_string()))
+                },
+                "zoom" => match rest.split_whitespace().map(num).collect::<Result<Vec<f32>, _>>()?[..] {
+                    [beat, amount, beats] => chart.zoom_pulses.push((beat, amount, beats)),
+                    _ => return Err(err("expected `zoom <beat> <amount> <beats>`".to_string()))
+                },
+                "tilt" => match rest.split_whitespace().map(num).collect::<Result<Vec<f32>, _>>()?[..] {
+                    [beat, radians, beats] => chart.tilts.push((beat, radians, beats)),
+                    _ => return Err(err("expected `tilt <beat> <radians> <beats>`".to_string()))
+                },
+                "freeze" => match rest.split_whitespace().collect::<Vec<_>>()[..] {
+                    [beat, beats] => chart.freezes.push((num(beat)?, num(beats)?, false)),
+                    [beat, beats, "harmless"] => chart.freezes.push((num(beat)?, num(beats)?, true)),
+                    _ => return Err(err("expected `freeze <beat> <beats> [harmless]`".to_string()))
+                },
+                "impact_flashes" => chart.impact_flashes = rest.trim().parse().map_err(|_| err(format!("expected `true` or `false`, got `{}`", rest.trim())))?,
+                "hitstop" => chart.hitstop = num(rest)?.max(0.0),
+                "audio" => chart.audio = rest.trim().to_string(),
+                "seed" => chart.seed = Some(rest.trim().parse().map_err(|_| err(format!("expected a seed, got `{}`", rest.trim())))?),
+                _ if chart.meta.parse_line(head, rest).map_err(err)? => {}
+                _ if header_only => break,
+                _ => chart.entries.push(ChartEntry::parse(line).map_err(|e| e.at(idx + 1))?),
+            }
+        }
+        if !tempo.is_empty() {
+            chart.tempo = TempoMap::new(tempo);
+        }
+        Ok(chart)
+    }
+    pub fn serialize(&self) -> String {
+        let mut text = self.meta.serialize();
+        text += &match self.tempo.points() {
+            [TempoPoint { time, bpm, beats_per_bar }] if *time == 0.0 && *beats_per_bar == 4.0 => format!("bpm {bpm}\n"),
+            points => points.iter().map(|p| format!("tempo {} {} {}\n", p.time, p.bpm, p.beats_per_bar)).collect::<String>()
+        };
+        text += &format!("offset {}\naudio {}\n", self.offset, self.audio);
+        if let Some(seed) = self.seed {
+            text += &format!("seed {seed}\n");
+        }
+        if !self.impact_flashes {
+            text += "impact_flashes false\n";
+        }
+        if self.hitstop != 1.0 {
+            text += &format!("hitstop {}\n", self.hitstop);
+        }
+        for c in &self.checkpoints {
+            text += &format!("checkpoint {}", c.beat);
+            if let Some(name) = &c.name { text += &format!(" {name}"); }
+            text += "\n";
+        }
+        if let Some((kind, color)) = self.background {
+            text += &format!("background {kind}");
+            if let Some(c) = color { text += &format!(" {}", hex_color(c)); }
+            text += "\n";
+        }
+        for (beat, intensity) in &self.intensity {
+            text += &format!("intensity {beat} {intensity}\n");
+        }
+        for (beat, name, beats) in &self.palette_shifts {
+            text += &format!("palette_shift {beat} {name} {beats}\n");
+        }
+        for (beat, color, intensity, decay) in &self.flashes {
+            text += &format!("flash {beat} {} {intensity} {decay}\n", hex_color(*color));
+        }
+        for (beat, alpha, beats) in &self.fades {
+            text += &format!("fade {beat} {alpha} {beats}\n");
+        }
+        for (beat, scale, beats) in &self.time_scales {
+            text += &format!("time_scale {beat} {scale} {beats}\n");
+        }
+        for (beat, amount, beats) in &self.zoom_pulses {
+            text += &format!("zoom {beat} {amount} {beats}\n");
+        }
+        for (beat, radians, beats) in &self.tilts {
+            text += &format!("tilt {beat} {radians} {beats}\n");
+        }
+        for (beat, beats, harmless) in &self.freezes {
+            text += &format!("freeze {beat} {beats}{}\n", if *harmless { " harmless" } else { "" });
+        }
+        for entry in &self.entries {
+            text += &format!("{entry}\n");
+        }
+        text
+    }
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ChartError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ChartError> {
+        fs::write(path, self.serialize())?;
+        Ok(())
+    }
+    /// The checkpoints, plus one at the start of the song if there's none there, so every chart can be respawned in.
+    pub fn checkpoints_with_start(&self) -> Vec<Checkpoint> {
+        let mut checkpoints = self.checkpoints.clone();
+        if !checkpoints.iter().any(|c| c.beat <= self.offset) {
+            checkpoints.insert(0, Checkpoint::named(self.offset, "start"));
+        }
+        checkpoints
+    }
+    /// (beat, category) of every event in `events`, sorted by beat, for the dev timeline.
+    pub fn timeline(&self) -> Vec<(f32, EventCategory)> {
+        let effects = self.intensity.iter().map(|e| e.0)
+            .chain(self.palette_shifts.iter().map(|e| e.0))
+            .chain(self.flashes.iter().map(|e| e.0))
+            .chain(self.fades.iter().map(|e| e.0))
+            .chain(self.time_scales.iter().map(|e| e.0))
+            .chain(self.zoom_pulses.iter().map(|e| e.0))
+            .chain(self.tilts.iter().map(|e| e.0))
+            .chain(self.freezes.iter().map(|e| e.0))
+            .map(|beat| (beat, EventCategory::Effect));
+        let mut timeline = self.entries.iter().map(|e| (e.beat, EventCategory::of_kind(e.spec.kind()))).chain(effects).collect::<Vec<_>>();
+        timeline.sort_by(|a, b| a.0.total_cmp(&b.0));
+        timeline
+    }
+    /// One event per entry, spawning its obstacle at its beat, and one per background intensity change, palette shift, flash, fade, time scale change, zoom, tilt and freeze.
+    pub fn events(&self) -> Vec<GSEvent> {
+        let intensity = self.intensity.iter().map(|&(beat, intensity)| GSEvent::new(beat, move |gs: &mut UpdateAccumulator, _| {
+            gs.background_intensity(intensity);
+        }));
+        let palette_shifts = self.palette_shifts.iter().filter_map(|(beat, name, beats)| {
+            let (palette, beats) = (Palette::named(name)?, *beats);
+            Some(GSEvent::new(*beat, move |gs: &mut UpdateAccumulator, _| gs.palette_shift(palette, beats)))
+        });
+        let flashes = self.flashes.iter().map(|&(beat, color, intensity, decay)| GSEvent::new(beat, move |gs: &mut UpdateAccumulator, _| {
+            gs.flash(color, intensity, decay);
+        }));
+        let fades = self.fades.iter().map(|&(beat, alpha, beats)| GSEvent::new(beat, move |gs: &mut UpdateAccumulator, _| {
+            gs.fade(alpha, beats);
+        }));
+        let time_scales = self.time_scales.iter().map(|&(beat, scale, beats)| GSEvent::new(beat, move |gs: &mut UpdateAccumulator, _| {
+            gs.time_scale(scale, beats);
+        }));
+        let zoom_pulses = self.zoom_pulses.iter().map(|&(beat, amount, beats)| GSEvent::new(beat, move |gs: &mut UpdateAccumulator, _| {
+            gs.zoom_pulse(amount, beats);
+        }));
+        let tilts = self.tilts.iter().map(|&(beat, radians, beats)| GSEvent::new(beat, move |gs: &mut UpdateAccumulator, _| {
+            gs.cam_rotate(radians, beats);
+        }));
+        let freezes = self.freezes.iter().map(|&(beat, beats, harmless)| GSEvent::new(beat, move |gs: &mut UpdateAccumulator, _| {
+            gs.freeze_all(beats, harmless);
+        }));
+        self.entries.iter().cloned().map(|entry| GSEvent::new(entry.beat, move |gs: &mut UpdateAccumulator, _| {
+            let time = gs.time();
+            let obst = entry.build(gs.rng());
+            gs.obstacle(Obst::new(obst, time).charted());
+        })).chain(intensity).chain(palette_shifts).chain(flashes).chain(fades).chain(time_scales).chain(zoom_pulses).chain(tilts).chain(freezes).collect()
+    }
+}
+
+/// Seconds between checks of a watched chart's modification time
+pub const CHART_POLL_SECS: f32 = 1.0;
+
+/// Where playback rewinds to when a watched chart is reloaded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReloadAnchor {
+    /// The start of the current measure, of this many beats
+    Measure(f32),
+    /// The current beat
+    KeepBeat,
+}
+impl Default for ReloadAnchor {
+    fn default() -> Self { ReloadAnchor::Measure(4.0) }
+}
+impl ReloadAnchor {
+    pub fn rewind(self, beat: f32) -> f32 {
+        match self {
+            ReloadAnchor::Measure(len) => (beat / len).floor() * len,
+            ReloadAnchor::KeepBeat => beat,
+        }
+    }
+}
+
+/// Polls a chart file for changes, for hot-reloading while the chart plays.
+pub struct ChartWatch {
+    pub path: PathBuf,
+    modified: Option<SystemTime>,
+    since_poll: f32,
+    /// Why the last change couldn't be loaded, shown until a change loads fine.
+    pub error: Option<String>,
+}
+impl ChartWatch {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+        ChartWatch { path, modified, since_poll: 0.0, error: None }
+    }
+    /// Returns the re-parsed chart if the file changed since the last poll and parses.
+    pub fn poll(&mut self, frame_time: f32) -> Option<Chart> {
+        self.since_poll += frame_time;
+        if self.since_poll < CHART_POLL_SECS { return None; }
+        self.since_poll = 0.0;
+        let modified = fs::metadata(&self.path).and_then(|m| m.modified()).ok()?;
+        if self.modified == Some(modified) { return None; }
+        self.modified = Some(modified);
+        match Chart::load(&self.path) {
+            Ok(chart) => {
+                self.error = None;
+                Some(chart)
+            }
+            Err(e) => {
+                self.error = Some(format!("{}: {e}", self.path.display()));
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::simulate;
+
+    /// A chart with every kind of obstacle in it.
+    pub(super) fn chart() -> Chart {
+        let px = |x: f32, y: f32| ChartVec::px(vec2(x, y));
+        let screen = |x: f32, y: f32| ChartVec::screen(vec2(x, y));
+        let mut chart = Chart {
+            meta: ChartMeta { title: Some("Round Trip".to_string()), difficulty: Some("Hard".to_string()), duration: Some(95.0), ..ChartMeta::default() },
+            tempo: TempoMap::new(vec![TempoPoint { time: 0.0, bpm: 140.0, beats_per_bar: 4.0 }, TempoPoint { time: 30.5, bpm: 160.0, beats_per_bar: 3.0 }]),
+            offset: 0.25,
+            audio: "assets/song.wav".to_string(),
+            seed: Some(906),
+            checkpoints: vec![Checkpoint::named(32.0, "First drop")],
+            flashes: vec![(64.0, Color::new(1.0, 1.0, 1.0, 1.0), 0.5, 1.0)],
+            time_scales: vec![(96.0, 0.5, 2.0)],
+            ..Chart::default()
+        };
+        chart.entries = vec![
+            ChartEntry::new(0.0, ObstacleSpec::Pellet { pos: screen(0.5, 0.0), vel: px(0.0, 200.0), rad: 10.0 }),
+            ChartEntry::new(1.5, ObstacleSpec::Bomb { start: px(100.0, 100.0), target: screen(0.5, 0.5), lifetime: 2.0, pellets: 12, pellet_vel: 300.0, pellet_rad: 8.0 }),
+            ChartEntry::new(4.0, ObstacleSpec::GrowLaser { start: screen(0.0, 0.5), end: screen(1.0, 0.5), thickness: 40.0, warning_time: 2.0, show_time: 1.0, jerk: Vec2::ZERO, grow_time: 0.25 }).ease("quad"),
+            ChartEntry::new(6.25, ObstacleSpec::SlamLaser { start: px(0.0, 0.0), end: px(800.0, 450.0), thickness: 30.0, warning_time: 1.0, show_time: 0.5, anticipation: 0.25, jerk: vec2(5.0, 0.0), shake: 2.0 }),
+            ChartEntry::new(8.0, ObstacleSpec::RotatableRect { center: screen(0.25, 0.75), size: px(200.0, 50.0), rot: 0.5, warning_time: 1.0, show_time: 2.0, grow_time: 0.25 }).ease("recip(4)"),
+            ChartEntry::new(8.0, ObstacleSpec::RotatingRect { center: screen(0.5, 0.5), size: px(600.0, 40.0), rot: 0.0, warning_time: 8.0, show_time: 4.0, grow_time: 0.25, rpb: 0.125 }),
+            ChartEntry::new(12.0, ObstacleSpec::SpinningArc { center: screen(0.5, 0.5), inner_rad: 100.0, outer_rad: 150.0, left_angle: 0.0, right_angle: 1.5, rpb: 0.25, warning_time: 1.0, show_time: 4.0 }),
+            ChartEntry::new(16.0, ObstacleSpec::CenterProj {
+                disp_amp: 75.0, disp_freq: Vec2::ONE, disp_phase: Vec2::ZERO, leave_time: 0.25, warning_time: 1.0, show_time: 8.0,
+                events: vec![(0.0, CenterEvent::Pulse), (1.0, CenterEvent::Lasers(8, 0.0))],
+            }),
+            ChartEntry::new(20.0, ObstacleSpec::GOLGrid { dims: (16, 9), max: 8, period: 1.0, warning_time: 0.0, first_warning_time: 1.0, populate: 20 }),
+            ChartEntry::new(24.0, ObstacleSpec::Periodic { steps: 8, interval: 0.5, trail: TrailSpec::Linear {
+                rect_life: 2.0, warning_time: 1.0, grow_time: 0.25, start: screen(0.1, 0.5), delta: screen(0.1, 0.0), scale: px(40.0, 40.0), rot: 0.0,
+            } }),
+            ChartEntry::new(28.0, ObstacleSpec::Periodic { steps: 4, interval: 1.0, trail: TrailSpec::Bezier {
+                rect_life: 2.0, warning_time: 1.0, grow_time: 0.25, p0: px(0.0, 0.0), p1: screen(0.25, 1.0), p2: screen(0.75, 0.0), p3: screen(1.0, 1.0), size: px(30.0, 30.0), align_to_tangent: true,
+            } }),
+        ];
+        chart
+    }
+
+    /// (beat, kind, anchor) of everything each of `chart`'s events spawns, in order.
+    fn spawns(chart: &Chart) -> Vec<(f32, &'static str, Option<Vec2>)> {
+        chart.events().iter().flat_map(|ev| {
+            simulate(std::slice::from_ref(ev), ev.0, ev.0, GameRng::new(906)).into_iter()
+                .map(|o| (ev.0, o.obstacle.name(), o.obstacle.anchor()))
+                .collect::<Vec<_>>()
+        }).collect()
+    }
+
+    #[test]
+    fn serialized_chart_parses_back() {
+        let chart = chart();
+        let text = chart.serialize();
+        let parsed = Chart::parse(&text).unwrap_or_else(|e| panic!("{e}\n{text}"));
+        assert_eq!(parsed, chart);
+        let (before, after) = (spawns(&chart), spawns(&parsed));
+        assert_eq!(before.len(), chart.entries.len());
+        assert_eq!(before, after);
+        // and the text is stable from there on
+        assert_eq!(parsed.serialize(), text);
+    }
+
+    #[test]
+    fn errors_point_at_the_line_and_field() {
+        let text = "bpm 120\n0 Pellet pos=(0, 0) vel=(0, 1)\n";
+        match Chart::parse(text) {
+            Err(ChartError::Syntax { line: 2, field: Some(field), .. }) => assert_eq!(field, "rad"),
+            other => panic!("expected a missing `rad` on line 2, got {other:?}"),
+        }
+        let text = "bpm 120\n0 Pellet pos=(0, 0) vel=(0, 1) rad=5 speed=2\n";
+        assert!(matches!(Chart::parse(text), Err(ChartError::Syntax { line: 2, field: Some(f), .. }) if f == "speed"));
+    }
+
+
+    /// A chart leaning on the run's randomness: a populated Game of Life grid and messy pellet bursts.
+    const RANDOM_CHART: &str = "bpm 120
+0 GOLGrid populate=150 period=0.5 first_warning_time=0.5
+0 CenterProj show_time=16 events=[(1, MessyPellets(24, 8, 100, 400)), (3, MessyPellets(24, 8, 100, 400)), (5, MessyPellets(24, 8, 100, 400))]
+";
+
+    /// (kind, anchor) of every obstacle alive at each of a few beats into a run seeded with `seed`.
+    fn run(seed: u64) -> Vec<Vec<(&'static str, Option<Vec2>)>> {
+        let events = Chart::parse(RANDOM_CHART).unwrap().events();
+        [2.0, 4.0, 6.0, 8.0].into_iter()
+            .map(|beat| simulate(&events, 0.0, beat, GameRng::new(seed)).iter().map(|o| (o.obstacle.name(), o.obstacle.anchor())).collect())
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_run() {
+        let (first, second) = (run(908), run(908));
+        assert!(first.iter().all(|alive| alive.len() > 2), "the chart should have something going on");
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn different_seed_different_run() {
+        let (first, second) = (run(908), run(909));
+        assert_ne!(first, second);
+    }
+
+
+    #[test]
+    fn tunable_easings_by_name() {
+        let run = |name: &str, t: f32| easing(name).unwrap_or_else(|| panic!("no {name}")).run(t);
+        assert_eq!(run("recip(3)", 0.4), crate::utils::recip_ease_k(0.4, 3.0));
+        assert_eq!(run("recip( 0.5 )", 2.0), crate::utils::recip_ease_k(2.0, 0.5));
+        assert_eq!(run("circ(2)", 1.3), crate::utils::circ_climb_period(1.3, 2.0));
+        assert_eq!(run("sqrt", 0.25), 0.5);
+        for name in ["recip", "recip()", "recip(x)", "recip(inf)", "circ(0)", "circ(-1)", "circ(NaN)", "quadratic"] {
+            assert!(easing(name).is_none(), "{name}");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use super::*;
+
+    /// Serializes `val` and reads it back, which has to give `val` again.
+    fn round_trip<T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug>(val: T) {
+        let json = serde_json::to_string(&val).unwrap();
+        assert_eq!(serde_json::from_str::<T>(&json).unwrap(), val, "{json}");
+    }
+
+    #[test]
+    fn chart_entries_round_trip() {
+        let entries = super::tests::chart().entries;
+        let kinds = entries.iter().map(|e| e.spec.kind()).collect::<std::collections::HashSet<_>>();
+        // every `ObstacleSpec` there is
+        assert_eq!(kinds.len(), 10);
+        for entry in entries {
+            round_trip(entry.spec.clone());
+            round_trip(entry);
+        }
+    }
+
+    #[test]
+    fn coords_round_trip() {
+        round_trip(Coord::Px(-12.5));
+        round_trip(Coord::Screen(0.75));
+        round_trip(ChartVec(Coord::Px(3.0), Coord::Screen(0.1)));
+    }
+
+    fn trails() -> [TrailSpec; 3] {
+        let (px, screen) = (ChartVec::px(vec2(40.0, 20.0)), ChartVec::screen(vec2(0.25, 0.5)));
+        [
+            TrailSpec::Linear { rect_life: 2.0, warning_time: 1.0, grow_time: 0.25, start: screen, delta: px, scale: px, rot: 0.3 },
+            TrailSpec::Chase { rect_life: 1.5, warning_time: 0.5, grow_time: 0.1, size: px, lag_beats: 0.75 },
+            TrailSpec::Bezier { rect_life: 2.0, warning_time: 1.0, grow_time: 0.25, p0: px, p1: screen, p2: px, p3: screen, size: px, align_to_tangent: true },
+        ]
+    }
+
+    #[test]
+    fn trails_round_trip() {
+        trails().into_iter().for_each(round_trip);
+    }
+
+    #[test]
+    fn periodics_round_trip() {
+        let kills = [OnEarlyKill::Drop, OnEarlyKill::FlushRemaining, OnEarlyKill::FlushWithin(1.5)];
+        let catch_ups = [CatchUp::Burst, CatchUp::Spread, CatchUp::Skip];
+        for (i, trail) in trails().into_iter().enumerate() {
+            for (on_early_kill, catch_up) in kills.into_iter().zip(catch_ups) {
+                round_trip(PeriodicSpec { trail, time_mod: 1.25, time_div: i + 3, interval: 0.5, max_steps: 12, on_early_kill, catch_up });
+            }
+        }
+    }
+
+    #[test]
+    fn bombs_round_trip() {
+        round_trip(BombSpawner::Pellets);
+        let bomb = Bomb::new(vec2(10.0, 20.0), vec2(400.0, 300.0), 2.0, 12, 300.0, 8.0, Box::new(Bomb::pellet_spawner));
+        round_trip(BombSpec::of(&bomb, BombSpawner::Pellets));
+        // partway there too
+        round_trip(BombSpec { time: 0.75, ..BombSpec::of(&bomb, BombSpawner::Pellets) });
+    }
+
+    #[test]
+    fn center_events_round_trip() {
+        for event in [
+            CenterEvent::Pulse, CenterEvent::SPulse(2.0), CenterEvent::Lasers(8, 0.5), CenterEvent::Pellets(16, 200.0, 8.0, 0.25, true),
+            CenterEvent::MessyPellets(20, 6.0, 100.0, 300.0), CenterEvent::PelletSpinner(4, 150.0, 6.0, 0.0, 8.0),
+        ] {
+            round_trip(event);
+        }
+    }
+}
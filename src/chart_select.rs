@@ -0,0 +1,114 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use macroquad::{prelude::*, rand::gen_range};
+
+use crate::{chart::Chart, save::{SaveData, chart_key}, utils::{acmul, screen_width, screen_height}, palette::Palette};
+
+/// Where the chart list looks for charts
+pub const CHARTS_DIR: &str = "charts";
+pub const CHART_EXTENSION: &str = "chart";
+const ROW_HEIGHT: f32 = 80.0;
+
+/// A chart file in the chart list.
+pub struct ChartListing {
+    pub path: PathBuf,
+    /// The chart without its entries, or why it couldn't be read
+    pub header: Result<Chart, String>,
+}
+impl ChartListing {
+    /// The title, or the file name for charts without one.
+    pub fn name(&self) -> String {
+        let stem = || self.path.file_stem().map_or_else(|| self.path.display().to_string(), |s| s.to_string_lossy().into_owned());
+        self.header.as_ref().ok().and_then(|c| c.meta.title.clone()).unwrap_or_else(stem)
+    }
+}
+
+/// The list of charts to pick a run from, with a random pick on top.
+pub struct ChartSelect {
+    pub listings: Vec<ChartListing>,
+    /// 0 is the random pick, the listings follow
+    pub selected: usize,
+}
+impl ChartSelect {
+    /// Lists the charts in `CHARTS_DIR`, selecting `last` if it's among them.
+    pub fn open(last: Option<&str>) -> Self {
+        Self::scan(CHARTS_DIR, last)
+    }
+    /// Lists the charts in `dir` by file name. Only their headers are read, so this stays quick with big charts.
+    pub fn scan(dir: impl AsRef<Path>, last: Option<&str>) -> Self {
+        let mut paths = match fs::read_dir(&dir) {
+            Ok(entries) => entries.filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == CHART_EXTENSION))
+                .collect::<Vec<_>>(),
+            Err(e) => {
+                println!("couldn't list the charts in {}: {e}", dir.as_ref().display());
+                vec![]
+            }
+        };
+        paths.sort();
+        let listings = paths.into_iter().map(|path| {
+            let header = Chart::load_header(&path).map_err(|e| e.to_string());
+            ChartListing { path, header }
+        }).collect::<Vec<_>>();
+        let selected = last.and_then(|last| listings.iter().position(|l| l.path == Path::new(last))).map_or(0, |i| i + 1);
+        ChartSelect { listings, selected }
+    }
+    /// Moves the selection `by` rows, wrapping around.
+    pub fn step(&mut self, by: isize) {
+        self.selected = (self.selected as isize + by).rem_euclid(self.listings.len() as isize + 1) as usize;
+    }
+    /// The chart to play for the selection, if it can be read. The random pick picks from every readable chart.
+    pub fn chosen(&self) -> Option<&Path> {
+        if self.selected == 0 {
+            let readable = self.listings.iter().filter(|l| l.header.is_ok()).collect::<Vec<_>>();
+            if readable.is_empty() { return None; }
+            return Some(&readable[gen_range(0, readable.len())].path);
+        }
+        self.listings.get(self.selected - 1).filter(|l| l.header.is_ok()).map(|l| l.path.as_path())
+    }
+    /// The chart file of the selected row, none for the random pick.
+    pub fn selected_path(&self) -> Option<&Path> {
+        self.selected.checked_sub(1).and_then(|i| self.listings.get(i)).map(|l| l.path.as_path())
+    }
+    pub fn draw(&self, save: &SaveData, palette: &Palette) {
+        clear_background(palette.background);
+        let center_y = screen_height() / 2.0;
+        // the selection stays in the middle
+        let row_y = |row: usize| center_y + (row as f32 - self.selected as f32) * ROW_HEIGHT;
+        let width = screen_width() - 40.0;
+        for row in 0..=self.listings.len() {
+            let y = row_y(row);
+            if y < -ROW_HEIGHT || y > screen_height() + ROW_HEIGHT { continue; }
+            let highlight = if row == self.selected { 0.3 } else { 0.1 };
+            draw_rectangle(20.0, y - ROW_HEIGHT / 2.0 + 5.0, width, ROW_HEIGHT - 10.0, acmul(palette.text, highlight));
+            if row == 0 {
+                let count = self.listings.iter().filter(|l| l.header.is_ok()).count();
+                draw_text(&format!("Random chart ({count} charts)"), 40.0, y + 10.0, 36.0, palette.text);
+                continue;
+            }
+            let listing = &self.listings[row - 1];
+            match &listing.header {
+                Ok(chart) => {
+                    let meta = &chart.meta;
+                    draw_rectangle(20.0, y - ROW_HEIGHT / 2.0 + 5.0, 10.0, ROW_HEIGHT - 10.0, meta.color.unwrap_or(palette.text));
+                    draw_text(&listing.name(), 40.0, y, 36.0, palette.text);
+                    let duration = meta.duration.map_or("-:--".to_string(), |d| format!("{}:{:02}", (d / 60.0) as u32, d as u32 % 60));
+                    let details = format!("{}  {}  {duration}", meta.artist.as_deref().unwrap_or("unknown artist"), meta.difficulty.as_deref().unwrap_or(""));
+                    draw_text(&details, 40.0, y + 26.0, 22.0, acmul(palette.text, 0.6));
+                    if let Some(best) = save.best(&chart_key(chart)) {
+                        let text = format!("best {} ({})", best.score, best.grade);
+                        let text_width = measure_text(&text, None, 28, 1.0).width;
+                        draw_text(&text, width - text_width, y + 10.0, 28.0, palette.accent);
+                    }
+                }
+                Err(e) => {
+                    draw_text(&listing.name(), 40.0, y, 36.0, acmul(palette.text, 0.35));
+                    draw_text(e, 40.0, y + 26.0, 22.0, acmul(palette.warning, 0.6));
+                }
+            }
+        }
+        if self.listings.is_empty() {
+            draw_text(&format!("no .{CHART_EXTENSION} files in {CHARTS_DIR}/"), 40.0, row_y(1), 28.0, acmul(palette.text, 0.5));
+        }
+    }
+}
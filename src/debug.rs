@@ -0,0 +1,142 @@
+//! The F3 overlay: frame times, what's alive and what the game loop did, for when a chart's frame rate dives.\
+//! Compiled into every build, it does nothing while disabled.
+
+use macroquad::prelude::*;
+
+use crate::{game_objects::{Obst, Player, HITBOX_THICKNESS}, utils::{RingBuffer, acmul, screen_height}};
+
+/// Frames the frame time graph covers
+pub const FRAME_SAMPLES: usize = 120;
+/// Obstacle types listed in the breakdown, the most numerous first
+const BREAKDOWN_ROWS: usize = 12;
+/// Pixels of graph per millisecond
+const GRAPH_SCALE: f32 = 3.0;
+/// What hitboxes that can hit now are outlined in
+const HITBOX_COLOR: Color = Color::new(1.0, 0.2, 0.3, 0.9);
+const PLAYER_HITBOX_COLOR: Color = Color::new(0.2, 1.0, 0.4, 0.9);
+
+/// What one update of the game loop did.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FrameCounters {
+    /// Obstacles asked to spawn, including dropped ones
+    pub spawns: usize,
+    /// Obstacles added to the level at the end of the update
+    pub pending: usize,
+    /// Events run
+    pub events: usize,
+}
+
+/// Everything the overlay shows that the game loop already has.
+pub struct DebugInfo<'a> {
+    pub obsts: &'a [Obst],
+    pub players: &'a [Player],
+    pub counters: FrameCounters,
+    /// Obstacles scheduled for later beats
+    pub deferred: usize,
+    pub beat: f32,
+    pub measure: i32,
+    pub trauma: f32,
+    pub jerk: f32,
+    pub seed: u64,
+}
+
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub enabled: bool,
+    /// Whether the collision shapes of the obstacles and players are drawn over them, F4 while the overlay is up
+    pub hitboxes: bool,
+    /// Seconds of each recent frame
+    frame_times: RingBuffer<f32, FRAME_SAMPLES>,
+    last_positions: Vec<Vec2>,
+    /// Pixels per second of each player over the last frame
+    speeds: Vec<f32>,
+}
+impl DebugOverlay {
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.frame_times.clear();
+        self.last_positions.clear();
+    }
+    pub fn toggle_hitboxes(&mut self) {
+        self.hitboxes = !self.hitboxes;
+    }
+    /// Outlines what actually collides over what's drawn, in the world's camera: each obstacle's `draw_debug`,
+    /// and each player's radius with the graze radius `graze_margin` past it. Nothing unless the overlay and hitboxes are on.
+    pub fn draw_hitboxes(&self, obsts: &[Obst], players: &[Player], graze_margin: f32, offset: Vec2) {
+        if !self.enabled || !self.hitboxes { return; }
+        for obst in obsts {
+            obst.obstacle.draw_debug(HITBOX_COLOR, offset);
+        }
+        for player in players.iter().filter(|p| p.alive()) {
+            let pos = player.pos + offset;
+            draw_circle_lines(pos.x, pos.y, player.rad, HITBOX_THICKNESS, PLAYER_HITBOX_COLOR);
+            if graze_margin > 0.0 {
+                draw_circle_lines(pos.x, pos.y, player.rad + graze_margin, HITBOX_THICKNESS, acmul(PLAYER_HITBOX_COLOR, 0.4));
+            }
+        }
+    }
+    /// Records a frame, only while enabled.
+    pub fn record(&mut self, frame_time: f32, players: &[Player]) {
+        if !self.enabled { return; }
+        self.frame_times.push(frame_time);
+        self.speeds.resize(players.len(), 0.0);
+        for (i, player) in players.iter().enumerate() {
+            if let Some(&last) = self.last_positions.get(i) {
+                self.speeds[i] = if frame_time > 0.0 { last.distance(player.pos) / frame_time } else { 0.0 };
+            }
+        }
+        self.last_positions.clear();
+        self.last_positions.extend(players.iter().map(|p| p.pos));
+    }
+    /// Draws in the bottom left corner, builds nothing while disabled.
+    pub fn draw(&self, info: &DebugInfo) {
+        if !self.enabled { return; }
+        let mut counts: Vec<(&'static str, usize)> = vec![];
+        for obst in info.obsts {
+            let name = obst.obstacle.name();
+            match counts.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((name, 1)),
+            }
+        }
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        let mut lines = vec![
+            format!("beat {:.2}  measure {}  seed {}", info.beat, info.measure, info.seed),
+            format!("obstacles {}  spawns {}  added {}  events {}", info.obsts.len(), info.counters.spawns, info.counters.pending, info.counters.events),
+            format!("scheduled {}", info.deferred),
+            format!("trauma {:.2}  jerk {:.1}", info.trauma, info.jerk),
+            format!("hitboxes {} (F4)", if self.hitboxes { "on" } else { "off" }),
+        ];
+        for (i, player) in info.players.iter().enumerate() {
+            let speed = self.speeds.get(i).copied().unwrap_or(0.0);
+            lines.push(format!("player {} at ({:.0}, {:.0})  {speed:.0} px/s", i + 1, player.pos.x, player.pos.y));
+        }
+        lines.extend(counts.iter().take(BREAKDOWN_ROWS).map(|(name, count)| format!("  {count:>5} {name}")));
+        if counts.len() > BREAKDOWN_ROWS {
+            lines.push(format!("  ...and {} more types", counts.len() - BREAKDOWN_ROWS));
+        }
+        let graph_height = 100.0;
+        let line_height = 18.0;
+        let width = 360.0;
+        let height = graph_height + lines.len() as f32 * line_height + 30.0;
+        let (x, y) = (10.0, screen_height() - height - 10.0);
+        draw_rectangle(x, y, width, height, Color::new(0.0, 0.0, 0.0, 0.75));
+        // a bar per frame, the line at 60 fps
+        let bottom = y + 10.0 + graph_height;
+        let bar_width = (width - 20.0) / FRAME_SAMPLES as f32;
+        for (i, secs) in self.frame_times.iter().enumerate() {
+            let ms = secs * 1000.0;
+            let bar = (ms * GRAPH_SCALE).min(graph_height);
+            let color = if ms > 1000.0 / 30.0 { RED } else if ms > 1000.0 / 55.0 { YELLOW } else { GREEN };
+            draw_rectangle(x + 10.0 + i as f32 * bar_width, bottom - bar, bar_width, bar, color);
+        }
+        let target = bottom - 1000.0 / 60.0 * GRAPH_SCALE;
+        draw_line(x + 10.0, target, x + width - 10.0, target, 1.0, acmul(WHITE, 0.5));
+        let worst = self.frame_times.iter().fold(0.0, f32::max) * 1000.0;
+        let latest = self.frame_times.iter().last().unwrap_or(0.0) * 1000.0;
+        draw_text(&format!("{latest:.1} ms  worst {worst:.1} ms"), x + 10.0, y + 24.0, 18.0, WHITE);
+        for (i, line) in lines.iter().enumerate() {
+            draw_text(line, x + 10.0, bottom + 20.0 + i as f32 * line_height, 18.0, WHITE);
+        }
+    }
+}
@@ -0,0 +1,254 @@
+//! A small chart editor: placing obstacles from templates, dragging them around and nudging their beats.\
+//! The world stands still at the cursor, showing what the chart has alive there. Fine tuning is still done in the file.
+
+use std::{f32::consts::PI, path::PathBuf};
+
+use macroquad::prelude::*;
+
+use crate::{chart::{Chart, ChartEntry, ChartError, ChartVec, ObstacleSpec}, game::simulate, game_objects::Obst, beat::snap, palette::Palette, timeline, utils::{acmul, mouse_position, GameRng}};
+
+/// Most edits undo remembers
+pub const UNDO_LIMIT: usize = 256;
+/// Beats before the cursor the preview plays from
+const PREVIEW_BEATS: f32 = 8.0;
+/// Entries this many beats around the cursor get a handle
+const HANDLE_BEATS: f32 = 1.0;
+/// Pixels from a handle a click still picks it
+const PICK_RADIUS: f32 = 16.0;
+const ROTATE_STEP: f32 = PI / 12.0;
+/// Divisions of a beat the cursor can snap to
+const SNAPS: [f32; 6] = [1.0, 2.0, 3.0, 4.0, 8.0, 16.0];
+
+/// What a click places, each centered on its position.
+pub fn templates() -> Vec<(&'static str, ObstacleSpec)> {
+    let px = |x: f32, y: f32| ChartVec::px(vec2(x, y));
+    vec![
+        ("Pellet", ObstacleSpec::Pellet { pos: px(0.0, 0.0), vel: px(0.0, 200.0), rad: 10.0 }),
+        ("Bomb", ObstacleSpec::Bomb { start: px(0.0, -400.0), target: px(0.0, 0.0), lifetime: 2.0, pellets: 12, pellet_vel: 200.0, pellet_rad: 10.0 }),
+        ("GrowLaser", ObstacleSpec::GrowLaser {
+            start: px(-400.0, 0.0), end: px(400.0, 0.0), thickness: 40.0, warning_time: 2.0, show_time: 1.0, jerk: Vec2::ZERO, grow_time: 0.25,
+        }),
+        ("SlamLaser", ObstacleSpec::SlamLaser {
+            start: px(-400.0, 0.0), end: px(400.0, 0.0), thickness: 40.0, warning_time: 2.0, show_time: 1.0, anticipation: 0.25, jerk: Vec2::ZERO, shake: 0.0,
+        }),
+        ("RotatableRect", ObstacleSpec::RotatableRect { center: px(0.0, 0.0), size: px(120.0, 120.0), rot: 0.0, warning_time: 2.0, show_time: 1.0, grow_time: 0.25 }),
+        ("RotatingRect", ObstacleSpec::RotatingRect {
+            center: px(0.0, 0.0), size: px(400.0, 40.0), rot: 0.0, warning_time: 2.0, show_time: 4.0, grow_time: 0.25, rpb: 0.25,
+        }),
+        ("SpinningArc", ObstacleSpec::SpinningArc {
+            center: px(0.0, 0.0), inner_rad: 100.0, outer_rad: 140.0, left_angle: 0.0, right_angle: PI, rpb: 0.25, warning_time: 1.0, show_time: 4.0,
+        }),
+    ]
+}
+
+pub struct Editor {
+    pub chart: Chart,
+    /// Where the chart is saved to
+    pub path: PathBuf,
+    /// The beat the world stands still at, and new obstacles are placed at
+    pub beat: f32,
+    /// Index into `SNAPS`
+    snap: usize,
+    /// Index into `templates`
+    template: usize,
+    /// Index into the chart's entries
+    pub selected: Option<usize>,
+    /// Where the mouse was last frame while dragging, and whether the drag moved anything yet
+    drag: Option<(Vec2, bool)>,
+    /// The entries before each edit, the latest last
+    undo: Vec<Vec<ChartEntry>>,
+    redo: Vec<Vec<ChartEntry>>,
+    /// Whether there are edits that aren't saved
+    pub dirty: bool,
+    /// Set after escaping with unsaved edits, so a second escape leaves anyway
+    leaving: bool,
+    /// What the last save said
+    status: String,
+    preview: Vec<Obst>,
+    /// The preview needs rebuilding
+    stale: bool,
+}
+impl Editor {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, ChartError> {
+        let path = path.into();
+        let chart = Chart::load(&path)?;
+        let beat = chart.offset;
+        Ok(Editor {
+            chart, path, beat, snap: 3, template: 0, selected: None, drag: None, undo: vec![], redo: vec![],
+            dirty: false, leaving: false, status: String::new(), preview: vec![], stale: true,
+        })
+    }
+    fn step(&self) -> f32 {
+        1.0 / SNAPS[self.snap]
+    }
+    fn bar(&self) -> f32 {
+        self.chart.tempo.beats_per_bar_at(self.beat - self.chart.offset)
+    }
+    fn set_beat(&mut self, beat: f32) {
+        self.beat = snap(beat, SNAPS[self.snap]).max(0.0);
+        self.stale = true;
+    }
+    /// Remembers the entries as they are before an edit.
+    fn checkpoint(&mut self) {
+        self.undo.push(self.chart.entries.clone());
+        if self.undo.len() > UNDO_LIMIT { self.undo.remove(0); }
+        self.redo.clear();
+        self.dirty = true;
+        self.leaving = false;
+        self.stale = true;
+    }
+    pub fn undo(&mut self) {
+        let Some(entries) = self.undo.pop() else { return };
+        self.redo.push(std::mem::replace(&mut self.chart.entries, entries));
+        self.after_history();
+    }
+    pub fn redo(&mut self) {
+        let Some(entries) = self.redo.pop() else { return };
+        self.undo.push(std::mem::replace(&mut self.chart.entries, entries));
+        self.after_history();
+    }
+    fn after_history(&mut self) {
+        self.selected = None;
+        self.drag = None;
+        self.dirty = true;
+        self.stale = true;
+    }
+    /// Adds the current template at `pos` on the cursor's beat, selecting it.
+    pub fn place(&mut self, pos: Vec2) {
+        let (_, mut spec) = templates().swap_remove(self.template);
+        if let Some(at) = spec.position() { spec.translate(pos - at); }
+        self.checkpoint();
+        self.chart.entries.push(ChartEntry::new(self.beat, spec));
+        self.selected = Some(self.chart.entries.len() - 1);
+    }
+    pub fn delete_selected(&mut self) {
+        let Some(i) = self.selected.take() else { return };
+        self.checkpoint();
+        self.chart.entries.remove(i);
+    }
+    pub fn nudge_selected(&mut self, beats: f32) {
+        let Some(i) = self.selected else { return };
+        self.checkpoint();
+        let entry = &mut self.chart.entries[i];
+        entry.beat = (entry.beat + beats).max(0.0);
+    }
+    pub fn rotate_selected(&mut self, angle: f32) {
+        let Some(i) = self.selected else { return };
+        self.checkpoint();
+        self.chart.entries[i].spec.rotate(angle);
+    }
+    /// (index, position) of the entries near the cursor that can be moved.
+    fn handles(&self) -> impl Iterator<Item = (usize, Vec2)> + '_ {
+        self.chart.entries.iter().enumerate()
+            .filter(|(_, e)| (e.beat - self.beat).abs() <= HANDLE_BEATS)
+            .filter_map(|(i, e)| Some((i, e.spec.position()?)))
+    }
+    /// The handle nearest `pos` within `PICK_RADIUS`.
+    fn pick(&self, pos: Vec2) -> Option<usize> {
+        self.handles().map(|(i, at)| (i, at.distance(pos)))
+            .filter(|&(_, dist)| dist <= PICK_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+    pub fn save(&mut self) {
+        match self.chart.save(&self.path) {
+            Ok(()) => {
+                self.dirty = false;
+                self.status = format!("saved {}", self.path.display());
+            }
+            Err(e) => self.status = format!("couldn't save: {e}"),
+        }
+    }
+    /// Handles a frame of input, returning whether to leave the editor.\
+    /// The editor's shortcuts are fixed keys, not `Bindings`, which are for playing.
+    pub fn update(&mut self) -> bool {
+        let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+        if is_key_pressed(KeyCode::Escape) {
+            if !self.dirty || self.leaving { return true; }
+            self.leaving = true;
+            self.status = "unsaved edits, escape again to leave without saving".to_string();
+        }
+        if ctrl {
+            if is_key_pressed(KeyCode::Z) { if shift { self.redo() } else { self.undo() } }
+            if is_key_pressed(KeyCode::Y) { self.redo(); }
+            if is_key_pressed(KeyCode::S) { self.save(); }
+        } else {
+            let wheel = mouse_wheel().1;
+            let steps = is_key_pressed(KeyCode::Right) as i32 - is_key_pressed(KeyCode::Left) as i32 - wheel.signum() as i32;
+            if steps != 0 { self.set_beat(self.beat + steps as f32 * self.step()); }
+            if is_key_pressed(KeyCode::PageDown) { self.set_beat(self.beat + self.bar()); }
+            if is_key_pressed(KeyCode::PageUp) { self.set_beat(self.beat - self.bar()); }
+            if is_key_pressed(KeyCode::Up) { self.snap = (self.snap + 1).min(SNAPS.len() - 1); }
+            if is_key_pressed(KeyCode::Down) { self.snap = self.snap.saturating_sub(1); }
+            if is_key_pressed(KeyCode::T) {
+                let count = templates().len();
+                self.template = if shift { (self.template + count - 1) % count } else { (self.template + 1) % count };
+            }
+            if is_key_pressed(KeyCode::Comma) { self.nudge_selected(-self.step()); }
+            if is_key_pressed(KeyCode::Period) { self.nudge_selected(self.step()); }
+            if is_key_pressed(KeyCode::R) { self.rotate_selected(if shift { -ROTATE_STEP } else { ROTATE_STEP }); }
+            if is_key_pressed(KeyCode::Delete) || is_key_pressed(KeyCode::Backspace) { self.delete_selected(); }
+        }
+        let mouse = Vec2::from(mouse_position());
+        if is_mouse_button_pressed(MouseButton::Left) {
+            if timeline::rect().contains(mouse) {
+                self.set_beat(timeline::beat_at(mouse.x, self.beat, self.bar()));
+            } else if let Some(i) = self.pick(mouse) {
+                self.selected = Some(i);
+                self.drag = Some((mouse, false));
+            } else {
+                self.place(mouse);
+            }
+        }
+        if let Some((last, moved)) = self.drag {
+            if !is_mouse_button_down(MouseButton::Left) {
+                self.drag = None;
+            } else if mouse != last {
+                // a whole drag undoes at once
+                if !moved { self.checkpoint(); }
+                if let Some(i) = self.selected { self.chart.entries[i].spec.translate(mouse - last); }
+                self.drag = Some((mouse, true));
+                self.stale = true;
+            }
+        }
+        if is_mouse_button_pressed(MouseButton::Right) { self.selected = None; }
+        if self.stale {
+            let rng = GameRng::new(self.chart.seed.unwrap_or(0));
+            self.preview = simulate(&self.chart.events(), self.beat - PREVIEW_BEATS, self.beat, rng);
+            self.stale = false;
+        }
+        false
+    }
+    pub fn draw(&self, palette: &Palette) {
+        clear_background(palette.background);
+        for obst in &self.preview {
+            obst.obstacle.draw(palette.obstacle, Vec2::ZERO);
+        }
+        for (i, at) in self.handles() {
+            let color = if self.selected == Some(i) { palette.accent } else { acmul(palette.text, 0.6) };
+            draw_circle_lines(at.x, at.y, PICK_RADIUS / 2.0, 2.0, color);
+        }
+        let mouse = Vec2::from(mouse_position());
+        draw_circle_lines(mouse.x, mouse.y, 4.0, 1.0, acmul(palette.text, 0.4));
+        timeline::draw(self.chart.timeline().into_iter(), self.beat, self.bar());
+        let measure = ((self.beat - self.chart.offset) / self.bar()).floor() as i32 + 1;
+        let (name, _) = templates().swap_remove(self.template);
+        let lines = [
+            format!("{}{}", self.path.display(), if self.dirty { " *" } else { "" }),
+            format!("beat {:.3}  measure {measure}  snap 1/{}  template {name}", self.beat, SNAPS[self.snap]),
+            match self.selected.and_then(|i| self.chart.entries.get(i)) {
+                Some(entry) => format!("selected {} at beat {:.3}", entry.spec.kind(), entry.beat),
+                None => "nothing selected".to_string(),
+            },
+            "click place/select, drag move, , . nudge, R rotate, Del delete, T template, arrows beat/snap".to_string(),
+            "ctrl+Z undo, ctrl+Y redo, ctrl+S save, Esc leave".to_string(),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            draw_text(line, 10.0, 24.0 + i as f32 * 22.0, 22.0, if i < 3 { palette.text } else { acmul(palette.text, 0.5) });
+        }
+        if !self.status.is_empty() {
+            draw_text(&self.status, 10.0, 24.0 + lines.len() as f32 * 22.0 + 8.0, 22.0, palette.accent);
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! Endless mode: a bar at a time, one pattern picked from a weighted pool, getting harder the longer the run goes.
+
+use std::{f32::consts::{FRAC_PI_2, TAU}, rc::Rc};
+
+use macroquad::prelude::{Vec2, vec2, Color};
+use strum::EnumCount;
+
+use crate::{game::UpdateAccumulator, game_objects::{Obstacle, Player, Bomb, SlamLaser, Periodic, GOLGrid}, utils::{GameRng, Edge, edge_point, Anchor, anchored, anchored_frac, screen_size, screen_height}, patterns::{WallsAlternating, LaserCage}, spawners::{jitter, JitterSpec}};
+
+macro_rules! builder {
+    ($name:ident: $type:ty) => {
+        pub fn $name(mut self, $name: $type) -> Self { self.$name = $name; self }
+    };
+}
+
+/// Beats until the difficulty is about two thirds of the way up
+pub const DIFFICULTY_RAMP_BEATS: f32 = 192.0;
+/// Most of the screen patterns may make lethal at once, roughly
+pub const MAX_LETHAL_AREA: f32 = 0.6;
+/// Bars before a category can be picked again
+pub const CATEGORY_COOLDOWN_BARS: usize = 2;
+
+/// From 0 at the start, approaching 1.
+pub fn difficulty(beats: f32) -> f32 {
+    1.0 - (-beats.max(0.0) / DIFFICULTY_RAMP_BEATS).exp()
+}
+
+/// What patterns are tuned with at a difficulty.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Knobs {
+    /// Multiplies the amount of things a pattern spawns
+    pub density: f32,
+    /// Pixels per second
+    pub pellet_speed: f32,
+    /// Beats of warning before something turns lethal
+    pub warning: f32,
+}
+impl Knobs {
+    pub fn at(difficulty: f32) -> Self {
+        Knobs {
+            density: 1.0 + 1.5 * difficulty,
+            pellet_speed: 200.0 + 250.0 * difficulty,
+            warning: 2.0 - difficulty,
+        }
+    }
+}
+
+/// What a pattern mostly throws at the player, so the same kind doesn't come over and over.
+#[derive(strum_macros::EnumCount, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternCategory {
+    Pellets,
+    Lasers,
+    Rects,
+    Grid,
+}
+
+pub type PatternFn = Box<dyn Fn(&mut UpdateAccumulator, f32)>;
+
+/// A pattern endless mode can pick, spawned with the difficulty.
+pub struct PatternTemplate {
+    pub weight: f32,
+    /// The pattern isn't picked before the difficulty reaches this
+    pub min_difficulty: f32,
+    pub category: PatternCategory,
+    /// Heavy patterns are never picked twice in a row
+    pub heavy: bool,
+    /// Rough fraction of the screen the pattern makes lethal, before `Knobs::density`
+    pub lethal_area: f32,
+    /// Beats until the pattern is gone
+    pub beats: f32,
+    pub spawn: PatternFn,
+}
+impl PatternTemplate {
+    pub fn new(weight: f32, min_difficulty: f32, category: PatternCategory, spawn: impl Fn(&mut UpdateAccumulator, f32) + 'static) -> Self {
+        PatternTemplate { weight, min_difficulty, category, heavy: false, lethal_area: 0.1, beats: 4.0, spawn: Box::new(spawn) }
+    }
+    pub fn heavy(mut self) -> Self {
+        self.heavy = true;
+        self
+    }
+    builder!(lethal_area: f32);
+    builder!(beats: f32);
+    fn area(&self, difficulty: f32) -> f32 {
+        self.lethal_area * Knobs::at(difficulty).density
+    }
+}
+
+fn random_edge_point(rng: &mut GameRng) -> Vec2 {
+    let edge = [Edge::Top, Edge::Right, Edge::Bottom, Edge::Left][rng.range(0, 4)];
+    edge_point(edge, rng.range(0.0, 1.0))
+}
+
+/// Bombs flying in from the edges, bursting into rough rings of pellets.
+pub fn pellet_rings(accum: &mut UpdateAccumulator, difficulty: f32) {
+    let knobs = Knobs::at(difficulty);
+    for _ in 0..(2.0 * knobs.density).round() as usize {
+        let start = random_edge_point(accum.rng());
+        let target = anchored_frac(Anchor::TopLeft, accum.rng().vec(vec2(0.2, 0.2), vec2(0.8, 0.8)));
+        let pellets = (10.0 * knobs.density) as usize;
+        // a little off a perfect ring, the same every time the run's seed is
+        let spray = jitter(Box::new(Bomb::pellet_spawner), JitterSpec { vel_angle: 0.08, vel_mag: 0.15, ..JitterSpec::default() });
+        accum.obst(Bomb::new(start, target, knobs.warning, pellets, knobs.pellet_speed, 10.0, spray));
+    }
+}
+
+/// Lasers fanning out from a corner, with gaps to stand in.
+pub fn laser_fan(accum: &mut UpdateAccumulator, difficulty: f32) {
+    let knobs = Knobs::at(difficulty);
+    let corner = accum.rng().range(0, 4);
+    let origin = anchored([Anchor::TopLeft, Anchor::TopRight, Anchor::BottomRight, Anchor::BottomLeft][corner], Vec2::ZERO);
+    let count = 2 + knobs.density as usize;
+    let base = corner as f32 * FRAC_PI_2;
+    let reach = screen_size().length() * 1.5;
+    for i in 0..count {
+        let angle = base + FRAC_PI_2 * (i as f32 + 0.5) / count as f32;
+        let end = origin + vec2(angle.cos(), angle.sin()) * reach;
+        accum.obst(SlamLaser::new(origin, end, 30.0, knobs.warning, 1.0, 0.2, Vec2::ZERO, 5.0));
+    }
+}
+
+/// Columns of rects raining down, leaving a gap.
+pub fn rect_rain(accum: &mut UpdateAccumulator, difficulty: f32) {
+    let knobs = Knobs::at(difficulty);
+    let columns = 8 + (4.0 * knobs.density) as usize;
+    let gap = accum.rng().range(0, columns - 1);
+    let steps = screen_height() as usize / 32 + 1;
+    for i in (0..columns).filter(|&i| i != gap && i != gap + 1) {
+        let start = edge_point(Edge::Top, (i as f32 + 0.5) / columns as f32);
+        accum.obst(Periodic::new(steps, 0.125, Periodic::linear(2.0, knobs.warning, 0.25, start, vec2(0.0, 32.0), vec2(30.0, 30.0), 0.0)));
+    }
+}
+
+/// A patch of the game of life, let loose for a while.
+pub fn gol_burst(accum: &mut UpdateAccumulator, difficulty: f32) {
+    let knobs = Knobs::at(difficulty);
+    let grid = GOLGrid::default()
+        .first_warning_time(knobs.warning)
+        .period(0.5)
+        .max(16)
+        .populate((50.0 * knobs.density) as usize, accum.rng());
+    accum.obst(grid);
+}
+
+/// Walls closing in from alternating sides, the gap narrowing as it gets harder.
+pub fn walls(accum: &mut UpdateAccumulator, difficulty: f32) {
+    let knobs = Knobs::at(difficulty);
+    WallsAlternating::default()
+        .interval(2.0)
+        .count(2)
+        .gap_width(350.0 - 150.0 * difficulty)
+        .warning(knobs.warning)
+        .first_far(accum.rng().chance(0.5))
+        .vertical(accum.rng().chance(0.5))
+        .spawn(accum);
+}
+
+/// A cage of lasers closing around the player.
+pub fn cage(accum: &mut UpdateAccumulator, difficulty: f32) {
+    let knobs = Knobs::at(difficulty);
+    let rot = accum.rng().range(0.0, TAU);
+    LaserCage::default()
+        .center(accum.player_pos())
+        .n(3 + knobs.density as usize)
+        .length(500.0 - 150.0 * difficulty)
+        .warning(knobs.warning)
+        .rot(rot)
+        .spawn(accum);
+}
+
+/// Every built-in pattern.
+pub fn default_pool() -> Vec<PatternTemplate> {
+    vec![
+        PatternTemplate::new(3.0, 0.0, PatternCategory::Pellets, pellet_rings).lethal_area(0.05).beats(6.0),
+        PatternTemplate::new(2.0, 0.0, PatternCategory::Rects, rect_rain).lethal_area(0.1).beats(6.0),
+        PatternTemplate::new(2.0, 0.15, PatternCategory::Lasers, laser_fan).heavy().lethal_area(0.12).beats(3.0),
+        PatternTemplate::new(1.0, 0.35, PatternCategory::Grid, gol_burst).heavy().lethal_area(0.15).beats(10.0),
+        PatternTemplate::new(1.5, 0.25, PatternCategory::Rects, walls).heavy().lethal_area(0.15).beats(4.0),
+        PatternTemplate::new(1.0, 0.5, PatternCategory::Lasers, cage).heavy().lethal_area(0.05).beats(4.0),
+    ]
+}
+
+/// Spawns a pattern every bar for as long as it lives. Picks are fair:
+/// no heavy pattern right after another, a cooldown for each category and a cap on the lethal area.
+#[derive(Clone)]
+pub struct Endless {
+    pool: Rc<Vec<PatternTemplate>>,
+    bar: f32,
+    /// Beats since spawning
+    time: f32,
+    next_bar: f32,
+    bars: usize,
+    last_heavy: bool,
+    /// Bar each category was last picked in
+    last_picked: [Option<usize>; PatternCategory::COUNT],
+    /// (beat, lethal area) of the patterns still around, by when they're gone
+    active: Vec<(f32, f32)>,
+}
+impl Endless {
+    /// The first pattern comes a bar in.
+    pub fn new(pool: Vec<PatternTemplate>, bar: f32) -> Self {
+        Endless { pool: Rc::new(pool), bar, time: 0.0, next_bar: bar, bars: 0, last_heavy: false, last_picked: [None; PatternCategory::COUNT], active: vec![] }
+    }
+    /// A template the rules allow, picked by weight. Cooldowns are waived if nothing else fits.
+    fn pick(&self, rng: &mut GameRng, difficulty: f32) -> Option<usize> {
+        let area = self.active.iter().map(|&(_, a)| a).sum::<f32>();
+        let allowed = |waive_cooldowns: bool| self.pool.iter().enumerate().filter(|(_, t)| {
+            let cooled = self.last_picked[t.category as usize].is_none_or(|bar| self.bars - bar >= CATEGORY_COOLDOWN_BARS);
+            t.min_difficulty <= difficulty
+                && !(t.heavy && self.last_heavy)
+                && area + t.area(difficulty) <= MAX_LETHAL_AREA
+                && (cooled || waive_cooldowns)
+        }).map(|(i, t)| (i, t.weight)).collect::<Vec<_>>();
+        let mut candidates = allowed(false);
+        if candidates.is_empty() { candidates = allowed(true); }
+        let total = candidates.iter().map(|&(_, w)| w).sum::<f32>();
+        if total <= 0.0 { return None; }
+        let mut roll = rng.range(0.0, total);
+        for &(i, weight) in &candidates {
+            if roll < weight { return Some(i); }
+            roll -= weight;
+        }
+        candidates.last().map(|&(i, _)| i)
+    }
+}
+impl Obstacle for Endless {
+    fn update(&mut self, to_add: &mut UpdateAccumulator, dtime: f32, time: f32, dease: f32, ease: f32) {
+        self.time += dtime;
+        while self.time >= self.next_bar {
+            self.next_bar += self.bar;
+            self.bars += 1;
+            let now = to_add.time();
+            self.active.retain(|&(until, _)| until > now);
+            let difficulty = difficulty(self.time);
+            // the background builds up with the patterns
+            to_add.background_intensity(1.0 + difficulty);
+            match self.pick(to_add.rng(), difficulty) {
+                Some(i) => {
+                    let template = &self.pool[i];
+                    (template.spawn)(to_add, difficulty);
+                    self.active.push((now + template.beats, template.area(difficulty)));
+                    self.last_heavy = template.heavy;
+                    self.last_picked[template.category as usize] = Some(self.bars);
+                }
+                // a breather, when everything is still too busy
+                None => self.last_heavy = false,
+            }
+        }
+    }
+    fn draw(&self, color: Color, offset: Vec2) {}
+    fn name(&self) -> &'static str { "Endless" }
+    fn box_clone(&self) -> Box<dyn Obstacle> { Box::new(self.clone()) }
+    fn collides(&self, player: Player) -> bool { false }
+    fn expired(&self) -> bool { false }
+    fn lethal(&self) -> bool { false }
+}
@@ -1,27 +1,157 @@
 
-use std::error::Error;
+use std::{error::Error, collections::VecDeque, path::Path};
 
-use macroquad::{prelude::{Vec2, Color, is_key_down, KeyCode, vec2, is_key_pressed, RED, SKYBLUE, WHITE}, window::{screen_width, screen_height, clear_background}, shapes::{draw_circle, draw_rectangle}, rand::gen_range, text::draw_text, miniquad::log::Level};
+use macroquad::{prelude::{Vec2, Rect, Color, vec2, RED, SKYBLUE, WHITE}, window::clear_background, camera::set_camera, shapes::{draw_circle, draw_circle_lines, draw_line, draw_poly, draw_rectangle, draw_rectangle_lines}, rand::gen_range, text::{draw_text, measure_text}, time::get_frame_time, input::{is_key_pressed, is_mouse_button_pressed, KeyCode, MouseButton}, miniquad::log::Level};
 use soloud::{Wav, AudioExt, LoadExt};
+use strum::{IntoEnumIterator, EnumCount};
 
-use crate::{game_objects::Obstacle, utils::{mix, centered_text_draw, acmul}, state_control::{EparLevel, EparState, ColorChange}, sound::Music};
+use crate::{input::{Input, Action}, game_objects::{Obstacle, Pickup, Pellet, PelletPool, ScoreOrb, DrawOrder}, utils::{mix, hue_cycle, centered_text_draw, acmul, cmul, circle_bounds, Contact, screen_size, screen_width, screen_height, mouse_position, set_screen_camera, RingBuffer, TrailBuffer, TRAIL_CAPACITY, CircleBatch, GameRng, draw_ribbon, set_smooth_edges}, state_control::{EparLevel, EparState, ColorChange}, sound::{Music, SoundBank, SoundId, SoundQueue, SoundRequest}, chart::{Chart, ChartWatch, ReloadAnchor}, tempo::TempoMap, beat::Schedule, scoring::{Score, ScoringConfig}, results::{Results, RunResult, Grading}, save::{SaveData, level_key, chart_key}, modifiers::Modifiers, background::{Background, BackgroundLayer, BeatClock}, palette::{Palette, PaletteShift}, overlay::{self, Flash, Fade, REDUCED_FLASH_SCALE}, debug::{DebugOverlay, DebugInfo, FrameCounters}, timeline::{self, EventCategory}, rewind::{RewindBuffer, REWIND_SECS}, inspector::Inspector, camera::Camera, spatial::SpatialHash, particles::{ParticleSystem, MAX_PARTICLES}, indicators::{self, Indicator, IndicatorStyle}, parallel};
 
-use super::game_objects::{Player, Obst};
+use super::game_objects::{Player, Obst, Effects};
 
 pub fn soft_pink() -> Color { Color { r: 1.0, g: 0.5, b: 0.8, a: 1.0 } }
 pub fn hit_color() -> Color { mix(soft_pink(), RED, 0.5) }
 pub fn dash_color() -> Color { mix(soft_pink(), SKYBLUE, 0.5) }
 pub fn hitdash_color() -> Color { mix(hit_color(), dash_color(), 0.5) }
+/// Color of each player in co-op. The first player keeps the usual soft pink.
+pub fn player_color(idx: usize) -> Color {
+    match idx {
+        0 => soft_pink(),
+        _ => Color { r: 0.5, g: 0.8, b: 1.0, a: 1.0 }
+    }
+}
+/// Ease-out of a bomb's shockwave, `t` from 0 to 1.
+fn wave_ease(t: f32) -> f32 {
+    1.0 - (1.0 - t.clamp(0.0, 1.0)).powi(3)
+}
+/// Beats `secs` of song take at `bpm`, the music playing at `speed` (which `Modifiers::speed_rate` is part of).
+fn frame_beats(secs: f32, bpm: f32, speed: f32) -> f32 {
+    secs / 60.0 * bpm * speed
+}
+/// Steps of at most `step_beats` a frame `beat_dt` long is split into, no more than `max`. Non-positive `step_beats` never splits it.
+fn substeps(beat_dt: f32, step_beats: f32, max: usize) -> usize {
+    if step_beats <= 0.0 { return 1; }
+    ((beat_dt / step_beats).ceil() as usize).clamp(1, max.max(1))
+}
+/// When step `step` of a frame `beat_dt` long split into `substeps` steps, ending at `time`, ends.
+fn step_time(time: f32, beat_dt: f32, substeps: usize, step: usize) -> f32 {
+    time - (substeps - 1 - step) as f32 * beat_dt / substeps as f32
+}
+/// Frozen obstacles' color, washed out and dimmed.
+fn frozen_tint(color: Color) -> Color {
+    let grey = (color.r + color.g + color.b) / 3.0;
+    cmul(mix(color, Color { r: grey, g: grey, b: grey, a: color.a }, 0.7), 0.75)
+}
+pub fn orb_color() -> Color { Color { r: 1.0, g: 0.85, b: 0.3, a: 1.0 } }
+pub fn shield_color() -> Color { Color { r: 0.5, g: 1.0, b: 0.8, a: 1.0 } }
 
 /// Will lag the game INTENSELY. Basically enables a "shader" (on the CPU!) for debugging collisions, not for actual use.
 pub const COLLISION_DBG: bool = false;
 /// When `COLLISION_DBG` is enabled, specifies the size of the rectangles used for collision debugging.
 pub const COLLISION_FRAGMENT_SIZE: usize = 20;
+/// Shows raw input values (e.g. gamepad sticks) on screen.
+pub const INPUT_DBG: bool = false;
+/// Default length of the invulnerability window after a hit, in beats.
+pub const DEFAULT_IFRAME_BEATS: f32 = 4.0;
+/// Default hit points of the player. Levels can change `GameState::max_hp`.
+pub const DEFAULT_MAX_HP: u32 = 3;
+/// Beats it takes a lost hit point to shrink away in the HUD.
+pub const HP_LOSS_ANIM_BEATS: f32 = 0.5;
+/// Default extra radius around the player that counts as a graze.
+pub const DEFAULT_GRAZE_MARGIN: f32 = 15.0;
+/// Default beats before the same obstacle can be grazed again.
+pub const DEFAULT_GRAZE_COOLDOWN: f32 = 1.0;
+/// Beats a graze spark stays on screen.
+pub const GRAZE_SPARK_BEATS: f32 = 0.25;
+/// Default speed multiplier while focused.
+pub const DEFAULT_FOCUS_FACTOR: f32 = 0.4;
+/// Default length of the player's motion trail in beats.
+pub const DEFAULT_TRAIL_BEATS: f32 = 0.5;
+/// How many beats of player positions are kept for `UpdateAccumulator::player_pos_at`.
+pub const PLAYER_HISTORY_BEATS: f32 = 16.0;
+/// Most players that can play at once in local co-op.
+pub const MAX_PLAYERS: usize = 2;
+/// Default lowest speed the player can be slowed to, as a fraction of `Player::pps`.
+pub const DEFAULT_SPEED_FLOOR: f32 = 0.2;
+/// Default highest speed the player can be sped up to, as a fraction of `Player::pps`.
+pub const DEFAULT_SPEED_CEILING: f32 = 3.0;
+/// Beats of invulnerability after respawning at a checkpoint.
+pub const RESPAWN_IFRAME_BEATS: f32 = 2.0;
+/// Default most score orbs alive at once.
+pub const DEFAULT_MAX_ORBS: usize = 64;
+/// Beats the sparkle of a collected pickup stays on screen.
+pub const PICKUP_SPARKLE_BEATS: f32 = 0.25;
+/// Default bomb charges each player starts with.
+pub const DEFAULT_BOMB_CHARGES: u32 = 2;
+/// Default beats between bombs.
+pub const DEFAULT_BOMB_COOLDOWN: f32 = 4.0;
+/// Default radius a bomb's shockwave grows to.
+pub const DEFAULT_BOMB_RADIUS: f32 = 300.0;
+/// Default beats a bomb's shockwave takes to grow to its full radius.
+pub const DEFAULT_BOMB_BEATS: f32 = 1.0;
+/// Default seconds the world freezes for when the run ends.
+pub const DEFAULT_HITSTOP_SECS: f32 = 0.1;
+/// Default seconds of slow motion after the hit-stop.
+pub const DEFAULT_SLOWMO_SECS: f32 = 0.75;
+/// Default time scale at the start of the slow motion, ramping back to 1.
+pub const DEFAULT_SLOWMO_SCALE: f32 = 0.25;
+/// Longest an obstacle's hit-stop can freeze the game for, in seconds.
+pub const MAX_HITSTOP_SECS: f32 = 0.1;
+/// Default seconds a graze freezes the game for.
+pub const DEFAULT_GRAZE_HITSTOP_SECS: f32 = 0.01;
+/// Default longest step obstacles take in beats when a frame runs long, a 16th note. About two frames at 60 fps and 120 bpm.
+pub const DEFAULT_SUBSTEP_BEATS: f32 = 0.0625;
+/// Default most steps a long frame is split into. A longer hitch makes the steps longer instead.
+pub const DEFAULT_MAX_SUBSTEPS: usize = 8;
+/// Slowest the time scale goes, so the beat clock keeps moving.
+pub const MIN_TIME_SCALE: f32 = 0.05;
+pub const MAX_TIME_SCALE: f32 = 4.0;
+/// Amount of particles bursting from the player on death.
+pub const DEATH_PARTICLES: usize = 48;
+/// Beats the shards of a broken shield fly for.
+pub const SHATTER_BEATS: f32 = 0.5;
+/// Beats an arena edge glows for after the player is pushed back from it.
+pub const EDGE_GLOW_BEATS: f32 = 0.25;
+/// Beats the combo counter stays enlarged for after going up.
+pub const COMBO_POP_BEATS: f32 = 0.25;
+/// Times the rainbow combo counter goes around the hues per beat.
+pub const COMBO_RAINBOW_TURNS: f32 = 0.25;
+/// Beats counted in before the music resumes after pausing
+pub const COUNT_IN_BEATS: f32 = 3.0;
+/// How far before the target a practice seek starts simulating the chart.\
+/// Obstacles spawned earlier than this are assumed to be gone by the target.
+pub const SEEK_LOOKBACK_BEATS: f32 = 8.0;
+/// Step of the simulation when seeking, in beats
+pub const SEEK_STEP_BEATS: f32 = 1.0 / 16.0;
+
+/// Seeking back in practice mode skips checkpoints less than this many beats behind, so it doesn't get stuck on them.
+pub const CHECKPOINT_SNAP_MARGIN: f32 = 1.0;
+
+/// A beat the players respawn at after dying, starting a section of the level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub beat: f32,
+    pub name: Option<String>,
+}
+impl Checkpoint {
+    pub fn new(beat: f32) -> Self {
+        Checkpoint { beat, name: None }
+    }
+    pub fn named(beat: f32, name: impl Into<String>) -> Self {
+        Checkpoint { beat, name: Some(name.into()) }
+    }
+    /// The name, or the beat for unnamed checkpoints.
+    pub fn label(&self) -> String {
+        self.name.clone().unwrap_or_else(|| format!("beat {}", self.beat))
+    }
+}
 
 /// Extra arguments for specializing `StateModifier`s and `Accumulatee`s
 #[derive(Default, Clone, Copy)]
 pub struct ModifyArgs {
     pub step: usize,
+    /// Total amount of steps of the caller, if it has any (e.g. `Periodic`).
+    pub total_steps: usize,
     pub pos: Vec2,
     pub vel: Vec2,
     pub rad: f32,
@@ -35,11 +165,136 @@ macro_rules! builder {
 impl ModifyArgs {
     pub fn new(time: f32) -> Self { ModifyArgs { time, ..Self::default() } }
     builder!(step: usize);
+    builder!(total_steps: usize);
     builder!(pos: Vec2);
     builder!(vel: Vec2);
     builder!(rad: f32);
 }
 
+/// Speed changes from zones and effects for a single frame.\
+/// Effective speed is `(pps + adds) * muls`, so multipliers also scale additions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeedModifiers {
+    pub add: f32,
+    pub mul: f32,
+    /// Focus mode's multiplier. Applied after the clamp, so focusing always slows by exactly this much,
+    /// even below the floor.
+    pub focus: f32,
+}
+impl Default for SpeedModifiers {
+    fn default() -> Self {
+        SpeedModifiers { add: 0.0, mul: 1.0, focus: 1.0 }
+    }
+}
+impl SpeedModifiers {
+    /// Effective speed for a base of `pps`, clamped to `[floor, ceiling] * pps` before focus applies.
+    pub fn apply(&self, pps: f32, floor: f32, ceiling: f32) -> f32 {
+        ((pps + self.add) * self.mul).clamp(pps * floor, pps * ceiling.max(floor)) * self.focus
+    }
+}
+
+/// A multiplier on how fast the game runs, music included, easing towards its target.\
+/// The ease runs on real time, so it's the same at any frame rate and at any scale.
+/// Hit-stops don't go through this, as the music plays on through them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeScale {
+    pub current: f32,
+    pub target: f32,
+    /// Change per beat of real time
+    rate: f32,
+}
+impl Default for TimeScale {
+    fn default() -> Self {
+        TimeScale { current: 1.0, target: 1.0, rate: 0.0 }
+    }
+}
+impl TimeScale {
+    /// Eases to `target` over `ramp_beats` of real time, at once if 0. Clamped so the beat clock never stops.
+    pub fn set(&mut self, target: f32, ramp_beats: f32) {
+        self.target = target.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+        if ramp_beats <= 0.0 {
+            self.current = self.target;
+            self.rate = 0.0;
+        } else {
+            self.rate = (self.target - self.current).abs() / ramp_beats;
+        }
+    }
+    /// Moves on by `real_beats` of unscaled time, returning the new scale.
+    pub fn advance(&mut self, real_beats: f32) -> f32 {
+        let step = self.rate * real_beats;
+        self.current += (self.target - self.current).clamp(-step, step);
+        self.current
+    }
+}
+
+/// When a co-op run fails. Single-player runs fail once the player is down either way.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CoopRule {
+    /// Downed players stay down while the others keep going.
+    #[default]
+    AllDown,
+    AnyDown
+}
+
+/// The options of the pause menu, top to bottom.
+#[derive(strum_macros::EnumIter, strum_macros::EnumCount, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseOption {
+    Resume,
+    Restart,
+    Quit
+}
+impl PauseOption {
+    /// The option `by` rows away, wrapping around.
+    fn step(self, by: isize) -> Self {
+        let idx = (self as isize + by).rem_euclid(Self::COUNT as isize);
+        Self::iter().nth(idx as usize).unwrap()
+    }
+}
+
+/// Which obstacles to drop once the obstacle budget is exceeded. Essential obstacles are never dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+pub enum BudgetPolicy {
+    /// New spawns are discarded.
+    DropNew,
+    /// The oldest live obstacles are removed.
+    DropOldest,
+    /// The live obstacles furthest from the nearest player are removed, then the oldest of those without an anchor.
+    DropFurthest
+}
+
+/// Obstacles waiting for the beat they were scheduled for with `UpdateAccumulator::spawn_at`, already started at it.\
+/// Sorted by beat, ties in the order they were scheduled.
+#[derive(Default)]
+pub struct DeferredSpawns(Vec<Obst>);
+impl DeferredSpawns {
+    fn add(&mut self, obst: Obst) {
+        let idx = self.0.partition_point(|o| o.start_time <= obst.start_time);
+        self.0.insert(idx, obst);
+    }
+    /// Takes out the ones due at `beat`, the earliest first.
+    fn due(&mut self, beat: f32) -> std::vec::Drain<'_, Obst> {
+        let due = self.0.partition_point(|o| o.start_time <= beat);
+        self.0.drain(..due)
+    }
+    fn retain(&mut self, f: impl FnMut(&Obst) -> bool) {
+        self.0.retain(f);
+    }
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// (beat, category) of each, for the dev timeline.
+    pub fn timeline(&self) -> impl Iterator<Item = (f32, EventCategory)> + '_ {
+        self.0.iter().map(|o| (o.start_time, EventCategory::of_kind(o.obstacle.name())))
+    }
+}
+
 pub struct UpdateAccumulator {
     obstacles_to_add: Vec<Obst>,
     events: Vec<Box<dyn StateModifier>>,
@@ -47,13 +302,120 @@ pub struct UpdateAccumulator {
     bg: Option<Color>,
     fg: Option<Color>,
     float: Option<f32>,
+    background_intensity: Option<f32>,
+    palette_shift: Option<(Palette, f32)>,
+    flashes: Vec<Flash>,
+    indicators: Vec<Indicator>,
+    /// Flashes of impacts, dropped if the level turned them off
+    impact_flashes: Vec<Flash>,
+    /// (target alpha, beats)
+    fade: Option<(f32, f32)>,
+    /// Seconds of hit-stop asked for, the longest request winning
+    hitstop: f32,
+    /// (tag or all of them, beats, harmless) of freezes asked for
+    freezes: Vec<(Option<u32>, f32, bool)>,
+    sounds: SoundQueue,
+    /// (target, ramp beats)
+    time_scale: Option<(f32, f32)>,
+    /// (amount, decay beats)
+    zoom_pulse: Option<(f32, f32)>,
+    /// (target radians, ramp beats)
+    cam_rotate: Option<(f32, f32)>,
+    /// The tag of the innermost `with_tag` running
+    tag: Option<u32>,
+    /// Tags of the live obstacles
+    tagged: Vec<u32>,
+    /// Borrowed from the `LevelState` for the duration of an update
+    pellets: PelletPool,
+    /// Borrowed from the `LevelState` for the duration of an update
+    deferred: DeferredSpawns,
+    /// Borrowed from the `LevelState` for the duration of an update
+    particles: ParticleSystem,
+    /// Whether the obstacle updating came from the chart, so what it schedules is marked like it
+    charted: bool,
+    /// (tag, whether kill hooks run) of the obstacles to remove, everything if there's no tag
+    removals: Vec<(Option<u32>, bool)>,
+    /// Obstacles asked for, dropped ones included
+    spawns: usize,
+    events_run: usize,
     shake: f32,
+    /// Directional shakes, added up
+    shake_lean: Vec2,
+    heal: u32,
+    /// Knockback for each player
+    push: Vec<Vec2>,
+    speed: SpeedModifiers,
     time: f32,
+    players: Vec<Player>,
+    arena: Rect,
+    /// (beat, position), oldest first. Borrowed from the `LevelState` for the duration of an update.
+    player_history: VecDeque<(f32, Vec2)>,
+    budget: Option<(usize, BudgetPolicy)>,
+    live_obstacles: usize,
+    dropped_spawns: usize,
+    /// Score orbs alive, including ones added this update
+    orbs: usize,
+    max_orbs: usize,
+    rng: GameRng,
+    modifiers: Modifiers,
 }
 impl UpdateAccumulator {
     pub fn time(&self) -> f32 {
         self.time
     }
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time;
+    }
+    /// The level's particles, for sparks and debris that never hit anything.
+    pub fn particles(&mut self) -> &mut ParticleSystem {
+        &mut self.particles
+    }
+    /// The run's random number generator. Anything random that affects the run should come from here.
+    pub fn rng(&mut self) -> &mut GameRng {
+        &mut self.rng
+    }
+    /// A generator for `(time, step)`, for `Clone`-able closures whose randomness shouldn't depend on call order.
+    pub fn rng_at(&self, time: f32, step: usize) -> GameRng {
+        self.rng.derive(time, step)
+    }
+    /// The first player still alive, which obstacles aim at. Always the player in single-player.
+    pub fn player(&self) -> Player {
+        self.players.iter().copied().find(Player::alive).or(self.players.first().copied()).unwrap_or_default()
+    }
+    pub fn player_pos(&self) -> Vec2 {
+        self.player().pos
+    }
+    /// Every player, including downed ones in co-op.
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+    /// The area the player is kept inside.
+    pub fn arena(&self) -> Rect {
+        self.arena
+    }
+    /// Whether the player is in focus mode.
+    pub fn focused(&self) -> bool {
+        self.player().focused
+    }
+    /// Where the player was `beats_ago` beats before the current time, interpolated between frames.\
+    /// Clamps to the oldest known position if the history doesn't go back far enough.
+    pub fn player_pos_at(&self, beats_ago: f32) -> Vec2 {
+        if beats_ago <= 0.0 { return self.player_pos(); }
+        let target = self.time - beats_ago;
+        let mut later = match self.player_history.back() {
+            Some(&sample) => sample,
+            None => return self.player_pos()
+        };
+        if later.0 <= target { return later.1; }
+        for &(t, pos) in self.player_history.iter().rev().skip(1) {
+            if t <= target {
+                let fac = if later.0 > t { (target - t) / (later.0 - t) } else { 0.0 };
+                return pos.lerp(later.1, fac);
+            }
+            later = (t, pos);
+        }
+        later.1
+    }
     pub fn new() -> Self {
         UpdateAccumulator {
             obstacles_to_add: vec![],
@@ -62,15 +424,211 @@ impl UpdateAccumulator {
             bg: None,
             fg: None,
             float: None,
+            background_intensity: None,
+            palette_shift: None,
+            flashes: vec![],
+            indicators: vec![],
+            impact_flashes: vec![],
+            fade: None,
+            hitstop: 0.0,
+            freezes: vec![],
+            sounds: SoundQueue::default(),
+            time_scale: None,
+            zoom_pulse: None,
+            cam_rotate: None,
+            tag: None,
+            tagged: vec![],
+            pellets: PelletPool::default(),
+            particles: ParticleSystem::default(),
+            deferred: DeferredSpawns::default(),
+            charted: false,
+            removals: vec![],
+            spawns: 0,
+            events_run: 0,
             shake: 0.0,
-            time: 0.0
+            shake_lean: Vec2::ZERO,
+            heal: 0,
+            push: vec![],
+            speed: SpeedModifiers::default(),
+            time: 0.0,
+            players: vec![],
+            arena: Rect::new(0.0, 0.0, screen_width(), screen_height()),
+            player_history: VecDeque::new(),
+            budget: None,
+            live_obstacles: 0,
+            dropped_spawns: 0,
+            orbs: 0,
+            max_orbs: DEFAULT_MAX_ORBS,
+            rng: GameRng::default(),
+            modifiers: Modifiers::default(),
+        }
+    }
+    /// The difficulty modifiers of the run.
+    pub fn modifiers(&self) -> &Modifiers {
+        &self.modifiers
+    }
+    /// Caps the amount of live obstacles at `max_live`, applying `policy` to anything over it.
+    pub fn set_budget(&mut self, max_live: usize, policy: BudgetPolicy) {
+        self.budget = Some((max_live, policy));
+    }
+    pub fn clear_budget(&mut self) {
+        self.budget = None;
+    }
+    /// Total amount of spawns dropped by the obstacle budget this level.
+    pub fn dropped_spawns(&self) -> usize {
+        self.dropped_spawns
+    }
+    fn push_obst(&mut self, mut obst: Obst) {
+        if obst.tag.is_none() { obst.tag = self.tag; }
+        obst.from_chart |= self.charted;
+        self.spawns += 1;
+        if let Some((max, BudgetPolicy::DropNew)) = self.budget {
+            if !obst.essential && self.live_obstacles + self.obstacles_to_add.len() >= max {
+                self.dropped_spawns += 1;
+                self.pellets.recycle(obst.obstacle);
+                return;
+            }
         }
+        self.obstacles_to_add.push(obst);
     }
     pub fn obst(&mut self, obst: impl Obstacle) {
-        self.obstacles_to_add.push(Obst::new(obst.box_clone(), self.time));
+        self.push_obst(Obst::new(obst.box_clone(), self.time));
+    }
+    /// What's been spawned so far and not yet put in the level.
+    #[cfg(test)]
+    pub(crate) fn added(&self) -> &[Obst] {
+        &self.obstacles_to_add
+    }
+    /// Adds an obstacle at `beat`, started then even if the frame it's added on comes a little late.\
+    /// It's added whether or not what scheduled it is still around, tagged with the tag in effect now.
+    /// ```
+    /// // an echo of the burst two beats after the bomb goes off
+    /// to_add.spawn_after(2.0, Bomb::new(start, target, 1.0, 8, 150.0, 10.0, Box::new(Bomb::pellet_spawner)));
+    /// ```
+    pub fn spawn_at(&mut self, beat: f32, obst: impl Obstacle) {
+        let mut obst = Obst::new(obst.box_clone(), beat);
+        obst.tag = self.tag;
+        obst.from_chart = self.charted;
+        self.deferred.add(obst);
+    }
+    /// `spawn_at` `beats` from now.
+    pub fn spawn_after(&mut self, beats: f32, obst: impl Obstacle) {
+        self.spawn_at(self.time + beats, obst);
+    }
+    /// Adds the scheduled obstacles due at `beat`.
+    fn spawn_due(&mut self, beat: f32) {
+        let mut deferred = std::mem::take(&mut self.deferred);
+        for obst in deferred.due(beat) {
+            self.push_obst(obst);
+        }
+        self.deferred = deferred;
+    }
+    /// Runs `f`, tagging every obstacle it adds that isn't tagged yet, including what spawners add on the way.\
+    /// Scopes nest, the innermost tag winning.
+    /// ```
+    /// accum.with_tag(BOSS_ADDS, |accum| patterns::laser_cage(accum, center, 6, 300.0, 2.0, 4.0));
+    /// ```
+    pub fn with_tag<R>(&mut self, tag: u32, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.within(Some(tag), f)
+    }
+    /// `with_tag`, or just `f` without a tag, keeping whatever tag is already in effect.
+    fn within<R>(&mut self, tag: Option<u32>, f: impl FnOnce(&mut Self) -> R) -> R {
+        let outer = self.tag;
+        self.tag = tag.or(outer);
+        let result = f(self);
+        self.tag = outer;
+        result
+    }
+    /// Runs the kill hook of an obstacle that's being removed, after the expiry hook if it expired.
+    /// What they spawn is tagged like it, and came from the chart if it did.
+    fn kill(&mut self, obst: &mut Obst, expired: bool) {
+        let outer = self.charted;
+        self.charted = obst.from_chart;
+        self.within(obst.tag, |accum| {
+            if expired { obst.obstacle.on_expire(accum); }
+            obst.obstacle.kill(accum)
+        });
+        self.charted = outer;
+    }
+    /// Removes the obstacles that are done or marked for removal in one pass, keeping the rest in order.\
+    /// Kill hooks run in list order, so what they spawn is added in that order, after this update's other spawns.
+    fn remove_dead(&mut self, obsts: &mut Vec<Obst>) {
+        for mut obst in obsts.extract_if(.., |obst| obst.marked_for_removal || obst.done()) {
+            // ones marked for removal are removed for that, even if they expired too
+            let expired = !obst.marked_for_removal;
+            self.kill(&mut obst, expired);
+            self.pellets.recycle(obst.obstacle);
+        }
+    }
+    /// Removes every obstacle tagged `tag`, ones added this update included, optionally running their kill hooks.\
+    /// Scheduled ones tagged `tag` are dropped, never having lived to be killed.
+    pub fn remove_tagged(&mut self, tag: u32, run_kill: bool) {
+        self.removals.push((Some(tag), run_kill));
+    }
+    /// Removes every obstacle, ones added this update included, optionally running their kill hooks. Scheduled ones are dropped.
+    pub fn remove_all(&mut self, run_kill: bool) {
+        self.removals.push((None, run_kill));
+    }
+    /// Freezes the obstacles tagged `tag` in place for `beats`, ones added this update included.
+    /// They aren't updated but are drawn, tinted, and still collide unless `harmless`. Their time picks up where it left off after.
+    pub fn freeze_tagged(&mut self, tag: u32, beats: f32, harmless: bool) {
+        self.freezes.push((Some(tag), beats, harmless));
+    }
+    /// Freezes every obstacle for `beats`, ones added this update included. See `freeze_tagged`.
+    pub fn freeze_all(&mut self, beats: f32, harmless: bool) {
+        self.freezes.push((None, beats, harmless));
+    }
+    /// Live obstacles tagged `tag`, including ones added this update.
+    pub fn count_tagged(&self, tag: u32) -> usize {
+        self.tagged.iter().filter(|&&t| t == tag).count() + self.obstacles_to_add.iter().filter(|o| o.tag == Some(tag)).count()
+    }
+    /// Carries out the removals and freezes asked for on `obsts` and the obstacles added this update.
+    /// Ones that should run their kill hooks are marked for removal, so they go the usual way.
+    fn apply_removals(&mut self, obsts: &mut Vec<Obst>) {
+        for (tag, beats, harmless) in std::mem::take(&mut self.freezes) {
+            let until = self.time + beats;
+            for list in [&mut *obsts, &mut self.obstacles_to_add] {
+                list.iter_mut().filter(|o| tag.is_none() || o.tag == tag).for_each(|o| o.freeze(until, harmless));
+            }
+        }
+        for (tag, run_kill) in std::mem::take(&mut self.removals) {
+            let matches = |o: &Obst| tag.is_none() || o.tag == tag;
+            self.deferred.retain(|o| !matches(o));
+            for list in [&mut *obsts, &mut self.obstacles_to_add] {
+                if run_kill {
+                    list.iter_mut().filter(|o| matches(o)).for_each(|o| o.marked_for_removal = true);
+                } else {
+                    list.retain(|o| !matches(o));
+                }
+            }
+        }
+    }
+    /// Adds a pellet, reusing the box of a dead one if there's one. Prefer it over `obst` for plain pellets.
+    pub fn pellet(&mut self, pos: Vec2, vel: Vec2, rad: f32) {
+        self.pellet_at(pos, vel, rad, self.time);
+    }
+    /// `pellet`, started at `time` instead of now.
+    pub fn pellet_at(&mut self, pos: Vec2, vel: Vec2, rad: f32, time: f32) {
+        let pellet = self.pellets.take(Pellet::new(pos, vel, rad));
+        self.push_obst(Obst::new(pellet, time));
+    }
+    /// Adds an obstacle drawn on `layer`, see `Obst::layer`.
+    pub fn obst_layered(&mut self, obst: impl Obstacle, layer: i8) {
+        self.push_obst(Obst::new(obst.box_clone(), self.time).layer(layer));
+    }
+    /// Adds an obstacle that started at `time` instead of now (e.g. back-dated `Periodic` steps).
+    pub fn obst_at(&mut self, obst: impl Obstacle, time: f32) {
+        self.push_obst(Obst::new(obst.box_clone(), time));
     }
     pub fn obstacle(&mut self, obst: Obst) {
-        self.obstacles_to_add.push(obst);
+        self.push_obst(obst);
+    }
+    /// Adds a score orb, unless there are already too many. Returns whether it was added.
+    pub fn orb(&mut self, orb: ScoreOrb) -> bool {
+        if self.orbs >= self.max_orbs { return false; }
+        self.orbs += 1;
+        self.obst(orb);
+        true
     }
     pub fn jerk(&mut self, jerk: Vec2) {
         self.jerk += jerk;
@@ -78,6 +636,33 @@ impl UpdateAccumulator {
     pub fn shake(&mut self, shake: f32) {
         self.shake += shake;
     }
+    /// Shakes the camera mostly along `shake`, for slams and the like. Adds up with the other directional shakes this frame.
+    pub fn shake_directional(&mut self, shake: Vec2) {
+        self.shake_lean += shake;
+    }
+    /// Multiplies the player's speed for this frame. Stacks multiplicatively.
+    pub fn speed_mul(&mut self, factor: f32) {
+        self.speed.mul *= factor;
+    }
+    /// Adds to the player's base speed for this frame, before multipliers.
+    pub fn speed_add(&mut self, delta: f32) {
+        self.speed.add += delta;
+    }
+    /// Shoves the player by `impulse` pixels, spread over `KNOCKBACK_BEATS`. Impulses from the same frame add up.
+    pub fn push_player(&mut self, impulse: Vec2) {
+        let idx = self.players.iter().position(Player::alive).unwrap_or(0);
+        self.push_nth_player(idx, impulse);
+    }
+    /// Like `push_player`, for a specific player in co-op.
+    pub fn push_nth_player(&mut self, idx: usize, impulse: Vec2) {
+        if let Some(push) = self.push.get_mut(idx) {
+            *push += impulse;
+        }
+    }
+    /// Heals every player still alive.
+    pub fn heal(&mut self, hp: u32) {
+        self.heal += hp;
+    }
     pub fn bg(&mut self, bg: Color) {
         self.bg = Some(bg);
     }
@@ -93,6 +678,56 @@ impl UpdateAccumulator {
     pub fn float(&mut self, float: f32) {
         self.float = Some(float)
     }
+    /// Eases the palette into `target` over `beats`, e.g. at the start of a section.
+    pub fn palette_shift(&mut self, target: Palette, beats: f32) {
+        self.palette_shift = Some((target, beats))
+    }
+    /// Flashes the screen `color`, fading out over `decay` beats. `intensity` is clamped to 0 to 1.
+    pub fn flash(&mut self, color: Color, intensity: f32, decay: f32) {
+        self.flashes.push(Flash::new(color, intensity, self.time, decay));
+    }
+    /// Points an arrow at the edge of the screen at `pos` for `beats` beats, whenever it's out of view.\
+    /// For threats coming from off-screen, `beats` being how long until they arrive. See `Obst::indicated` for following an obstacle.
+    /// ```
+    /// to_add.indicate(vec2(screen_width() + 200.0, y), IndicatorStyle::Chevron, 2.0);
+    /// ```
+    pub fn indicate(&mut self, pos: Vec2, style: IndicatorStyle, beats: f32) {
+        self.indicators.push(Indicator { pos, style, until: self.time + beats });
+    }
+    /// A small white flash for something hitting hard, unless the level turned these off.
+    pub fn impact_flash(&mut self, intensity: f32) {
+        self.impact_flashes.push(Flash::impact(intensity, self.time));
+    }
+    /// Fades the screen to black at `alpha` over `beats`, 0 fading back in. The latest fade wins.
+    pub fn fade(&mut self, alpha: f32, beats: f32) {
+        self.fade = Some((alpha.clamp(0.0, 1.0), beats));
+    }
+    /// Eases the speed of the whole game, music included, to `target` over `ramp_beats`, e.g. for bullet time. The latest request wins.
+    pub fn time_scale(&mut self, target: f32, ramp_beats: f32) {
+        self.time_scale = Some((target, ramp_beats));
+    }
+    /// Zooms the camera in by `amount` (0.1 for 10%), easing back out over `decay` beats. The latest pulse wins.
+    pub fn zoom_pulse(&mut self, amount: f32, decay: f32) {
+        self.zoom_pulse = Some((amount, decay));
+    }
+    /// Tilts the camera to `target` radians clockwise over `ramp_beats`. Only what's drawn turns, collisions don't.
+    pub fn cam_rotate(&mut self, target: f32, ramp_beats: f32) {
+        self.cam_rotate = Some((target, ramp_beats));
+    }
+    /// Freezes the game for `secs` of real time while the music plays on, e.g. when something slams down.\
+    /// Requests don't stack, the longest one wins.
+    pub fn hitstop(&mut self, secs: f32) {
+        self.hitstop = self.hitstop.max(secs);
+    }
+    /// Plays `id` at `volume` out of 1 and `pitch` times its speed at the end of the frame.\
+    /// Asking for the same sound at the same pitch again in the frame doesn't play it again, see `SoundQueue`.
+    pub fn sound(&mut self, id: SoundId, volume: f32, pitch: f32) {
+        self.sounds.request(SoundRequest { id, volume, pitch });
+    }
+    /// Eases the background's intensity to `intensity`, 1 being the usual.
+    pub fn background_intensity(&mut self, intensity: f32) {
+        self.background_intensity = Some(intensity)
+    }
     pub fn sm(&mut self, modifier: Box<dyn StateModifier>) {
         self.events.push(modifier);
     }
@@ -101,6 +736,15 @@ impl UpdateAccumulator {
     }
 }
 
+impl Effects for UpdateAccumulator {
+    fn jerk(&mut self, jerk: Vec2) { UpdateAccumulator::jerk(self, jerk) }
+    fn shake(&mut self, shake: f32) { UpdateAccumulator::shake(self, shake) }
+    fn shake_directional(&mut self, shake: Vec2) { UpdateAccumulator::shake_directional(self, shake) }
+    fn impact_flash(&mut self, intensity: f32) { UpdateAccumulator::impact_flash(self, intensity) }
+    fn hitstop(&mut self, secs: f32) { UpdateAccumulator::hitstop(self, secs) }
+    fn sound(&mut self, id: SoundId, volume: f32, pitch: f32) { UpdateAccumulator::sound(self, id, volume, pitch) }
+}
+
 pub trait ColorEase {
     fn apply(&self, time: f32) -> Color;
     fn box_clone(&self) -> Box<dyn ColorEase>;
@@ -150,6 +794,37 @@ impl GSEvent {
         self
     }
 }
+/// The obstacles alive at `to` had `events` played from `from` with no players around, stepping like a seek. For previewing charts.
+pub fn simulate(events: &[GSEvent], from: f32, to: f32, rng: GameRng) -> Vec<Obst> {
+    let mut accum = UpdateAccumulator::new();
+    accum.rng = rng;
+    let mut pending = events.iter().filter(|e| e.0 >= from && e.0 <= to).collect::<Vec<_>>();
+    pending.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut pending = pending.into_iter().peekable();
+    let mut obsts: Vec<Obst> = vec![];
+    let mut time = from;
+    loop {
+        while let Some(ev) = pending.next_if(|e| e.0 <= time) {
+            accum.time = ev.0;
+            ev.1.run(&mut accum, ModifyArgs::default());
+        }
+        accum.spawn_due(time);
+        obsts.append(&mut accum.obstacles_to_add);
+        if time >= to { break; }
+        let dt = SEEK_STEP_BEATS.min(to - time);
+        time += dt;
+        accum.time = time;
+        for obst in &mut obsts {
+            obst.tick_freeze(time, dt);
+            if obst.frozen { continue; }
+            let (t, dt) = (obst.age(time), obst.frame_beats(dt));
+            accum.within(obst.tag, |accum| obst.obstacle.update(accum, dt, t, dt, t));
+        }
+        accum.apply_removals(&mut obsts);
+        obsts.retain_mut(|o| !o.marked_for_removal && !o.done());
+    }
+    obsts
+}
 impl Clone for GSEvent {
     fn clone(&self) -> Self {
         GSEvent(self.0, self.1.box_clone())
@@ -157,29 +832,301 @@ impl Clone for GSEvent {
 }
 pub struct LevelState {
     events: Vec<GSEvent>,
+    /// Every event of the level, kept to replay them after respawning at a checkpoint.
+    chart: Vec<GSEvent>,
+    /// Calls levels written in code schedule alongside their events. Not replayed after respawning.
+    pub schedule: Schedule,
+    /// (beat, category) of every event in `chart`, sorted by beat, for the dev timeline
+    pub timeline: Vec<(f32, EventCategory)>,
+    /// Where the player respawns after dying, sorted. Without any, dying ends the run.
+    pub checkpoints: Vec<Checkpoint>,
+    /// Deaths in each section: before the first checkpoint, then after each one
+    section_deaths: Vec<usize>,
+    /// Deaths this attempt
+    pub deaths: usize,
+    /// Offset of the level in beats, to seek the music to a checkpoint
+    offset: f32,
     obsts: Vec<Obst>,
     time: f32,
-    pub player: Player,
-    pub hits_left: usize,
-    pub fg_color: Box<dyn ColorEase>,
-    pub bg_color: Box<dyn ColorEase>,
-    pub cam_jerk: Vec2,
-    pub cam_shake: f32,
-    pub cam_float: f32,
+    /// Every player, in the order of `GameState::player_count`. Downed players stay in here.
+    pub players: Vec<Player>,
+    player_history: VecDeque<(f32, Vec2)>,
+    pub budget: Option<(usize, BudgetPolicy)>,
+    pub dropped_spawns: usize,
+    /// Opacity of the red flash after a hit
+    pub hit_flash: f32,
+    pub score: Score,
+    /// (position, time, color) of recently collected pickups
+    pickup_sparkles: Vec<(Vec2, f32, Color)>,
+    /// (position, time) of recent grazes
+    graze_sparks: Vec<(Vec2, f32)>,
+    /// Speed modifiers from the last obstacle pass, applied to the next frame's movement.
+    speed_mods: SpeedModifiers,
+    /// (center, start time) of bomb shockwaves
+    shockwaves: Vec<(Vec2, f32)>,
+    /// Seconds since the run ended, while the death sequence plays
+    death: Option<f32>,
+    /// Recent snapshots of the world, played backwards after the death sequence
+    rewind: RewindBuffer,
+    /// Where the obstacles are this frame, for the collision checks
+    spatial: SpatialHash,
+    draw_order: DrawOrder,
+    /// Dead pellets, for new ones to reuse
+    pellet_pool: PelletPool,
+    /// Sparks and debris, drawn over the obstacles
+    particles: ParticleSystem,
+    /// Obstacles scheduled for later beats
+    pub deferred: DeferredSpawns,
+    /// Pellets drawn this frame
+    circles: CircleBatch,
+    /// The trail being drawn, kept between frames so drawing it doesn't allocate
+    ribbon_points: Vec<Vec2>,
+    /// Seconds into the rewind, while it plays
+    rewinding: Option<f32>,
+    /// The selected option while the pause menu is open. Nothing updates while paused.
+    pub paused: Option<PauseOption>,
+    /// Beats left of the count-in after resuming
+    count_in: Option<f32>,
+    /// (position, velocity in pixels per second) of the particles bursting from the player on death
+    death_particles: Vec<(Vec2, Vec2)>,
+    /// Shards of a broken shield: (origin, direction, spawn time)
+    shards: Vec<(Vec2, Vec2, f32)>,
+    /// (position, time) of each player's motion trail
+    trails: Vec<TrailBuffer>,
+    /// Beat each arena edge was last touched at, in the order left, top, right, bottom.
+    edge_touched: [f32; 4],
+    /// The level's own colors, shown over the palette's if it allows
+    pub fg_color: Option<Box<dyn ColorEase>>,
+    pub bg_color: Option<Box<dyn ColorEase>>,
+    pub palette: Palette,
+    pub palette_shift: Option<PaletteShift>,
+    /// Drawn between the background color and the obstacles
+    pub background: Option<BackgroundLayer>,
+    /// Flashes still fading out, drawn over the obstacles
+    flashes: Vec<Flash>,
+    /// Arrows toward spots threats are coming from
+    indicators: Vec<Indicator>,
+    fade: Fade,
+    /// Seconds left of the current hit-stop
+    hitstop: f32,
+    /// Set during a hit-stop, so the next update catches up to the music
+    resync: bool,
+    /// What the last update did, for the debug overlay
+    pub counters: FrameCounters,
+    pub camera: Camera,
 }
 impl LevelState {
+    /// The palette as of now, partway through a shift if there's one.
+    pub fn current_palette(&self) -> Palette {
+        self.palette_shift.map_or(self.palette, |shift| shift.at(self.time))
+    }
+    /// Starts fading to `to` from wherever the fade is now.
+    fn start_fade(&mut self, to: f32, beats: f32) {
+        self.fade = Fade { from: self.fade.alpha(self.time), to, start: self.time, beats };
+    }
+    /// Starts easing into `to` from wherever the palette is now.
+    fn shift_palette(&mut self, to: Palette, beats: f32) {
+        self.palette = self.current_palette();
+        self.palette_shift = Some(PaletteShift { from: self.palette, to, start: self.time, beats });
+    }
+    /// Index of the last checkpoint passed.
+    pub fn last_checkpoint(&self) -> Option<usize> {
+        self.checkpoints.iter().rposition(|c| c.beat <= self.time)
+    }
+    fn sort_checkpoints(&mut self) {
+        self.checkpoints.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+        self.section_deaths.resize(self.checkpoints.len() + 1, 0);
+    }
+    /// Counts a death in the current section.
+    fn record_death(&mut self) {
+        let section = self.last_checkpoint().map_or(0, |i| i + 1);
+        if section >= self.section_deaths.len() { self.section_deaths.resize(section + 1, 0); }
+        self.section_deaths[section] += 1;
+    }
+    /// (section, deaths) of every section someone died in.
+    pub fn section_deaths(&self) -> Vec<(String, usize)> {
+        self.section_deaths.iter().enumerate().filter(|&(_, &deaths)| deaths > 0).map(|(i, &deaths)| {
+            let name = if i == 0 { "before the first checkpoint".to_string() } else { self.checkpoints[i - 1].label() };
+            (name, deaths)
+        }).collect()
+    }
+    /// The first checkpoint on the way from `from` to `to`, else `to`.\
+    /// Checkpoints less than `CHECKPOINT_SNAP_MARGIN` behind `from` are passed over when going back.
+    fn snap_to_checkpoint(&self, from: f32, to: f32) -> f32 {
+        let mut beats = self.checkpoints.iter().map(|c| c.beat);
+        if to < from {
+            beats.rev().find(|&b| b > to && b < from - CHECKPOINT_SNAP_MARGIN).unwrap_or(to)
+        } else {
+            beats.find(|&b| b > from && b < to).unwrap_or(to)
+        }
+    }
     pub fn new() -> Self {
         LevelState {
             events: vec![],
+            chart: vec![],
+            schedule: Schedule::default(),
+            timeline: vec![],
+            checkpoints: vec![],
+            section_deaths: vec![],
+            deaths: 0,
+            offset: 0.0,
             obsts: vec![],
-            player: Player::default(),
+            players: vec![Player::default()],
+            player_history: VecDeque::new(),
+            budget: None,
+            dropped_spawns: 0,
             time: 0.0,
-            hits_left: 3,
-            fg_color: Box::new(|_|Color::new(1.0, 0.0, 0.5, 1.0)),
-            bg_color: Box::new(|_|Color::new(0.0, 0.0, 0.0, 1.0)),
-            cam_jerk: Vec2::ZERO,
-            cam_shake: 0.0,
-            cam_float: 0.0,
+            hit_flash: 0.0,
+            score: Score::default(),
+            pickup_sparkles: vec![],
+            graze_sparks: vec![],
+            shards: vec![],
+            shockwaves: vec![],
+            death: None,
+            rewind: RewindBuffer::default(),
+            spatial: SpatialHash::default(),
+            draw_order: DrawOrder::default(),
+            pellet_pool: PelletPool::default(),
+            particles: ParticleSystem::new(MAX_PARTICLES, 0),
+            deferred: DeferredSpawns::default(),
+            circles: CircleBatch::default(),
+            ribbon_points: Vec::with_capacity(TRAIL_CAPACITY),
+            rewinding: None,
+            paused: None,
+            count_in: None,
+            death_particles: vec![],
+            speed_mods: SpeedModifiers::default(),
+            trails: vec![TrailBuffer::new()],
+            edge_touched: [f32::NEG_INFINITY; 4],
+            fg_color: None,
+            bg_color: None,
+            palette: Palette::default(),
+            palette_shift: None,
+            background: None,
+            flashes: vec![],
+            indicators: vec![],
+            fade: Fade::default(),
+            hitstop: 0.0,
+            resync: false,
+            counters: FrameCounters::default(),
+            camera: Camera::default(),
+        }
+    }
+}
+impl LevelState {
+    /// Where player `idx` out of `count` spawns, spread out vertically with the first player on top.
+    fn spawn_pos(idx: usize, count: usize) -> Vec2 {
+        vec2(0.125, (idx + 1) as f32 / (count + 1) as f32) * screen_size()
+    }
+    /// Runs the events and scheduled calls due by `time`, and adds the spawns scheduled by then.
+    fn run_events(&mut self, accum: &mut UpdateAccumulator, time: f32) {
+        while self.events.first().is_some_and(|ev| ev.0 <= time) {
+            let ev = self.events.remove(0);
+            accum.time = ev.0;
+            accum.events_run += 1;
+            ev.1.run(accum, ModifyArgs::default());
+        }
+        self.schedule.run(time, accum);
+        accum.spawn_due(time);
+    }
+    /// One of the early steps of a long frame: the obstacles move on by `dt` to `time`, die and spawn as if the frame had ended there.\
+    /// Returns where something lethal touched each player on the way, at the point `along` the way through their move this frame from `from`.
+    fn substep(&mut self, accum: &mut UpdateAccumulator, time: f32, dt: f32, parallel: bool, from: &[Vec2], along: f32) -> Vec<Option<Contact>> {
+        self.time = time;
+        accum.time = time;
+        self.update_obstacles(accum, dt, parallel);
+        accum.apply_removals(&mut self.obsts);
+        let touched = self.players.iter().zip(from).map(|(player, &from)| {
+            if !player.alive() || !player.vulnerable() { return None; }
+            let at = Player { pos: from.lerp(player.pos, along), ..*player };
+            self.obsts.iter().filter(|o| o.obstacle.lethal()).find_map(|o| o.contact(at))
+        }).collect();
+        accum.remove_dead(&mut self.obsts);
+        self.obsts.append(&mut accum.obstacles_to_add);
+        accum.live_obstacles = self.obsts.len();
+        accum.tagged = self.obsts.iter().filter_map(|o| o.tag).collect();
+        touched
+    }
+    /// Moves the obstacles on to `time` through a frame `beat_dt` long split into `substeps` steps, running the events due between them.\
+    /// Returns where something lethal touched each player in the earlier steps, see `substep`.
+    fn advance(&mut self, accum: &mut UpdateAccumulator, time: f32, beat_dt: f32, substeps: usize, parallel: bool, from: &[Vec2]) -> Vec<Option<Contact>> {
+        let step_dt = beat_dt / substeps as f32;
+        let mut touched = vec![None; self.players.len()];
+        for step in 0..substeps - 1 {
+            let along = (step + 1) as f32 / substeps as f32;
+            let step_touched = self.substep(accum, step_time(time, beat_dt, substeps, step), step_dt, parallel, from, along);
+            for (touched, step_touched) in touched.iter_mut().zip(step_touched) {
+                *touched = touched.or(step_touched);
+            }
+            self.run_events(accum, step_time(time, beat_dt, substeps, step + 1));
+        }
+        self.time = time;
+        accum.time = time;
+        self.update_obstacles(accum, step_dt, parallel);
+        accum.apply_removals(&mut self.obsts);
+        touched
+    }
+    /// Updates every obstacle, the movers on other threads first if `parallel` and there are enough of them.
+    fn update_obstacles(&mut self, accum: &mut UpdateAccumulator, beat_dt: f32, parallel: bool) {
+        let time = self.time;
+        self.obsts.iter_mut().for_each(|o| o.tick_freeze(time, beat_dt));
+        let moved = if parallel { parallel::update_movers(&mut self.obsts, self.time, beat_dt) } else { None };
+        let mut effects = moved.iter().flatten().copied().peekable();
+        for (i, obst) in self.obsts.iter_mut().enumerate() {
+            // what the movers before this one did happens first, like it would have in order
+            while let Some((_, effect)) = effects.next_if(|&(at, _)| at < i) {
+                effect.apply(accum);
+            }
+            if obst.frozen || moved.is_some() && obst.obstacle.parallel().is_some() { continue; }
+            let (t, dt) = (obst.age(self.time), obst.frame_beats(beat_dt));
+            // whatever a chart's obstacle spawns came from the chart too
+            accum.charted = obst.from_chart;
+            // what a tagged obstacle spawns is tagged like it
+            accum.within(obst.tag, |accum| obst.obstacle.update(accum, dt, t, dt, t));
+        }
+        effects.for_each(|(_, effect)| effect.apply(accum));
+        accum.charted = false;
+    }
+    /// Keeps `player` inside `arena`, lighting up the edges it was pushed back from.
+    fn clamp_player(&mut self, player: &mut Player, arena: Rect) {
+        for (edge, touched) in player.clamp_to(arena).into_iter().enumerate() {
+            if touched { self.edge_touched[edge] = self.time; }
+        }
+    }
+    /// Removes non-essential obstacles over the budget according to its policy, running their kill hooks on `accum`.\
+    /// What those spawn is left in `accum` to be added after.
+    fn enforce_budget(&mut self, accum: &mut UpdateAccumulator) {
+        let (max, policy) = match self.budget {
+            Some((max, policy)) if policy != BudgetPolicy::DropNew => (max, policy),
+            _ => return
+        };
+        let excess = self.obsts.len().saturating_sub(max);
+        if excess == 0 { return; }
+        let mut candidates = (0..self.obsts.len()).filter(|&i| !self.obsts[i].essential).collect::<Vec<usize>>();
+        let players = &self.players;
+        let dist = |pos: Vec2| players.iter().filter(|p| p.alive()).map(|p| p.pos.distance_squared(pos)).fold(f32::INFINITY, f32::min);
+        match policy {
+            BudgetPolicy::DropFurthest => candidates.sort_by(|&a, &b| {
+                let (a, b) = (&self.obsts[a], &self.obsts[b]);
+                match (a.obstacle.anchor(), b.obstacle.anchor()) {
+                    (Some(pa), Some(pb)) => dist(pb).total_cmp(&dist(pa)),
+                    (Some(_), None) => std::cmp::Ordering::Less,
+                    (None, Some(_)) => std::cmp::Ordering::Greater,
+                    (None, None) => a.start_time.total_cmp(&b.start_time)
+                }
+            }),
+            _ => candidates.sort_by(|&a, &b| self.obsts[a].start_time.total_cmp(&self.obsts[b].start_time))
+        }
+        let mut drop = vec![false; self.obsts.len()];
+        for &i in candidates.iter().take(excess) {
+            drop[i] = true;
+        }
+        self.dropped_spawns += excess.min(candidates.len());
+        // dropped ones are killed like any other, in list order, so a culled bomb still bursts
+        let mut idx = 0;
+        for mut obst in self.obsts.extract_if(.., |_| { idx += 1; drop[idx - 1] }) {
+            accum.kill(&mut obst, false);
+            accum.pellets.recycle(obst.obstacle);
         }
     }
 }
@@ -187,31 +1134,365 @@ pub struct GameState {
     pub state: EparState,
     pub mus: Music,
     pub bpm: f32,
-    pub wav: Wav
+    pub wav: Wav,
+    pub input: Input,
+    /// Input of the second player in co-op
+    pub coop_input: Input,
+    /// Players in the next level, up to `MAX_PLAYERS`
+    pub player_count: usize,
+    pub coop_rule: CoopRule,
+    /// Beats of invulnerability after a hit. 0 disables invulnerability frames.
+    pub iframe_beats: f32,
+    /// The area the player is kept inside. `None` uses the whole screen.
+    pub arena: Option<Rect>,
+    /// Level, start and speed of the level being played, for restarting.
+    pub current_level: Option<(EparLevel, f32, f32)>,
+    /// The chart being played instead of a built-in level, if any.
+    pub current_chart: Option<(Chart, f32, f32)>,
+    /// Dev flag: watches chart files for changes and reloads them while they play.
+    pub hot_reload: bool,
+    pub reload_anchor: ReloadAnchor,
+    pub chart_watch: Option<ChartWatch>,
+    /// Practice mode: the seek actions jump around the chart and the beat is shown.
+    pub practice: bool,
+    /// Seed of every run, overriding the chart's. `None` picks a new one each run.
+    pub seed: Option<u64>,
+    pub rng: GameRng,
+    pub scoring: ScoringConfig,
+    /// Whether the song starts over when it ends, so the run only ends on death (e.g. endless mode)
+    pub loop_music: bool,
+    /// Whether slam lasers and bombs flash the screen. Charts can turn this off.
+    pub impact_flashes: bool,
+    /// Difficulty modifiers of the next run
+    pub modifiers: Modifiers,
+    pub grading: Grading,
+    pub save: SaveData,
+    /// Hit points the player starts the level with.
+    pub max_hp: u32,
+    /// Extra radius around the player that counts as a graze
+    pub graze_margin: f32,
+    /// Beats before the same obstacle can be grazed again
+    pub graze_cooldown: f32,
+    /// Speed multiplier while focused. Not held up by `speed_floor`.
+    pub focus_factor: f32,
+    /// Lowest speed modifiers can slow the player to, as a fraction of `Player::pps`, before focus
+    pub speed_floor: f32,
+    /// Highest speed modifiers can speed the player up to, as a fraction of `Player::pps`
+    pub speed_ceiling: f32,
+    /// Most score orbs alive at once
+    pub max_orbs: usize,
+    /// Bomb charges each player starts with
+    pub bomb_charges: u32,
+    /// Beats between bombs
+    pub bomb_cooldown: f32,
+    /// Radius a bomb's shockwave grows to
+    pub bomb_radius: f32,
+    /// Beats the shockwave takes to grow to its full radius
+    pub bomb_beats: f32,
+    /// Whether obstacles cleared by bombs run their `kill` hooks (e.g. bombs bursting into pellets)
+    pub bomb_triggers_kill: bool,
+    /// Seconds the world freezes for when the run ends
+    pub hitstop_secs: f32,
+    /// Multiplies the hit-stops obstacles ask for, set by charts. 0 disables them.
+    pub hitstop_scale: f32,
+    /// Seconds the game freezes for on a graze. 0 disables it.
+    pub graze_hitstop_secs: f32,
+    /// Seconds of slow motion after the hit-stop, before the run actually ends
+    pub slowmo_secs: f32,
+    /// Time scale at the start of the slow motion
+    pub slowmo_scale: f32,
+    /// How fast the game runs, see `set_time_scale`
+    pub time_scale: TimeScale,
+    /// Checks collisions along the player's path when it moves further than its radius in a frame.
+    pub swept_collision: bool,
+    /// Updates the obstacles that can be on several threads, when there are thousands of them. Comes out the same either way.
+    pub parallel_updates: bool,
+    /// Longest step obstacles take in beats, longer frames are split into several steps so nothing skips through the player. 0 disables it.
+    pub substep_beats: f32,
+    /// Most steps a frame is split into, the steps getting longer past it
+    pub max_substeps: usize,
+    /// What obstacles' sounds play, nothing until loaded
+    pub sounds: SoundBank,
+    /// If set, the focus key toggles focus mode instead of having to be held.
+    pub focus_toggle: bool,
+    pub trail_enabled: bool,
+    /// Beats of movement shown by the player's trail
+    pub trail_beats: f32,
+    /// Overrides the player's color for the trail
+    pub trail_color: Option<Color>,
+    pub debug: DebugOverlay,
+    /// Freezes the level to look into its obstacles, with `hot_reload` on
+    pub inspector: Inspector,
 }
 impl GameState {
     pub fn set_fg_color(&mut self, clr: Color) {
-        self.state.map(|s|s.fg_color = Box::new(move|_|clr));
+        self.state.map(|s|s.fg_color = Some(Box::new(move|_|clr)));
     }
     pub fn set_bg_color(&mut self, clr: Color) {
-        self.state.map(|s|s.bg_color = Box::new(move|_|clr));
+        self.state.map(|s|s.bg_color = Some(Box::new(move|_|clr)));
+    }
+    /// Draws `visual` behind the obstacles, in `color` or else a dim foreground color.
+    pub fn set_background(&mut self, visual: Box<dyn Background>, color: Option<Color>) {
+        self.state.map(|s| s.background = Some(BackgroundLayer::new(visual, color)));
+    }
+    /// Creates a game state using the mouse-follow control scheme alongside the keyboard.
+    pub fn with_mouse_control(mus: Music) -> Self {
+        let mut gs = Self::new(mus);
+        gs.input.mouse_enabled = true;
+        gs
     }
     pub fn new(mus: Music) -> Self {
         GameState {
             bpm: 0.0,
             state: EparState::MainMenu,
             mus,
-            wav: Wav::default()
+            wav: Wav::default(),
+            input: Input::player_one(),
+            coop_input: Input::player_two(),
+            player_count: 1,
+            coop_rule: CoopRule::default(),
+            iframe_beats: DEFAULT_IFRAME_BEATS,
+            arena: None,
+            current_level: None,
+            current_chart: None,
+            hot_reload: false,
+            reload_anchor: ReloadAnchor::default(),
+            chart_watch: None,
+            practice: false,
+            seed: None,
+            rng: GameRng::default(),
+            scoring: ScoringConfig::default(),
+            loop_music: false,
+            impact_flashes: true,
+            modifiers: Modifiers::default(),
+            grading: Grading::default(),
+            save: SaveData::default(),
+            max_hp: DEFAULT_MAX_HP,
+            graze_margin: DEFAULT_GRAZE_MARGIN,
+            graze_cooldown: DEFAULT_GRAZE_COOLDOWN,
+            focus_factor: DEFAULT_FOCUS_FACTOR,
+            speed_floor: DEFAULT_SPEED_FLOOR,
+            speed_ceiling: DEFAULT_SPEED_CEILING,
+            max_orbs: DEFAULT_MAX_ORBS,
+            bomb_charges: DEFAULT_BOMB_CHARGES,
+            bomb_cooldown: DEFAULT_BOMB_COOLDOWN,
+            bomb_radius: DEFAULT_BOMB_RADIUS,
+            bomb_beats: DEFAULT_BOMB_BEATS,
+            bomb_triggers_kill: false,
+            hitstop_secs: DEFAULT_HITSTOP_SECS,
+            hitstop_scale: 1.0,
+            graze_hitstop_secs: DEFAULT_GRAZE_HITSTOP_SECS,
+            slowmo_secs: DEFAULT_SLOWMO_SECS,
+            slowmo_scale: DEFAULT_SLOWMO_SCALE,
+            time_scale: TimeScale::default(),
+            swept_collision: true,
+            parallel_updates: false,
+            substep_beats: DEFAULT_SUBSTEP_BEATS,
+            max_substeps: DEFAULT_MAX_SUBSTEPS,
+            sounds: SoundBank::default(),
+            focus_toggle: false,
+            trail_enabled: true,
+            trail_beats: DEFAULT_TRAIL_BEATS,
+            trail_color: None,
+            debug: DebugOverlay::default(),
+            inspector: Inspector::default(),
+        }
+    }
+    /// Amount of grazes in the current level.
+    pub fn grazes(&self) -> usize {
+        match &self.state {
+            EparState::InGame(s) => s.score.stats.grazes,
+            _ => 0
         }
     }
     pub fn load_level(&mut self, lvl: EparLevel, start: f32, speed: f32) -> Result<(), Box<dyn Error>> {
-        let state = LevelState::new();
+        self.current_level = Some((lvl, start, speed));
+        self.current_chart = None;
+        self.chart_watch = None;
         self.wav = Wav::default();
+        self.seed_rng(None);
+        let palette = self.save.settings.palette();
+        self.state.map(|s| s.palette = palette);
         let (offset, bpm, audiofile) = lvl.level()(self);
-        self.bpm = bpm;
+        self.start_level(offset, TempoMap::constant(bpm), audiofile, start, speed)
+    }
+    /// Plays a chart loaded from a file, like `load_level` does a built-in level.
+    pub fn load_chart(&mut self, chart: Chart, start: f32, speed: f32) -> Result<(), Box<dyn Error>> {
+        self.current_level = None;
+        self.wav = Wav::default();
+        self.seed_rng(chart.seed);
+        let palette = chart.meta.palette.as_deref().and_then(Palette::named).unwrap_or_else(|| self.save.settings.palette());
+        self.state.map(|s| s.palette = palette);
+        self.impact_flashes = chart.impact_flashes;
+        self.hitstop_scale = chart.hitstop;
+        self.add_events(chart.events());
+        if let Some((kind, color)) = chart.background {
+            self.set_background(kind.create(), color);
+        }
+        for c in chart.checkpoints_with_start() {
+            self.add_checkpoint(c);
+        }
+        let res = self.start_level(chart.offset, chart.tempo.clone(), &chart.audio, start, speed);
+        // the chart knows what its events spawn
+        let timeline = chart.timeline();
+        self.state.map(|s| s.timeline = timeline);
+        self.current_chart = Some((chart, start, speed));
+        res
+    }
+    /// Seeds the run from `seed`, else `chart_seed`, else a random one.
+    fn seed_rng(&mut self, chart_seed: Option<u64>) {
+        let random = || (macroquad::rand::rand() as u64) << 32 | macroquad::rand::rand() as u64;
+        self.rng = GameRng::new(self.seed.or(chart_seed).unwrap_or_else(random));
+    }
+    /// Loads and plays the chart at `path`, watching it for changes if `hot_reload` is set.
+    pub fn load_chart_file(&mut self, path: impl AsRef<Path>, start: f32, speed: f32) -> Result<(), Box<dyn Error>> {
+        let chart = Chart::load(&path)?;
+        self.chart_watch = self.hot_reload.then(|| ChartWatch::new(path.as_ref()));
+        self.load_chart(chart, start, speed)
+    }
+    /// Jumps to `target` (chart beats), replaying the chart with every obstacle in the state it would be in there.\
+    /// The last `SEEK_LOOKBACK_BEATS` are simulated so stateful obstacles (`Periodic`, `CenterProj`...) are right,
+    /// while the events before that only get to change colors and such. The players get brief invulnerability.
+    pub fn seek_beat(&mut self, target: f32) {
+        let speed = self.mus.get_speed();
+        let arena = self.arena();
+        let EparState::InGame(s) = &mut self.state else { return };
+        // the music can't start before its beginning
+        let target = target.max(s.offset);
+        let from = target - SEEK_LOOKBACK_BEATS;
+        self.rng.rewind(from);
+        let mut accum = UpdateAccumulator::new();
+        accum.players = s.players.clone();
+        accum.push = vec![Vec2::ZERO; s.players.len()];
+        accum.arena = arena;
+        accum.rng = std::mem::take(&mut self.rng);
+        accum.modifiers = self.modifiers;
+        s.obsts.clear();
+        // the replay schedules them again
+        s.deferred.clear();
+        s.events = s.chart.iter().filter(|e| e.0 >= target).cloned().collect();
+        let mut past = s.chart.iter().filter(|e| e.0 < target).cloned().collect::<VecDeque<GSEvent>>();
+        while past.front().is_some_and(|e| e.0 < from) {
+            let ev = past.pop_front().unwrap();
+            accum.time = ev.0;
+            ev.1.run(&mut accum, ModifyArgs::default());
+            accum.obstacles_to_add.clear();
+            accum.deferred.clear();
+        }
+        s.time = from;
+        while s.time < target {
+            while past.front().is_some_and(|e| e.0 <= s.time) {
+                let ev = past.pop_front().unwrap();
+                accum.time = ev.0;
+                ev.1.run(&mut accum, ModifyArgs::default());
+            }
+            accum.spawn_due(s.time);
+            s.obsts.append(&mut accum.obstacles_to_add);
+            let dt = SEEK_STEP_BEATS.min(target - s.time);
+            s.time += dt;
+            accum.time = s.time;
+            s.update_obstacles(&mut accum, dt, self.parallel_updates);
+            accum.apply_removals(&mut s.obsts);
+            accum.remove_dead(&mut s.obsts);
+        }
+        s.obsts.append(&mut accum.obstacles_to_add);
+        s.deferred = std::mem::take(&mut accum.deferred);
+        s.time = target;
+        for player in &mut s.players {
+            player.isecs = player.isecs.max(RESPAWN_IFRAME_BEATS);
+            player.knockback = Vec2::ZERO;
+        }
+        s.trails.iter_mut().for_each(TrailBuffer::clear);
+        s.player_history.clear();
+        s.graze_sparks.clear();
+        s.particles.clear();
+        s.shards.clear();
+        s.shockwaves.clear();
+        s.flashes.clear();
+        s.indicators.clear();
+        s.hitstop = 0.0;
+        s.resync = false;
+        s.rewind.clear();
+        s.camera.clear_jerk();
+        s.camera.clear_shake();
+        if let Some(fg) = accum.fg { s.fg_color = Some(Box::new(move |_|fg)); }
+        if let Some(bg) = accum.bg { s.bg_color = Some(Box::new(move |_|bg)); }
+        if let Some((to, beats)) = accum.palette_shift { s.shift_palette(to, beats); }
+        if let Some(float) = accum.float { s.camera.float = float; }
+        if let Some((to, beats)) = accum.fade { s.start_fade(to, beats); }
+        if let Some((to, _)) = accum.cam_rotate { s.camera.rotate_to(to, 0.0); }
+        if let (Some(intensity), Some(background)) = (accum.background_intensity, &mut s.background) { background.target_intensity = intensity; }
+        let seek = (target - s.offset) / speed;
+        self.rng = std::mem::take(&mut accum.rng);
+        // seeking skips the ramp
+        if let Some((scale, _)) = accum.time_scale { self.set_time_scale(scale, 0.0); }
+        for i in accum.events {
+            i.run(self, ModifyArgs::default());
+        }
+        if let Err(e) = self.mus.seek_to(seek) {
+            println!("couldn't seek to beat {target}: {e}");
+        }
+    }
+    /// Swaps in a changed chart: clears what the old one spawned and rewinds to `reload_anchor`.\
+    /// The players are left alone. Changes to the bpm, offset or audio need a restart.
+    fn reload_chart(&mut self, chart: Chart) {
+        let speed = self.mus.get_speed();
+        let anchor = self.reload_anchor;
+        let seek = match &mut self.state {
+            EparState::InGame(s) => {
+                let beat = anchor.rewind(s.time);
+                s.obsts.retain(|o| !o.from_chart);
+                s.deferred.retain(|o| !o.from_chart);
+                s.chart = chart.events();
+                s.chart.sort_by(|a, b| a.0.total_cmp(&b.0));
+                s.timeline = chart.timeline();
+                s.events = s.chart.iter().filter(|e| e.0 >= beat).cloned().collect();
+                s.checkpoints = chart.checkpoints_with_start();
+                s.sort_checkpoints();
+                s.time = beat;
+                self.rng.rewind(beat);
+                (beat - s.offset) / speed
+            }
+            _ => return
+        };
+        if let Some((current, ..)) = &mut self.current_chart {
+            *current = chart;
+        }
+        if let Err(e) = self.mus.seek_to(seek) {
+            println!("couldn't seek after reloading the chart: {e}");
+        }
+    }
+    fn start_level(&mut self, offset: f32, tempo: TempoMap, audiofile: &str, start: f32, speed: f32) -> Result<(), Box<dyn Error>> {
+        let speed = speed * self.modifiers.speed_rate;
+        self.bpm = tempo.bpm_at_beats(start - offset);
+        let max_hp = self.player_max_hp();
+        let bombs = self.bomb_charges;
+        let count = self.player_count.clamp(1, MAX_PLAYERS);
+        let modifiers = self.modifiers;
+        self.state.map(|s| {
+            s.players = (0..count).map(|i| Player {
+                pos: LevelState::spawn_pos(i, count),
+                hp: max_hp,
+                max_hp,
+                bombs,
+                color: if i == 0 { s.palette.player } else { player_color(i) },
+                rad: Player::default().rad * modifiers.player_size.scale(),
+                ..Player::default()
+            }).collect();
+            s.score.stats.modifiers = modifiers;
+            s.trails = (0..count).map(|_| TrailBuffer::new()).collect();
+        });
         self.sort();
+        self.state.map(|s| {
+            s.chart = s.events.clone();
+            s.timeline = s.chart.iter().map(|e| (e.0, EventCategory::Other)).collect();
+            s.section_deaths.clear();
+            s.sort_checkpoints();
+            s.offset = offset;
+        });
         self.wav.load(audiofile)?;
-        self.mus.replace(&self.wav, bpm, offset / speed);
+        self.wav.set_looping(self.loop_music);
+        self.mus.replace(&self.wav, tempo, offset / speed);
         self.mus.speed(speed);
         self.snip(start + offset);
         self.mus.seek(start / speed)?;
@@ -220,18 +1501,185 @@ impl GameState {
     pub fn reset(&mut self) {
         self.mus.stop();
         self.state.map(|s| {
-            s.fg_color = Box::new(|_|Color::new(1.0, 0.0, 0.5, 1.0));
-            s.bg_color = Box::new(|_|Color::new(0.0, 0.0, 0.0, 1.0));
-            s.cam_float = 0.0;
-            s.cam_jerk = Vec2::ZERO;
-            s.cam_shake = 0.0;
-            s.hits_left = 3;
+            s.fg_color = None;
+            s.bg_color = None;
+            s.palette_shift = None;
+            s.background = None;
+            s.flashes.clear();
+            s.indicators.clear();
+            s.fade = Fade::default();
+            s.hitstop = 0.0;
+            s.resync = false;
+            s.camera = Camera::default();
+            s.hit_flash = 0.0;
+            s.score = Score::default();
+            s.pickup_sparkles.clear();
+            s.graze_sparks.clear();
+            s.particles.clear();
+            s.shards.clear();
+            s.shockwaves.clear();
+            s.death = None;
+            s.rewind.clear();
+            s.rewinding = None;
+            s.schedule = Schedule::default();
+            s.timeline.clear();
+            s.paused = None;
+            s.count_in = None;
+            s.death_particles.clear();
+            s.speed_mods = SpeedModifiers::default();
+            s.trails.iter_mut().for_each(TrailBuffer::clear);
+            for player in &mut s.players {
+                player.shield = None;
+                player.hp_lost_at = f32::NEG_INFINITY;
+            }
+            s.edge_touched = [f32::NEG_INFINITY; 4];
             s.time = 0.0;
             s.events = vec![];
+            s.chart = vec![];
+            s.checkpoints = vec![];
+            s.section_deaths = vec![];
             s.obsts = vec![];
+            s.deferred.clear();
+            s.player_history.clear();
+            s.budget = None;
+            s.dropped_spawns = 0;
         });
         self.bpm = 0.0;
         self.wav = Wav::default();
+        self.iframe_beats = DEFAULT_IFRAME_BEATS;
+        self.max_hp = DEFAULT_MAX_HP;
+        self.graze_margin = DEFAULT_GRAZE_MARGIN;
+        self.graze_cooldown = DEFAULT_GRAZE_COOLDOWN;
+        self.arena = None;
+        self.speed_floor = DEFAULT_SPEED_FLOOR;
+        self.speed_ceiling = DEFAULT_SPEED_CEILING;
+        self.scoring = ScoringConfig::default();
+        self.loop_music = false;
+        self.impact_flashes = true;
+        self.hitstop_scale = 1.0;
+        self.set_time_scale(1.0, 0.0);
+        self.inspector = Inspector::default();
+    }
+    /// Eases the speed of the game to `target` over `ramp_beats` of real time.\
+    /// Obstacles and the beat clock slow down with the music, so they stay in sync. Pausing stops everything regardless.
+    pub fn set_time_scale(&mut self, target: f32, ramp_beats: f32) {
+        self.time_scale.set(target, ramp_beats);
+        self.mus.set_time_scale(self.time_scale.current);
+    }
+    /// `max_hp`, unless a modifier says otherwise.
+    pub fn player_max_hp(&self) -> u32 {
+        if self.modifiers.one_hp { 1 } else { self.max_hp }
+    }
+    pub fn arena(&self) -> Rect {
+        self.arena.unwrap_or_else(|| Rect::new(0.0, 0.0, screen_width(), screen_height()))
+    }
+    /// Marks `beat` as a checkpoint to respawn at. Call while loading a level.
+    pub fn checkpoint(&mut self, beat: f32) {
+        self.add_checkpoint(Checkpoint::new(beat));
+    }
+    pub fn add_checkpoint(&mut self, checkpoint: Checkpoint) {
+        self.state.map(|s| s.checkpoints.push(checkpoint));
+    }
+    /// Respawns every player at the last checkpoint that was passed, replaying the chart from there.\
+    /// Obstacles can't be rewound, so all of them are cleared and the chart spawns them again:
+    /// the section starts as clean as if it was reached without dying.\
+    /// Returns false if there is no checkpoint to respawn at.
+    pub fn respawn(&mut self) -> bool {
+        let speed = self.mus.get_speed();
+        let max_hp = self.player_max_hp();
+        let bombs = self.bomb_charges;
+        let checkpoint = match &mut self.state {
+            EparState::InGame(s) => match s.last_checkpoint().map(|i| s.checkpoints[i].beat) {
+                Some(checkpoint) => {
+                    s.deaths += 1;
+                    s.obsts.clear();
+                    s.deferred.clear();
+                    s.events = s.chart.iter().filter(|e| e.0 >= checkpoint).cloned().collect();
+                    s.time = checkpoint;
+                    let count = s.players.len();
+                    for (i, player) in s.players.iter_mut().enumerate() {
+                        *player = Player {
+                            pos: LevelState::spawn_pos(i, count),
+                            hp: max_hp,
+                            max_hp,
+                            isecs: RESPAWN_IFRAME_BEATS,
+                            bombs,
+                            color: player.color,
+                            pps: player.pps,
+                            rad: player.rad,
+                            ..Player::default()
+                        };
+                    }
+                    s.trails.iter_mut().for_each(TrailBuffer::clear);
+                    s.player_history.clear();
+                    s.graze_sparks.clear();
+                    s.particles.clear();
+                    s.indicators.clear();
+                    s.shards.clear();
+                    s.shockwaves.clear();
+                    s.pickup_sparkles.clear();
+                    s.speed_mods = SpeedModifiers::default();
+                    s.rewind.clear();
+                    self.rng.rewind(checkpoint);
+                    (checkpoint - s.offset) / speed
+                }
+                None => return false
+            },
+            _ => return false
+        };
+        // the chart replays its own time scale changes from the checkpoint
+        self.set_time_scale(1.0, 0.0);
+        if let Err(e) = self.mus.seek_to(checkpoint) {
+            println!("couldn't seek to checkpoint: {e}");
+            return false;
+        }
+        true
+    }
+    /// Starts the current level over with a fresh state.
+    /// Replays the level or chart from the start with the same seed.
+    pub fn restart(&mut self) -> Result<(), Box<dyn Error>> {
+        let chosen = self.seed.replace(self.rng.seed());
+        let res = self.replay();
+        self.seed = chosen;
+        res
+    }
+    fn replay(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some((lvl, start, speed)) = self.current_level {
+            self.state = EparState::InGame(Box::new(LevelState::new()));
+            self.reset();
+            self.load_level(lvl, start, speed)
+        } else if let Some((chart, start, speed)) = self.current_chart.take() {
+            self.state = EparState::InGame(Box::new(LevelState::new()));
+            self.reset();
+            self.load_chart(chart, start, speed)
+        } else {
+            Ok(())
+        }
+    }
+    /// Key of the level or chart being played in the save.
+    pub fn run_key(&self) -> Option<String> {
+        self.current_level.map(|(lvl, _, _)| level_key(lvl))
+            .or_else(|| self.current_chart.as_ref().map(|(chart, _, _)| chart_key(chart)))
+    }
+    /// Ends the run and shows its results, saving them if they're a new personal best.\
+    /// Practice runs aren't saved, since seeking around makes the result meaningless.
+    pub fn finish(&mut self, cleared: bool) {
+        let EparState::InGame(s) = &self.state else { return };
+        let played = self.mus.tempo().beats_to_seconds(s.time - s.offset).max(0.0);
+        // a looping song has no end to survive to
+        let song_seconds = if self.loop_music { played } else { self.wav.length() as f32 };
+        let seconds_survived = if cleared { song_seconds } else { played.min(song_seconds) };
+        let result = RunResult { cleared, seconds_survived, song_seconds, score: s.score, sections: s.section_deaths() };
+        let grade = self.grading.grade(&result);
+        let new_best = match self.run_key() {
+            Some(key) if !self.practice => self.save.record(&key, &result, grade),
+            _ => false
+        };
+        // the totals change with every run
+        self.save.stats.record(&result);
+        self.save.persist();
+        self.reset();
+        self.state = EparState::Results(Results::new(result, grade, new_best));
     }
     pub fn exit(&mut self) {
         self.mus.stop();
@@ -271,76 +1719,442 @@ impl GameState {
         self.state.map(|s|s.events.clear());
     }
     pub fn update(&mut self, mus_time: f32, frame_time: f32) {
+        let arena = self.arena();
         match &mut self.state {
             EparState::InGame(state) => {
-                if is_key_pressed(KeyCode::Escape) {
-                    self.reset();
+                self.input.update();
+                self.coop_input.update();
+                self.bpm = self.mus.bpm();
+                let pressed = |action| self.input.is_pressed(action) || self.coop_input.is_pressed(action);
+                // the music is paused too, so the beat clock stands still and nothing needs to catch up afterwards
+                if let Some(selected) = state.paused {
+                    if pressed(Action::MoveUp) { state.paused = Some(selected.step(-1)); }
+                    if pressed(Action::MoveDown) { state.paused = Some(selected.step(1)); }
+                    let chosen = if pressed(Action::Pause) { Some(PauseOption::Resume) } else if pressed(Action::Dash) { Some(selected) } else { None };
+                    match chosen {
+                        Some(PauseOption::Resume) => {
+                            state.paused = None;
+                            state.count_in = Some(COUNT_IN_BEATS);
+                        }
+                        Some(PauseOption::Restart) => if let Err(e) = self.restart() { println!("couldn't restart: {e}"); },
+                        Some(PauseOption::Quit) => self.exit(),
+                        None => {}
+                    }
+                    return;
+                }
+                if let Some(left) = state.count_in {
+                    if pressed(Action::Pause) {
+                        state.count_in = None;
+                        state.paused = Some(PauseOption::Resume);
+                        return;
+                    }
+                    let left = left - frame_beats(frame_time, self.bpm, self.mus.get_speed());
+                    state.count_in = (left > 0.0).then_some(left);
+                    if left <= 0.0 { self.mus.pause(false); }
+                    return;
+                }
+                // dev builds of charts can be frozen to look through the obstacles
+                if self.hot_reload && state.death.is_none() && state.rewinding.is_none() {
+                    // a dev key, not bound, see `Bindings`
+                    if is_key_pressed(KeyCode::I) {
+                        self.inspector.toggle();
+                        self.mus.pause(self.inspector.frozen);
+                    }
+                    if self.inspector.frozen {
+                        // stepping through the obstacles seeks through them, on the seek keys
+                        if pressed(Action::SeekBack) { self.inspector.step(-1, state.obsts.len()); }
+                        if pressed(Action::SeekForward) { self.inspector.step(1, state.obsts.len()); }
+                        return;
+                    }
+                }
+                if state.death.is_none() && state.rewinding.is_none() {
+                    if let Some(chart) = self.chart_watch.as_mut().and_then(|w| w.poll(frame_time)) {
+                        self.reload_chart(chart);
+                        return;
+                    }
+                }
+                if let Some(elapsed) = state.rewinding {
+                    let elapsed = elapsed + frame_time;
+                    let skip = self.input.any_pressed() || self.coop_input.any_pressed();
+                    if skip || elapsed >= REWIND_SECS {
+                        state.rewinding = None;
+                        self.mus.pause(false);
+                        if !self.respawn() { self.finish(false); }
+                    } else {
+                        state.rewinding = Some(elapsed);
+                    }
                     return;
                 }
+                if let Some(elapsed) = state.death {
+                    // hit-stop, then slow motion ramping back to normal speed, while the music is paused
+                    let elapsed = elapsed + frame_time;
+                    state.death = Some(elapsed);
+                    let skip = self.input.any_pressed() || self.coop_input.any_pressed();
+                    if skip || elapsed >= self.hitstop_secs + self.slowmo_secs {
+                        state.death = None;
+                        state.death_particles.clear();
+                        // skipping the death sequence skips the rewind too, and there's nothing to rewind to without a checkpoint
+                        if !skip && self.save.settings.rewind && !state.rewind.is_empty() && state.last_checkpoint().is_some() {
+                            state.rewinding = Some(0.0);
+                            return;
+                        }
+                        self.mus.pause(false);
+                        if !self.respawn() { self.finish(false); }
+                        return;
+                    }
+                    if elapsed < self.hitstop_secs { return; }
+                    let scale = self.time_scale.advance(frame_beats(frame_time, self.bpm, self.mus.get_speed()));
+                    for (pos, vel) in &mut state.death_particles {
+                        *pos += *vel * frame_time * scale;
+                    }
+                    let beat_dt = frame_beats(frame_time, self.bpm, self.mus.get_speed()) * scale;
+                    state.time += beat_dt;
+                    let mut accum = UpdateAccumulator::new();
+                    accum.players = state.players.clone();
+                    accum.push = vec![Vec2::ZERO; state.players.len()];
+                    accum.arena = arena;
+                    accum.modifiers = self.modifiers;
+                    accum.time = state.time;
+                    accum.rng = std::mem::take(&mut self.rng);
+                    accum.deferred = std::mem::take(&mut state.deferred);
+                    accum.particles = std::mem::take(&mut state.particles);
+                    // only spawns carry over, nothing else can change the run anymore
+                    accum.spawn_due(state.time);
+                    state.update_obstacles(&mut accum, beat_dt, self.parallel_updates);
+                    self.sounds.play(accum.sounds.drain(), self.save.settings.sfx_volume);
+                    self.rng = accum.rng;
+                    state.deferred = accum.deferred;
+                    state.particles = accum.particles;
+                    state.particles.update(beat_dt);
+                    state.obsts.append(&mut accum.obstacles_to_add);
+                    state.camera.decay_shake(beat_dt);
+                    return;
+                }
+                if pressed(Action::Pause) {
+                    state.paused = Some(PauseOption::Resume);
+                    self.mus.pause(true);
+                    return;
+                }
+                if self.input.is_pressed(Action::Restart) || self.coop_input.is_pressed(Action::Restart) {
+                    if let Err(e) = self.restart() { println!("couldn't restart: {e}"); }
+                    return;
+                }
+                if self.practice {
+                    let seeks = [(Action::SeekBack, -1.0), (Action::SeekForward, 1.0), (Action::SeekBackFar, -4.0), (Action::SeekForwardFar, 4.0)];
+                    if let Some((_, measures)) = seeks.into_iter().find(|&(action, _)| pressed(action)) {
+                        let bar = self.mus.tempo().beats_per_bar_at(state.time - state.offset);
+                        let measure = (state.time / bar).floor();
+                        // stops at checkpoints on the way, so sections can be practiced from their start
+                        let target = state.snap_to_checkpoint(state.time, (measure + measures) * bar);
+                        self.seek_beat(target);
+                        return;
+                    }
+                    // clicking the dev timeline jumps to where it was clicked
+                    if self.hot_reload && is_mouse_button_pressed(MouseButton::Left) {
+                        let (x, y) = mouse_position();
+                        if timeline::rect().contains(vec2(x, y)) {
+                            let bar = self.mus.tempo().beats_per_bar_at(state.time - state.offset);
+                            let target = timeline::beat_at(x, state.time, bar);
+                            self.seek_beat(target);
+                            return;
+                        }
+                    }
+                }
+                if state.hitstop > 0.0 {
+                    // the music plays on, only the game stands still
+                    state.hitstop -= frame_time;
+                    state.resync = true;
+                    if state.hitstop > 0.0 { return; }
+                }
+                let last_time = state.time;
                 state.time = mus_time;
                 let smargs = ModifyArgs::default();
                 let mut accum = UpdateAccumulator::new();
-                'event_calls: loop {
-                    if state.events.is_empty() { break 'event_calls; }
-                    let time = state.events[0].0;
-                    if state.events.len() > 0 && time <= mus_time {
-                        let ev = state.events.remove(0);
-                        accum.time = time;
-                        ev.1.run(&mut accum, smargs);
+                accum.players = state.players.clone();
+                accum.push = vec![Vec2::ZERO; state.players.len()];
+                accum.arena = arena;
+                accum.modifiers = self.modifiers;
+                // obstacles update after movement, so their modifiers apply on the next frame
+                accum.speed = std::mem::take(&mut state.speed_mods);
+                accum.player_history = std::mem::take(&mut state.player_history);
+                accum.pellets = std::mem::take(&mut state.pellet_pool);
+                accum.deferred = std::mem::take(&mut state.deferred);
+                accum.particles = std::mem::take(&mut state.particles);
+                accum.budget = state.budget;
+                accum.live_obstacles = state.obsts.len();
+                accum.tagged = state.obsts.iter().filter_map(|o| o.tag).collect();
+                accum.dropped_spawns = state.dropped_spawns;
+                accum.max_orbs = self.max_orbs;
+                accum.rng = std::mem::take(&mut self.rng);
+                accum.orbs = state.obsts.iter().filter(|o| matches!(o.obstacle.pickup(), Some(Pickup::Score(_)))).count();
+                let scale = self.time_scale.advance(frame_beats(frame_time, self.bpm, self.mus.get_speed()));
+                self.mus.set_time_scale(scale);
+                let mut beat_dt = frame_beats(frame_time, self.bpm, self.mus.get_speed()) * scale;
+                // everything moves on by the beats the hit-stop missed at once, so nothing lags behind the music
+                if std::mem::take(&mut state.resync) { beat_dt = beat_dt.max(mus_time - last_time); }
+                // a long frame moves the obstacles in several steps, ending at the music's time
+                let substeps = substeps(beat_dt, self.substep_beats, self.max_substeps);
+                state.run_events(&mut accum, step_time(mus_time, beat_dt, substeps, 0));
+                let inputs = [&self.input, &self.coop_input];
+                let frame_start = state.players.iter().map(|p| p.pos).collect::<Vec<Vec2>>();
+                // taken, so what the obstacles set this frame starts from nothing
+                let frame_mods = std::mem::take(&mut accum.speed);
+                for i in 0..state.players.len() {
+                    let mut player = state.players[i];
+                    if !player.alive() { continue; }
+                    let input = inputs[i.min(MAX_PLAYERS - 1)];
+                    // decremented before collision checks, so the frame it runs out is checked again
+                    player.isecs = (player.isecs - beat_dt).max(0.0);
+                    player.shield = player.shield.map(|left| left - beat_dt).filter(|&left| left > 0.0);
+                    player.bomb_cooldown = (player.bomb_cooldown - beat_dt).max(0.0);
+                    if input.is_pressed(Action::Bomb) && player.bombs > 0 && player.bomb_cooldown <= 0.0 {
+                        player.bombs -= 1;
+                        player.bomb_cooldown = self.bomb_cooldown;
+                        state.shockwaves.push((player.pos, state.time));
+                    }
+                    if self.modifiers.no_focus {
+                        player.focused = false;
+                    } else if self.focus_toggle {
+                        if input.is_pressed(Action::Focus) { player.focused = !player.focused; }
                     } else {
-                        break 'event_calls;
+                        player.focused = input.is_down(Action::Focus);
+                    }
+                    let mut mods = frame_mods;
+                    if player.focused { mods.focus = self.focus_factor; }
+                    let speed = mods.apply(player.pps, self.speed_floor, self.speed_ceiling);
+                    let dir = input.movement(player.pos, speed * frame_time);
+                    if input.is_pressed(Action::Dash) {
+                        // dashes towards the cursor even if the player is already on it
+                        let dash_dir = input.mouse_target().map_or(dir, |target| target - player.pos);
+                        player.start_dash(dash_dir);
+                    }
+                    if !player.dashing() {
+                        player.pos += dir * speed * frame_time;
                     }
+                    player.update_dash(beat_dt);
+                    // after the dash, so it can't carry the player out either
+                    state.clamp_player(&mut player, arena);
+                    state.trails[i].advance(player.pos, state.time, self.trail_beats);
+                    state.players[i] = player;
                 }
-                if state.player.dash > 0.0 {
-                    state.player.pps = 800.0;
-                    state.player.dash -= frame_time;
-                } else {
-                    state.player.pps = 300.0;
-                }
-                if state.player.isecs > 0.0 {
-                    state.player.isecs -= frame_time;
-                }
-                if is_key_down(KeyCode::W) { state.player.pos.y -= state.player.pps * frame_time; }
-                if is_key_down(KeyCode::S) { state.player.pos.y += state.player.pps * frame_time; }
-                if is_key_down(KeyCode::A) { state.player.pos.x -= state.player.pps * frame_time; }
-                if is_key_down(KeyCode::D) { state.player.pos.x += state.player.pps * frame_time; }
-                if state.player.dash <= 0.0 && is_key_pressed(KeyCode::Space) { state.player.dash = 0.3; }
-                state.cam_jerk *= 0.8;
-                state.cam_shake *= 0.95;
+                state.camera.update(beat_dt);
+                state.hit_flash *= 0.9;
         
                 accum.time = state.time;
+                accum.players = state.players.clone();
+                let primary = accum.player_pos();
+                accum.player_history.push_back((state.time, primary));
+                while accum.player_history.front().is_some_and(|&(t, _)| t < state.time - PLAYER_HISTORY_BEATS) {
+                    accum.player_history.pop_front();
+                }
         
-                let mut i = 0;
-                while i < state.obsts.len() {
-                    let start = state.obsts[i].start_time;
-                    let dt = frame_time / 60.0 * self.bpm * self.mus.get_speed();
-                    let t = state.time - start;
-                    state.obsts[i].obstacle.update(&mut accum, dt, t, dt, t);
-                    i += 1;
+                // what went through a player in the earlier steps hits them at the end of the frame
+                let touched = state.advance(&mut accum, mus_time, beat_dt, substeps, self.parallel_updates, &frame_start);
+                // before collisions, so the player is safe the moment the wave reaches something
+                state.shockwaves.retain(|&(_, start)| state.time - start < self.bomb_beats);
+                for &(center, start) in &state.shockwaves {
+                    let rad = self.bomb_radius * wave_ease((state.time - start) / self.bomb_beats);
+                    // anchorless obstacles (lasers, emitters, bosses) are immune, and pickups and the like are left alone
+                    let caught = |o: &Obst| o.obstacle.lethal() && o.obstacle.anchor().is_some_and(|a| a.distance_squared(center) <= rad * rad);
+                    if self.bomb_triggers_kill {
+                        state.obsts.iter_mut().filter(|o| caught(o)).for_each(|o| o.marked_for_removal = true);
+                    } else {
+                        state.obsts.retain(|o| !caught(o));
+                    }
                 }
-                for obst in &state.obsts {
-                    if state.player.dash <= 0.0 && state.player.isecs <= 0.0 && obst.obstacle.collides(state.player) {
-                        state.player.isecs = 2.0;
-                        println!("hit {}", state.hits_left);
-                        if state.hits_left > 0 {
-                            state.hits_left -= 1;
+                state.speed_mods = accum.speed;
+                state.spatial.rebuild(&state.obsts);
+                let mut killers = vec![];
+                for (i, &from) in frame_start.iter().enumerate() {
+                    let mut player = state.players[i];
+                    if !player.alive() { continue; }
+                    player.knockback += accum.push[i];
+                    let vulnerable = player.vulnerable();
+                    let step = player.knockback_step(beat_dt);
+                    // split into steps no longer than the player's radius so nothing lethal gets skipped over
+                    let substeps = (step.length() / player.rad).ceil().max(1.0) as usize;
+                    for _ in 0..substeps {
+                        player.pos += step / substeps as f32;
+                        state.clamp_player(&mut player, arena);
+                        let near = state.spatial.query(circle_bounds(player.pos, player.rad));
+                        if vulnerable && near.iter().map(|&idx| &state.obsts[idx]).any(|o| o.obstacle.lethal() && o.collides(player)) {
+                            player.knockback = Vec2::ZERO;
+                            break;
                         }
                     }
-                }
-                let mut idx = 0;
-                while idx < state.obsts.len() {
-                    if state.obsts[idx].marked_for_removal || state.obsts[idx].obstacle.should_kill() {
-                        state.obsts.swap_remove(idx).obstacle.kill(&mut accum);
+                    // pickups are collected even while dashing or invulnerable
+                    for &idx in state.spatial.query(circle_bounds(player.pos, player.rad)) {
+                        let obst = &mut state.obsts[idx];
+                        if obst.marked_for_removal { continue; }
+                        let pickup = match obst.obstacle.pickup() {
+                            Some(pickup) if obst.collides(player) => pickup,
+                            _ => continue
+                        };
+                        obst.marked_for_removal = true;
+                        let color = match pickup {
+                            Pickup::Shield(duration) => {
+                                player.shield = Some(duration.unwrap_or(f32::INFINITY));
+                                accum.sound(SoundId::Pickup, 0.8, 0.8);
+                                shield_color()
+                            }
+                            Pickup::Score(value) => {
+                                state.score.orb(value, state.time, &self.scoring);
+                                accum.sound(SoundId::Pickup, 0.6, 1.0);
+                                orb_color()
+                            }
+                        };
+                        state.pickup_sparkles.push((obst.obstacle.anchor().unwrap_or(player.pos), state.time, color));
+                    }
+                    // fast players are treated as a capsule from where they started the frame, so they can't tunnel
+                    let swept = self.swept_collision && from.distance(player.pos) > player.rad;
+                    let hits = |obst: &Obst| if swept {
+                        obst.collides_swept(from, player.pos, player.rad)
                     } else {
-                        idx += 1;
+                        obst.collides(player)
+                    };
+                    let reach = if swept { circle_bounds(from, player.rad).combine_with(circle_bounds(player.pos, player.rad)) } else { circle_bounds(player.pos, player.rad) };
+                    if vulnerable {
+                        let hit = state.spatial.query(reach).iter().copied()
+                            .find(|&idx| state.obsts[idx].obstacle.lethal() && hits(&state.obsts[idx]))
+                            // a swept hit can be behind the player by now
+                            .map(|idx| (Some(idx), state.obsts[idx].contact(player).unwrap_or_else(Contact::unknown)))
+                            // and a hit in an earlier step long gone, not to be pointed out
+                            .or(touched[i].map(|contact| (None, contact)));
+                        if let Some((idx, contact)) = hit {
+                            player.isecs = self.iframe_beats;
+                            if player.shield.take().is_some() {
+                                // the shield takes the hit instead, shattering away from it
+                                state.camera.shake(10.0);
+                                for i in 0..12 {
+                                    let angle = i as f32 / 12.0 * std::f32::consts::TAU;
+                                    let dir = (vec2(angle.cos(), angle.sin()) + contact.normal * 0.75).normalize_or_zero();
+                                    state.shards.push((player.pos, dir, state.time));
+                                }
+                            } else {
+                                player.hp = player.hp.saturating_sub(1);
+                                if player.hp == 0 { killers.extend(idx); }
+                                state.score.hit();
+                                player.hp_lost_at = state.time;
+                                state.hit_flash = 0.5;
+                                state.camera.shake(20.0);
+                            }
+                        }
                     }
+                    if self.graze_margin > 0.0 {
+                        let grazer = Player { rad: player.rad + self.graze_margin, ..player };
+                        for &idx in state.spatial.query(circle_bounds(grazer.pos, grazer.rad)) {
+                            let obst = &mut state.obsts[idx];
+                            // cooldown first, it's much cheaper than the collision checks
+                            if state.time - obst.grazed_at < self.graze_cooldown || !obst.obstacle.lethal() { continue; }
+                            let Some(contact) = obst.contact(grazer) else { continue };
+                            if obst.collides(player) { continue; }
+                            obst.grazed_at = state.time;
+                            state.score.graze(state.time, &self.scoring);
+                            state.camera.shake(2.0);
+                            accum.hitstop(self.graze_hitstop_secs);
+                            accum.sound(SoundId::Graze, 0.5, 1.0);
+                            // the spark goes on the edge of the graze margin, where the obstacle passed
+                            let spark = match contact.point {
+                                Some(point) => player.pos + (point - player.pos).clamp_length_max(grazer.rad),
+                                None => player.pos + obst.obstacle.anchor().map_or(Vec2::ZERO, |a| (a - player.pos).normalize_or_zero()) * grazer.rad,
+                            };
+                            state.graze_sparks.push((spark, state.time));
+                            accum.particles().directional(spark, spark - player.pos, 0.6, 5, 60.0..160.0, 0.15..0.35, WHITE);
+                        }
+                    }
+                    state.players[i] = player;
+                }
+                // before anything gets removed, so the indices are still right
+                for idx in killers {
+                    state.obsts[idx].killer = true;
                 }
+                if state.players.iter().any(Player::alive) {
+                    state.score.survive(beat_dt, state.time, &self.scoring);
+                }
+                state.shards.retain(|&(_, _, t)| state.time - t < SHATTER_BEATS);
+                state.graze_sparks.retain(|&(_, t)| state.time - t < GRAZE_SPARK_BEATS);
+                state.pickup_sparkles.retain(|&(_, t, _)| state.time - t < PICKUP_SPARKLE_BEATS);
+                accum.remove_dead(&mut state.obsts);
+                state.counters = FrameCounters { spawns: accum.spawns, pending: accum.obstacles_to_add.len(), events: accum.events_run };
+                state.obsts.append(&mut accum.obstacles_to_add);
+                state.budget = accum.budget;
+                state.dropped_spawns = accum.dropped_spawns;
+                state.enforce_budget(&mut accum);
+                // what the culled ones spawned when killed lives to be counted against the budget next update
                 state.obsts.append(&mut accum.obstacles_to_add);
-                state.cam_jerk += accum.jerk;
-                state.cam_shake += accum.shake;
-                if let Some(fg) = accum.fg { state.fg_color = Box::new(move |_|fg); }
-                if let Some(bg) = accum.bg { state.bg_color = Box::new(move |_|bg); }
-                if let Some(float) = accum.float { state.cam_float = float; }
+                state.player_history = std::mem::take(&mut accum.player_history);
+                state.pellet_pool = std::mem::take(&mut accum.pellets);
+                state.deferred = std::mem::take(&mut accum.deferred);
+                state.particles = std::mem::take(&mut accum.particles);
+                state.particles.update(beat_dt);
+                self.rng = std::mem::take(&mut accum.rng);
+                state.camera.jerk(accum.jerk);
+                state.camera.shake(accum.shake);
+                state.camera.shake_directional(accum.shake_lean);
+                if let Some(fg) = accum.fg { state.fg_color = Some(Box::new(move |_|fg)); }
+                if let Some(bg) = accum.bg { state.bg_color = Some(Box::new(move |_|bg)); }
+                if let Some((to, beats)) = accum.palette_shift { state.shift_palette(to, beats); }
+                if state.palette_shift.is_some_and(|shift| shift.done(state.time)) {
+                    state.palette = state.palette_shift.take().unwrap().to;
+                }
+                if let Some(float) = accum.float { state.camera.float = float; }
+                if let Some((amount, decay)) = accum.zoom_pulse { state.camera.zoom_pulse(amount, decay, state.time); }
+                if let Some((to, beats)) = accum.cam_rotate { state.camera.rotate_to(to, beats); }
+                state.flashes.append(&mut accum.flashes);
+                if self.impact_flashes { state.flashes.append(&mut accum.impact_flashes); }
+                self.sounds.play(accum.sounds.drain(), self.save.settings.sfx_volume);
+                let time = state.time;
+                state.flashes.retain(|f| !f.done(time));
+                state.indicators.append(&mut accum.indicators);
+                state.indicators.retain(|i| i.live(time));
+                if let Some((to, beats)) = accum.fade { state.start_fade(to, beats); }
+                if let Some((scale, beats)) = accum.time_scale {
+                    self.time_scale.set(scale, beats);
+                    self.mus.set_time_scale(self.time_scale.current);
+                }
+                if self.save.settings.hitstop {
+                    state.hitstop = state.hitstop.max((accum.hitstop * self.hitstop_scale).min(MAX_HITSTOP_SECS));
+                }
+                if let Some(background) = &mut state.background {
+                    if let Some(intensity) = accum.background_intensity { background.target_intensity = intensity; }
+                    let bar_phase = self.mus.tempo().bar_phase(state.time - state.offset);
+                    background.update(BeatClock::new(state.time - state.offset, bar_phase), beat_dt);
+                }
+                for player in state.players.iter_mut().filter(|p| p.alive()) {
+                    player.hp = (player.hp + accum.heal).min(player.max_hp);
+                }
+                if self.save.settings.rewind {
+                    let palette = state.current_palette();
+                    let color = match &state.fg_color {
+                        Some(fg) if palette.level_colors => fg.apply(state.time),
+                        _ => palette.obstacle
+                    };
+                    state.rewind.record(frame_time, &state.players, &state.obsts, color);
+                }
+                let failed = match self.coop_rule {
+                    CoopRule::AllDown => state.players.iter().all(|p| !p.alive()),
+                    CoopRule::AnyDown => state.players.iter().any(|p| !p.alive())
+                };
+                if failed {
+                    state.record_death();
+                    state.death = Some(0.0);
+                    state.hitstop = 0.0;
+                    for player in &state.players {
+                        for _ in 0..DEATH_PARTICLES / state.players.len() {
+                            let angle = gen_range(0.0, std::f32::consts::TAU);
+                            let vel = vec2(angle.cos(), angle.sin()) * gen_range(50.0, 400.0);
+                            state.death_particles.push((player.pos, vel));
+                        }
+                    }
+                    state.camera.shake(20.0);
+                    self.mus.pause(true);
+                    // slow motion ramping back to normal speed, the music being paused it plays on its own
+                    self.time_scale.set(self.slowmo_scale, 0.0);
+                    self.time_scale.set(1.0, frame_beats(self.slowmo_secs, self.bpm, self.mus.get_speed()));
+                    return;
+                }
+                // only the obstacles that end the run are highlighted
+                state.obsts.iter_mut().for_each(|o| o.killer = false);
                 for i in accum.events {
                     i.run(self, smargs);
                 }
@@ -349,30 +2163,276 @@ impl GameState {
         }
     }
     pub fn draw(&mut self) {
+        let trail = if self.trail_enabled { self.trail_beats } else { 0.0 };
+        let trail_color = self.trail_color;
+        let input = &self.input;
+        let arena = self.arena();
+        let custom_arena = self.arena.is_some();
+        let (hitstop_secs, slowmo_secs) = (self.hitstop_secs, self.slowmo_secs);
+        let (bomb_radius, bomb_beats) = (self.bomb_radius, self.bomb_beats);
+        let (practice, tempo, dev) = (self.practice, self.mus.tempo(), self.hot_reload);
+        let flash_scale = if self.save.settings.reduce_flashing { REDUCED_FLASH_SCALE } else { 1.0 };
+        let seed = self.rng.seed();
+        let debug = &mut self.debug;
+        let inspector = &self.inspector;
+        let shake_intensity = self.save.settings.shake_intensity;
+        let glow = self.save.settings.glow;
+        let graze_margin = self.graze_margin;
+        set_smooth_edges(self.save.settings.smooth_edges);
         self.state.map(|s| {
-            let offset = s.cam_jerk
-                + vec2(gen_range(-s.cam_shake, s.cam_shake), gen_range(-s.cam_shake, s.cam_shake))
-                + vec2((s.time).sin(), (s.time * 1.2).sin()) * s.cam_float;
-            clear_background(s.bg_color.apply(s.time));
-            for obst in &mut s.obsts {
-                obst.obstacle.draw(s.fg_color.apply(s.time), offset);
-            }
-            let color = match (s.player.isecs > 0.0, s.player.dash > 0.0) {
-                (false, false) => soft_pink(),
-                (true, false) => hit_color(),
-                (false, true) => dash_color(),
-                (true, true) => hitdash_color()
+            // the shake holds still while paused instead of jittering in place
+            let shake_scale = if s.paused.is_some() || s.count_in.is_some() { 0.0 } else { shake_intensity };
+            let offset = s.camera.offset(s.time, shake_scale);
+            let camera = s.camera.camera2d(s.time, shake_scale);
+            let palette = s.current_palette();
+            let level_color = |color: &Option<Box<dyn ColorEase>>, fallback: Color| match color {
+                Some(color) if palette.level_colors => color.apply(s.time),
+                _ => fallback
+            };
+            clear_background(level_color(&s.bg_color, palette.background));
+            set_camera(&camera);
+            let fg = level_color(&s.fg_color, palette.obstacle);
+            if let Some(background) = &s.background {
+                background.draw(acmul(fg, 0.5));
+            }
+            let view = s.camera.visible(s.time).offset(-offset);
+            let (order, under) = s.draw_order.sort(&s.obsts);
+            let obst_color = |obst: &Obst| {
+                // the killer flashes during the death sequence
+                if s.death.is_some() && obst.killer {
+                    mix(fg, WHITE, (0.5 + 0.5 * (s.time * std::f32::consts::TAU * 2.0).cos()) * flash_scale)
+                } else if obst.frozen {
+                    frozen_tint(fg)
+                } else {
+                    fg
+                }
+            };
+            let draw_obsts = |indices: &[usize], batch: &mut CircleBatch| {
+                // all the glows first, so none of them go over an obstacle
+                if glow {
+                    for obst in indices.iter().map(|&i| &s.obsts[i]).filter(|obst| obst.near(view)) {
+                        obst.obstacle.draw_glow(obst_color(obst), offset);
+                    }
+                }
+                for obst in indices.iter().map(|&i| &s.obsts[i]) {
+                    if !obst.near(view) { continue; }
+                    let color = obst_color(obst);
+                    if !obst.obstacle.draw_batched(color, offset, batch) {
+                        obst.obstacle.draw(color, offset);
+                    }
+                }
+                batch.flush();
             };
-            draw_circle(s.player.pos.x + offset.x, s.player.pos.y + offset.y, s.player.rad, color);
-            let tpos = s.player.pos + offset + vec2(-s.player.rad, -s.player.rad * 2.0);
-            draw_text(&format!("{}", s.hits_left), tpos.x, tpos.y, s.player.rad * 5.0, WHITE);
+            draw_obsts(&order[..under], &mut s.circles);
+            if trail > 0.0 {
+                for (player, trail_points) in s.players.iter().zip(&s.trails) {
+                    trail_points.positions_into(offset, &mut s.ribbon_points);
+                    let color = trail_color.unwrap_or(player.color);
+                    draw_ribbon(&s.ribbon_points, player.rad * 2.0, 0.0, acmul(color, 0.5), acmul(color, 0.0));
+                }
+            }
+            if custom_arena {
+                draw_rectangle_lines(arena.x + offset.x, arena.y + offset.y, arena.w, arena.h, 2.0, acmul(WHITE, 0.25));
+            }
+            for (edge, &t) in s.edge_touched.iter().enumerate() {
+                let fade = 1.0 - (s.time - t) / EDGE_GLOW_BEATS;
+                if fade <= 0.0 { continue; }
+                // layered strips, so the glow is strongest right at the edge
+                for layer in 1..=4 {
+                    let width = layer as f32 * 4.0;
+                    let (x, y, w, h) = match edge {
+                        0 => (arena.x, arena.y, width, arena.h),
+                        1 => (arena.x, arena.y, arena.w, width),
+                        2 => (arena.right() - width, arena.y, width, arena.h),
+                        _ => (arena.x, arena.bottom() - width, arena.w, width)
+                    };
+                    draw_rectangle(x + offset.x, y + offset.y, w, h, acmul(WHITE, fade * 0.15));
+                }
+            }
+            for player in &s.players {
+                let pos = player.pos + offset;
+                if !player.alive() {
+                    draw_circle_lines(pos.x, pos.y, player.rad, 1.0, acmul(player.color, 0.5));
+                    continue;
+                }
+                let dash = mix(player.color, SKYBLUE, 0.5);
+                let hit = mix(player.color, RED, 0.5);
+                player.draw_streak(dash, offset);
+                let mut color = match (player.isecs > 0.0, player.dashing()) {
+                    (false, false) => player.color,
+                    (true, false) => hit,
+                    (false, true) => dash,
+                    (true, true) => mix(hit, dash, 0.5)
+                };
+                // blinks every eighth of a beat while invulnerable
+                if player.isecs > 0.0 && (player.isecs * 8.0) as i32 % 2 == 1 {
+                    color = acmul(color, 0.25);
+                }
+                draw_circle(pos.x, pos.y, player.rad, color);
+                if player.focused || player.isecs > 0.0 {
+                    // true hitbox
+                    draw_circle(pos.x, pos.y, player.rad, WHITE);
+                    draw_circle_lines(pos.x, pos.y, player.rad + 3.0, 1.0, acmul(WHITE, 0.5));
+                }
+                if let Some(left) = player.shield {
+                    // pulses in the last beat
+                    let alpha = if left < 1.0 { 0.2 + 0.3 * (left * std::f32::consts::TAU * 4.0).cos().abs() } else { 0.4 };
+                    draw_circle_lines(pos.x, pos.y, player.rad + 8.0, 2.0, acmul(shield_color(), alpha));
+                }
+            }
+            draw_obsts(&order[under..], &mut s.circles);
+            debug.draw_hitboxes(&s.obsts, &s.players, graze_margin, offset);
+            for &(origin, dir, t) in &s.shards {
+                let fade = 1.0 - (s.time - t) / SHATTER_BEATS;
+                // starts at the shield ring around a default-sized player
+                let from = origin + dir * (13.0 + (1.0 - fade) * 40.0) + offset;
+                let to = from + dir * 6.0 * fade;
+                draw_line(from.x, from.y, to.x, to.y, 2.0, acmul(shield_color(), fade));
+            }
+            for &(center, start) in &s.shockwaves {
+                let t = (s.time - start) / bomb_beats;
+                let pos = center + offset;
+                draw_circle_lines(pos.x, pos.y, bomb_radius * wave_ease(t), 4.0, acmul(WHITE, 0.6 * (1.0 - t)));
+            }
+            for &(pos, t, color) in &s.pickup_sparkles {
+                let progress = (s.time - t) / PICKUP_SPARKLE_BEATS;
+                let pos = pos + offset;
+                // a little star that grows and fades
+                for i in 0..4 {
+                    let angle = i as f32 * std::f32::consts::FRAC_PI_4 * 2.0 + progress;
+                    let arm = vec2(angle.cos(), angle.sin()) * (4.0 + progress * 8.0);
+                    draw_line(pos.x - arm.x, pos.y - arm.y, pos.x + arm.x, pos.y + arm.y, 1.5, acmul(color, 1.0 - progress));
+                }
+            }
+            let death_fade = s.death.map_or(0.0, |elapsed| 1.0 - (elapsed / (hitstop_secs + slowmo_secs)).min(1.0));
+            // each player's burst is contiguous
+            let per_player = (DEATH_PARTICLES / s.players.len()).max(1);
+            for (player, burst) in s.players.iter().zip(s.death_particles.chunks(per_player)) {
+                for &(pos, _) in burst {
+                    draw_circle(pos.x + offset.x, pos.y + offset.y, 3.0 * death_fade, acmul(player.color, death_fade));
+                }
+            }
+            s.particles.draw(offset, &mut s.circles);
+            for &(pos, t) in &s.graze_sparks {
+                let fade = 1.0 - (s.time - t) / GRAZE_SPARK_BEATS;
+                draw_circle(pos.x + offset.x, pos.y + offset.y, 4.0 * fade, acmul(WHITE, fade));
+            }
+            // the overlays and HUD stay upright
+            set_screen_camera();
+            if let Some((color, alpha)) = overlay::composite(&s.flashes, s.time) {
+                draw_rectangle(0.0, 0.0, screen_width(), screen_height(), acmul(color, alpha * flash_scale));
+            }
+            draw_rectangle(0.0, 0.0, screen_width(), screen_height(), acmul(palette.warning, s.hit_flash * flash_scale));
+            let fade = s.fade.alpha(s.time);
+            if fade > 0.0 {
+                draw_rectangle(0.0, 0.0, screen_width(), screen_height(), Color::new(0.0, 0.0, 0.0, fade));
+            }
+            if let Some(elapsed) = s.rewinding {
+                // the world as it was, over the world as it is
+                draw_rectangle(0.0, 0.0, screen_width(), screen_height(), acmul(palette.background, 0.85));
+                s.rewind.draw(elapsed / REWIND_SECS, offset);
+            } else if s.death.is_none() {
+                let fixed = s.indicators.iter().map(|i| (i.pos, i.style, i.until));
+                let tracked = s.obsts.iter().filter_map(|o| Some((o.obstacle.anchor()?, o.indicator?))).map(|(pos, (style, until))| (pos, style, until));
+                let targets = fixed.chain(tracked)
+                    .filter(|&(.., until)| s.time < until)
+                    .map(|(pos, style, until)| (indicators::to_screen(&camera, pos + offset), style, until - s.time))
+                    .collect::<Vec<_>>();
+                indicators::draw(&targets, palette.warning);
+            }
+            // HUD, one row of hit points per player
+            for (row, player) in s.players.iter().enumerate() {
+                let lost_anim = ((s.time - player.hp_lost_at) / HP_LOSS_ANIM_BEATS).clamp(0.0, 1.0);
+                for i in 0..player.max_hp {
+                    let pos = vec2(20.0 + i as f32 * 20.0, 20.0 + row as f32 * 20.0);
+                    if i < player.hp {
+                        draw_circle(pos.x, pos.y, 7.0, player.color);
+                    } else if i == player.hp && lost_anim < 1.0 {
+                        draw_circle(pos.x, pos.y, 7.0 * (1.0 - lost_anim), mix(player.color, palette.warning, 0.5));
+                    }
+                    draw_circle_lines(pos.x, pos.y, 7.0, 1.0, acmul(palette.text, 0.5));
+                }
+                // bomb charges after the hit points, dimmed while on cooldown
+                let bomb_alpha = if player.bomb_cooldown > 0.0 { 0.4 } else { 0.9 };
+                for i in 0..player.bombs {
+                    let pos = vec2(30.0 + (player.max_hp + i) as f32 * 20.0, 20.0 + row as f32 * 20.0);
+                    draw_poly(pos.x, pos.y, 4, 6.0, 45.0, acmul(palette.text, bomb_alpha));
+                }
+            }
+            let graze_y = 28.0 + s.players.len() as f32 * 20.0;
+            draw_text(&format!("graze {}", s.score.stats.grazes), 12.0, graze_y, 20.0, acmul(palette.text, 0.75));
+            if let Some(err) = self.chart_watch.as_ref().and_then(|w| w.error.as_ref()) {
+                draw_text(err, 12.0, graze_y + 20.0, 20.0, palette.warning);
+            }
+            // score in the top right corner, under the practice info
+            let score_y = if practice { 56.0 } else { 28.0 };
+            let score_text = format!("{}", s.score.points);
+            let width = measure_text(&score_text, None, 28, 1.0).width;
+            draw_text(&score_text, screen_width() - width - 12.0, score_y, 28.0, acmul(palette.text, 0.9));
+            if s.score.combo.count > 0 {
+                let pop = 1.0 - ((s.time - s.score.bumped_at) / COMBO_POP_BEATS).clamp(0.0, 1.0);
+                let size = 20.0 * (1.0 + 0.5 * pop);
+                let combo_text = format!("{} combo x{}", s.score.combo.count, s.score.combo.multiplier(&self.scoring));
+                let width = measure_text(&combo_text, None, size as u16, 1.0).width;
+                let color = if self.save.settings.rainbow_combo {
+                    acmul(hue_cycle(palette.accent, COMBO_RAINBOW_TURNS, s.time), 0.75 + 0.25 * pop)
+                } else {
+                    mix(acmul(palette.text, 0.75), palette.accent, pop)
+                };
+                draw_text(&combo_text, screen_width() - width - 12.0, score_y + 24.0, size, color);
+            }
+            if practice {
+                let bar = tempo.beats_per_bar_at(s.time - s.offset);
+                let text = format!("beat {:.2}  measure {}  {} bpm", s.time, (s.time / bar).floor() as i32 + 1, tempo.bpm_at_beats(s.time - s.offset));
+                let width = measure_text(&text, None, 20, 1.0).width;
+                draw_text(&text, screen_width() - width - 12.0, 28.0, 20.0, acmul(palette.text, 0.75));
+            }
+            if dev {
+                let bar = tempo.beats_per_bar_at(s.time - s.offset);
+                let (start, end) = timeline::span(s.time, bar);
+                let from = s.timeline.partition_point(|e| e.0 < start);
+                let events = s.timeline[from..].iter().copied().take_while(|e| e.0 < end);
+                timeline::draw(events.chain(s.schedule.timeline()).chain(s.deferred.timeline()), s.time, bar);
+                inspector.draw(&s.obsts, &s.players, s.time, offset, &camera);
+            }
+            if let Some(selected) = s.paused {
+                draw_rectangle(0.0, 0.0, screen_width(), screen_height(), acmul(palette.background, 0.6));
+                for (i, option) in PauseOption::iter().enumerate() {
+                    let pos = screen_size() / 2.0 + vec2(0.0, (i as f32 - 1.0) * 50.0);
+                    let color = if option == selected { palette.text } else { acmul(palette.text, 0.4) };
+                    centered_text_draw(&format!("{option:?}"), pos, 40.0, color);
+                }
+            } else if let Some(left) = s.count_in {
+                draw_rectangle(0.0, 0.0, screen_width(), screen_height(), acmul(palette.background, 0.6 * left / COUNT_IN_BEATS));
+                centered_text_draw(&format!("{}", left.ceil()), screen_size() / 2.0, 80.0, acmul(palette.text, left.fract().max(0.25)));
+            }
+            if debug.enabled {
+                debug.record(get_frame_time(), &s.players);
+                let bar = tempo.beats_per_bar_at(s.time - s.offset);
+                debug.draw(&DebugInfo {
+                    obsts: &s.obsts,
+                    players: &s.players,
+                    counters: s.counters,
+                    deferred: s.deferred.len(),
+                    beat: s.time,
+                    measure: (s.time / bar).floor() as i32 + 1,
+                    trauma: s.camera.trauma(),
+                    jerk: s.camera.jerk_offset().length(),
+                    seed,
+                });
+            }
+            if INPUT_DBG {
+                let raw = input.raw_stick();
+                let stick = input.stick();
+                draw_text(&format!("{:?} stick raw ({:.2}, {:.2}) -> ({:.2}, {:.2})", input.device, raw.x, raw.y, stick.x, stick.y), 12.0, screen_height() - 12.0, 20.0, WHITE);
+            }
             if COLLISION_DBG {
                 for x in (0..screen_width() as usize).step_by(COLLISION_FRAGMENT_SIZE) {
                     for y in (0..screen_height() as usize).step_by(COLLISION_FRAGMENT_SIZE) {
                         for i in &s.obsts {
                             if i.obstacle.collides(Player {
                                 pos: vec2(x as f32, y as f32),
-                                ..s.player
+                                ..s.players[0]
                             }) {
                                 draw_rectangle(x as f32, y as f32, COLLISION_FRAGMENT_SIZE as f32, COLLISION_FRAGMENT_SIZE as f32, acmul(RED, 0.5));
                             }
@@ -389,3 +2449,681 @@ impl GameState {
         self.state.map(|s|s.obsts.push(Obst::new(Box::new(obst), time)));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game_objects::{GrowLaser, Periodic, Ease, Bomb}, utils::{recip_ease_fn, best_of}};
+
+    const SPAWNS: usize = 10_000;
+    const BUDGET: usize = 2_000;
+
+    /// A level whose obstacles are `SPAWNS` pellets, the nth started at beat n and n pixels right of the player.
+    fn crowded(policy: BudgetPolicy) -> LevelState {
+        let mut level = LevelState::new();
+        let player = level.players[0].pos;
+        level.obsts = (0..SPAWNS).map(|i| Obst::new(Box::new(Pellet::new(player + vec2(i as f32, 0.0), Vec2::ZERO, 1.0)), i as f32)).collect();
+        level.budget = Some((BUDGET, policy));
+        level
+    }
+
+    #[test]
+    fn budget_drops_new_spawns() {
+        let mut accum = UpdateAccumulator::new();
+        accum.set_budget(BUDGET, BudgetPolicy::DropNew);
+        for i in 0..SPAWNS {
+            accum.pellet(vec2(i as f32, 0.0), Vec2::ZERO, 1.0);
+        }
+        assert_eq!(accum.obstacles_to_add.len(), BUDGET);
+        assert_eq!(accum.dropped_spawns(), SPAWNS - BUDGET);
+        // the first ones made it
+        assert_eq!(accum.obstacles_to_add.last().and_then(|o| o.obstacle.anchor()), Some(vec2((BUDGET - 1) as f32, 0.0)));
+        assert_eq!(accum.spawns, SPAWNS);
+    }
+
+    #[test]
+    fn budget_counts_live_obstacles_against_new_spawns() {
+        let mut accum = UpdateAccumulator::new();
+        accum.set_budget(BUDGET, BudgetPolicy::DropNew);
+        accum.live_obstacles = BUDGET - 10;
+        for _ in 0..SPAWNS {
+            accum.pellet(Vec2::ZERO, Vec2::ZERO, 1.0);
+        }
+        assert_eq!(accum.obstacles_to_add.len(), 10);
+    }
+
+    #[test]
+    fn budget_drops_oldest() {
+        let mut level = crowded(BudgetPolicy::DropOldest);
+        level.enforce_budget(&mut UpdateAccumulator::new());
+        assert_eq!(level.obsts.len(), BUDGET);
+        assert_eq!(level.dropped_spawns, SPAWNS - BUDGET);
+        assert!(level.obsts.iter().all(|o| o.start_time >= (SPAWNS - BUDGET) as f32));
+        // the rest keep their order
+        assert!(level.obsts.windows(2).all(|w| w[0].start_time < w[1].start_time));
+    }
+
+    #[test]
+    fn budget_drops_furthest() {
+        let mut level = crowded(BudgetPolicy::DropFurthest);
+        // the newest are the furthest, so this isn't just dropping the oldest
+        level.obsts.reverse();
+        level.enforce_budget(&mut UpdateAccumulator::new());
+        assert_eq!(level.obsts.len(), BUDGET);
+        let player = level.players[0].pos;
+        assert!(level.obsts.iter().all(|o| o.obstacle.anchor().unwrap().distance(player) < BUDGET as f32));
+    }
+
+    #[test]
+    fn budget_falls_back_to_oldest_without_anchors() {
+        let mut level = crowded(BudgetPolicy::DropFurthest);
+        let laser = GrowLaser::new(Vec2::ZERO, vec2(100.0, 0.0), 10.0, 1.0, 1.0, Vec2::ZERO);
+        // anchorless ones go after every anchored one, the oldest first
+        level.obsts.extend((0..BUDGET).map(|i| Obst::new(Box::new(laser), -(i as f32))));
+        level.enforce_budget(&mut UpdateAccumulator::new());
+        assert_eq!(level.obsts.len(), BUDGET);
+        assert!(level.obsts.iter().all(|o| o.obstacle.anchor().is_none()));
+        level.budget = Some((BUDGET / 2, BudgetPolicy::DropFurthest));
+        level.enforce_budget(&mut UpdateAccumulator::new());
+        assert!(level.obsts.iter().all(|o| o.start_time > -((BUDGET / 2) as f32)));
+    }
+
+    #[test]
+    fn budget_keeps_essentials() {
+        for policy in [BudgetPolicy::DropNew, BudgetPolicy::DropOldest, BudgetPolicy::DropFurthest] {
+            let mut level = crowded(policy);
+            for obst in level.obsts.iter_mut().step_by(2) {
+                obst.essential = true;
+            }
+            level.enforce_budget(&mut UpdateAccumulator::new());
+            let essentials = level.obsts.iter().filter(|o| o.essential).count();
+            assert_eq!(essentials, SPAWNS / 2, "{policy:?}");
+            if policy != BudgetPolicy::DropNew {
+                // nothing else fits once the essentials are over the budget
+                assert_eq!(level.obsts.len(), SPAWNS / 2, "{policy:?}");
+            }
+
+            let mut accum = UpdateAccumulator::new();
+            accum.set_budget(BUDGET, policy);
+            for _ in 0..SPAWNS {
+                accum.obstacle(Obst::new(Box::new(Pellet::new(Vec2::ZERO, Vec2::ZERO, 1.0)), 0.0).essential());
+            }
+            assert_eq!(accum.obstacles_to_add.len(), SPAWNS, "{policy:?}");
+        }
+    }
+
+    #[test]
+    fn culled_bombs_still_burst() {
+        let mut level = crowded(BudgetPolicy::DropOldest);
+        let bomb = Bomb::new(Vec2::ZERO, Vec2::ZERO, 1.0, 8, 100.0, 5.0, Box::new(Bomb::pellet_spawner));
+        // the oldest of all, so the first to go
+        level.obsts.insert(0, Obst::new(Box::new(bomb), -1.0));
+        let mut accum = UpdateAccumulator::new();
+        level.enforce_budget(&mut accum);
+        assert_eq!(level.obsts.len(), BUDGET);
+        assert!(level.obsts.iter().all(|o| o.start_time >= 0.0));
+        assert_eq!(accum.obstacles_to_add.len(), 8);
+    }
+
+
+    /// Halves the player's speed while it's alive.
+    #[derive(Clone, Copy)]
+    struct Slow;
+    impl Obstacle for Slow {
+        fn update(&mut self, to_add: &mut UpdateAccumulator, dtime: f32, time: f32, dease: f32, ease: f32) { to_add.speed_mul(0.5) }
+        fn draw(&self, color: Color, offset: Vec2) {}
+        fn box_clone(&self) -> Box<dyn Obstacle> { Box::new(*self) }
+        fn collides(&self, player: Player) -> bool { false }
+    }
+
+    #[test]
+    fn speed_adds_before_multiplying() {
+        let (mut first, mut second) = (UpdateAccumulator::new(), UpdateAccumulator::new());
+        first.speed_add(100.0);
+        first.speed_mul(0.5);
+        second.speed_mul(0.5);
+        second.speed_add(100.0);
+        // (300 + 100) * 0.5, whichever came first
+        assert_eq!(first.speed.apply(300.0, 0.0, 10.0), 200.0);
+        assert_eq!(second.speed.apply(300.0, 0.0, 10.0), 200.0);
+    }
+
+    #[test]
+    fn speed_clamps_to_floor_and_ceiling() {
+        let slow = SpeedModifiers { mul: 0.01, ..SpeedModifiers::default() };
+        let fast = SpeedModifiers { add: 5000.0, ..SpeedModifiers::default() };
+        assert_eq!(slow.apply(300.0, DEFAULT_SPEED_FLOOR, DEFAULT_SPEED_CEILING), 300.0 * DEFAULT_SPEED_FLOOR);
+        assert_eq!(fast.apply(300.0, DEFAULT_SPEED_FLOOR, DEFAULT_SPEED_CEILING), 300.0 * DEFAULT_SPEED_CEILING);
+        // a ceiling under the floor gives way to it
+        assert_eq!(fast.apply(300.0, 0.5, 0.25), 150.0);
+        assert_eq!(SpeedModifiers::default().apply(300.0, DEFAULT_SPEED_FLOOR, DEFAULT_SPEED_CEILING), 300.0);
+    }
+
+    #[test]
+    fn focus_slows_past_the_floor() {
+        let floored = SpeedModifiers { mul: 0.01, focus: 0.1, ..SpeedModifiers::default() };
+        assert!((floored.apply(300.0, DEFAULT_SPEED_FLOOR, DEFAULT_SPEED_CEILING) - 300.0 * DEFAULT_SPEED_FLOOR * 0.1).abs() < 1e-3);
+        let focused = SpeedModifiers { focus: 0.1, ..SpeedModifiers::default() };
+        assert!((focused.apply(300.0, DEFAULT_SPEED_FLOOR, DEFAULT_SPEED_CEILING) - 30.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn speed_modifiers_last_one_frame() {
+        let mut level = LevelState::new();
+        level.obsts.push(Obst::new(Box::new(Slow), 0.0));
+        let mut accum = UpdateAccumulator::new();
+        level.update_obstacles(&mut accum, 0.1, false);
+        assert_eq!(std::mem::take(&mut accum.speed).mul, 0.5);
+        // the next frame's slow-down doesn't stack on this one's
+        level.update_obstacles(&mut accum, 0.1, false);
+        assert_eq!(std::mem::take(&mut accum.speed).mul, 0.5);
+        level.obsts.clear();
+        level.update_obstacles(&mut accum, 0.1, false);
+        assert_eq!(accum.speed, SpeedModifiers::default());
+    }
+
+
+    /// Bursts into a pellet when killed.
+    #[derive(Clone, Copy)]
+    struct Burst;
+    impl Obstacle for Burst {
+        fn update(&mut self, to_add: &mut UpdateAccumulator, dtime: f32, time: f32, dease: f32, ease: f32) {}
+        fn draw(&self, color: Color, offset: Vec2) {}
+        fn box_clone(&self) -> Box<dyn Obstacle> { Box::new(*self) }
+        fn collides(&self, player: Player) -> bool { false }
+        fn kill(&mut self, to_add: &mut UpdateAccumulator) { to_add.pellet(Vec2::ZERO, Vec2::ZERO, 1.0) }
+    }
+
+    #[test]
+    fn kill_hooks_of_charted_obstacles_spawn_charted() {
+        let mut accum = UpdateAccumulator::new();
+        let mut obsts = vec![Obst::new(Box::new(Burst), 0.0).charted(), Obst::new(Box::new(Burst), 0.0)];
+        obsts.iter_mut().for_each(|o| o.marked_for_removal = true);
+        accum.remove_dead(&mut obsts);
+        let from_chart = accum.obstacles_to_add.iter().map(|o| o.from_chart).collect::<Vec<_>>();
+        assert_eq!(from_chart, [true, false]);
+        // and it's back to what it was after
+        assert!(!accum.charted);
+        accum.pellet(Vec2::ZERO, Vec2::ZERO, 1.0);
+        assert!(!accum.obstacles_to_add.last().unwrap().from_chart);
+    }
+
+
+    /// Frames at 60 FPS a 120 BPM run at `speed_rate` takes to reach beat 64, and what it spawned on the way.
+    fn run_at(speed_rate: f32) -> (usize, Vec<(f32, Option<Vec2>)>) {
+        let mods = Modifiers { speed_rate, ..Modifiers::default() };
+        let mut level = LevelState::new();
+        level.events = (0..64).map(|b| GSEvent::new(b as f32, move |ac: &mut UpdateAccumulator, _| ac.pellet(vec2(100.0 + b as f32, 100.0), vec2(10.0, 0.0), 5.0))).collect();
+        let mut frames = 0;
+        let mut spawned = vec![];
+        while level.time < 64.0 {
+            let mut accum = UpdateAccumulator::new();
+            level.time += frame_beats(1.0 / 60.0, 120.0, mods.speed_rate);
+            accum.time = level.time;
+            level.run_events(&mut accum, level.time);
+            spawned.extend(accum.obstacles_to_add.iter().map(|o| (o.start_time, o.obstacle.anchor())));
+            frames += 1;
+        }
+        (frames, spawned)
+    }
+
+    #[test]
+    fn faster_runs_reach_the_end_in_fewer_frames() {
+        let (normal, normal_spawns) = run_at(1.0);
+        let (fast, fast_spawns) = run_at(1.5);
+        // 64 beats at 120 BPM are 32 seconds, 1920 frames, and 2/3 of that at 1.5x
+        assert!(normal.abs_diff(1920) <= 1);
+        assert!(fast.abs_diff(normal * 2 / 3) <= 1);
+        // with the same obstacles on the same beats
+        assert_eq!(normal_spawns.len(), 64);
+        assert_eq!(normal_spawns, fast_spawns);
+    }
+
+
+    #[test]
+    fn time_scale_ramps_the_same_at_any_frame_rate() {
+        let (mut smooth, mut choppy) = (TimeScale::default(), TimeScale::default());
+        smooth.set(0.25, 2.0);
+        choppy.set(0.25, 2.0);
+        for _ in 0..60 { smooth.advance(1.0 / 60.0); }
+        for _ in 0..6 { choppy.advance(1.0 / 6.0); }
+        assert!((smooth.current - choppy.current).abs() < 1e-4);
+        assert!((smooth.current - 0.625).abs() < 1e-4);
+        // and it stops at the target
+        smooth.advance(10.0);
+        assert_eq!(smooth.current, 0.25);
+    }
+
+    #[test]
+    fn time_scale_never_stops_the_clock() {
+        let mut scale = TimeScale::default();
+        scale.set(0.0, 0.0);
+        assert_eq!(scale.current, MIN_TIME_SCALE);
+        scale.set(100.0, 1.0);
+        scale.advance(100.0);
+        assert_eq!(scale.current, MAX_TIME_SCALE);
+    }
+
+    /// Steps `level` through 60 FPS frames of 120 BPM for `secs`, the time scale easing between `scales` one after another.
+    fn run_scaled(level: &mut LevelState, secs: f32, scales: &[(f32, f32)]) -> usize {
+        let mut scale = TimeScale::default();
+        let mut spawned = 0;
+        let frames = (secs * 60.0).round() as usize;
+        for frame in 0..frames {
+            let (target, ramp) = scales[frame * scales.len() / frames];
+            if scale.target != target { scale.set(target, ramp); }
+            let real = frame_beats(1.0 / 60.0, 120.0, 1.0);
+            let dt = real * scale.advance(real);
+            let mut accum = UpdateAccumulator::new();
+            level.time += dt;
+            accum.time = level.time;
+            level.update_obstacles(&mut accum, dt, false);
+            spawned += accum.obstacles_to_add.len();
+        }
+        spawned
+    }
+
+    #[test]
+    fn periodic_catches_up_under_scale_changes() {
+        let mut level = LevelState::new();
+        level.obsts.push(Obst::new(Box::new(Periodic::new(64, 0.25, Box::new(|ac: &mut UpdateAccumulator, _| ac.pellet(Vec2::ZERO, Vec2::ZERO, 1.0)))), 0.0));
+        let spawned = run_scaled(&mut level, 8.0, &[(1.0, 0.0), (0.25, 1.0), (0.05, 0.5), (3.0, 2.0)]);
+        // every step the scaled beats went past fired, no more, no fewer
+        assert_eq!(spawned, ((level.time / 0.25).floor() as usize).min(64));
+        assert!(level.time > 4.0);
+    }
+
+    #[test]
+    fn ease_lands_in_the_same_place_under_scale_changes() {
+        let eased = || Obst::new(Box::new(Ease::anon(Pellet::new(Vec2::ZERO, vec2(100.0, 0.0), 1.0), recip_ease_fn(3.0))), 0.0);
+        let (mut steady, mut scaled) = (LevelState::new(), LevelState::new());
+        steady.obsts.push(eased());
+        scaled.obsts.push(eased());
+        run_scaled(&mut scaled, 4.0, &[(0.5, 0.5), (2.0, 1.0), (0.1, 0.0), (1.0, 1.0)]);
+        // the steady one catches up to the same beat in one go
+        let mut accum = UpdateAccumulator::new();
+        steady.time = scaled.time;
+        accum.time = steady.time;
+        steady.update_obstacles(&mut accum, steady.time, false);
+        let at = |level: &LevelState| level.obsts[0].obstacle.anchor().unwrap();
+        assert!(at(&steady).distance(at(&scaled)) < 1e-2, "{} vs {}", at(&steady), at(&scaled));
+    }
+
+
+    /// Whether each of a few players is hit by a pellet, a laser and a far-off pellet, the camera set up by `camera`.
+    fn hits_with_camera(camera: impl Fn(&mut Camera)) -> Vec<bool> {
+        let mut level = LevelState::new();
+        camera(&mut level.camera);
+        level.camera.update(0.5);
+        level.obsts = vec![
+            Obst::new(Box::new(Pellet::new(vec2(400.0, 300.0), Vec2::ZERO, 20.0)), 0.0),
+            Obst::new(Box::new(GrowLaser::new(vec2(0.0, 700.0), vec2(1600.0, 700.0), 40.0, 0.0, 8.0, Vec2::ZERO)), 0.0),
+            Obst::new(Box::new(Pellet::new(vec2(1400.0, 100.0), Vec2::ZERO, 20.0)), 0.0),
+        ];
+        let at = [vec2(410.0, 300.0), vec2(800.0, 710.0), vec2(800.0, 100.0), vec2(100.0, 300.0)];
+        level.players = at.iter().map(|&pos| Player { pos, ..Player::default() }).collect();
+        let mut accum = UpdateAccumulator::new();
+        level.time = 1.0;
+        level.update_obstacles(&mut accum, 1.0, false);
+        level.substep(&mut accum, 1.0, 0.0, false, &at, 1.0).iter().map(Option::is_some).collect()
+    }
+
+    #[test]
+    fn collisions_ignore_the_camera() {
+        let plain = hits_with_camera(|_| {});
+        assert_eq!(plain, [true, true, false, false]);
+        assert_eq!(hits_with_camera(|c| c.rotate_to(1.2, 0.0)), plain);
+        assert_eq!(hits_with_camera(|c| { c.rotate_to(-2.5, 0.0); c.zoom_pulse(0.5, 4.0, 0.0); c.shake(30.0); c.jerk(vec2(100.0, 0.0)); }), plain);
+    }
+
+
+    fn tags(obsts: &[Obst]) -> Vec<Option<u32>> {
+        obsts.iter().map(|o| o.tag).collect()
+    }
+
+    #[test]
+    fn innermost_tag_wins() {
+        let mut accum = UpdateAccumulator::new();
+        accum.with_tag(1, |accum| {
+            accum.pellet(Vec2::ZERO, Vec2::ZERO, 1.0);
+            accum.with_tag(2, |accum| accum.pellet(Vec2::ZERO, Vec2::ZERO, 1.0));
+            // already tagged ones keep theirs
+            accum.obstacle(Obst::new(Box::new(Burst), 0.0).tag(7));
+            accum.pellet(Vec2::ZERO, Vec2::ZERO, 1.0);
+        });
+        accum.pellet(Vec2::ZERO, Vec2::ZERO, 1.0);
+        accum.spawn_after(1.0, Burst);
+        assert_eq!(tags(&accum.obstacles_to_add), [Some(1), Some(2), Some(7), Some(1), None]);
+        assert_eq!((accum.count_tagged(1), accum.count_tagged(2), accum.count_tagged(3)), (2, 1, 0));
+    }
+
+    #[test]
+    fn what_killed_obstacles_spawn_is_tagged_like_them() {
+        let mut accum = UpdateAccumulator::new();
+        let bomb = Bomb::new(Vec2::ZERO, vec2(100.0, 0.0), 1.0, 8, 100.0, 5.0, Box::new(Bomb::pellet_spawner));
+        let mut obsts = vec![Obst::new(Box::new(bomb), 0.0).tag(3), Obst::new(Box::new(Burst), 0.0).tag(4), Obst::new(Box::new(Burst), 0.0)];
+        obsts.iter_mut().for_each(|o| o.marked_for_removal = true);
+        // nested inside another tag, the obstacles' own still win
+        accum.with_tag(9, |accum| accum.remove_dead(&mut obsts));
+        let mut expected = vec![Some(3); 8];
+        expected.extend([Some(4), Some(9)]);
+        assert_eq!(tags(&accum.obstacles_to_add), expected);
+    }
+
+    #[test]
+    fn removing_tagged_runs_kill_hooks_only_when_asked() {
+        let mut accum = UpdateAccumulator::new();
+        let mut obsts = [Some(1), Some(1), Some(2), None].map(|tag| {
+            let obst = Obst::new(Box::new(Burst), 0.0);
+            if let Some(tag) = tag { obst.tag(tag) } else { obst }
+        }).to_vec();
+        accum.with_tag(1, |accum| {
+            accum.obst(Burst);
+            accum.spawn_after(1.0, Burst);
+        });
+
+        accum.remove_tagged(1, false);
+        accum.apply_removals(&mut obsts);
+        accum.remove_dead(&mut obsts);
+        assert_eq!(tags(&obsts), [Some(2), None]);
+        assert!(accum.obstacles_to_add.is_empty(), "kill hooks ran, or the one added this update survived");
+        accum.spawn_due(2.0);
+        assert!(accum.obstacles_to_add.is_empty(), "the scheduled one wasn't dropped");
+
+        accum.remove_tagged(2, true);
+        accum.apply_removals(&mut obsts);
+        accum.remove_dead(&mut obsts);
+        assert_eq!(tags(&obsts), [None]);
+        assert_eq!(tags(&accum.obstacles_to_add), [Some(2)]);
+
+        accum.remove_all(false);
+        accum.apply_removals(&mut obsts);
+        accum.remove_dead(&mut obsts);
+        assert!(obsts.is_empty() && accum.obstacles_to_add.is_empty());
+    }
+
+
+    /// Spawns a pellet at `(n, 0)` when killed and `(n, 1)` when it expires, expiring if `expires`.
+    #[derive(Clone, Copy)]
+    struct Numbered { n: f32, expires: bool }
+    impl Obstacle for Numbered {
+        fn update(&mut self, to_add: &mut UpdateAccumulator, dtime: f32, time: f32, dease: f32, ease: f32) {}
+        fn draw(&self, color: Color, offset: Vec2) {}
+        fn box_clone(&self) -> Box<dyn Obstacle> { Box::new(*self) }
+        fn collides(&self, player: Player) -> bool { false }
+        fn expired(&self) -> bool { self.expires }
+        fn on_expire(&mut self, to_add: &mut UpdateAccumulator) { to_add.pellet(vec2(self.n, 1.0), Vec2::ZERO, 1.0) }
+        fn kill(&mut self, to_add: &mut UpdateAccumulator) { to_add.pellet(vec2(self.n, 0.0), Vec2::ZERO, 1.0) }
+        fn anchor(&self) -> Option<Vec2> { Some(vec2(self.n, 0.0)) }
+    }
+
+    #[test]
+    fn removing_the_dead_in_one_pass_matches_removing_them_one_by_one() {
+        let mut rng = GameRng::new(939);
+        for _ in 0..50 {
+            let obsts = (0..rng.range(0.0, 300.0) as usize).map(|n| {
+                let mut obst = Obst::new(Box::new(Numbered { n: n as f32, expires: rng.chance(0.3) }), 0.0);
+                obst.marked_for_removal = rng.chance(0.3);
+                obst
+            }).collect::<Vec<_>>();
+            let anchors = |obsts: &[Obst]| obsts.iter().map(|o| o.obstacle.anchor()).collect::<Vec<_>>();
+
+            let mut one_pass = obsts.clone();
+            let mut accum = UpdateAccumulator::new();
+            accum.remove_dead(&mut one_pass);
+            let spawned = anchors(&accum.obstacles_to_add);
+
+            // the old way, shifting everything after each removal down
+            let mut one_by_one = obsts;
+            let mut reference = UpdateAccumulator::new();
+            let mut i = 0;
+            while i < one_by_one.len() {
+                if one_by_one[i].marked_for_removal || one_by_one[i].done() {
+                    let mut obst = one_by_one.remove(i);
+                    let expired = !obst.marked_for_removal;
+                    reference.kill(&mut obst, expired);
+                } else {
+                    i += 1;
+                }
+            }
+            assert_eq!(anchors(&one_pass), anchors(&one_by_one));
+            assert_eq!(spawned, anchors(&reference.obstacles_to_add));
+        }
+    }
+
+    #[test]
+    fn mass_expiry_costs_about_as_much_as_carrying_on() {
+        let frame = |vel: Vec2| {
+            let pellets = (0..10_000).map(|i| Obst::new(Box::new(Pellet::new(vec2(10.0, (i % 800) as f32 + 50.0), vel, 5.0)), 0.0)).collect::<Vec<_>>();
+            let mut left = 0;
+            let secs = best_of(|| {
+                let mut level = LevelState::new();
+                level.obsts = pellets.clone();
+                let mut accum = UpdateAccumulator::new();
+                level.update_obstacles(&mut accum, 1.0, false);
+                accum.remove_dead(&mut level.obsts);
+                left = level.obsts.len();
+            });
+            (secs, left)
+        };
+        let (normal, kept) = frame(vec2(1.0, 0.0));
+        let (expiry, left) = frame(vec2(-100.0, 0.0));
+        assert_eq!((kept, left), (10_000, 0));
+        // removing them one by one, shifting the rest down each time, would take many times longer
+        assert!(expiry < normal * 3.0, "{expiry}s all expiring, {normal}s carrying on");
+    }
+
+
+    #[test]
+    fn pellets_spawned_and_killed_every_frame_stop_allocating() {
+        let mut accum = UpdateAccumulator::new();
+        let mut obsts = vec![];
+        let burst = Bomb::new(Vec2::ZERO, Vec2::ZERO, 1.0, 50, 100.0, 5.0, Box::new(Bomb::pellet_spawner));
+        let mut addresses = None;
+        for _ in 0..5 {
+            // half straight from the accumulator, half through a bomb's spawner
+            (0..50).for_each(|_| accum.pellet(Vec2::ZERO, Vec2::ZERO, 5.0));
+            let mut bomb = Obst::new(Box::new(burst.clone()), 0.0);
+            accum.kill(&mut bomb, false);
+            obsts.append(&mut accum.obstacles_to_add);
+            let mut now = obsts.iter().map(|o| &*o.obstacle as *const dyn Obstacle as *const u8 as usize).collect::<Vec<_>>();
+            now.sort_unstable();
+            assert_eq!(now.len(), 100);
+            // the same boxes every time after the first
+            if let Some(before) = &addresses { assert_eq!(&now, before); }
+            addresses = Some(now);
+            obsts.iter_mut().for_each(|o| o.marked_for_removal = true);
+            accum.remove_dead(&mut obsts);
+        }
+    }
+
+
+    #[test]
+    fn parallel_frames_match_serial_ones() {
+        let level = |parallel: bool| {
+            let mut level = LevelState::new();
+            level.obsts = crate::parallel::tests::mixed_movers(crate::parallel::PARALLEL_MIN_MOVERS * 2, 9451);
+            // something that has to go in order between them
+            level.obsts.insert(100, Obst::new(Box::new(Periodic::new(64, 0.25, Box::new(|ac: &mut UpdateAccumulator, _| ac.pellet(Vec2::ZERO, Vec2::ZERO, 1.0)))), 0.0));
+            let mut accum = UpdateAccumulator::new();
+            for _ in 0..30 {
+                level.time += 0.1;
+                level.update_obstacles(&mut accum, 0.1, parallel);
+            }
+            let states = level.obsts.iter().map(|o| o.obstacle.debug_fields()).collect::<Vec<_>>();
+            let spawned = accum.obstacles_to_add.iter().map(|o| (o.start_time, o.obstacle.anchor())).collect::<Vec<_>>();
+            (states, spawned, (accum.jerk, accum.shake, accum.shake_lean, accum.hitstop), accum.sounds.drain().collect::<Vec<_>>(), accum.impact_flashes.len())
+        };
+        let serial = level(false);
+        assert!(!serial.1.is_empty() && !serial.3.is_empty(), "nothing to compare");
+        assert!(serial == level(true));
+    }
+
+
+    #[test]
+    fn bursts_of_bombs_play_one_explosion() {
+        let mut accum = UpdateAccumulator::new();
+        let bomb = Bomb::new(Vec2::ZERO, Vec2::ZERO, 1.0, 8, 100.0, 5.0, Box::new(Bomb::pellet_spawner));
+        for _ in 0..40 {
+            accum.kill(&mut Obst::new(Box::new(bomb.clone()), 0.0), false);
+        }
+        let mut laser = Obst::new(Box::new(GrowLaser::new(Vec2::ZERO, vec2(100.0, 0.0), 10.0, 0.5, 1.0, Vec2::ZERO)), 0.0);
+        // warning, then firing once
+        for t in [0.25, 0.75, 1.0] {
+            laser.obstacle.update(&mut accum, 0.25, t, 0.25, t);
+        }
+        let played = accum.sounds.drain().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(played, [SoundId::BombExplode, SoundId::LaserFire]);
+    }
+
+
+    #[test]
+    fn long_frames_split_into_capped_steps() {
+        assert_eq!(substeps(0.01, DEFAULT_SUBSTEP_BEATS, DEFAULT_MAX_SUBSTEPS), 1);
+        assert_eq!(substeps(DEFAULT_SUBSTEP_BEATS * 3.5, DEFAULT_SUBSTEP_BEATS, DEFAULT_MAX_SUBSTEPS), 4);
+        // a ten second hitch jumps after the cap
+        assert_eq!(substeps(frame_beats(10.0, 120.0, 1.0), DEFAULT_SUBSTEP_BEATS, DEFAULT_MAX_SUBSTEPS), DEFAULT_MAX_SUBSTEPS);
+        assert_eq!(substeps(5.0, 0.0, DEFAULT_MAX_SUBSTEPS), 1);
+        assert_eq!(substeps(5.0, 0.1, 0), 1);
+        // the steps end where the frame does, evenly spaced
+        assert_eq!(step_time(2.0, 1.0, 4, 3), 2.0);
+        assert_eq!(step_time(2.0, 1.0, 4, 0), 1.25);
+    }
+
+    /// A frame `secs` long at 120 BPM, split the way the game would with steps of at most `step_beats`.
+    fn run_frame(level: &mut LevelState, secs: f32, step_beats: f32) {
+        let beat_dt = frame_beats(secs, 120.0, 1.0);
+        let time = level.time + beat_dt;
+        let substeps = substeps(beat_dt, step_beats, 10);
+        let mut accum = UpdateAccumulator::new();
+        level.run_events(&mut accum, step_time(time, beat_dt, substeps, 0));
+        level.advance(&mut accum, time, beat_dt, substeps, false, &[]);
+        accum.remove_dead(&mut level.obsts);
+        level.obsts.append(&mut accum.obstacles_to_add);
+    }
+
+    /// Pellets moving, firing in bursts and easing in, a chart spawning more halfway through.
+    /// Nothing fires right on a 0.06 beat step, where rounding would decide which frame it's on.
+    fn hitch_level() -> LevelState {
+        let mut level = LevelState::new();
+        level.obsts = vec![
+            Obst::new(Box::new(Pellet::new(vec2(100.0, 100.0), vec2(300.0, 50.0), 5.0)), 0.0),
+            Obst::new(Box::new(Periodic::new(64, 0.065, Box::new(|ac: &mut UpdateAccumulator, _| ac.pellet(vec2(800.0, 450.0), vec2(-200.0, 0.0), 5.0)))), 0.0),
+            Obst::new(Box::new(Ease::anon(Pellet::new(vec2(200.0, 700.0), vec2(400.0, 0.0), 5.0), recip_ease_fn(3.0))), 0.0),
+        ];
+        level.events = vec![GSEvent::new(0.33, |ac: &mut UpdateAccumulator, _| ac.pellet(vec2(400.0, 200.0), vec2(0.0, 300.0), 5.0))];
+        level
+    }
+
+    #[test]
+    fn one_long_frame_lands_where_ten_short_ones_do() {
+        let (mut long, mut short, mut unsplit) = (hitch_level(), hitch_level(), hitch_level());
+        run_frame(&mut long, 0.3, 0.06);
+        (0..10).for_each(|_| run_frame(&mut short, 0.03, 0.06));
+        run_frame(&mut unsplit, 0.3, 0.0);
+        assert!((long.time - short.time).abs() < 1e-5);
+        let anchors = |level: &LevelState| level.obsts.iter().filter_map(|o| o.obstacle.anchor()).collect::<Vec<_>>();
+        let (long, short) = (anchors(&long), anchors(&short));
+        // which it wouldn't without the steps
+        assert_ne!(anchors(&unsplit), short);
+        // the pellet, the eased one, the charted one and a burst of them
+        assert!(long.len() > 10);
+        assert_eq!(long.len(), short.len());
+        for (a, b) in long.iter().zip(&short) {
+            assert!(a.distance(*b) < 1e-2, "{a} against {b}");
+        }
+    }
+
+
+    #[test]
+    fn bombs_burst_into_particles() {
+        let mut accum = UpdateAccumulator::new();
+        accum.particles = ParticleSystem::new(MAX_PARTICLES, 0);
+        let bomb = Bomb::new(Vec2::ZERO, vec2(100.0, 0.0), 1.0, 8, 100.0, 5.0, Box::new(Bomb::pellet_spawner));
+        accum.kill(&mut Obst::new(Box::new(bomb), 0.0), false);
+        assert!(!accum.particles.is_empty());
+        // only the pellets are obstacles
+        assert_eq!(accum.obstacles_to_add.len(), 8);
+    }
+
+    const FREEZE_DT: f32 = 0.125;
+    const FREEZE_AT: f32 = 1.0;
+    const FREEZE_BEATS: f32 = 2.0;
+
+    /// Runs `obst` alone in a level frame by frame until beat 5, frozen right after the frame at `FREEZE_AT` for `FREEZE_BEATS` if `frozen`.
+    /// Calls `frame` after every frame.
+    fn run_freezing(obst: Obst, frozen: bool, mut frame: impl FnMut(&LevelState, &mut UpdateAccumulator)) {
+        let mut level = LevelState::new();
+        level.obsts = vec![obst];
+        let mut accum = UpdateAccumulator::new();
+        for i in 0..=(5.0 / FREEZE_DT) as usize {
+            let time = i as f32 * FREEZE_DT;
+            level.time = time;
+            accum.time = time;
+            level.update_obstacles(&mut accum, FREEZE_DT, false);
+            if frozen && time == FREEZE_AT { accum.freeze_all(FREEZE_BEATS, false); }
+            accum.apply_removals(&mut level.obsts);
+            frame(&level, &mut accum);
+            level.obsts.append(&mut accum.obstacles_to_add);
+            level.obsts.truncate(1);
+        }
+    }
+
+    /// (time, anchor) of an eased pellet every frame.
+    fn eased_path(frozen: bool) -> Vec<(f32, Vec2)> {
+        let eased = Obst::new(Box::new(Ease::anon(Pellet::new(Vec2::ZERO, vec2(100.0, 0.0), 1.0), recip_ease_fn(3.0))), 0.0);
+        let mut path = vec![];
+        run_freezing(eased, frozen, |level, _| path.push((level.time, level.obsts[0].obstacle.anchor().unwrap())));
+        path
+    }
+
+    #[test]
+    fn eased_obstacles_pick_up_where_they_froze() {
+        let (normal, frozen) = (eased_path(false), eased_path(true));
+        for &(time, pos) in &frozen {
+            // held still, then carrying on as if the freeze never was
+            let expected = if time <= FREEZE_AT + FREEZE_BEATS { time.min(FREEZE_AT) } else { time - FREEZE_BEATS };
+            let &(_, at) = normal.iter().find(|&&(t, _)| t == expected).unwrap();
+            assert!(pos.distance(at) < 1e-3, "at {time}: {pos}, expected {at}");
+        }
+        // so thawing never jumps further than a frame ever moves
+        let step = |path: &[(f32, Vec2)]| path.windows(2).map(|w| w[0].1.distance(w[1].1)).fold(0.0, f32::max);
+        assert!(step(&frozen) <= step(&normal) + 1e-3);
+    }
+
+    /// Level times of each step of a 10 step `Periodic` a half beat apart.
+    fn periodic_steps(frozen: bool) -> Vec<Vec<f32>> {
+        let periodic = Periodic::new(10, 0.5, Box::new(|gs: &mut UpdateAccumulator, sm: ModifyArgs| gs.pellet(sm.pos, Vec2::ZERO, 1.0)));
+        let mut frames = vec![];
+        run_freezing(Obst::new(Box::new(periodic), 0.0), frozen, |level, accum| frames.push(vec![level.time; accum.obstacles_to_add.len()]));
+        frames
+    }
+
+    #[test]
+    fn periodics_dont_catch_up_on_the_freeze() {
+        let (normal, frozen) = (periodic_steps(false), periodic_steps(true));
+        assert!(frozen.iter().all(|frame| frame.len() <= 1));
+        // the steps after the freeze come as late as it was long
+        let shifted = normal.concat().into_iter().map(|t| if t > FREEZE_AT { t + FREEZE_BEATS } else { t }).filter(|&t| t <= 5.0).collect::<Vec<_>>();
+        assert_eq!(frozen.concat(), shifted);
+    }
+
+    #[test]
+    fn harmless_freezes_stop_collisions_until_they_thaw() {
+        let player = Player { pos: vec2(100.0, 100.0), rad: 5.0, ..Player::default() };
+        for harmless in [false, true] {
+            let mut level = LevelState::new();
+            level.obsts = vec![Obst::new(Box::new(Pellet::new(player.pos, Vec2::ZERO, 10.0)), 0.0)];
+            let mut accum = UpdateAccumulator::new();
+            accum.freeze_all(1.0, harmless);
+            accum.apply_removals(&mut level.obsts);
+            assert!(level.obsts[0].frozen);
+            assert_eq!(level.obsts[0].collides(player), !harmless, "harmless: {harmless}");
+            assert_eq!(level.obsts[0].contact(player).is_some(), !harmless);
+            level.time = 1.0;
+            level.update_obstacles(&mut accum, 1.0, false);
+            assert!(!level.obsts[0].frozen);
+            assert!(level.obsts[0].collides(player));
+        }
+    }
+}
@@ -1,11 +1,10 @@
-use std::f32::consts::TAU;
+use std::{collections::VecDeque, f32::consts::TAU};
 
-use macroquad::{prelude::{Vec2, Rect, Color, WHITE, vec2}, shapes::{draw_circle, draw_line, draw_triangle}, window::{screen_height, screen_width}, rand::gen_range};
+use macroquad::{prelude::{Vec2, Rect, Color, WHITE, vec2}, shapes::{draw_triangle, draw_circle_lines}};
 use paste::paste;
 use perlin2d::PerlinNoise2D;
-use rand::{seq::SliceRandom, thread_rng};
 
-use crate::{utils::{sq, self, collide_cr, mix, draw_rrect, collide_cc, screen_center, acmul, circ_climb, adjust, screen_size, recip_ease, collide_circ_arc, draw_arc, cmul}, game::{Accumulatee, ModifyArgs, UpdateAccumulator}};
+use crate::{utils::{sq, self, screen_width, screen_height, collide_cr, rectify_line, mix, mix_linear, with_alpha, draw_rrect, draw_rrect_styled, collide_cc, screen_center, acmul, circ_climb, adjust, screen_size, recip_ease, collide_circ_arc, draw_arc, ArcMesh, draw_dashed_circle, draw_glow_circle, cmul, cubic_bezier, cubic_bezier_tangent, collide_capsule, collide_capsule_circle, collide_capsule_rect, collide_capsules, contact_capsule, circle_bounds, line_bounds, contact_cc, contact_cr, contact_arc, Contact, CircleBatch, GameRng, draw_circle_aa, draw_line_aa, draw_capsule_lines, draw_rrect_outline, draw_arc_outline}, game::{Accumulatee, ModifyArgs, UpdateAccumulator, shield_color, soft_pink, orb_color}, patterns::Ring, sound::SoundId, indicators::IndicatorStyle};
 
 use super::game::GameState;
 
@@ -21,16 +20,147 @@ macro_rules! vec2_builder {
         pub fn $fname(mut self, x: f32, y: f32) -> Self { self.$vname = macroquad::prelude::Vec2::new(x, y); self }
     };
 }
+/// `(field, value)` pairs of `self` for `Obstacle::debug_fields`, to two decimals.
+macro_rules! debug_fields {
+    ($self:ident: $($field:ident),*) => {
+        vec![$((stringify!($field).to_string(), format!("{:.2?}", $self.$field))),*]
+    };
+}
+
+/// The layer the player is drawn over. Obstacles on higher layers draw over the player.
+pub const PLAYER_LAYER: i8 = 0;
+/// For warnings of what's coming, under the hazards already there
+pub const TELEGRAPH_LAYER: i8 = -1;
+/// How far past their edge glowing obstacles glow, as a multiple of their radius
+pub const GLOW_SCALE: f32 = 2.5;
+/// Alpha of a glow at its middle, out of the obstacle's
+pub const GLOW_INTENSITY: f32 = 0.35;
+/// For hazards that flash in and should be seen over everything
+pub const FLASH_LAYER: i8 = 1;
+/// Whether the obstacles' white flashes fade in linear light, which keeps them from going muddy halfway. Off mixes them as they are
+const LINEAR_FLASHES: bool = true;
+
+/// Pixels wide the outlines of hitboxes are
+pub const HITBOX_THICKNESS: f32 = 1.5;
+/// What hitboxes that can't hit yet are outlined in, so warnings that don't match their hitbox stand out
+pub const HITBOX_WARNING_COLOR: Color = Color::new(1.0, 0.85, 0.2, 0.9);
+
+/// `color` for hitboxes that can hit now, `HITBOX_WARNING_COLOR` for the ones that will later.
+fn hitbox_color(color: Color, armed: bool) -> Color {
+    if armed { color } else { HITBOX_WARNING_COLOR }
+}
+fn draw_circle_hitbox(center: Vec2, rad: f32, color: Color) {
+    draw_circle_lines(center.x, center.y, rad, HITBOX_THICKNESS, color);
+}
+/// Outlines the rect `collide_cr` tests with the same arguments, which turns the other way from `draw_rrect`.
+fn draw_rect_hitbox(center: Vec2, size: Vec2, rot: f32, color: Color) {
+    draw_rrect_outline(center, size, -rot, HITBOX_THICKNESS, color);
+}
+
+/// How the obstacles mix their flashes, see `LINEAR_FLASHES`.
+fn flash_mix(color1: Color, color2: Color, by: f32) -> Color {
+    if LINEAR_FLASHES { mix_linear(color1, color2, by) } else { mix(color1, color2, by) }
+}
 
 /// Traits cannot hold members, so Obst contains markers (e.g. manual removal)
 pub struct Obst {
     pub obstacle: Box<dyn Obstacle>,
     pub marked_for_removal: bool,
+    /// Essential obstacles (bosses, set pieces) are never dropped by the obstacle budget.
+    pub essential: bool,
+    /// Last time the player grazed this obstacle
+    pub grazed_at: f32,
+    /// Set on the obstacle that ended the run, so the death sequence can highlight it
+    pub killer: bool,
+    /// Spawned by a chart file (or by an obstacle that was), so hot-reloading can clear it
+    pub from_chart: bool,
+    /// Obstacles draw by layer, the lowest first. Within a layer, in the order they were added
+    pub layer: i8,
+    /// For removing groups of obstacles at once, see `UpdateAccumulator::remove_tagged`
+    pub tag: Option<u32>,
+    /// Held in place without updating, see `UpdateAccumulator::freeze_tagged`. Still drawn, tinted
+    pub frozen: bool,
+    /// Beat the freeze ends at
+    pub thaw_at: f32,
+    /// Whether it stops colliding while frozen
+    pub frozen_harmless: bool,
+    /// Beats it spent frozen, left out of the time it's updated with so nothing catches up after thawing
+    pub frozen_beats: f32,
+    /// How much of the last frame it spent frozen, see `frame_beats`
+    pub frozen_in_frame: f32,
+    /// (style, beat it arrives at) of the arrow pointing at it from the edge of the screen while it's out of view, see `indicated`
+    pub indicator: Option<(IndicatorStyle, f32)>,
     pub start_time: f32
 }
 impl Obst {
     pub fn new(obst: Box<dyn Obstacle>, start_time: f32) -> Self {
-        Obst { obstacle: obst, marked_for_removal: false, start_time }
+        Obst { obstacle: obst, marked_for_removal: false, essential: false, grazed_at: f32::NEG_INFINITY, killer: false, from_chart: false, layer: 0, tag: None,
+            frozen: false, thaw_at: 0.0, frozen_harmless: false, frozen_beats: 0.0, frozen_in_frame: 0.0, indicator: None, start_time }
+    }
+    pub fn layer(mut self, layer: i8) -> Self {
+        self.layer = layer;
+        self
+    }
+    pub fn tag(mut self, tag: u32) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+    pub fn essential(mut self) -> Self {
+        self.essential = true;
+        self
+    }
+    pub fn charted(mut self) -> Self {
+        self.from_chart = true;
+        self
+    }
+    /// Points an arrow at its anchor from the edge of the screen whenever it's out of view, for the first `beats` beats.
+    pub fn indicated(mut self, style: IndicatorStyle, beats: f32) -> Self {
+        self.indicator = Some((style, self.start_time + beats));
+        self
+    }
+    /// Freezes it until `until`, colliding all the while unless `harmless`. Freezing it again never thaws it sooner.
+    pub fn freeze(&mut self, until: f32, harmless: bool) {
+        self.thaw_at = if self.frozen { self.thaw_at.max(until) } else { until };
+        self.frozen = true;
+        self.frozen_harmless = harmless;
+    }
+    /// Counts how much of the frame `dt` long up to `time` it spent frozen, thawing it once the freeze is over.
+    pub fn tick_freeze(&mut self, time: f32, dt: f32) {
+        self.frozen_in_frame = 0.0;
+        if !self.frozen { return; }
+        self.frozen_in_frame = (self.thaw_at.min(time) - (time - dt)).clamp(0.0, dt);
+        self.frozen_beats += self.frozen_in_frame;
+        if time >= self.thaw_at { self.frozen = false; }
+    }
+    /// The part of the frame `dt` long it wasn't frozen for. What it's updated by, so the frame it thaws in doesn't move it further than it had time to.
+    pub fn frame_beats(&self, dt: f32) -> f32 {
+        dt - self.frozen_in_frame
+    }
+    /// Beats since it started at `time`, not counting the ones it spent frozen. What it's updated with.
+    pub fn age(&self, time: f32) -> f32 {
+        time - self.start_time - self.frozen_beats
+    }
+    /// Whether `area` reaches the obstacle's bounds. Always true for obstacles without bounds.\
+    /// Never true for obstacles frozen harmless, so they're left out of every collision check.
+    pub fn near(&self, area: Rect) -> bool {
+        !(self.frozen && self.frozen_harmless) && self.obstacle.bounds().is_none_or(|bounds| bounds.overlaps(&area))
+    }
+    /// Whether the obstacle is done, through `should_kill` so obstacles that only implement that still expire.
+    #[allow(deprecated)]
+    pub fn done(&mut self) -> bool {
+        self.obstacle.should_kill()
+    }
+    /// `Obstacle::collides`, skipping the exact check when the player is nowhere near.
+    pub fn collides(&self, player: Player) -> bool {
+        self.near(circle_bounds(player.pos, player.rad)) && self.obstacle.collides(player)
+    }
+    /// `Obstacle::contact`, skipping the exact check when the player is nowhere near.
+    pub fn contact(&self, player: Player) -> Option<Contact> {
+        if self.near(circle_bounds(player.pos, player.rad)) { self.obstacle.contact(player) } else { None }
+    }
+    /// `Obstacle::collides_swept`, skipping the exact check when the path is nowhere near.
+    pub fn collides_swept(&self, from: Vec2, to: Vec2, rad: f32) -> bool {
+        self.near(circle_bounds(from, rad).combine_with(circle_bounds(to, rad))) && self.obstacle.collides_swept(from, to, rad)
     }
 }
 impl Clone for Obst {
@@ -39,17 +169,168 @@ impl Clone for Obst {
     }
 }
 
+/// Obstacle indices in drawing order, worked out every frame without sorting or allocating.
+#[derive(Default)]
+pub struct DrawOrder {
+    order: Vec<usize>,
+    /// Obstacles on each layer, then where each layer starts in `order`
+    layers: Vec<usize>,
+}
+impl DrawOrder {
+    /// The order to draw `obsts` in, and how many of them go under the player.
+    pub fn sort(&mut self, obsts: &[Obst]) -> (&[usize], usize) {
+        self.order.clear();
+        // usually everything is on the same layer, which is the order they're already in
+        if let Some(first) = obsts.first().map(|o| o.layer).filter(|&first| obsts.iter().all(|o| o.layer == first)) {
+            self.order.extend(0..obsts.len());
+            return (&self.order, if first <= PLAYER_LAYER { obsts.len() } else { 0 });
+        }
+        let slot = |layer: i8| (layer as i16 - i8::MIN as i16) as usize;
+        self.layers.clear();
+        self.layers.resize(256, 0);
+        for obst in obsts {
+            self.layers[slot(obst.layer)] += 1;
+        }
+        let mut start = 0;
+        for count in &mut self.layers {
+            (*count, start) = (start, start + *count);
+        }
+        let under = self.layers[slot(PLAYER_LAYER) + 1];
+        self.order.resize(obsts.len(), 0);
+        for (i, obst) in obsts.iter().enumerate() {
+            let at = &mut self.layers[slot(obst.layer)];
+            self.order[*at] = i;
+            *at += 1;
+        }
+        (&self.order, under)
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct Player {
     pub pos: Vec2,
     pub rad: f32,
     pub pps: f32,
+    /// Beats left until the next dash is available (dash + cooldown).
     pub dash: f32,
     pub isecs: f32,
+    pub hp: u32,
+    pub max_hp: u32,
+    /// Pixels travelled by a dash
+    pub dash_distance: f32,
+    /// Length of a dash in beats
+    pub dash_beats: f32,
+    /// Beats after a dash before the next one can be used
+    pub dash_cooldown: f32,
+    pub dash_dir: Vec2,
+    pub dash_origin: Vec2,
+    /// Focus mode slows the player down and shows the hitbox.
+    pub focused: bool,
+    /// Knockback displacement that hasn't been applied yet.
+    pub knockback: Vec2,
+    /// Beats left on the shield, which absorbs the next hit. Infinite if it lasts until broken.
+    pub shield: Option<f32>,
+    /// Last time the player lost a hit point
+    pub hp_lost_at: f32,
+    pub color: Color,
+    /// Bomb charges left
+    pub bombs: u32,
+    /// Beats until the next bomb can be used
+    pub bomb_cooldown: f32,
 }
 impl Default for Player {
     fn default() -> Self {
-        Player { pos: Vec2::new(screen_width() / 2.0, screen_height() / 2.0), rad: 5.0, pps: 100.0, dash: 0.0, isecs: 0.0 }
+        Player {
+            pos: Vec2::new(screen_width() / 2.0, screen_height() / 2.0),
+            rad: 5.0,
+            pps: 300.0,
+            dash: 0.0,
+            isecs: 0.0,
+            hp: 3,
+            max_hp: 3,
+            dash_distance: 200.0,
+            dash_beats: 0.5,
+            dash_cooldown: 1.0,
+            dash_dir: Vec2::ZERO,
+            dash_origin: Vec2::ZERO,
+            focused: false,
+            knockback: Vec2::ZERO,
+            shield: None,
+            hp_lost_at: f32::NEG_INFINITY,
+            color: soft_pink(),
+            bombs: 0,
+            bomb_cooldown: 0.0,
+        }
+    }
+}
+/// How long the dash afterimage stays after the dash itself, in beats.
+pub const DASH_STREAK_BEATS: f32 = 0.25;
+/// Knockback is (almost entirely) applied within this many beats.
+pub const KNOCKBACK_BEATS: f32 = 0.2;
+impl Player {
+    /// Downed players (in co-op) no longer move or collide.
+    pub fn alive(&self) -> bool {
+        self.hp > 0
+    }
+    /// Player is invincible while dashing.
+    pub fn dashing(&self) -> bool {
+        self.dash > self.dash_cooldown
+    }
+    /// Whether lethal obstacles can hit the player, which they can't while it's dashing or has i-frames.
+    pub fn vulnerable(&self) -> bool {
+        !self.dashing() && self.isecs <= 0.0
+    }
+    /// Beats since the last dash started.
+    pub fn since_dash(&self) -> f32 {
+        self.dash_beats + self.dash_cooldown - self.dash
+    }
+    /// Starts a dash in `dir`, if one is available.
+    pub fn start_dash(&mut self, dir: Vec2) {
+        if self.dash <= 0.0 && dir != Vec2::ZERO {
+            self.dash = self.dash_beats + self.dash_cooldown;
+            self.dash_dir = dir.normalize();
+            self.dash_origin = self.pos;
+        }
+    }
+    /// Advances the dash by `dbeats`, moving the player along it with a quadratic ease-out.\
+    /// The eased distance is identical regardless of how the beats are split across frames.
+    pub fn update_dash(&mut self, dbeats: f32) {
+        if self.dash <= 0.0 { return; }
+        let ease = |dash: f32| {
+            let t = ((self.dash_beats + self.dash_cooldown - dash) / self.dash_beats).clamp(0.0, 1.0);
+            1.0 - sq(1.0 - t)
+        };
+        let before = ease(self.dash);
+        self.dash = (self.dash - dbeats).max(0.0);
+        self.pos += self.dash_dir * self.dash_distance * (ease(self.dash) - before);
+    }
+    /// Takes the part of the knockback to apply over `dbeats`, decaying exponentially.
+    pub fn knockback_step(&mut self, dbeats: f32) -> Vec2 {
+        // 95% applied after KNOCKBACK_BEATS
+        let step = self.knockback * (1.0 - (-3.0 * dbeats / KNOCKBACK_BEATS).exp());
+        self.knockback -= step;
+        step
+    }
+    /// Keeps the player fully inside `arena`, or centered along a side it's too big for.\
+    /// Returns which edges it was pushed back from, in the order left, top, right, bottom.
+    pub fn clamp_to(&mut self, arena: Rect) -> [bool; 4] {
+        // the center's between the two unless the arena's too small, where they meet at it
+        let center = arena.center();
+        let min = (arena.point() + self.rad).min(center);
+        let max = (arena.point() + arena.size() - self.rad).max(center);
+        let clamped = self.pos.clamp(min, max);
+        let touched = [self.pos.x < min.x, self.pos.y < min.y, self.pos.x > max.x, self.pos.y > max.y];
+        self.pos = clamped;
+        touched
+    }
+    /// Draws the afterimage streak left behind by a dash.
+    pub fn draw_streak(&self, color: Color, offset: Vec2) {
+        let since = self.since_dash();
+        if self.dash <= 0.0 || since > self.dash_beats + DASH_STREAK_BEATS { return; }
+        let fade = 1.0 - (since / (self.dash_beats + DASH_STREAK_BEATS)).clamp(0.0, 1.0);
+        let from = self.dash_origin + offset;
+        let to = self.pos + offset;
+        draw_line_aa(from.x, from.y, to.x, to.y, self.rad * 2.0 * fade, acmul(color, fade * 0.5));
     }
 }
 pub trait Obstacle {
@@ -64,12 +345,77 @@ pub trait Obstacle {
     fn update(&mut self, to_add: &mut UpdateAccumulator, dtime: f32, time: f32, dease: f32, ease: f32);
     fn draw(&self, color: Color, offset: Vec2);
     fn box_clone(&self) -> Box<dyn Obstacle>;
+    /// Type name, for the debug overlay's breakdown. Wrappers pass on what they wrap.
+    fn name(&self) -> &'static str { "unknown" }
     fn collides(&self, player: Player) -> bool;
-    fn should_kill(&mut self) -> bool;
+    /// Whether a player of radius `rad` moving from `from` to `to` this frame hit the obstacle on the way.\
+    /// Defaults to checking `collides` at points along the way, at most `rad` apart.
+    /// Where the player touches the obstacle, if they do. Defaults to `collides`, not knowing where.
+    fn contact(&self, player: Player) -> Option<Contact> {
+        self.collides(player).then(Contact::unknown)
+    }
+    fn collides_swept(&self, from: Vec2, to: Vec2, rad: f32) -> bool {
+        let steps = (from.distance(to) / rad.max(1.0)).ceil().max(1.0) as usize;
+        (0..=steps).any(|i| self.collides(Player { pos: from.lerp(to, i as f32 / steps as f32), rad, ..Player::default() }))
+    }
+    /// Whether the obstacle is done and should be removed. Only looks, changing nothing.\
+    /// Defaults to never, for obstacles still implementing `should_kill`; new ones should implement this.
+    fn expired(&self) -> bool { false }
+    /// The old `expired`, which could change the obstacle. Only asked when removing obstacles.
+    #[deprecated(note = "implement `expired`, and `on_expire` for anything that has to happen on expiry")]
+    fn should_kill(&mut self) -> bool { self.expired() }
+    /// Called when the obstacle is removed for expiring, before `kill`. Removals for other reasons don't call it.
+    fn on_expire(&mut self, to_add: &mut UpdateAccumulator) {}
     /// Called before dropping. Use to trigger behaviour on death (e.g. bombs).
     fn kill(&mut self, to_add: &mut UpdateAccumulator) {}
+    /// Representative position of the obstacle, if it has one. Lasers, emitters and the like don't.
+    fn anchor(&self) -> Option<Vec2> { None }
+    /// Whether touching the obstacle costs a hit. Non-lethal obstacles (e.g. bumpers) handle contact in `update`.
+    fn lethal(&self) -> bool { true }
+    /// What the player gets for touching the obstacle, if it's a pickup. Pickups are removed once collected.
+    fn pickup(&self) -> Option<Pickup> { None }
+    /// (field, value) of the parameters worth checking when a chart misbehaves, for the dev inspector.
+    fn debug_fields(&self) -> Vec<(String, String)> { vec![] }
+    /// A box the obstacle is never drawn or touched outside of, for skipping the exact checks. `None` is always checked.
+    fn bounds(&self) -> Option<Rect> { None }
+    /// Draws the obstacle into `batch` instead of on its own if it can, returning whether it did.\
+    /// Batched obstacles show up once the batch is flushed, over the ones drawn in the meantime.
+    fn draw_batched(&self, color: Color, offset: Vec2, batch: &mut CircleBatch) -> bool { false }
+    /// Draws the glow under the obstacle when glows are on, before any obstacle itself is drawn. Most don't glow.
+    fn draw_glow(&self, color: Color, offset: Vec2) {}
+    /// Outlines the shape `collides` tests against, in `color` if it can hit now and `HITBOX_WARNING_COLOR` while it's still a warning.\
+    /// For the debug overlay, drawn over the obstacle. Obstacles that never collide draw nothing.
+    fn draw_debug(&self, color: Color, offset: Vec2) {}
+    /// The box back if it holds a plain `Pellet`, so `PelletPool` can reuse it.
+    fn into_pellet(self: Box<Self>) -> Option<Box<Pellet>> { None }
+    /// The obstacle as a `Mover`, if its update can run on another thread. Its `update` should just `step` then.
+    fn parallel(&mut self) -> Option<&mut dyn Mover> { None }
+}
+/// What an update can do to the rest of the game short of spawning anything, so it can be done away from the accumulator.
+pub trait Effects {
+    fn jerk(&mut self, jerk: Vec2);
+    fn shake(&mut self, shake: f32);
+    fn shake_directional(&mut self, shake: Vec2);
+    fn impact_flash(&mut self, intensity: f32);
+    fn hitstop(&mut self, secs: f32);
+    fn sound(&mut self, id: SoundId, volume: f32, pitch: f32);
+}
+/// An obstacle whose update only moves it along and has `Effects`, reading nothing but the time it's given.\
+/// Those can be updated in parallel, see `GameState::parallel_updates`.
+pub trait Mover: Send {
+    /// `Obstacle::update`, with just the effects to go on.
+    fn step(&mut self, effects: &mut dyn Effects, dtime: f32, time: f32, dease: f32, ease: f32);
+}
+/// Effects granted by pickups.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pickup {
+    /// A shield that absorbs one hit, lasting for the given beats or until broken.
+    Shield(Option<f32>),
+    /// Points, multiplied by the combo.
+    Score(u32),
 }
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pellet {
     pub pos: Vec2,
     pub vel: Vec2,
@@ -80,19 +426,74 @@ impl Pellet {
         Pellet { pos, vel, rad }
     }
 }
+impl Mover for Pellet {
+    fn step(&mut self, effects: &mut dyn Effects, dtime: f32, time: f32, dease: f32, ease: f32) {
+        self.pos += self.vel * dease;
+    }
+}
 impl Obstacle for Pellet {
+    fn name(&self) -> &'static str { "Pellet" }
     fn box_clone(&self) -> Box<dyn Obstacle> { Box::new(*self) }
     fn collides(&self, player: Player) -> bool {
         collide_cc(self.pos, self.rad, player.pos, player.rad)
     }
+    fn contact(&self, player: Player) -> Option<Contact> {
+        self.collides(player).then(|| contact_cc(self.pos, self.rad, player.pos, player.rad))
+    }
+    fn collides_swept(&self, from: Vec2, to: Vec2, rad: f32) -> bool {
+        collide_capsule_circle(from, to, rad, self.pos, self.rad)
+    }
     fn draw(&self, color: Color, offset: Vec2) {
-        draw_circle(self.pos.x + offset.x, self.pos.y + offset.y, self.rad, color);
+        draw_circle_aa(self.pos.x + offset.x, self.pos.y + offset.y, self.rad, color);
     }
-    fn should_kill(&mut self) -> bool {
+    fn draw_batched(&self, color: Color, offset: Vec2, batch: &mut CircleBatch) -> bool {
+        batch.circle(self.pos + offset, self.rad, color);
+        true
+    }
+    fn draw_debug(&self, color: Color, offset: Vec2) { draw_circle_hitbox(self.pos + offset, self.rad, color) }
+    fn draw_glow(&self, color: Color, offset: Vec2) {
+        draw_glow_circle(self.pos + offset, self.rad * GLOW_SCALE, color, GLOW_INTENSITY);
+    }
+    fn expired(&self) -> bool {
         !Rect::new(-self.rad, -self.rad, screen_width() + self.rad, screen_height() + self.rad).contains(self.pos)
     }
     fn update(&mut self, to_add: &mut UpdateAccumulator, beat_delta: f32, time: f32, dease: f32, ease: f32) {
-        self.pos += self.vel * dease;
+        self.step(to_add, beat_delta, time, dease, ease)
+    }
+    fn parallel(&mut self) -> Option<&mut dyn Mover> { Some(self) }
+    fn anchor(&self) -> Option<Vec2> { Some(self.pos) }
+    fn bounds(&self) -> Option<Rect> { Some(circle_bounds(self.pos, self.rad)) }
+    fn into_pellet(self: Box<Self>) -> Option<Box<Pellet>> { Some(self) }
+    fn debug_fields(&self) -> Vec<(String, String)> { debug_fields!(self: pos, vel, rad) }
+}
+
+/// Most dead pellets `PelletPool` keeps for reuse
+pub const MAX_POOLED_PELLETS: usize = 20_000;
+/// The boxes of dead pellets, reused for new ones so dense patterns don't allocate and free one per pellet.\
+/// Owned by the level, lent to the `UpdateAccumulator` while it updates.
+#[derive(Default)]
+pub struct PelletPool {
+    // the boxes are what's being kept
+    #[allow(clippy::vec_box)]
+    free: Vec<Box<Pellet>>,
+}
+impl PelletPool {
+    /// `pellet` in a reused box if there's one.
+    pub fn take(&mut self, pellet: Pellet) -> Box<Pellet> {
+        match self.free.pop() {
+            Some(mut boxed) => {
+                *boxed = pellet;
+                boxed
+            }
+            None => Box::new(pellet)
+        }
+    }
+    /// Keeps the box of `obstacle` for later if it's a pellet and there's room, dropping it otherwise.
+    pub fn recycle(&mut self, obstacle: Box<dyn Obstacle>) {
+        if self.free.len() >= MAX_POOLED_PELLETS { return; }
+        if let Some(pellet) = obstacle.into_pellet() {
+            self.free.push(pellet);
+        }
     }
 }
 
@@ -124,11 +525,9 @@ impl Bomb {
         }
     }
     pub fn pellet_spawner(gs: &mut UpdateAccumulator, args: ModifyArgs) {
-        gs.obstacle(Obst::new(Box::new(Pellet {
-            pos: args.pos,
-            vel: args.vel,
-            rad: args.rad
-        }), args.time))
+        // back-dated spawns start where they would have been by now
+        let elapsed = (gs.time() - args.time).max(0.0);
+        gs.pellet_at(args.pos + args.vel * elapsed, args.vel, args.rad, args.time)
     }
     pub fn pos(&self, offset: Vec2) -> Vec2 {
         (self.start - self.target) / (self.time * self.snappiness + 1.0) + self.target + offset
@@ -147,7 +546,7 @@ impl Obstacle for Bomb {
     fn draw(&self, color: Color, offset: Vec2) {
         let pos = self.pos(offset);
         let size = self.time * self.rad;
-        draw_circle(pos.x, pos.y, size, color);
+        draw_circle_aa(pos.x, pos.y, size, color);
         let rot = self.time * 3.0;
         let size_fac = 1.2;
         let c1 = vec2(rot.cos(), rot.sin()) * size * size_fac + pos;
@@ -157,16 +556,29 @@ impl Obstacle for Bomb {
         draw_triangle(c1, c2, c3, color);
         draw_triangle(c1, c4, c3, color);
     }
+    fn draw_debug(&self, color: Color, offset: Vec2) { draw_circle_hitbox(self.pos(offset), self.rad * self.time, color) }
+    fn name(&self) -> &'static str { "Bomb" }
     fn box_clone(&self) -> Box<dyn Obstacle> { Box::new(self.clone()) }
     fn collides(&self, player: Player) -> bool {
         utils::collide_cc(self.pos(Vec2::ZERO), self.rad * self.time, player.pos, player.rad)
     }
-    fn should_kill(&mut self) -> bool { self.time >= self.life }
+    fn contact(&self, player: Player) -> Option<Contact> {
+        self.collides(player).then(|| contact_cc(self.pos(Vec2::ZERO), self.rad * self.time, player.pos, player.rad))
+    }
+    fn expired(&self) -> bool { self.time >= self.life }
+    fn debug_fields(&self) -> Vec<(String, String)> { debug_fields!(self: start, target, time, life, pellets, pellet_vel, pellet_rad) }
+    fn anchor(&self) -> Option<Vec2> { Some(self.pos(Vec2::ZERO)) }
+    // the spinning square reaches past the circle
+    fn bounds(&self) -> Option<Rect> { Some(circle_bounds(self.pos(Vec2::ZERO), self.rad * self.time * 1.2)) }
     fn kill(&mut self, to_add: &mut UpdateAccumulator) {
         let pos = self.pos(Vec2::ZERO);
+        to_add.impact_flash(0.08);
+        to_add.hitstop(0.02);
+        to_add.sound(SoundId::BombExplode, 1.0, 1.0);
+        to_add.particles().burst(pos, 24, 80.0..320.0, 0.3..0.7, WHITE);
         for i in 0..self.pellets {
             let period = i as f32 / self.pellets as f32 * TAU;
-            self.spawner.run(to_add, ModifyArgs::new(to_add.time()).pos(pos).vel(Vec2 {
+            self.spawner.run(to_add, ModifyArgs::new(to_add.time()).step(i).total_steps(self.pellets).pos(pos).vel(Vec2 {
                 x: period.sin() * self.pellet_vel,
                 y: period.cos() * self.pellet_vel
             }).rad(self.pellet_rad));
@@ -175,6 +587,7 @@ impl Obstacle for Bomb {
 }
 
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GrowLaser {
     pub start: Vec2,
     pub end: Vec2,
@@ -207,6 +620,10 @@ impl GrowLaser {
     builder!(fade_opacity: f32);
     builder!(fade_in: f32);
     builder!(grow_time: f32);
+    /// Whether there's a line to hit, which there isn't if it starts and ends in the same place.
+    fn spans(&self) -> bool {
+        rectify_line(self.start, self.end, self.thickness).is_some()
+    }
     /// Calculates smoothed thickness
     pub fn thick(&self) -> f32 {
         let total_time = self.warning_time + self.show_time;
@@ -219,43 +636,59 @@ impl GrowLaser {
         }
     }
 }
-impl Obstacle for GrowLaser {
-    fn update(&mut self, accum: &mut UpdateAccumulator, beat_delta: f32, time: f32, dease: f32, ease: f32) {
+impl Mover for GrowLaser {
+    fn step(&mut self, effects: &mut dyn Effects, dtime: f32, time: f32, dease: f32, ease: f32) {
         self.current_time = time;
         if !self.shown && self.current_time >= self.warning_time {
-            accum.jerk(self.jerk);
+            effects.jerk(self.jerk);
+            effects.sound(SoundId::LaserFire, 0.6, 1.2);
             self.shown = true;
         }
     }
+}
+impl Obstacle for GrowLaser {
+    fn debug_fields(&self) -> Vec<(String, String)> { debug_fields!(self: start, end, thickness, warning_time, show_time, grow_time, current_time) }
+    fn update(&mut self, accum: &mut UpdateAccumulator, beat_delta: f32, time: f32, dease: f32, ease: f32) {
+        self.step(accum, beat_delta, time, dease, ease)
+    }
+    fn parallel(&mut self) -> Option<&mut dyn Mover> { Some(self) }
 
     fn draw(&self, mut color: Color, offset: Vec2) {
         if self.current_time < self.warning_time {
-            if self.current_time < self.fade_in {
-                color.a = self.current_time / self.fade_in * self.fade_opacity;
-            } else {
-                color.a = self.fade_opacity;
-            }
+            color = with_alpha(color, self.fade_opacity * (self.current_time / self.fade_in).min(1.0));
         }
-        draw_line(self.start.x + offset.x, self.start.y + offset.y, self.end.x + offset.x, self.end.y + offset.y, self.thick(), color);
+        draw_line_aa(self.start.x + offset.x, self.start.y + offset.y, self.end.x + offset.x, self.end.y + offset.y, self.thick(), color);
+    }
+
+    fn draw_debug(&self, color: Color, offset: Vec2) {
+        if !self.spans() { return; }
+        draw_capsule_lines(self.start + offset, self.end + offset, self.thick() / 2.0, HITBOX_THICKNESS, hitbox_color(color, self.current_time >= self.warning_time));
     }
 
+    fn name(&self) -> &'static str { "GrowLaser" }
     fn box_clone(&self) -> Box<dyn Obstacle> {
         Box::new(*self)
     }
 
     fn collides(&self, player: Player) -> bool {
-        self.current_time >= self.warning_time && {
-            let (center, size, rot) = utils::rectify_line(self.start, self.end, self.thick());
-            utils::collide_cr(center, size, rot, player.pos, player.rad)
-        }
+        self.current_time >= self.warning_time && self.spans() && collide_capsule(self.start, self.end, self.thick() / 2.0, player.pos, player.rad)
+    }
+    fn contact(&self, player: Player) -> Option<Contact> {
+        self.collides(player).then(|| contact_capsule(self.start, self.end, self.thick() / 2.0, player.pos, player.rad))
+    }
+    fn collides_swept(&self, from: Vec2, to: Vec2, rad: f32) -> bool {
+        self.current_time >= self.warning_time && self.spans() && collide_capsules(self.start, self.end, self.thick() / 2.0, from, to, rad)
     }
+    // thick() overshoots thickness by a pixel as it starts shrinking
+    fn bounds(&self) -> Option<Rect> { Some(line_bounds(self.start, self.end, self.thick().abs())) }
 
-    fn should_kill(&mut self) -> bool {
+    fn expired(&self) -> bool {
         self.current_time >= self.warning_time + self.show_time
     }
 }
 
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SlamLaser {
     pub start: Vec2,
     pub end: Vec2,
@@ -288,7 +721,7 @@ impl SlamLaser {
     /// Will flash and fade out from white for `self.grow_time` beats, this function calculates the mix.
     pub fn color(&self, normal: Color) -> Color {
         if (self.warning_time..=self.warning_time + 0.5).contains(&self.current_time) {
-            mix(WHITE, normal, (self.current_time - self.warning_time) / 0.5)
+            flash_mix(WHITE, normal, (self.current_time - self.warning_time) / 0.5)
         } else {
             normal
         }
@@ -304,54 +737,117 @@ impl SlamLaser {
             self.current_time / self.warning_time * self.anticipation
         } else if self.current_time > total - self.leave_time {
             let exit_point = total - self.leave_time;
-            -sq((self.current_time - exit_point) / self.leave_time) + 1.0
+            (1.0 - sq((self.current_time - exit_point) / self.leave_time)).max(0.0)
         } else {
             1.0
         }
     }
+    /// How far the slam has reached, None while it's no longer than a point (as it is at the very end) and can't hit anything.
+    fn reach(&self) -> Option<Vec2> {
+        let end = self.start.lerp(self.end, self.slam());
+        rectify_line(self.start, end, self.thickness).map(|_| end)
+    }
 }
-impl Obstacle for SlamLaser {
-    fn update(&mut self, accum: &mut UpdateAccumulator, beat_delta: f32, time: f32, dease: f32, ease: f32) {
+impl Mover for SlamLaser {
+    fn step(&mut self, effects: &mut dyn Effects, dtime: f32, time: f32, dease: f32, ease: f32) {
         self.current_time = time;
         if !self.shown && self.current_time >= self.warning_time {
-            accum.jerk(self.jerk);
-            accum.shake(self.shake);
+            effects.jerk(self.jerk);
+            // mostly along the slam
+            effects.shake_directional((self.end - self.start).normalize_or_zero() * self.shake);
+            effects.impact_flash(0.1);
+            effects.hitstop(0.03);
+            effects.sound(SoundId::LaserFire, 1.0, 0.8);
             self.shown = true;
         }
     }
+}
+impl Obstacle for SlamLaser {
+    fn debug_fields(&self) -> Vec<(String, String)> { debug_fields!(self: start, end, thickness, warning_time, show_time, anticipation, current_time) }
+    fn update(&mut self, accum: &mut UpdateAccumulator, beat_delta: f32, time: f32, dease: f32, ease: f32) {
+        self.step(accum, beat_delta, time, dease, ease)
+    }
+    fn parallel(&mut self) -> Option<&mut dyn Mover> { Some(self) }
 
     fn draw(&self, mut color: Color, offset: Vec2) {
         let mut color = self.color(color);
         let end = self.start.lerp(self.end, self.slam());
-        draw_line(self.start.x + offset.x, self.start.y + offset.y, end.x + offset.x, end.y + offset.y, self.thickness, color);
+        draw_line_aa(self.start.x + offset.x, self.start.y + offset.y, end.x + offset.x, end.y + offset.y, self.thickness, color);
         if self.current_time < self.warning_time {
-            color.a = self.current_time / self.warning_time * 0.5;
-            draw_line(self.start.x + offset.x, self.start.y + offset.y, self.end.x + offset.x, self.end.y + offset.y, self.thickness, color);
+            color = with_alpha(color, self.current_time / self.warning_time * 0.5);
+            draw_line_aa(self.start.x + offset.x, self.start.y + offset.y, self.end.x + offset.x, self.end.y + offset.y, self.thickness, color);
         }
     }
 
+    fn draw_debug(&self, color: Color, offset: Vec2) {
+        if self.current_time < self.warning_time {
+            // where it slams to, not where the anticipation has it
+            if rectify_line(self.start, self.end, self.thickness).is_none() { return; }
+            draw_capsule_lines(self.start + offset, self.end + offset, self.thickness / 2.0, HITBOX_THICKNESS, HITBOX_WARNING_COLOR);
+        } else if let Some(end) = self.reach() {
+            draw_capsule_lines(self.start + offset, end + offset, self.thickness / 2.0, HITBOX_THICKNESS, color);
+        }
+    }
+
+    fn name(&self) -> &'static str { "SlamLaser" }
     fn box_clone(&self) -> Box<dyn Obstacle> {
         Box::new(*self)
     }
 
     fn collides(&self, player: Player) -> bool {
-        self.current_time >= self.warning_time && {
-            let (center, size, rot) = utils::rectify_line(self.start, self.start.lerp(self.end, self.slam()), self.thickness);
-            utils::collide_cr(center, size, rot, player.pos, player.rad)
-        }
+        self.current_time >= self.warning_time && self.reach().is_some_and(|end| collide_capsule(self.start, end, self.thickness / 2.0, player.pos, player.rad))
+    }
+    fn contact(&self, player: Player) -> Option<Contact> {
+        let end = self.reach()?;
+        self.collides(player).then(|| contact_capsule(self.start, end, self.thickness / 2.0, player.pos, player.rad))
+    }
+    fn collides_swept(&self, from: Vec2, to: Vec2, rad: f32) -> bool {
+        self.current_time >= self.warning_time && self.reach().is_some_and(|end| collide_capsules(self.start, end, self.thickness / 2.0, from, to, rad))
+    }
+    // the whole span the slam can reach, the warning is drawn over it anyway
+    fn bounds(&self) -> Option<Rect> {
+        Some(line_bounds(self.start, self.start.lerp(self.end, self.anticipation.max(1.0)), self.thickness))
     }
 
-    fn should_kill(&mut self) -> bool {
+    fn expired(&self) -> bool {
         self.current_time >= self.warning_time + self.show_time
     }
 }
 
+/// How a `Periodic` handles several steps becoming due in the same frame (e.g. a frame hitch).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CatchUp {
+    /// All missed steps fire at once, at the current time.
+    #[default]
+    Burst,
+    /// All missed steps fire, back-dated to when they should have happened.
+    Spread,
+    /// Only the most recent missed step fires.
+    Skip
+}
+
+/// What a `Periodic` does with the steps it hasn't reached when it gets removed early.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OnEarlyKill {
+    /// Remaining steps never happen.
+    #[default]
+    Drop,
+    /// Every remaining step runs immediately.
+    FlushRemaining,
+    /// Steps that would have happened within the given amount of beats run immediately.
+    FlushWithin(f32)
+}
+
 pub struct Periodic {
     pub modifier: Box<dyn Accumulatee>,
     pub time_mod: f32,
     pub time_div: usize,
     pub interval: f32,
     pub max_steps: usize,
+    pub on_early_kill: OnEarlyKill,
+    pub catch_up: CatchUp,
 }
 impl Periodic {
     pub fn new(steps: usize, interval: f32, modifier: Box<dyn Accumulatee>) -> Self {
@@ -360,13 +856,28 @@ impl Periodic {
             time_mod: 0.0,
             time_div: 0,
             interval,
-            max_steps: steps
+            max_steps: steps,
+            on_early_kill: OnEarlyKill::Drop,
+            catch_up: CatchUp::Burst
         }
     }
+    builder!(on_early_kill: OnEarlyKill);
+    builder!(catch_up: CatchUp);
+    /// Runs the next step as if it happened `beats_ago` beats ago.\
+    /// The density modifier can skip the step or run extra copies, spread out towards the previous step.
+    fn run_step(&mut self, to_add: &mut UpdateAccumulator, beats_ago: f32) {
+        let copies = to_add.modifiers().density_copies(self.time_div);
+        for copy in 0..copies {
+            let ago = beats_ago + self.interval * copy as f32 / copies as f32;
+            self.modifier.run(to_add, ModifyArgs::new(to_add.time() - ago).step(self.time_div).total_steps(self.max_steps));
+        }
+        self.time_div += 1;
+    }
     pub fn rect_trail(rect_life: f32, warning_time: f32, grow_time: f32, positioner: impl Fn(usize) -> (Vec2, Vec2, f32) + Clone + 'static) -> Box<dyn Accumulatee> {
         Box::new(move |gs: &mut UpdateAccumulator, sm: ModifyArgs| {
             let (center, size, rot) = positioner(sm.step);
-            gs.obst(RotatableRect {
+            // trails are laid out well ahead of the player, under whatever's already there
+            gs.obstacle(Obst::new(Box::new(RotatableRect {
                 center,
                 size,
                 rot,
@@ -374,14 +885,55 @@ impl Periodic {
                 show_time: rect_life,
                 current_time: 0.0,
                 grow_time,
-            })
+                warning_style: WarningStyle::Fill,
+            }), sm.time).layer(TELEGRAPH_LAYER))
         })
     }
     pub fn linear(rect_life: f32, warning_time: f32, grow_time: f32, start: Vec2, delta: Vec2, scale: Vec2, rot: f32) -> Box<dyn Accumulatee> {
         Self::rect_trail(rect_life, warning_time, grow_time, move |i| (start + delta * (i as f32 - 1.0), scale, rot))
     }
+    /// Places each step's rect where the player was `lag_beats` beats before the step.
+    pub fn chase(rect_life: f32, warning_time: f32, grow_time: f32, size: Vec2, lag_beats: f32) -> Box<dyn Accumulatee> {
+        Box::new(move |gs: &mut UpdateAccumulator, sm: ModifyArgs| {
+            let center = gs.player_pos_at(lag_beats + gs.time() - sm.time);
+            gs.obstacle(Obst::new(Box::new(RotatableRect {
+                center,
+                size,
+                rot: 0.0,
+                warning_time,
+                show_time: rect_life,
+                current_time: 0.0,
+                grow_time,
+                warning_style: WarningStyle::Fill,
+            }), sm.time).layer(TELEGRAPH_LAYER))
+        })
+    }
+    /// Places each step's rect along a cubic bezier curve (`p0` -> `p3`), the last step landing on `p3`.\
+    /// If `align_to_tangent` is set, the rects are rotated to follow the curve.
+    #[allow(clippy::too_many_arguments)]
+    pub fn bezier_trail(rect_life: f32, warning_time: f32, grow_time: f32, p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2, size: Vec2, align_to_tangent: bool) -> Box<dyn Accumulatee> {
+        Box::new(move |gs: &mut UpdateAccumulator, sm: ModifyArgs| {
+            let t = if sm.total_steps > 1 { sm.step as f32 / (sm.total_steps - 1) as f32 } else { 0.0 };
+            let rot = if align_to_tangent {
+                let tangent = cubic_bezier_tangent(p0, p1, p2, p3, t);
+                -tangent.y.atan2(tangent.x)
+            } else { 0.0 };
+            gs.obst_at(RotatableRect {
+                center: cubic_bezier(p0, p1, p2, p3, t),
+                size,
+                rot,
+                warning_time,
+                show_time: rect_life,
+                current_time: 0.0,
+                grow_time,
+                warning_style: WarningStyle::Fill,
+            }, sm.time)
+        })
+    }
 }
 impl Obstacle for Periodic {
+    fn name(&self) -> &'static str { "Periodic" }
+    fn debug_fields(&self) -> Vec<(String, String)> { debug_fields!(self: interval, max_steps, time_div, time_mod) }
     fn box_clone(&self) -> Box<dyn Obstacle> {
         Box::new(Periodic {
             modifier: self.modifier.box_clone(),
@@ -390,20 +942,70 @@ impl Obstacle for Periodic {
     }
     fn collides(&self, player: Player) -> bool { false }
     fn draw(&self, color: Color, offset: Vec2) { }
-    fn should_kill(&mut self) -> bool {
+    fn expired(&self) -> bool {
         self.time_div >= self.max_steps
     }
     fn update(&mut self, to_add: &mut UpdateAccumulator, beat_delta: f32, time: f32, dease: f32, ease: f32) {
         self.time_mod += beat_delta;
-        while self.time_mod >= self.interval {
-            self.modifier.run(to_add, ModifyArgs::new(to_add.time()).step(self.time_div));
+        if self.catch_up == CatchUp::Skip && self.time_mod >= self.interval * 2.0 {
+            let missed = (self.time_mod / self.interval).floor() - 1.0;
+            self.time_div = (self.time_div + missed as usize).min(self.max_steps);
+            self.time_mod -= missed * self.interval;
+        }
+        while self.time_mod >= self.interval && self.time_div < self.max_steps {
             self.time_mod -= self.interval;
-            self.time_div += 1;
+            let beats_ago = if self.catch_up == CatchUp::Spread { self.time_mod } else { 0.0 };
+            self.run_step(to_add, beats_ago);
+        }
+    }
+    fn kill(&mut self, to_add: &mut UpdateAccumulator) {
+        match self.on_early_kill {
+            OnEarlyKill::Drop => {},
+            OnEarlyKill::FlushRemaining => while self.time_div < self.max_steps {
+                self.run_step(to_add, 0.0);
+            },
+            OnEarlyKill::FlushWithin(beats) => {
+                // beats until the next step would have happened
+                let mut until = self.interval - self.time_mod;
+                while self.time_div < self.max_steps && until <= beats {
+                    self.run_step(to_add, 0.0);
+                    until += self.interval;
+                }
+            }
         }
     }
 }
 
+/// Pixels wide the outline of an outlined rect warning is
+const WARNING_STROKE: f32 = 3.0;
+
+/// How a rect draws while it's still a warning. Outlines stay readable where several warnings overlap.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WarningStyle {
+    /// A translucent fill
+    #[default]
+    Fill,
+    /// Just the border
+    Outline,
+    /// A fainter fill inside the border
+    Both,
+}
+impl WarningStyle {
+    /// Draws a rect's warning `progress` of the way to showing, fading in.
+    fn draw(self, center: Vec2, size: Vec2, rot: f32, color: Color, progress: f32) {
+        let faded = |alpha: f32| with_alpha(color, progress * alpha);
+        let (fill, stroke) = match self {
+            WarningStyle::Fill => (Some(faded(0.5)), None),
+            WarningStyle::Outline => (None, Some((WARNING_STROKE, faded(1.0)))),
+            WarningStyle::Both => (Some(faded(0.25)), Some((WARNING_STROKE, faded(1.0)))),
+        };
+        draw_rrect_styled(center, size, rot, fill, stroke)
+    }
+}
+
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RotatableRect {
     pub center: Vec2,
     pub size: Vec2,
@@ -412,8 +1014,10 @@ pub struct RotatableRect {
     pub show_time: f32,
     pub current_time: f32,
     pub grow_time: f32,
+    pub warning_style: WarningStyle,
 }
 impl RotatableRect {
+    builder!(warning_style: WarningStyle);
     /// Calculates the animated size\
     /// `allow_oversize` specifies whether or not the size can overshoot `self.size`.
     pub fn size(&self, allow_oversize: bool) -> Vec2 {
@@ -429,35 +1033,58 @@ impl RotatableRect {
     /// Will flash and fade out from white for `self.grow_time` beats, this function calculates the mix.
     pub fn color(&self, normal: Color) -> Color {
         if (self.warning_time..=self.warning_time + self.grow_time).contains(&self.current_time) {
-            mix(WHITE, normal, (self.current_time - self.warning_time) / self.grow_time)
+            flash_mix(WHITE, normal, (self.current_time - self.warning_time) / self.grow_time)
         } else {
             normal
         }
     }
 }
 impl Obstacle for RotatableRect {
+    fn name(&self) -> &'static str { "RotatableRect" }
+    fn debug_fields(&self) -> Vec<(String, String)> { debug_fields!(self: center, size, rot, warning_time, show_time, grow_time, current_time) }
     fn box_clone(&self) -> Box<dyn Obstacle> {
         Box::new(self.clone())
     }
+    // `collide_cr` turns the other way from `draw_rrect`, so the rotation is negated to test what's drawn, like `RotatingRect` does
     fn collides(&self, player: Player) -> bool {
-        self.current_time >= self.warning_time && collide_cr(self.center, self.size(false), self.rot, player.pos, player.rad)
+        self.current_time >= self.warning_time && collide_cr(self.center, self.size(false), -self.rot, player.pos, player.rad)
     }
-    fn draw(&self, mut color: Color, offset: Vec2) {
-        color = self.color(color);
+    fn contact(&self, player: Player) -> Option<Contact> {
+        self.collides(player).then(|| contact_cr(self.center, self.size(false), -self.rot, player.pos, player.rad))
+    }
+    fn collides_swept(&self, from: Vec2, to: Vec2, rad: f32) -> bool {
+        self.current_time >= self.warning_time && collide_capsule_rect(from, to, rad, self.center, self.size(false), -self.rot)
+    }
+    fn draw(&self, color: Color, offset: Vec2) {
         if self.current_time < self.warning_time {
-            color.a = self.current_time / self.warning_time * 0.5;
+            return self.warning_style.draw(self.center + offset, self.size(true), self.rot, color, self.current_time / self.warning_time);
         }
-        draw_rrect(self.center + offset, self.size(true), self.rot, color)
+        draw_rrect(self.center + offset, self.size(true), self.rot, self.color(color))
+    }
+    fn draw_debug(&self, color: Color, offset: Vec2) {
+        draw_rect_hitbox(self.center + offset, self.size(false), -self.rot, hitbox_color(color, self.current_time >= self.warning_time));
+    }
+    fn anchor(&self) -> Option<Vec2> { Some(self.center) }
+    fn bounds(&self) -> Option<Rect> {
+        let size = self.size(true).abs().max(self.size(false).abs());
+        Some(circle_bounds(self.center, size.length() / 2.0))
     }
-    fn should_kill(&mut self) -> bool {
+    fn expired(&self) -> bool {
         self.current_time >= self.show_time + self.warning_time
     }
     fn update(&mut self, game_state: &mut UpdateAccumulator, beat_delta: f32, time: f32, dease: f32, ease: f32) {
+        self.step(game_state, beat_delta, time, dease, ease)
+    }
+    fn parallel(&mut self) -> Option<&mut dyn Mover> { Some(self) }
+}
+impl Mover for RotatableRect {
+    fn step(&mut self, effects: &mut dyn Effects, dtime: f32, time: f32, dease: f32, ease: f32) {
         self.current_time = time;
     }
 }
 
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RotatingRect {
     pub center: Vec2,
     pub size: Vec2,
@@ -468,6 +1095,7 @@ pub struct RotatingRect {
     pub ease_time: f32,
     pub grow_time: f32,
     pub rpb: f32,
+    pub warning_style: WarningStyle,
 }
 impl Default for RotatingRect {
     fn default() -> Self {
@@ -480,7 +1108,8 @@ impl Default for RotatingRect {
             current_time: 0.0,
             ease_time: 0.0,
             grow_time: 0.25,
-            rpb: 0.25
+            rpb: 0.25,
+            warning_style: WarningStyle::Fill,
         }
     }
 }
@@ -492,6 +1121,7 @@ impl RotatingRect {
     builder!(show_time: f32);
     builder!(grow_time: f32);
     builder!(rpb: f32);
+    builder!(warning_style: WarningStyle);
     /// Calculates the animated size\
     /// `allow_oversize` specifies whether or not the size can overshoot `self.size`.
     pub fn get_size(&self) -> Vec2 {
@@ -505,7 +1135,7 @@ impl RotatingRect {
     /// Will flash and fade out from white for `self.grow_time` beats, this function calculates the mix.
     pub fn color(&self, normal: Color) -> Color {
         if (self.warning_time..=self.warning_time + self.grow_time).contains(&self.current_time) {
-            mix(WHITE, normal, (self.current_time - self.warning_time) / self.grow_time)
+            flash_mix(WHITE, normal, (self.current_time - self.warning_time) / self.grow_time)
         } else {
             normal
         }
@@ -516,44 +1146,90 @@ impl RotatingRect {
     }
 }
 impl Obstacle for RotatingRect {
+    fn name(&self) -> &'static str { "RotatingRect" }
+    fn debug_fields(&self) -> Vec<(String, String)> { debug_fields!(self: center, size, rot, rpb, warning_time, show_time, current_time) }
     fn box_clone(&self) -> Box<dyn Obstacle> {
         Box::new(self.clone())
     }
     fn collides(&self, player: Player) -> bool {
         self.current_time >= self.warning_time && collide_cr(self.center, self.get_size(), -self.get_rot(), player.pos, player.rad)
     }
-    fn draw(&self, mut color: Color, offset: Vec2) {
-        color = self.color(color);
+    fn contact(&self, player: Player) -> Option<Contact> {
+        self.collides(player).then(|| contact_cr(self.center, self.get_size(), -self.get_rot(), player.pos, player.rad))
+    }
+    fn draw(&self, color: Color, offset: Vec2) {
         if self.current_time < self.warning_time {
-            color.a = self.current_time / self.warning_time * 0.5;
+            return self.warning_style.draw(self.center + offset, self.get_size(), self.get_rot(), color, self.current_time / self.warning_time);
         }
-        draw_rrect(self.center + offset, self.get_size(), self.get_rot(), color)
+        draw_rrect(self.center + offset, self.get_size(), self.get_rot(), self.color(color))
+    }
+    fn draw_debug(&self, color: Color, offset: Vec2) {
+        draw_rect_hitbox(self.center + offset, self.get_size(), -self.get_rot(), hitbox_color(color, self.current_time >= self.warning_time));
     }
-    fn should_kill(&mut self) -> bool {
+    fn anchor(&self) -> Option<Vec2> { Some(self.center) }
+    // whichever way it has spun to
+    fn bounds(&self) -> Option<Rect> { Some(circle_bounds(self.center, self.get_size().length() / 2.0)) }
+    fn expired(&self) -> bool {
         self.current_time >= self.show_time + self.warning_time
     }
     fn update(&mut self, game_state: &mut UpdateAccumulator, beat_delta: f32, time: f32, dease: f32, ease: f32) {
+        self.step(game_state, beat_delta, time, dease, ease)
+    }
+    fn parallel(&mut self) -> Option<&mut dyn Mover> { Some(self) }
+}
+impl Mover for RotatingRect {
+    fn step(&mut self, effects: &mut dyn Effects, dtime: f32, time: f32, dease: f32, ease: f32) {
         self.current_time = time;
         self.ease_time = ease;
     }
 }
 
+/// Emits `max` pellets one by one around a circle, one every `period` beats.\
+/// Standalone spinners emit from `pos`, `CenterProj` runs its own from the projectile's position.
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PelletSpinner {
     // counting
-    count: usize,
-    max: usize,
+    pub count: usize,
+    pub max: usize,
 
     // timing
-    phase: f32,
-    period: f32,
-    start_time: f32,
+    pub phase: f32,
+    pub period: f32,
+    pub start_time: f32,
 
     // pellet
-    rad: f32,
-    speed: f32
+    pub rad: f32,
+    pub speed: f32,
+
+    pub pos: Vec2,
+}
+impl Default for PelletSpinner {
+    fn default() -> Self {
+        PelletSpinner {
+            count: 0,
+            max: 16,
+            phase: 0.0,
+            period: 0.25,
+            start_time: 0.0,
+            rad: 10.0,
+            speed: 200.0,
+            pos: screen_center()
+        }
+    }
 }
 impl PelletSpinner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the amount of pellets to emit.
+    pub fn count(mut self, count: usize) -> Self { self.max = count; self }
+    builder!(period: f32);
+    builder!(phase: f32);
+    builder!(rad: f32);
+    builder!(speed: f32);
+    builder!(pos: Vec2);
+    builder!(start_time: f32);
     pub fn run(&mut self, time: f32, cur_pos: Vec2, cur_rad: f32, to_add: &mut UpdateAccumulator) -> bool {
         if time >= self.start_time + self.period * self.count as f32 && self.count < self.max {
             self.count += 1;
@@ -561,13 +1237,24 @@ impl PelletSpinner {
                 ((self.count as f32 / self.max as f32 + self.phase) * TAU).cos(),
                 ((self.count as f32 / self.max as f32 + self.phase) * TAU).sin(),
             );
-            to_add.obst(Pellet::new(cur_pos + circ * (cur_rad - self.rad), circ * self.speed, self.rad))
+            to_add.pellet(cur_pos + circ * (cur_rad - self.rad), circ * self.speed, self.rad)
         }
         self.count >= self.max
     }
 }
+impl Obstacle for PelletSpinner {
+    fn update(&mut self, to_add: &mut UpdateAccumulator, beat_delta: f32, time: f32, dease: f32, ease: f32) {
+        self.run(time, self.pos, self.rad, to_add);
+    }
+    fn draw(&self, color: Color, offset: Vec2) { }
+    fn name(&self) -> &'static str { "PelletSpinner" }
+    fn box_clone(&self) -> Box<dyn Obstacle> { Box::new(*self) }
+    fn collides(&self, player: Player) -> bool { false }
+    fn expired(&self) -> bool { self.count >= self.max }
+}
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CenterProj {
     disp_amp: f32,
     disp_freq: Vec2,
@@ -579,7 +1266,8 @@ pub struct CenterProj {
     warning_time: f32,
     show_time: f32,
     leave_time: f32,
-    pub events: Vec<(f32, CenterEvent)>,
+    /// Sorted by beat, the next first
+    pub events: VecDeque<(f32, CenterEvent)>,
     pellet_spinners: Vec<PelletSpinner>
 }
 impl Default for CenterProj {
@@ -594,7 +1282,7 @@ impl Default for CenterProj {
             warning_time: 1.0,
             show_time: 32.0,
             leave_time: 0.25,
-            events: vec![],
+            events: VecDeque::new(),
             disp_phase: Vec2::ZERO,
             pellet_spinners: vec![]
         }
@@ -627,7 +1315,7 @@ impl CenterProj {
     builder!(show_time: f32);
     pub fn evs(mut self, mut events: impl IntoIterator<Item = (f32, CenterEvent)>) -> Self {
         for i in events.into_iter() {
-            self.events.push(i);
+            self.events.push_back(i);
         }
         self
     }
@@ -635,7 +1323,7 @@ impl CenterProj {
         if time < self.warning_time {
             acmul(color, self.time / self.warning_time * 0.5)
         } else {
-            mix(color, WHITE, self.pulse)
+            flash_mix(color, WHITE, self.pulse)
         }
     }
     pub fn size(&self, time: f32) -> f32 {
@@ -646,7 +1334,7 @@ impl CenterProj {
         }
     }
     pub fn sort(mut self) -> Self {
-        self.events.sort_by(|(a, _), (b, _)|a.total_cmp(b));
+        self.events.make_contiguous().sort_by(|(a, _), (b, _)|a.total_cmp(b));
         self
     }
     pub fn employ(&mut self, event: CenterEvent, to_add: &mut UpdateAccumulator) {
@@ -658,39 +1346,32 @@ impl CenterProj {
             CenterEvent::Lasers(count, phase) => {
                 let start = self.trackpos(self.time + 1.0);
                 for i in 0..count {
-                    to_add.obst(SlamLaser::new(start, start + vec2(
+                    to_add.obst_layered(SlamLaser::new(start, start + vec2(
                         ((i as f32 / count as f32 + phase) * TAU).cos(),
                         ((i as f32 / count as f32 + phase) * TAU).sin()
-                    ) * 1250.0, 20.0, 1.0, 1.0, 0.05, Vec2::ZERO, 0.0).leave_time(0.5))
+                    ) * 1250.0, 20.0, 1.0, 1.0, 0.05, Vec2::ZERO, 0.0).leave_time(0.5), FLASH_LAYER)
                 }
             },
             CenterEvent::Pellets(count, speed, rad, phase, is_strong) => {
-                let start = self.trackpos(self.time);
-                for i in 0..count {
-                    let circ = vec2(
-                        ((i as f32 / count as f32 + phase) * TAU).cos(),
-                        ((i as f32 / count as f32 + phase) * TAU).sin(),
-                    );
-                    if is_strong {
-                        to_add.obst(Ease::anon(
-                            Pellet::new(start + circ * (self.rad - rad), circ * speed, rad),
-                            |t| recip_ease(t * 3.0) + t
-                        ))
-                    } else {
-                        to_add.obst(Pellet::new(start + circ * (self.rad - rad), circ * speed, rad))
-                    }
-                }
+                Ring::default()
+                    .center(self.trackpos(self.time))
+                    .count(count)
+                    .speed(speed)
+                    .rad(rad)
+                    .phase(phase)
+                    .offset(self.rad - rad)
+                    .strong(is_strong)
+                    .spawn(to_add);
             },
             CenterEvent::PelletSpinner(count, speed, rad, phase, ppb) => {
-                self.pellet_spinners.push(PelletSpinner {
-                    count: 0,
-                    max: count,
-                    phase,
-                    period: 1.0 / ppb,
-                    start_time: self.time,
-                    rad,
-                    speed
-                })
+                self.pellet_spinners.push(PelletSpinner::new()
+                    .count(count)
+                    .phase(phase)
+                    .period(1.0 / ppb)
+                    .start_time(self.time)
+                    .rad(rad)
+                    .speed(speed)
+                )
             },
             CenterEvent::SPulse(strength) => {
                 self.pulse = 1.0;
@@ -699,10 +1380,10 @@ impl CenterProj {
             CenterEvent::MessyPellets(count, rad, min_speed, max_speed) => {
                 let pos = self.trackpos(self.time);
                 for i in 0..count {
-                    let speed = gen_range(min_speed, max_speed);
-                    let period = gen_range(0.0, TAU);
+                    let speed = to_add.rng().range(min_speed, max_speed);
+                    let period = to_add.rng().range(0.0, TAU);
                     let vel = vec2(period.sin(), period.cos()) * speed;
-                    to_add.obst(Pellet::new(pos, vel, rad));
+                    to_add.pellet(pos, vel, rad);
                 }
             }
         }
@@ -713,19 +1394,17 @@ impl Obstacle for CenterProj {
         self.time = time;
         self.ease = ease;
         self.pulse *= 0.975;
-        while self.events.len() > 0 {
-            if self.time - self.warning_time >= self.events[0].0 {
-                self.employ(self.events[0].1, to_add);
-                self.events.remove(0);
-            } else {
-                break;
-            }
+        while let Some(&(beat, event)) = self.events.front() {
+            if self.time - self.warning_time < beat { break; }
+            self.employ(event, to_add);
+            self.events.pop_front();
         }
         let mut i = 0;
         let pos = self.trackpos(self.ease);
+        // swapping the last one in changes the order spinners run in, which nothing depends on
         while i < self.pellet_spinners.len() {
             if self.pellet_spinners[i].run(self.time, pos, self.rad, to_add) {
-                self.pellet_spinners.remove(i);
+                self.pellet_spinners.swap_remove(i);
             } else {
                 i += 1;
             }
@@ -733,15 +1412,26 @@ impl Obstacle for CenterProj {
     }
     fn draw(&self, color: Color, offset: Vec2) {
         let pos = self.trackpos(self.ease) + offset;
-        draw_circle(pos.x, pos.y, self.size(self.time), self.color(color, self.time));
+        draw_circle_aa(pos.x, pos.y, self.size(self.time), self.color(color, self.time));
     }
+    fn draw_glow(&self, color: Color, offset: Vec2) {
+        draw_glow_circle(self.trackpos(self.ease) + offset, self.size(self.time) * GLOW_SCALE, self.color(color, self.time), GLOW_INTENSITY);
+    }
+    fn draw_debug(&self, color: Color, offset: Vec2) { draw_circle_hitbox(self.trackpos(self.ease) + offset, self.size(self.time), color) }
+    fn name(&self) -> &'static str { "CenterProj" }
     fn box_clone(&self) -> Box<dyn Obstacle> { Box::new(self.clone()) }
     fn collides(&self, player: Player) -> bool { collide_cc(self.trackpos(self.ease), self.size(self.time), player.pos, player.rad) }
-    fn should_kill(&mut self) -> bool {
+    fn contact(&self, player: Player) -> Option<Contact> {
+        self.collides(player).then(|| contact_cc(self.trackpos(self.ease), self.size(self.time), player.pos, player.rad))
+    }
+    fn anchor(&self) -> Option<Vec2> { Some(self.trackpos(self.ease)) }
+    fn bounds(&self) -> Option<Rect> { Some(circle_bounds(self.trackpos(self.ease), self.size(self.time).abs())) }
+    fn expired(&self) -> bool {
         self.time > self.warning_time + self.show_time
     }
 }
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CenterEvent {
     Pulse,
     /// pulse strength
@@ -767,6 +1457,7 @@ pub const MOORE_OFFSETS: [(isize, isize); 8] = [
     (1, 1)
 ];
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GOLGrid {
     width: usize,
     height: usize,
@@ -843,10 +1534,10 @@ impl GOLGrid {
             *self.gol.get(y as usize * self.width + x as usize).unwrap_or(&false)
         }
     }
-    pub fn populate(mut self, count: usize) -> Self {
+    pub fn populate(mut self, count: usize, rng: &mut GameRng) -> Self {
         let len = self.gol.len();
         for _ in 0..count {
-            self.gol[gen_range(0, len)] = true;
+            self.gol[rng.range(0, len)] = true;
         }
         self
     }
@@ -869,6 +1560,7 @@ impl Obstacle for GOLGrid {
                             show_time: self.period * 1.25,
                             current_time: 0.0,
                             grow_time: self.period / 4.0,
+                            warning_style: WarningStyle::Fill,
                         })
                     }
                 }
@@ -876,9 +1568,10 @@ impl Obstacle for GOLGrid {
         }
     }
     fn draw(&self, color: Color, offset: Vec2) { }
+    fn name(&self) -> &'static str { "GOLGrid" }
     fn box_clone(&self) -> Box<dyn Obstacle> { Box::new(self.clone()) }
     fn collides(&self, player: Player) -> bool { false }
-    fn should_kill(&mut self) -> bool { self.ticks >= self.max }
+    fn expired(&self) -> bool { self.ticks >= self.max }
 }
 
 pub trait Easing {
@@ -933,11 +1626,29 @@ impl Clone for Ease {
     }
 }
 impl Obstacle for Ease {
+    fn name(&self) -> &'static str { self.proj.name() }
     fn box_clone(&self) -> Box<dyn Obstacle> { Box::new(self.clone()) }
     fn collides(&self, player: Player) -> bool { self.proj.collides(player) }
+    fn contact(&self, player: Player) -> Option<Contact> { self.proj.contact(player) }
+    fn collides_swept(&self, from: Vec2, to: Vec2, rad: f32) -> bool { self.proj.collides_swept(from, to, rad) }
     fn draw(&self, color: Color, offset: Vec2) { self.proj.draw(color, offset) }
+    fn draw_batched(&self, color: Color, offset: Vec2, batch: &mut CircleBatch) -> bool { self.proj.draw_batched(color, offset, batch) }
+    fn draw_glow(&self, color: Color, offset: Vec2) { self.proj.draw_glow(color, offset) }
+    fn draw_debug(&self, color: Color, offset: Vec2) { self.proj.draw_debug(color, offset) }
     fn kill(&mut self, to_add: &mut UpdateAccumulator) { self.proj.kill(to_add) }
+    fn expired(&self) -> bool { self.proj.expired() }
+    #[allow(deprecated)]
     fn should_kill(&mut self) -> bool { self.proj.should_kill() }
+    fn on_expire(&mut self, to_add: &mut UpdateAccumulator) { self.proj.on_expire(to_add) }
+    fn anchor(&self) -> Option<Vec2> { self.proj.anchor() }
+    fn bounds(&self) -> Option<Rect> { self.proj.bounds() }
+    fn lethal(&self) -> bool { self.proj.lethal() }
+    fn pickup(&self) -> Option<Pickup> { self.proj.pickup() }
+    fn debug_fields(&self) -> Vec<(String, String)> {
+        let mut fields = self.proj.debug_fields();
+        fields.push(("eased".to_string(), format!("{:.2}", self.prev)));
+        fields
+    }
     fn update(&mut self, to_add: &mut UpdateAccumulator, beat_delta: f32, relative_time: f32, dease: f32, ease: f32) {
         let time = self.ease.run(ease);
         let de = time - self.prev;
@@ -946,7 +1657,8 @@ impl Obstacle for Ease {
     }
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SpinningArc {
     pub center: Vec2,
     pub inner_rad: f32,
@@ -959,6 +1671,9 @@ pub struct SpinningArc {
     pub ease: f32,
 
     pub time: f32,
+    /// Kept from update to update, worked out again whenever the shape changes
+    #[cfg_attr(feature = "serde", serde(skip))]
+    mesh: ArcMesh,
 }
 impl SpinningArc {
     pub fn new() -> Self {
@@ -971,7 +1686,7 @@ impl SpinningArc {
         if self.time < self.warning_time {
             cmul(color, self.time / self.warning_time)
         } else if (0.0..1.0).contains(&(self.time - self.warning_time)) {
-            mix(WHITE, color, self.time - self.warning_time)
+            flash_mix(WHITE, color, self.time - self.warning_time)
         } else {
             color
         }
@@ -985,25 +1700,687 @@ impl SpinningArc {
     builder!(warning_time: f32);
     builder!(show_time: f32);
 }
-impl Obstacle for SpinningArc {
-    fn update(&mut self, to_add: &mut UpdateAccumulator, beat_delta: f32, relative_time: f32, dease: f32, ease: f32) {
+impl Mover for SpinningArc {
+    fn step(&mut self, effects: &mut dyn Effects, dtime: f32, relative_time: f32, dease: f32, ease: f32) {
         self.time = relative_time;
         self.ease = ease;
+        let span = self.right_angle - self.left_angle;
+        if !self.mesh.matches(self.inner_rad, self.outer_rad, span, None) {
+            self.mesh = ArcMesh::new(self.inner_rad, self.outer_rad, span, None);
+        }
+    }
+}
+impl Obstacle for SpinningArc {
+    fn update(&mut self, to_add: &mut UpdateAccumulator, beat_delta: f32, relative_time: f32, dease: f32, ease: f32) {
+        self.step(to_add, beat_delta, relative_time, dease, ease)
     }
+    fn parallel(&mut self) -> Option<&mut dyn Mover> { Some(self) }
 
     fn draw(&self, color: Color, offset: Vec2) {
-        draw_arc(self.center + offset, self.inner_rad, self.outer_rad, self.left_angle + self.rot(), self.right_angle + self.rot(), 32, self.color(color))
+        // the mesh is only made on updating, so there's none before the first
+        if self.mesh.matches(self.inner_rad, self.outer_rad, self.right_angle - self.left_angle, None) {
+            self.mesh.draw(self.center + offset, self.left_angle.min(self.right_angle) + self.rot(), self.color(color))
+        } else {
+            draw_arc(self.center + offset, self.inner_rad, self.outer_rad, self.left_angle + self.rot(), self.right_angle + self.rot(), None, self.color(color))
+        }
     }
 
+    fn draw_debug(&self, color: Color, offset: Vec2) {
+        let color = hitbox_color(color, self.time >= self.warning_time);
+        draw_arc_outline(self.center + offset, self.inner_rad, self.outer_rad, self.left_angle + self.rot(), self.right_angle + self.rot(), HITBOX_THICKNESS, color);
+    }
+
+    fn name(&self) -> &'static str { "SpinningArc" }
+    fn debug_fields(&self) -> Vec<(String, String)> { debug_fields!(self: center, inner_rad, outer_rad, left_angle, right_angle, rpb, warning_time, show_time, time) }
     fn box_clone(&self) -> Box<dyn Obstacle> {
         Box::new(self.clone())
     }
 
     fn collides(&self, player: Player) -> bool {
-        collide_circ_arc(player.pos, player.rad, self.center, self.outer_rad, self.inner_rad, -self.rot(), self.right_angle - self.rot() - self.left_angle) && self.time >= self.warning_time
+        self.time >= self.warning_time && collide_circ_arc(player.pos, player.rad, self.center, self.outer_rad, self.inner_rad, self.left_angle + self.rot(), self.right_angle + self.rot())
+    }
+    fn contact(&self, player: Player) -> Option<Contact> {
+        self.collides(player).then(|| {
+            contact_arc(player.pos, player.rad, self.center, self.outer_rad, self.inner_rad, self.left_angle + self.rot(), self.right_angle + self.rot())
+        })
     }
+    fn bounds(&self) -> Option<Rect> { Some(circle_bounds(self.center, self.outer_rad.max(self.inner_rad))) }
 
-    fn should_kill(&mut self) -> bool {
+    fn expired(&self) -> bool {
         self.time >= self.warning_time + self.show_time
     }
 }
+
+/// How long a bumper wobbles after knocking the player back, in beats.
+pub const BUMP_PULSE_BEATS: f32 = 0.5;
+/// A non-lethal circle that knocks the player away from its center.
+#[derive(Clone, Copy)]
+pub struct Bumper {
+    pub pos: Vec2,
+    pub rad: f32,
+    /// Pixels the player is pushed by per bump
+    pub strength: f32,
+    /// Beats before the same bumper can bump again
+    pub cooldown: f32,
+    pub warning_time: f32,
+    pub show_time: f32,
+
+    pub time: f32,
+    pub bumped_at: f32,
+    pub bump_dir: Vec2,
+}
+impl Default for Bumper {
+    fn default() -> Self {
+        Bumper {
+            pos: Vec2::ZERO,
+            rad: 30.0,
+            strength: 150.0,
+            cooldown: 0.5,
+            warning_time: 1.0,
+            show_time: 4.0,
+            time: 0.0,
+            bumped_at: f32::NEG_INFINITY,
+            bump_dir: vec2(1.0, 0.0),
+        }
+    }
+}
+impl Bumper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    builder!(pos: Vec2);
+    builder!(rad: f32);
+    builder!(strength: f32);
+    builder!(cooldown: f32);
+    builder!(warning_time: f32);
+    builder!(show_time: f32);
+}
+impl Obstacle for Bumper {
+    fn update(&mut self, to_add: &mut UpdateAccumulator, beat_delta: f32, relative_time: f32, dease: f32, ease: f32) {
+        self.time = relative_time;
+        if self.time < self.warning_time || self.time - self.bumped_at < self.cooldown { return; }
+        let bumped = to_add.players().iter().position(|p| p.alive() && collide_cc(self.pos, self.rad, p.pos, p.rad));
+        if let Some(idx) = bumped {
+            // straight out of the center goes right
+            self.bump_dir = (to_add.players()[idx].pos - self.pos).try_normalize().unwrap_or(vec2(1.0, 0.0));
+            self.bumped_at = self.time;
+            to_add.push_nth_player(idx, self.bump_dir * self.strength);
+        }
+    }
+    fn draw(&self, color: Color, offset: Vec2) {
+        let color = if self.time < self.warning_time { cmul(color, self.time / self.warning_time * 0.5) } else { color };
+        let since = self.time - self.bumped_at;
+        // squash along the bump, stretch across it, wobbling back into shape
+        let pulse = if since < BUMP_PULSE_BEATS { (1.0 - since / BUMP_PULSE_BEATS) * (since * TAU * 3.0).cos() * 0.3 } else { 0.0 };
+        let along = self.bump_dir * self.rad * (1.0 - pulse);
+        let across = self.bump_dir.perp() * self.rad * (1.0 + pulse);
+        let center = self.pos + offset;
+        let segments = 32;
+        for i in 0..segments {
+            let (a0, a1) = (i as f32 / segments as f32 * TAU, (i + 1) as f32 / segments as f32 * TAU);
+            let p0 = center + along * a0.cos() + across * a0.sin();
+            let p1 = center + along * a1.cos() + across * a1.sin();
+            draw_triangle(center, p0, p1, color);
+        }
+        draw_circle_aa(center.x, center.y, self.rad * 0.3, acmul(WHITE, 0.5));
+    }
+    fn draw_debug(&self, color: Color, offset: Vec2) { draw_circle_hitbox(self.pos + offset, self.rad, hitbox_color(color, self.time >= self.warning_time)) }
+    fn name(&self) -> &'static str { "Bumper" }
+    fn box_clone(&self) -> Box<dyn Obstacle> { Box::new(*self) }
+    fn collides(&self, player: Player) -> bool {
+        self.time >= self.warning_time && collide_cc(self.pos, self.rad, player.pos, player.rad)
+    }
+    fn expired(&self) -> bool {
+        self.time >= self.warning_time + self.show_time
+    }
+    fn anchor(&self) -> Option<Vec2> { Some(self.pos) }
+    fn lethal(&self) -> bool { false }
+}
+
+/// Grants a shield that absorbs one hit.
+#[derive(Clone, Copy)]
+pub struct ShieldPickup {
+    pub pos: Vec2,
+    pub rad: f32,
+    /// Beats the shield lasts once collected. `None` lasts until broken.
+    pub duration: Option<f32>,
+    /// Beats before the pickup disappears if it isn't collected
+    pub show_time: f32,
+
+    pub time: f32,
+}
+impl Default for ShieldPickup {
+    fn default() -> Self {
+        ShieldPickup {
+            pos: Vec2::ZERO,
+            rad: 10.0,
+            duration: None,
+            show_time: 8.0,
+            time: 0.0,
+        }
+    }
+}
+impl ShieldPickup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    builder!(pos: Vec2);
+    builder!(rad: f32);
+    builder!(show_time: f32);
+    pub fn duration(mut self, duration: f32) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+}
+impl Obstacle for ShieldPickup {
+    fn update(&mut self, to_add: &mut UpdateAccumulator, beat_delta: f32, relative_time: f32, dease: f32, ease: f32) {
+        self.time = relative_time;
+    }
+    fn draw(&self, color: Color, offset: Vec2) {
+        // always friendly-colored, so it can't be mistaken for a projectile
+        let pos = self.pos + offset;
+        draw_circle_aa(pos.x, pos.y, self.rad, acmul(shield_color(), 0.8));
+        let ring = self.rad + 4.0 + (self.time * TAU).sin() * 2.0;
+        draw_dashed_circle(pos, ring, 1.5, 6.0, 4.0, self.time, acmul(shield_color(), 0.5));
+    }
+    fn draw_debug(&self, color: Color, offset: Vec2) { draw_circle_hitbox(self.pos + offset, self.rad, color) }
+    fn name(&self) -> &'static str { "ShieldPickup" }
+    fn box_clone(&self) -> Box<dyn Obstacle> { Box::new(*self) }
+    fn collides(&self, player: Player) -> bool {
+        collide_cc(self.pos, self.rad, player.pos, player.rad)
+    }
+    fn expired(&self) -> bool {
+        self.time >= self.show_time
+    }
+    fn anchor(&self) -> Option<Vec2> { Some(self.pos) }
+    fn lethal(&self) -> bool { false }
+    fn pickup(&self) -> Option<Pickup> { Some(Pickup::Shield(self.duration)) }
+}
+
+/// Points that drift around and get pulled in by nearby players. Spawn them with `UpdateAccumulator::orb`.
+#[derive(Clone, Copy)]
+pub struct ScoreOrb {
+    pub pos: Vec2,
+    /// Drift in pixels per beat
+    pub vel: Vec2,
+    pub rad: f32,
+    /// Players closer than this pull the orb in
+    pub magnet_rad: f32,
+    /// Pixels per beat the orb is pulled in with right next to the player
+    pub magnet_speed: f32,
+    pub value: u32,
+    /// Beats before the orb fades out if it isn't collected
+    pub lifetime: f32,
+
+    pub time: f32,
+}
+impl Default for ScoreOrb {
+    fn default() -> Self {
+        ScoreOrb {
+            pos: Vec2::ZERO,
+            vel: Vec2::ZERO,
+            rad: 4.0,
+            magnet_rad: 100.0,
+            magnet_speed: 800.0,
+            value: 10,
+            lifetime: 8.0,
+            time: 0.0,
+        }
+    }
+}
+impl ScoreOrb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    builder!(pos: Vec2);
+    builder!(vel: Vec2);
+    builder!(rad: f32);
+    builder!(magnet_rad: f32);
+    builder!(magnet_speed: f32);
+    builder!(value: u32);
+    builder!(lifetime: f32);
+}
+impl Obstacle for ScoreOrb {
+    fn update(&mut self, to_add: &mut UpdateAccumulator, beat_delta: f32, relative_time: f32, dease: f32, ease: f32) {
+        self.time = relative_time;
+        let nearest = to_add.players().iter()
+            .filter(|p| p.alive())
+            .map(|p| p.pos)
+            .min_by(|a, b| a.distance_squared(self.pos).total_cmp(&b.distance_squared(self.pos)));
+        match nearest {
+            Some(target) if target.distance(self.pos) < self.magnet_rad => {
+                // pulled in faster the closer it gets
+                let dist = target.distance(self.pos);
+                let closeness = 1.0 - dist / self.magnet_rad;
+                let speed = self.magnet_speed * (0.1 + 0.9 * sq(closeness));
+                self.pos += (target - self.pos).normalize_or_zero() * (speed * dease).min(dist);
+                self.vel = Vec2::ZERO;
+            }
+            _ => self.pos += self.vel * dease
+        }
+    }
+    fn draw(&self, color: Color, offset: Vec2) {
+        let fade = (self.lifetime - self.time).clamp(0.0, 1.0);
+        let pos = self.pos + offset;
+        draw_circle_aa(pos.x, pos.y, self.rad, acmul(orb_color(), fade));
+        draw_circle_aa(pos.x, pos.y, self.rad * 0.5, acmul(WHITE, fade));
+    }
+    fn draw_glow(&self, color: Color, offset: Vec2) {
+        let fade = (self.lifetime - self.time).clamp(0.0, 1.0);
+        draw_glow_circle(self.pos + offset, self.rad * GLOW_SCALE, acmul(orb_color(), fade), GLOW_INTENSITY);
+    }
+    fn draw_debug(&self, color: Color, offset: Vec2) { draw_circle_hitbox(self.pos + offset, self.rad, color) }
+    fn name(&self) -> &'static str { "ScoreOrb" }
+    fn box_clone(&self) -> Box<dyn Obstacle> { Box::new(*self) }
+    fn collides(&self, player: Player) -> bool {
+        collide_cc(self.pos, self.rad, player.pos, player.rad)
+    }
+    fn expired(&self) -> bool {
+        self.time >= self.lifetime
+    }
+    fn anchor(&self) -> Option<Vec2> { Some(self.pos) }
+    fn lethal(&self) -> bool { false }
+    fn pickup(&self) -> Option<Pickup> { Some(Pickup::Score(self.value)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+    use crate::utils::{rotate, best_of};
+
+    fn player_at(pos: Vec2) -> Player {
+        Player { pos, rad: 1.0, ..Player::default() }
+    }
+
+    #[test]
+    fn rotated_rect_collides_where_drawn() {
+        let center = vec2(400.0, 300.0);
+        for rot in [0.3, 1.0, -0.7, 2.5] {
+            let rect = RotatableRect { center, size: vec2(200.0, 20.0), rot, warning_time: 0.0, show_time: 10.0, current_time: 1.0, grow_time: 0.25, warning_style: WarningStyle::Fill };
+            // `draw_rrect` lays the long side along `rot`
+            let along = rotate(Vec2::X, rot);
+            assert!(rect.collides(player_at(center + along * 90.0)), "along at {rot}");
+            assert!(!rect.collides(player_at(center + along.perp() * 90.0)), "across at {rot}");
+        }
+    }
+
+
+    /// One of each obstacle with bounds, placed and sized at random, `age` beats into its life.
+    fn random_obstacles(rng: &mut GameRng, age: f32) -> Vec<Box<dyn Obstacle>> {
+        let mut at = || rng.vec(Vec2::ZERO, screen_size());
+        let (a, b, c, d) = (at(), at(), at(), at());
+        let mut obstacles: Vec<Box<dyn Obstacle>> = vec![
+            Box::new(Pellet::new(a, b - a, rng.range(2.0, 40.0))),
+            Box::new(Bomb::new(a, b, rng.range(1.0, 4.0), 8, 200.0, 10.0, Box::new(Bomb::pellet_spawner))),
+            Box::new(GrowLaser::new(a, b, rng.range(5.0, 80.0), rng.range(0.0, 2.0), rng.range(1.0, 4.0), Vec2::ZERO)),
+            Box::new(SlamLaser::new(c, d, rng.range(5.0, 80.0), rng.range(0.0, 2.0), rng.range(1.0, 4.0), rng.range(0.0, 2.0), Vec2::ZERO, 0.0)),
+            Box::new(RotatableRect {
+                center: a, size: rng.vec(vec2(10.0, 10.0), vec2(600.0, 300.0)), rot: rng.range(-PI, PI),
+                warning_time: rng.range(0.0, 2.0), show_time: rng.range(1.0, 4.0), current_time: 0.0, grow_time: 0.25, warning_style: WarningStyle::Fill,
+            }),
+            Box::new(RotatingRect::default().center(b).size(rng.vec(vec2(10.0, 10.0), vec2(900.0, 60.0))).rot(rng.range(-PI, PI)).rpb(rng.range(-0.5, 0.5)).warning_time(rng.range(0.0, 2.0)).show_time(4.0)),
+            Box::new(SpinningArc::new().center(c).inner_rad(rng.range(20.0, 200.0)).outer_rad(rng.range(200.0, 400.0)).left_angle(rng.range(0.0, TAU)).right_angle(rng.range(0.0, TAU)).rpb(rng.range(-0.5, 0.5)).show_time(4.0)),
+            Box::new(CenterProj::new().show_time(8.0).warning_time(rng.range(0.0, 2.0))),
+        ];
+        let mut accum = UpdateAccumulator::new();
+        let steps = (age / 0.05).ceil().max(1.0);
+        for obstacle in &mut obstacles {
+            for step in 1..=steps as usize {
+                let t = age * step as f32 / steps;
+                obstacle.update(&mut accum, age / steps, t, age / steps, t);
+            }
+        }
+        obstacles
+    }
+
+    #[test]
+    fn bounds_never_change_what_collides() {
+        let mut rng = GameRng::new(935);
+        for round in 0..100 {
+            let age = rng.range(0.0, 5.0);
+            for obstacle in random_obstacles(&mut rng, age) {
+                let name = obstacle.name();
+                assert!(obstacle.bounds().is_some(), "{name} has no bounds");
+                let obst = Obst::new(obstacle, 0.0);
+                for _ in 0..50 {
+                    let player = Player { pos: rng.vec(vec2(-100.0, -100.0), screen_size() + 100.0), rad: rng.range(1.0, 30.0), ..Player::default() };
+                    assert_eq!(obst.collides(player), obst.obstacle.collides(player), "{name} in round {round} against {:?}", player.pos);
+                    let to = player.pos + rng.vec(vec2(-300.0, -300.0), vec2(300.0, 300.0));
+                    assert_eq!(
+                        obst.collides_swept(player.pos, to, player.rad), obst.obstacle.collides_swept(player.pos, to, player.rad),
+                        "{name} in round {round} swept from {:?} to {to:?}", player.pos
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bounds_skip_most_exact_checks_and_beat_them() {
+        let mut rng = GameRng::new(2000);
+        let obsts = (0..2000).map(|_| Obst::new(Box::new(RotatableRect {
+            center: rng.vec(Vec2::ZERO, screen_size()), size: rng.vec(vec2(10.0, 10.0), vec2(120.0, 60.0)), rot: rng.range(-PI, PI),
+            warning_time: 0.0, show_time: 10.0, current_time: 1.0, grow_time: 0.25, warning_style: WarningStyle::Fill,
+        }), 0.0)).collect::<Vec<_>>();
+        let players = (0..200).map(|_| player_at(rng.vec(Vec2::ZERO, screen_size()))).collect::<Vec<_>>();
+        let near = players.iter().map(|p| obsts.iter().filter(|o| o.near(circle_bounds(p.pos, p.rad))).count()).sum::<usize>();
+        assert!(near * 20 < obsts.len() * players.len(), "{near} of {} pairs near", obsts.len() * players.len());
+
+        let hits = |check: &dyn Fn(&Obst, Player) -> bool| players.iter().map(|&p| obsts.iter().filter(|o| check(o, p)).count()).sum::<usize>();
+        let (mut exact_hits, mut culled_hits) = (0, 0);
+        let exact = best_of(|| exact_hits = hits(&|o, p| o.obstacle.collides(p)));
+        let culled = best_of(|| culled_hits = hits(&|o, p| o.collides(p)));
+        assert_eq!(exact_hits, culled_hits);
+        assert!(culled < exact, "{culled}s with bounds, {exact}s exact");
+    }
+
+
+    #[test]
+    fn pool_hands_back_the_boxes_it_was_given() {
+        let mut pool = PelletPool::default();
+        let boxed = pool.take(Pellet::new(Vec2::ZERO, Vec2::ZERO, 1.0));
+        let address = &*boxed as *const Pellet;
+        pool.recycle(boxed);
+        let reused = pool.take(Pellet::new(vec2(1.0, 2.0), Vec2::ONE, 3.0));
+        assert_eq!(&*reused as *const Pellet, address);
+        assert_eq!((reused.pos, reused.vel, reused.rad), (vec2(1.0, 2.0), Vec2::ONE, 3.0));
+        assert!(pool.free.is_empty());
+    }
+
+    #[test]
+    fn pool_keeps_only_pellets_and_only_so_many() {
+        let mut pool = PelletPool::default();
+        pool.recycle(Box::new(GrowLaser::new(Vec2::ZERO, Vec2::ONE, 1.0, 1.0, 1.0, Vec2::ZERO)));
+        assert!(pool.free.is_empty());
+        for _ in 0..MAX_POOLED_PELLETS + 10 {
+            pool.recycle(Box::new(Pellet::new(Vec2::ZERO, Vec2::ZERO, 1.0)));
+        }
+        assert_eq!(pool.free.len(), MAX_POOLED_PELLETS);
+    }
+
+    #[test]
+    fn pooled_waves_of_pellets_reuse_the_first_ones_boxes() {
+        let mut pool = PelletPool::default();
+        let wave = |pool: &mut PelletPool| (0..2000).map(|i| pool.take(Pellet::new(vec2(i as f32, 0.0), Vec2::ZERO, 5.0))).collect::<Vec<_>>();
+        let address = |pellet: &Pellet| pellet as *const Pellet;
+        let first = wave(&mut pool);
+        let boxes = first.iter().map(|p| address(p)).collect::<std::collections::HashSet<_>>();
+        first.into_iter().for_each(|p| pool.recycle(p));
+        for _ in 0..10 {
+            let live = wave(&mut pool);
+            // nothing new was allocated
+            assert!(live.iter().all(|p| boxes.contains(&address(p))));
+            live.into_iter().for_each(|p| pool.recycle(p));
+        }
+        assert_eq!(pool.free.len(), 2000);
+    }
+
+
+    #[test]
+    fn lasers_with_no_length_hit_nothing() {
+        let mut accum = UpdateAccumulator::new();
+        let mut grow = GrowLaser::new(vec2(300.0, 300.0), vec2(300.0, 300.0), 40.0, 0.0, 4.0, Vec2::ZERO);
+        let mut slam = SlamLaser::new(vec2(300.0, 300.0), vec2(300.0, 300.0), 40.0, 0.0, 4.0, 0.2, Vec2::ZERO, 0.0);
+        for t in [0.5, 1.0, 2.0] {
+            grow.update(&mut accum, 0.5, t, 0.5, t);
+            slam.update(&mut accum, 0.5, t, 0.5, t);
+            for obstacle in [&grow as &dyn Obstacle, &slam] {
+                assert!(!obstacle.collides(player_at(vec2(300.0, 300.0))), "{}", obstacle.name());
+                assert!(!obstacle.collides_swept(vec2(200.0, 300.0), vec2(400.0, 300.0), 5.0), "{}", obstacle.name());
+                assert!(obstacle.bounds().is_some_and(|b| b.x.is_finite() && b.w.is_finite()), "{}", obstacle.name());
+            }
+        }
+    }
+
+    #[test]
+    fn laser_tips_are_round() {
+        let mut laser = GrowLaser::new(vec2(100.0, 100.0), vec2(300.0, 100.0), 20.0, 0.0, 4.0, Vec2::ZERO);
+        laser.current_time = 1.0;
+        // just off the end, past where a square end's corner would be
+        assert!(!laser.collides(Player { pos: vec2(308.0, 108.0), rad: 1.0, ..Player::default() }));
+        assert!(laser.collides(Player { pos: vec2(310.5, 100.0), rad: 1.0, ..Player::default() }));
+    }
+
+
+    #[test]
+    fn spinning_arcs_hit_where_they_are_after_many_turns() {
+        let mut accum = UpdateAccumulator::new();
+        let mut arc = SpinningArc::new().center(vec2(800.0, 450.0)).inner_rad(100.0).outer_rad(150.0).left_angle(-0.5).right_angle(0.5).rpb(1.37).show_time(100.0);
+        let mut time = 0.0;
+        for _ in 0..200 {
+            time += 0.05;
+            arc.update(&mut accum, 0.05, time, 0.05, time);
+            // the middle of the arc as drawn, and across from it
+            let middle = arc.rot();
+            let at = |angle: f32| player_at(arc.center + vec2(angle.sin(), angle.cos()) * 125.0);
+            assert!(arc.collides(at(middle)), "missed the middle after {} turns", middle / TAU);
+            assert!(!arc.collides(at(middle + PI)), "hit across from the middle after {} turns", middle / TAU);
+        }
+        assert!(arc.rot() > 10.0 * TAU);
+    }
+
+
+    #[test]
+    fn slam_lasers_hit_nothing_before_they_reach_out() {
+        let mut accum = UpdateAccumulator::new();
+        // no anticipation, so it's a point until the slam
+        let mut slam = SlamLaser::new(vec2(100.0, 100.0), vec2(500.0, 100.0), 40.0, 1.0, 4.0, 0.0, Vec2::ZERO, 0.0);
+        slam.update(&mut accum, 0.0, 0.0, 0.0, 0.0);
+        assert_eq!(slam.slam(), 0.0);
+        for pos in [vec2(100.0, 100.0), vec2(110.0, 100.0), vec2(300.0, 100.0)] {
+            assert!(!slam.collides(player_at(pos)), "{pos}");
+        }
+        assert!(slam.contact(player_at(vec2(100.0, 100.0))).is_none());
+        slam.update(&mut accum, 1.5, 1.5, 1.5, 1.5);
+        assert!(slam.collides(player_at(vec2(300.0, 100.0))));
+    }
+
+
+    /// A 10-step `Periodic` a beat apart, each step a pellet at (100 x its step, 0).
+    fn ten_steps() -> Periodic {
+        Periodic::new(10, 1.0, Box::new(|gs: &mut UpdateAccumulator, sm: ModifyArgs| gs.pellet(vec2(sm.step as f32 * 100.0, 0.0), Vec2::ZERO, 5.0)))
+    }
+
+    /// The steps of the pellets `ten_steps` spawned into `accum`.
+    fn steps_spawned(accum: &UpdateAccumulator) -> Vec<usize> {
+        accum.added().iter().map(|o| (o.obstacle.anchor().unwrap().x / 100.0).round() as usize).collect()
+    }
+
+    /// `periodic` run in half-beat frames until 3.5 beats in, three steps along, and then killed.
+    fn killed_after_three(periodic: Periodic) -> Vec<usize> {
+        let mut accum = UpdateAccumulator::new();
+        let mut periodic = periodic;
+        for frame in 1..=7 {
+            accum.set_time(frame as f32 * 0.5);
+            periodic.update(&mut accum, 0.5, frame as f32 * 0.5, 0.5, frame as f32 * 0.5);
+        }
+        assert_eq!(steps_spawned(&accum), [0, 1, 2]);
+        periodic.kill(&mut accum);
+        steps_spawned(&accum)
+    }
+
+    #[test]
+    fn early_killed_periodics_drop_what_is_left_by_default() {
+        assert_eq!(ten_steps().on_early_kill, OnEarlyKill::Drop);
+        assert_eq!(killed_after_three(ten_steps()), [0, 1, 2]);
+    }
+
+    #[test]
+    fn early_killed_periodics_can_flush_everything_left() {
+        let spawned = killed_after_three(ten_steps().on_early_kill(OnEarlyKill::FlushRemaining));
+        assert_eq!(spawned, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn early_killed_periodics_can_flush_whats_coming_soon() {
+        // the next steps were half a beat and a beat and a half off, the one after two and a half
+        let spawned = killed_after_three(ten_steps().on_early_kill(OnEarlyKill::FlushWithin(2.0)));
+        assert_eq!(spawned, [0, 1, 2, 3, 4]);
+        assert_eq!(killed_after_three(ten_steps().on_early_kill(OnEarlyKill::FlushWithin(0.25))), [0, 1, 2]);
+        assert_eq!(killed_after_three(ten_steps().on_early_kill(OnEarlyKill::FlushWithin(100.0))).len(), 10);
+    }
+
+
+    const ORIGIN: Vec2 = vec2(100.0, 100.0);
+    const VEL: Vec2 = vec2(40.0, 0.0);
+
+    /// (start time, where it is) of spawned pellets
+    type Spawns = Vec<(f32, Vec2)>;
+
+    /// What a `Periodic` a beat apart spawns under `catch_up`,
+    /// moving along `VEL` from `ORIGIN`, over half-beat frames to beat 1 and a 3-beat frame after.
+    fn caught_up(catch_up: CatchUp) -> (Spawns, Spawns) {
+        let spawner = |gs: &mut UpdateAccumulator, sm: ModifyArgs| Bomb::pellet_spawner(gs, sm.pos(ORIGIN + vec2(0.0, sm.step as f32)).vel(VEL).rad(5.0));
+        let mut periodic = Periodic::new(10, 1.0, Box::new(spawner)).catch_up(catch_up);
+        let spawned = |accum: &UpdateAccumulator| accum.added().iter().map(|o| (o.start_time, o.obstacle.anchor().unwrap())).collect::<Spawns>();
+        let mut accum = UpdateAccumulator::new();
+        for (time, dt) in [(0.5, 0.5), (1.0, 0.5)] {
+            accum.set_time(time);
+            periodic.update(&mut accum, dt, time, dt, time);
+        }
+        let smooth = spawned(&accum);
+        let mut accum = UpdateAccumulator::new();
+        accum.set_time(4.0);
+        periodic.update(&mut accum, 3.0, 4.0, 3.0, 4.0);
+        assert_eq!(periodic.time_div, 4, "{catch_up:?} lost count of its steps");
+        (smooth, spawned(&accum))
+    }
+
+    #[test]
+    fn half_beat_frames_catch_nothing_up() {
+        for catch_up in [CatchUp::Burst, CatchUp::Spread, CatchUp::Skip] {
+            assert_eq!(caught_up(catch_up).0, [(1.0, ORIGIN)], "{catch_up:?}");
+        }
+    }
+
+    #[test]
+    fn bursts_fire_every_missed_step_at_once() {
+        let (_, hitch) = caught_up(CatchUp::Burst);
+        assert_eq!(hitch, [(4.0, ORIGIN + vec2(0.0, 1.0)), (4.0, ORIGIN + vec2(0.0, 2.0)), (4.0, ORIGIN + vec2(0.0, 3.0))]);
+    }
+
+    #[test]
+    fn spreads_fire_every_missed_step_where_it_would_be() {
+        let (_, hitch) = caught_up(CatchUp::Spread);
+        // steps 1 and 2 were due 2 and 1 beats ago, and have moved on since
+        assert_eq!(hitch, [(2.0, ORIGIN + vec2(0.0, 1.0) + VEL * 2.0), (3.0, ORIGIN + vec2(0.0, 2.0) + VEL), (4.0, ORIGIN + vec2(0.0, 3.0))]);
+    }
+
+    #[test]
+    fn skips_fire_only_the_latest_missed_step() {
+        let (_, hitch) = caught_up(CatchUp::Skip);
+        assert_eq!(hitch, [(4.0, ORIGIN + vec2(0.0, 3.0))]);
+    }
+
+
+    /// Dashes right from the origin over `frames` equal frames spanning the dash, returning where the player ends up.
+    fn dash_over(frames: usize) -> Vec2 {
+        let mut player = player_at(Vec2::ZERO);
+        player.start_dash(Vec2::X);
+        for _ in 0..frames {
+            player.update_dash(player.dash_beats / frames as f32);
+        }
+        player.pos
+    }
+
+    #[test]
+    fn dashes_cover_the_same_distance_at_any_frame_rate() {
+        let one = dash_over(1);
+        assert!((one.x - Player::default().dash_distance).abs() < 1e-3, "{one}");
+        assert!((dash_over(10) - one).length() < 1e-3);
+        assert!((dash_over(7) - one).length() < 1e-3);
+    }
+
+    #[test]
+    fn dashing_players_are_hit_on_their_first_vulnerable_frame() {
+        let mut player = player_at(Vec2::ZERO);
+        // big and slow enough to stay on the player all dash long
+        let pellet = Obst::new(Box::new(Pellet::new(Vec2::ZERO, Vec2::ZERO, player.dash_distance * 2.0)), 0.0);
+        player.start_dash(Vec2::X);
+        let frame = player.dash_beats / 8.0;
+        let mut frames = 0;
+        while !(player.vulnerable() && pellet.collides(player)) {
+            assert!(pellet.collides(player));
+            player.update_dash(frame);
+            frames += 1;
+        }
+        // hit the frame the dash ended, not one later
+        assert_eq!(frames, 8);
+        assert!(!player.dashing());
+        // i-frames protect the same way
+        player.isecs = frame;
+        assert!(!player.vulnerable());
+    }
+
+
+    #[test]
+    fn players_stop_in_the_arena_corners() {
+        let arena = Rect::new(100.0, 50.0, 300.0, 200.0);
+        let rad = 10.0;
+        let mut player = Player { pos: arena.center(), rad, ..Player::default() };
+        // straight through the bottom right corner
+        let mut touched = [false; 4];
+        for _ in 0..100 {
+            player.pos += vec2(7.0, 5.0);
+            touched = player.clamp_to(arena);
+        }
+        assert_eq!(player.pos, arena.point() + arena.size() - rad);
+        assert_eq!(touched, [false, false, true, true]);
+        // and back out through the top left
+        for _ in 0..100 {
+            player.pos -= vec2(7.0, 5.0);
+            touched = player.clamp_to(arena);
+        }
+        assert_eq!(player.pos, arena.point() + rad);
+        assert_eq!(touched, [true, true, false, false]);
+    }
+
+    #[test]
+    fn players_bigger_than_the_arena_stay_centered() {
+        let arena = Rect::new(100.0, 50.0, 10.0, 300.0);
+        let mut player = Player { pos: vec2(500.0, 500.0), rad: 20.0, ..Player::default() };
+        assert_eq!(player.clamp_to(arena), [false, false, true, true]);
+        // centered across, and against the bottom
+        assert_eq!(player.pos, vec2(105.0, 330.0));
+        player.pos = vec2(0.0, 200.0);
+        assert_eq!(player.clamp_to(arena), [true, false, false, false]);
+        assert_eq!(player.pos, vec2(105.0, 200.0));
+        // already there, nothing pushes it
+        assert_eq!(player.clamp_to(arena), [false; 4]);
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use super::*;
+    use crate::utils::GameRng;
+
+    /// Serializes `obstacle` and reads it back, which has to serialize the same, before and after both are updated.
+    fn round_trip<T: Obstacle + serde::Serialize + serde::de::DeserializeOwned>(mut obstacle: T) {
+        let json = serde_json::to_value(&obstacle).unwrap();
+        let mut back = serde_json::from_value::<T>(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&back).unwrap(), json, "{}", obstacle.name());
+        let mut accum = UpdateAccumulator::new();
+        for t in [0.5, 1.0, 1.5] {
+            obstacle.update(&mut accum, 0.5, t, 0.5, t);
+            back.update(&mut accum, 0.5, t, 0.5, t);
+        }
+        assert_eq!(serde_json::to_value(&back).unwrap(), serde_json::to_value(&obstacle).unwrap(), "{} once updated", obstacle.name());
+    }
+
+    #[test]
+    fn obstacles_round_trip() {
+        let center = vec2(400.0, 300.0);
+        round_trip(Pellet::new(center, vec2(100.0, -50.0), 8.0));
+        round_trip(GrowLaser::new(Vec2::ZERO, center, 30.0, 1.0, 2.0, vec2(4.0, 0.0)));
+        round_trip(SlamLaser::new(Vec2::ZERO, center, 30.0, 1.0, 2.0, 0.25, vec2(4.0, 0.0), 2.0));
+        round_trip(RotatableRect { center, size: vec2(200.0, 20.0), rot: 0.5, warning_time: 1.0, show_time: 2.0, current_time: 0.25, grow_time: 0.25, warning_style: WarningStyle::Outline });
+        round_trip(RotatingRect { center, size: vec2(600.0, 40.0), rpb: 0.125, warning_style: WarningStyle::Both, ..RotatingRect::default() });
+        round_trip(PelletSpinner::new().count(12).period(0.25).rad(6.0).speed(150.0).pos(center));
+        round_trip(SpinningArc::new().center(center).inner_rad(100.0).outer_rad(150.0).left_angle(0.0).right_angle(1.5).rpb(0.25).show_time(4.0));
+        round_trip(CenterProj::new().show_time(8.0).evs([(0.0, CenterEvent::Pulse), (1.0, CenterEvent::Lasers(8, 0.0))]).sort());
+        round_trip(GOLGrid::default().dims(16, 9).max(8).populate(20, &mut GameRng::new(942)));
+    }
+}
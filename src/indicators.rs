@@ -0,0 +1,218 @@
+//! Arrows at the edge of the screen pointing at threats that haven't come into view yet.\
+//! Arrows pointing about the same way merge into one with a count, so a volley from off-screen is one arrow and not a smear.
+
+use std::f32::consts::TAU;
+
+use macroquad::prelude::*;
+
+use crate::utils::{acmul, centered_text_draw, screen_width, screen_height};
+
+/// Pixels between the tips of the arrows and the edge of the screen
+pub const INDICATOR_INSET: f32 = 20.0;
+/// Arrows closer than this around the center of the screen merge, in radians (about 6 degrees)
+pub const MERGE_ANGLE: f32 = 0.1;
+const ARROW_SIZE: f32 = 14.0;
+const ARROW_THICKNESS: f32 = 3.0;
+/// Pulses per beat right as the threat arrives, up from one
+const MAX_PULSE_RATE: f32 = 6.0;
+/// Beats left when the pulse starts speeding up
+const PULSE_RAMP_BEATS: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IndicatorStyle {
+    /// A single chevron
+    #[default]
+    Chevron,
+    /// Two chevrons, for the big stuff like walls
+    Double,
+}
+
+/// A threat on its way to a spot, see `UpdateAccumulator::indicate`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Indicator {
+    pub pos: Vec2,
+    pub style: IndicatorStyle,
+    /// Beat the threat arrives at, and the arrow goes
+    pub until: f32,
+}
+impl Indicator {
+    pub fn live(&self, time: f32) -> bool {
+        time < self.until
+    }
+}
+
+/// Where `world`, offset like the obstacles are, ends up on the screen through `camera`.
+pub fn to_screen(camera: &Camera2D, world: Vec2) -> Vec2 {
+    let clip = camera.matrix().transform_point3(vec3(world.x, world.y, 0.0));
+    vec2((clip.x / 2.0 + 0.5) * screen_width(), (0.5 - clip.y / 2.0) * screen_height())
+}
+
+/// Where the line from the center of `area` to `target` crosses the edge of `area` shrunk by `inset` on every side.\
+/// None if `target` is inside `area`, so there's nothing to point at.
+pub fn clamp_to_border(area: Rect, inset: f32, target: Vec2) -> Option<Vec2> {
+    if !target.is_finite() || area.contains(target) { return None; }
+    let center = area.center();
+    let half = (area.size() / 2.0 - inset).max(Vec2::ZERO);
+    let delta = target - center;
+    // whichever edge the line reaches first
+    let mut scale = 1.0f32;
+    if delta.x != 0.0 { scale = scale.min(half.x / delta.x.abs()); }
+    if delta.y != 0.0 { scale = scale.min(half.y / delta.y.abs()); }
+    Some(center + delta * scale)
+}
+
+/// `angles` in radians grouped around the circle, each group the indices within `max_gap` of the first one in it, in order.\
+/// Groups start after the widest gap, so one never straddles it for no reason.
+pub fn merge_angles(angles: &[f32], max_gap: f32) -> Vec<Vec<usize>> {
+    let mut order = (0..angles.len()).collect::<Vec<_>>();
+    let around = |i: usize| angles[i].rem_euclid(TAU);
+    order.sort_by(|&a, &b| around(a).total_cmp(&around(b)));
+    let Some(widest) = (0..order.len()).max_by(|&a, &b| {
+        let gap = |i: usize| (around(order[(i + 1) % order.len()]) - around(order[i])).rem_euclid(TAU);
+        gap(a).total_cmp(&gap(b))
+    }) else { return vec![] };
+    let len = order.len();
+    order.rotate_left((widest + 1) % len);
+    let mut groups: Vec<Vec<usize>> = vec![];
+    for i in order {
+        match groups.last_mut() {
+            Some(group) if (around(i) - around(group[0])).rem_euclid(TAU) <= max_gap => group.push(i),
+            _ => groups.push(vec![i]),
+        }
+    }
+    groups
+}
+
+/// How far through its pulse an arrow is with `left` beats to go. Once a beat, speeding up smoothly over the last few beats.
+fn pulse_phase(left: f32) -> f32 {
+    if left >= PULSE_RAMP_BEATS { return left; }
+    // the rate climbs linearly, so the phase is its integral
+    let into = PULSE_RAMP_BEATS - left;
+    left - (MAX_PULSE_RATE - 1.0) * into * into / (2.0 * PULSE_RAMP_BEATS)
+}
+
+fn draw_chevron(tip: Vec2, dir: Vec2, color: Color) {
+    let back = tip - dir * ARROW_SIZE;
+    let side = dir.perp() * ARROW_SIZE * 0.7;
+    for wing in [back + side, back - side] {
+        draw_line(wing.x, wing.y, tip.x, tip.y, ARROW_THICKNESS, color);
+    }
+}
+
+/// Draws an arrow at the edge of the screen for each of `targets` off it, given as (screen position, style, beats left).\
+/// Arrows close together merge, pointing at the one arriving first, with the number of them next to it.
+pub fn draw(targets: &[(Vec2, IndicatorStyle, f32)], color: Color) {
+    let screen = Rect::new(0.0, 0.0, screen_width(), screen_height());
+    let center = screen.center();
+    let off_screen = targets.iter().copied()
+        .filter_map(|(pos, style, left)| Some((clamp_to_border(screen, INDICATOR_INSET, pos)?, pos, style, left)))
+        .collect::<Vec<_>>();
+    let angles = off_screen.iter().map(|&(_, pos, ..)| (pos.y - center.y).atan2(pos.x - center.x)).collect::<Vec<_>>();
+    for group in merge_angles(&angles, MERGE_ANGLE) {
+        let first = group.iter().copied().min_by(|&a, &b| off_screen[a].3.total_cmp(&off_screen[b].3)).unwrap_or(group[0]);
+        let (tip, pos, _, left) = off_screen[first];
+        let dir = (pos - center).normalize_or_zero();
+        let alpha = 0.6 + 0.4 * (pulse_phase(left.max(0.0)) * TAU).cos();
+        let color = acmul(color, alpha);
+        draw_chevron(tip, dir, color);
+        if group.iter().any(|&i| off_screen[i].2 == IndicatorStyle::Double) {
+            draw_chevron(tip - dir * ARROW_SIZE * 0.6, dir, color);
+        }
+        if group.len() > 1 {
+            let badge = tip - dir * ARROW_SIZE * 2.4;
+            draw_circle(badge.x, badge.y, 9.0, acmul(BLACK, 0.5 * alpha));
+            centered_text_draw(&group.len().to_string(), badge, 16.0, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vec2, b: Vec2) -> bool {
+        a.distance(b) < 1e-3
+    }
+
+    #[test]
+    fn nothing_points_at_whats_in_view() {
+        let screen = Rect::new(0.0, 0.0, 800.0, 600.0);
+        for target in [vec2(400.0, 300.0), vec2(0.0, 0.0), vec2(799.0, 599.0), vec2(5.0, 590.0)] {
+            assert_eq!(clamp_to_border(screen, INDICATOR_INSET, target), None);
+        }
+        assert_eq!(clamp_to_border(screen, INDICATOR_INSET, vec2(f32::NAN, 0.0)), None);
+        assert_eq!(clamp_to_border(screen, INDICATOR_INSET, vec2(f32::INFINITY, 0.0)), None);
+    }
+
+    #[test]
+    fn arrows_sit_on_the_inset_border_toward_the_target() {
+        for (w, h) in [(800.0, 600.0), (1920.0, 1080.0), (600.0, 800.0), (3440.0, 1440.0), (100.0, 1000.0)] {
+            let screen = Rect::new(0.0, 0.0, w, h);
+            let (center, inset) = (screen.center(), INDICATOR_INSET);
+            for i in 0..64 {
+                let angle = i as f32 / 64.0 * TAU;
+                let target = center + Vec2::from_angle(angle) * (w + h);
+                let tip = clamp_to_border(screen, inset, target).unwrap();
+                // on one of the inset edges and inside the rest
+                let on_x = (tip.x - inset).abs() < 1e-2 || (tip.x - (w - inset)).abs() < 1e-2;
+                let on_y = (tip.y - inset).abs() < 1e-2 || (tip.y - (h - inset)).abs() < 1e-2;
+                assert!(on_x || on_y, "{tip} on {w}x{h}");
+                assert!((inset - 1e-2..=w - inset + 1e-2).contains(&tip.x) && (inset - 1e-2..=h - inset + 1e-2).contains(&tip.y));
+                // and right on the way there
+                assert!((tip - center).normalize().distance(Vec2::from_angle(angle)) < 1e-4, "{tip} on {w}x{h}");
+            }
+        }
+    }
+
+    #[test]
+    fn straight_across_and_into_corners() {
+        let screen = Rect::new(0.0, 0.0, 1000.0, 500.0);
+        let clamp = |target| clamp_to_border(screen, 20.0, target).unwrap();
+        assert!(close(clamp(vec2(5000.0, 250.0)), vec2(980.0, 250.0)));
+        assert!(close(clamp(vec2(-1.0, 250.0)), vec2(20.0, 250.0)));
+        assert!(close(clamp(vec2(500.0, -300.0)), vec2(500.0, 20.0)));
+        assert!(close(clamp(vec2(500.0, 9000.0)), vec2(500.0, 480.0)));
+        // along the diagonal of the inset border, its corner
+        assert!(close(clamp(vec2(500.0, 250.0) + vec2(480.0, 230.0) * 10.0), vec2(980.0, 480.0)));
+        // screens smaller than the inset keep arrows in the middle
+        let tiny = Rect::new(0.0, 0.0, 30.0, 30.0);
+        assert!(close(clamp_to_border(tiny, 20.0, vec2(100.0, 50.0)).unwrap(), vec2(15.0, 15.0)));
+    }
+
+    #[test]
+    fn screens_off_the_origin_clamp_the_same() {
+        let screen = Rect::new(-400.0, 100.0, 800.0, 600.0);
+        let tip = clamp_to_border(screen, 20.0, vec2(0.0, -1000.0)).unwrap();
+        assert!(close(tip, vec2(0.0, 120.0)));
+    }
+
+    #[test]
+    fn close_angles_merge() {
+        assert!(merge_angles(&[], MERGE_ANGLE).is_empty());
+        assert_eq!(merge_angles(&[1.0], MERGE_ANGLE), [vec![0]]);
+        let groups = merge_angles(&[0.0, 0.05, 1.0, 1.08, 3.0], MERGE_ANGLE);
+        assert_eq!(groups, [vec![0, 1], vec![2, 3], vec![4]]);
+        // groups go round through zero rather than splitting at it
+        let groups = merge_angles(&[TAU - 0.02, 0.03, 2.0], MERGE_ANGLE);
+        assert_eq!(groups, [vec![0, 1], vec![2]]);
+        // angles past a turn are the same angles
+        assert_eq!(merge_angles(&[0.5, 0.5 + TAU, 0.5 - TAU], MERGE_ANGLE).len(), 1);
+        // every angle is in exactly one group
+        let angles = (0..100).map(|i| i as f32 * 0.37).collect::<Vec<_>>();
+        let mut seen = merge_angles(&angles, MERGE_ANGLE).concat();
+        seen.sort();
+        assert_eq!(seen, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn pulses_speed_up_smoothly_toward_the_end() {
+        let rate = |left: f32| (pulse_phase(left + 1e-3) - pulse_phase(left)) / 1e-3;
+        assert!((rate(10.0) - 1.0).abs() < 1e-2);
+        assert!((rate(PULSE_RAMP_BEATS + 1e-3) - rate(PULSE_RAMP_BEATS - 2e-3)).abs() < 1e-2);
+        assert!((rate(0.0) - MAX_PULSE_RATE).abs() < 1e-2);
+        for i in 0..100 {
+            let left = i as f32 * 0.05;
+            assert!(rate(left + 0.05) <= rate(left) + 1e-2);
+        }
+    }
+}
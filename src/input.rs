@@ -0,0 +1,460 @@
+use macroquad::prelude::{Vec2, KeyCode, MouseButton, is_key_down, is_key_pressed, is_mouse_button_pressed, is_mouse_button_down, get_last_key_pressed};
+use strum::{IntoEnumIterator, EnumCount};
+
+use crate::{Possibly, utils::mouse_position};
+
+/// Everything the player can do, independent of the device used.
+#[derive(strum_macros::EnumIter, strum_macros::EnumCount, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Dash,
+    Focus,
+    Pause,
+    Restart,
+    Bomb,
+    /// Practice mode only: seek a measure back or forward
+    SeekBack,
+    SeekForward,
+    /// Practice mode only: seek 4 measures back or forward
+    SeekBackFar,
+    SeekForwardFar,
+}
+
+/// The device last used by the player.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Device {
+    #[default]
+    Keyboard,
+    Gamepad,
+    /// Only used if `Input::mouse_enabled` is set
+    Mouse
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftShoulder,
+    RightShoulder,
+    Start,
+    Select
+}
+pub const GAMEPAD_BUTTONS: usize = 8;
+
+/// A snapshot of a gamepad, as reported by a `GamepadSource`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct GamepadState {
+    /// Raw left stick, each axis -1..1 (y down)
+    pub left_stick: Vec2,
+    pub buttons: [bool; GAMEPAD_BUTTONS],
+}
+impl GamepadState {
+    pub fn button(&self, button: GamepadButton) -> bool {
+        self.buttons[button as usize]
+    }
+}
+
+/// Backend providing gamepad state.\
+/// macroquad 0.3 has no gamepad API, so with the `gamepad` feature the first player's gamepad comes from gilrs (`GilrsGamepad`).
+/// Without it, or where gilrs can't run, `NoGamepad` is polled and gamepads do nothing.
+pub trait GamepadSource {
+    /// Called once per frame. `None` if no gamepad is connected.
+    fn poll(&mut self) -> Option<GamepadState>;
+}
+/// Never connected.
+pub struct NoGamepad;
+impl GamepadSource for NoGamepad {
+    fn poll(&mut self) -> Option<GamepadState> { None }
+}
+
+/// Gamepads through gilrs, following the one last used.
+#[cfg(feature = "gamepad")]
+pub struct GilrsGamepad {
+    gilrs: gilrs::Gilrs,
+    active: Option<gilrs::GamepadId>,
+}
+#[cfg(feature = "gamepad")]
+impl GilrsGamepad {
+    /// `None` if gilrs doesn't work here.
+    pub fn new() -> Option<Self> {
+        match gilrs::Gilrs::new() {
+            Ok(gilrs) => Some(GilrsGamepad { gilrs, active: None }),
+            Err(e) => {
+                println!("couldn't open the gamepads: {e}");
+                None
+            }
+        }
+    }
+}
+#[cfg(feature = "gamepad")]
+impl GamepadSource for GilrsGamepad {
+    fn poll(&mut self) -> Option<GamepadState> {
+        use gilrs::{Axis, Button};
+        // in `GamepadButton` order, gilrs calling the shoulders triggers
+        const BUTTONS: [Button; GAMEPAD_BUTTONS] = [
+            Button::South, Button::East, Button::West, Button::North,
+            Button::LeftTrigger, Button::RightTrigger, Button::Start, Button::Select
+        ];
+        // the state only updates as the events are taken
+        while let Some(event) = self.gilrs.next_event() {
+            self.active = Some(event.id);
+        }
+        let pad = self.active.and_then(|id| self.gilrs.connected_gamepad(id))
+            .or_else(|| self.gilrs.gamepads().next().map(|(_, pad)| pad))?;
+        Some(GamepadState {
+            // gilrs has y up
+            left_stick: Vec2::new(pad.value(Axis::LeftStickX), -pad.value(Axis::LeftStickY)),
+            buttons: BUTTONS.map(|button| pad.is_pressed(button)),
+        })
+    }
+}
+
+/// Lists every `KeyCode`, since it can't be iterated or parsed.
+macro_rules! all_keys {
+    ($($key:ident),+) => {
+        const ALL_KEYS: &[KeyCode] = &[$(KeyCode::$key),+];
+    };
+}
+all_keys!(
+    Space, Apostrophe, Comma, Minus, Period, Slash,
+    Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+    Semicolon, Equal,
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    LeftBracket, Backslash, RightBracket, GraveAccent, World1, World2,
+    Escape, Enter, Tab, Backspace, Insert, Delete, Right, Left, Down, Up,
+    PageUp, PageDown, Home, End, CapsLock, ScrollLock, NumLock, PrintScreen, Pause,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24, F25,
+    Kp0, Kp1, Kp2, Kp3, Kp4, Kp5, Kp6, Kp7, Kp8, Kp9,
+    KpDecimal, KpDivide, KpMultiply, KpSubtract, KpAdd, KpEnter, KpEqual,
+    LeftShift, LeftControl, LeftAlt, LeftSuper, RightShift, RightControl, RightAlt, RightSuper, Menu
+);
+pub fn key_name(key: KeyCode) -> String {
+    format!("{key:?}")
+}
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    ALL_KEYS.iter().copied().find(|&k| key_name(k) == name)
+}
+
+/// Keyboard keys bound to each `Action`. An action can have several keys.\
+/// Everything playing a level or getting around the menus goes through these. Only keys that aren't anyone's controls read raw keys:
+/// the dev keys (F3, F4, the inspector's I, the main menu's U), the editor's shortcuts, and the main menu's setting toggles, whose labels name their keys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings {
+    keys: [Vec<KeyCode>; Action::COUNT],
+}
+impl Default for Bindings {
+    fn default() -> Self {
+        let mut bindings = Bindings { keys: Default::default() };
+        for action in Action::iter() {
+            bindings.keys[action as usize] = match action {
+                Action::MoveUp => vec![KeyCode::W],
+                Action::MoveDown => vec![KeyCode::S],
+                Action::MoveLeft => vec![KeyCode::A],
+                Action::MoveRight => vec![KeyCode::D],
+                Action::Dash => vec![KeyCode::Space],
+                Action::Focus => vec![KeyCode::LeftShift],
+                Action::Pause => vec![KeyCode::Escape],
+                Action::Restart => vec![KeyCode::R],
+                Action::Bomb => vec![KeyCode::X],
+                Action::SeekBack => vec![KeyCode::LeftBracket],
+                Action::SeekForward => vec![KeyCode::RightBracket],
+                Action::SeekBackFar => vec![KeyCode::Minus],
+                Action::SeekForwardFar => vec![KeyCode::Equal],
+            };
+        }
+        bindings
+    }
+}
+impl Bindings {
+    /// Default bindings for the second player in co-op:\
+    /// arrows, right shift to focus, right control or enter to dash and slash to bomb.
+    pub fn player_two() -> Self {
+        let mut bindings = Bindings { keys: Default::default() };
+        bindings.set(Action::MoveUp, KeyCode::Up);
+        bindings.set(Action::MoveDown, KeyCode::Down);
+        bindings.set(Action::MoveLeft, KeyCode::Left);
+        bindings.set(Action::MoveRight, KeyCode::Right);
+        bindings.set(Action::Dash, KeyCode::RightControl);
+        bindings.add(Action::Dash, KeyCode::Enter);
+        bindings.set(Action::Focus, KeyCode::RightShift);
+        bindings.set(Action::Bomb, KeyCode::Slash);
+        bindings
+    }
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        &self.keys[action as usize]
+    }
+    /// Replaces every key bound to `action` with `key`.
+    pub fn set(&mut self, action: Action, key: KeyCode) {
+        self.keys[action as usize] = vec![key];
+    }
+    /// Binds another key to `action`.
+    pub fn add(&mut self, action: Action, key: KeyCode) {
+        if !self.keys[action as usize].contains(&key) {
+            self.keys[action as usize].push(key);
+        }
+    }
+    pub fn clear(&mut self, action: Action) {
+        self.keys[action as usize].clear();
+    }
+    pub fn is_down(&self, action: Action) -> bool {
+        self.keys(action).iter().any(|&k| is_key_down(k))
+    }
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.keys(action).iter().any(|&k| is_key_pressed(k))
+    }
+    /// One action per line, e.g. `Dash = Space X`. Actions that aren't listed keep their defaults, unknown ones are skipped.
+    pub fn parse(text: &str) -> Possibly<Self> {
+        let mut bindings = Self::default();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            let (action, keys) = line.split_once('=').ok_or_else(|| format!("line {}: expected `Action = Keys`", idx + 1))?;
+            // actions from newer versions are skipped
+            let Some(action) = Action::iter().find(|a| format!("{a:?}") == action.trim()) else { continue };
+            bindings.clear(action);
+            for key in keys.split_whitespace() {
+                bindings.add(action, key_from_name(key).ok_or_else(|| format!("line {}: unknown key `{key}`", idx + 1))?);
+            }
+        }
+        Ok(bindings)
+    }
+    pub fn serialize(&self) -> String {
+        Action::iter().map(|a| format!(
+            "{a:?} = {}\n",
+            self.keys(a).iter().map(|&k| key_name(k)).collect::<Vec<_>>().join(" ")
+        )).collect()
+    }
+}
+
+fn buttons(action: Action) -> &'static [GamepadButton] {
+    match action {
+        Action::Dash => &[GamepadButton::South],
+        Action::Focus => &[GamepadButton::LeftShoulder, GamepadButton::RightShoulder],
+        Action::Pause => &[GamepadButton::Start],
+        Action::Bomb => &[GamepadButton::East],
+        // movement is analog
+        _ => &[]
+    }
+}
+
+/// The player stops following the mouse when this close to it.
+pub const MOUSE_EPSILON: f32 = 1.0;
+/// How far the stick has to be pushed along an axis to count as pressing that direction, e.g. in menus.
+pub const STICK_PRESS: f32 = 0.5;
+
+/// Merges keyboard, gamepad and mouse input into actions. Call `update` once per frame.
+pub struct Input {
+    pub bindings: Bindings,
+    /// `GilrsGamepad` for the first player with the `gamepad` feature, otherwise `NoGamepad`. See `GamepadSource`.
+    pub gamepad: Box<dyn GamepadSource>,
+    pad: GamepadState,
+    prev_pad: GamepadState,
+    /// Stick magnitudes below this are ignored.
+    pub dead_zone: f32,
+    /// Exponent applied to the stick magnitude after the dead zone. 1 is linear.
+    pub response_curve: f32,
+    pub device: Device,
+    /// Enables the mouse-follow control scheme: the player moves towards the cursor when it moves.
+    pub mouse_enabled: bool,
+    last_mouse: Vec2,
+}
+impl Input {
+    pub fn new() -> Self {
+        Input {
+            bindings: Bindings::default(),
+            gamepad: Box::new(NoGamepad),
+            pad: GamepadState::default(),
+            prev_pad: GamepadState::default(),
+            dead_zone: 0.2,
+            response_curve: 1.5,
+            device: Device::Keyboard,
+            mouse_enabled: false,
+            last_mouse: Vec2::ZERO,
+        }
+    }
+    /// Input for the first player, with the gamepad if the `gamepad` feature is on.
+    pub fn player_one() -> Self {
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepad) = GilrsGamepad::new() {
+            return Input { gamepad: Box::new(gamepad), ..Self::new() };
+        }
+        Self::new()
+    }
+    /// Input for the second player in co-op. The gamepad is the first player's.
+    pub fn player_two() -> Self {
+        Input { bindings: Bindings::player_two(), ..Self::new() }
+    }
+    pub fn update(&mut self) {
+        self.prev_pad = self.pad;
+        self.pad = self.gamepad.poll().unwrap_or_default();
+        let mouse = Vec2::from(mouse_position());
+        let mouse_moved = mouse.distance_squared(self.last_mouse) > 1.0;
+        self.last_mouse = mouse;
+        // only movement takes over, so e.g. focusing doesn't stop the mouse from being followed
+        let moving = [Action::MoveUp, Action::MoveDown, Action::MoveLeft, Action::MoveRight]
+            .into_iter().any(|a| self.bindings.is_down(a));
+        if moving {
+            self.device = Device::Keyboard;
+        } else if self.pad.buttons.contains(&true) || self.pad.left_stick.length() > self.dead_zone {
+            self.device = Device::Gamepad;
+        } else if self.mouse_enabled && mouse_moved {
+            self.device = Device::Mouse;
+        }
+    }
+    /// Where the player should move to, if the mouse is in control.
+    pub fn mouse_target(&self) -> Option<Vec2> {
+        (self.mouse_enabled && self.device == Device::Mouse).then_some(self.last_mouse)
+    }
+    fn mouse_action(&self, action: Action, check: fn(MouseButton) -> bool) -> bool {
+        self.mouse_enabled && action == Action::Dash && check(MouseButton::Left)
+    }
+    pub fn is_down(&self, action: Action) -> bool {
+        self.bindings.is_down(action)
+            || buttons(action).iter().any(|&b| self.pad.button(b))
+            || self.mouse_action(action, is_mouse_button_down)
+    }
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.bindings.is_pressed(action)
+            || buttons(action).iter().any(|&b| self.pad.button(b) && !self.prev_pad.button(b))
+            || self.mouse_action(action, is_mouse_button_pressed)
+            || self.stick_pressed(action)
+    }
+    /// Whether the stick was pushed past `STICK_PRESS` in a movement action's direction this frame.
+    fn stick_pressed(&self, action: Action) -> bool {
+        let along = |pad: &GamepadState| match action {
+            Action::MoveUp => -pad.left_stick.y,
+            Action::MoveDown => pad.left_stick.y,
+            Action::MoveLeft => -pad.left_stick.x,
+            Action::MoveRight => pad.left_stick.x,
+            _ => 0.0
+        };
+        along(&self.pad) > STICK_PRESS && along(&self.prev_pad) <= STICK_PRESS
+    }
+    /// Whether any key, mouse button or gamepad button was pressed this frame.
+    pub fn any_pressed(&self) -> bool {
+        get_last_key_pressed().is_some()
+            || is_mouse_button_pressed(MouseButton::Left)
+            || is_mouse_button_pressed(MouseButton::Right)
+            || self.pad.buttons.iter().zip(self.prev_pad.buttons).any(|(&now, before)| now && !before)
+    }
+    /// Stick position after the dead zone and response curve. Never longer than 1.
+    pub fn stick(&self) -> Vec2 {
+        let raw = self.pad.left_stick;
+        let mag = raw.length();
+        if mag <= self.dead_zone { return Vec2::ZERO; }
+        let scaled = ((mag - self.dead_zone) / (1.0 - self.dead_zone)).min(1.0).powf(self.response_curve);
+        raw / mag * scaled
+    }
+    pub fn raw_stick(&self) -> Vec2 {
+        self.pad.left_stick
+    }
+    /// Movement direction from the last used device, to be multiplied by the player's speed.\
+    /// `max_step` is the furthest the player can move this frame, used to stop exactly on the cursor.
+    pub fn movement(&self, player_pos: Vec2, max_step: f32) -> Vec2 {
+        match self.device {
+            Device::Gamepad => self.stick(),
+            Device::Mouse => {
+                let delta = self.last_mouse - player_pos;
+                let dist = delta.length();
+                if dist < MOUSE_EPSILON || max_step <= 0.0 { return Vec2::ZERO; }
+                delta / dist * (dist / max_step).min(1.0)
+            }
+            Device::Keyboard => {
+                let mut dir = Vec2::ZERO;
+                if self.is_down(Action::MoveUp) { dir.y -= 1.0; }
+                if self.is_down(Action::MoveDown) { dir.y += 1.0; }
+                if self.is_down(Action::MoveLeft) { dir.x -= 1.0; }
+                if self.is_down(Action::MoveRight) { dir.x += 1.0; }
+                dir
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_stick(stick: Vec2) -> Input {
+        let mut input = Input::new();
+        input.pad.left_stick = stick;
+        input
+    }
+
+    #[test]
+    fn stick_ignores_the_dead_zone() {
+        assert_eq!(with_stick(Vec2::new(0.15, 0.1)).stick(), Vec2::ZERO);
+        assert!(with_stick(Vec2::new(0.3, 0.0)).stick().x > 0.0);
+    }
+
+    #[test]
+    fn stick_diagonals_are_no_faster() {
+        let diagonal = with_stick(Vec2::new(1.0, 1.0)).stick();
+        let straight = with_stick(Vec2::new(1.0, 0.0)).stick();
+        assert!((diagonal.length() - 1.0).abs() < 1e-5);
+        assert!((straight.length() - 1.0).abs() < 1e-5);
+        // and keep their direction
+        assert!((diagonal.x - diagonal.y).abs() < 1e-5);
+    }
+
+    #[test]
+    fn stick_curve_is_monotonic() {
+        let mut last = 0.0;
+        for i in 0..=100 {
+            let mag = with_stick(Vec2::new(i as f32 / 100.0, 0.0)).stick().length();
+            assert!(mag >= last);
+            last = mag;
+        }
+        assert!((last - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn stick_presses_once_per_push() {
+        let mut input = with_stick(Vec2::new(0.0, -0.8));
+        assert!(input.stick_pressed(Action::MoveUp));
+        assert!(!input.stick_pressed(Action::MoveDown));
+        input.prev_pad = input.pad;
+        assert!(!input.stick_pressed(Action::MoveUp));
+    }
+
+
+    fn with_mouse(at: Vec2) -> Input {
+        let mut input = Input::new();
+        input.mouse_enabled = true;
+        input.device = Device::Mouse;
+        input.last_mouse = at;
+        input
+    }
+
+    #[test]
+    fn mouse_flicks_move_the_player_at_most_its_speed() {
+        let (pps, dtime) = (300.0, 1.0 / 60.0);
+        let max_step = pps * dtime;
+        // the cursor jumps across the screen in one frame
+        let target = Vec2::new(1500.0, -800.0);
+        let input = with_mouse(target);
+        let mut pos = Vec2::ZERO;
+        let mut frames = 0;
+        loop {
+            let step = input.movement(pos, max_step) * max_step;
+            let left = pos.distance(target);
+            if left > max_step {
+                // capped at exactly pps * dtime, straight towards the cursor
+                assert!((step.length() - max_step).abs() <= max_step * 1e-6, "{} moved", step.length());
+                assert!(step.normalize().dot((target - pos).normalize()) > 1.0 - 1e-6);
+            }
+            assert!(step.length() <= left.min(max_step) * (1.0 + 1e-6), "overshot by {}", step.length() - left);
+            pos += step;
+            frames += 1;
+            if pos.distance(target) < MOUSE_EPSILON { break; }
+        }
+        assert_eq!(frames, (target.length() / max_step).ceil() as usize);
+        // and it stays put once there
+        assert_eq!(input.movement(pos, max_step), Vec2::ZERO);
+    }
+}
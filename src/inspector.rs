@@ -0,0 +1,68 @@
+//! The dev-mode inspector: freezes the level and steps through the live obstacles one by one, for finding the mistimed one.
+
+use macroquad::prelude::*;
+
+use crate::{game_objects::{Obst, Player}, utils::{acmul, screen_width, set_screen_camera}};
+
+const PANEL_WIDTH: f32 = 340.0;
+const LINE_HEIGHT: f32 = 18.0;
+const HIGHLIGHT: Color = YELLOW;
+
+#[derive(Default)]
+pub struct Inspector {
+    /// Whether the level is frozen for inspecting
+    pub frozen: bool,
+    /// Place in the spawn order of the selected obstacle
+    selected: usize,
+}
+impl Inspector {
+    pub fn toggle(&mut self) {
+        self.frozen = !self.frozen;
+        self.selected = 0;
+    }
+    /// Moves the selection `by` obstacles in spawn order, wrapping around.
+    pub fn step(&mut self, by: isize, count: usize) {
+        if count == 0 { return; }
+        self.selected = (self.selected as isize + by).rem_euclid(count as isize) as usize;
+    }
+    /// Indices of `obsts`, the earliest spawned first.
+    fn order(obsts: &[Obst]) -> Vec<usize> {
+        let mut order = (0..obsts.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| obsts[a].start_time.total_cmp(&obsts[b].start_time));
+        order
+    }
+    /// Outlines the selected obstacle through the level's `camera` and lists what it's up to on the right. Draws nothing unless frozen.
+    pub fn draw(&self, obsts: &[Obst], players: &[Player], time: f32, offset: Vec2, camera: &Camera2D) {
+        if !self.frozen { return; }
+        let order = Self::order(obsts);
+        let mut lines = vec![format!("inspecting {}/{}  [ ] to cycle, I to resume", (self.selected + 1).min(order.len()), order.len())];
+        if let Some(obst) = order.get(self.selected).map(|&i| &obsts[i]) {
+            // the highlight goes where the obstacle is drawn, the panel stays upright
+            set_camera(camera);
+            obst.obstacle.draw(acmul(HIGHLIGHT, 0.6), offset);
+            let anchor = obst.obstacle.anchor();
+            if let Some(at) = anchor {
+                draw_circle_lines(at.x + offset.x, at.y + offset.y, 24.0, 2.0, HIGHLIGHT);
+            }
+            set_screen_camera();
+            lines.push(obst.obstacle.name().to_string());
+            lines.push(format!("spawned at beat {:.3}, {:.3} beats old", obst.start_time, time - obst.start_time));
+            if obst.frozen {
+                lines.push(format!("frozen until beat {:.3}{}", obst.thaw_at, if obst.frozen_harmless { ", harmless" } else { "" }));
+            }
+            for (i, player) in players.iter().enumerate() {
+                lines.push(format!("touching player {}: {}", i + 1, obst.obstacle.collides(*player)));
+            }
+            lines.push(anchor.map_or("no anchor".to_string(), |at| format!("anchor ({:.1}, {:.1})", at.x, at.y)));
+            let flags = [(obst.essential, "essential"), (obst.from_chart, "from chart"), (!obst.obstacle.lethal(), "harmless"), (obst.obstacle.expired(), "expired"), (obst.frozen, "frozen")];
+            let flags = flags.iter().filter(|f| f.0).map(|f| f.1).collect::<Vec<_>>();
+            if !flags.is_empty() { lines.push(flags.join(", ")); }
+            lines.extend(obst.obstacle.debug_fields().into_iter().map(|(field, value)| format!("  {field} = {value}")));
+        }
+        let x = screen_width() - PANEL_WIDTH - 10.0;
+        draw_rectangle(x, 10.0, PANEL_WIDTH, lines.len() as f32 * LINE_HEIGHT + 16.0, Color::new(0.0, 0.0, 0.0, 0.75));
+        for (i, line) in lines.iter().enumerate() {
+            draw_text(line, x + 10.0, 30.0 + i as f32 * LINE_HEIGHT, 18.0, if i == 0 { HIGHLIGHT } else { WHITE });
+        }
+    }
+}
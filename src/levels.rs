@@ -1,21 +1,24 @@
 use std::f32::{consts::{PI, TAU, FRAC_PI_2}, NEG_INFINITY};
 
-use macroquad::{prelude::{vec2, ORANGE, BLACK, WHITE, Vec2, RED, YELLOW, SKYBLUE, GRAY, Color}, window::{screen_width, screen_height}, rand::gen_range};
+use macroquad::prelude::{vec2, ORANGE, BLACK, WHITE, Vec2, RED, YELLOW, SKYBLUE, GRAY, Color};
 
 // imports galore
 use crate::{
     game::{GameState, GSEvent, UpdateAccumulator, ModifyArgs},
+    endless::{Endless, default_pool},
+    scoring::ScoringConfig,
+    background::BackgroundKind,
     generators::{repeat_periodic, clone_offset, remove},
     spawners::{HorLaserSpawner, LaserSpawner, BombSideSpawner},
     game_objects::{
-        Obst, Pellet, Periodic, SlamLaser, RotatableRect, Bomb, RotatingRect, CenterProj,
+        Obst, Pellet, Periodic, SlamLaser, RotatableRect, Bomb, RotatingRect, WarningStyle, CenterProj,
         CenterEvent, Obstacle,
         GOLGrid, GrowLaser, Ease, SpinningArc
     },
     utils::{
-        cmul, gay, mix, screen_center, screen_size, rand_vec,
+        cmul, gay, mix, screen_center, screen_size, screen_width, screen_height,
         floor_vec, screen, tev_rep, ez, repeat_events, rep_off,
-        gen_sign, sq
+        sq, anchored, Anchor
     }
 };
 
@@ -100,19 +103,19 @@ pub fn moonlight_sonata(state: &mut GameState) -> (f32, f32, &'static str) {
     }));
     state.add_events(
         repeat_periodic(|accum: &mut UpdateAccumulator, _| {
-            accum.obst(Bomb::new(vec2(screen_width(), screen_height() / 2.0), vec2(screen_width() - 200.0, screen_height() / 2.0), 2.0, 8, 250.0, 6.0, Box::new(Bomb::pellet_spawner)))
+            accum.obst(Bomb::new(anchored(Anchor::Right, Vec2::ZERO), anchored(Anchor::Right, vec2(-200.0, 0.0)), 2.0, 8, 250.0, 6.0, Box::new(Bomb::pellet_spawner)))
         }, 4, -2.0, 1.0)
 
         .into_iter().chain(repeat_periodic(|accum: &mut UpdateAccumulator, _| {
-            accum.obst(Bomb::new(vec2(0.0, screen_height() / 2.0), vec2(200.0, screen_height() / 2.0), 2.0, 8, 250.0, 6.0, Box::new(Bomb::pellet_spawner)))
+            accum.obst(Bomb::new(anchored(Anchor::Left, Vec2::ZERO), anchored(Anchor::Left, vec2(200.0, 0.0)), 2.0, 8, 250.0, 6.0, Box::new(Bomb::pellet_spawner)))
         }, 4, 2.0, 1.0))
 
         .chain(repeat_periodic(|accum: &mut UpdateAccumulator, _| {
-            accum.obst(Bomb::new(vec2(screen_width() / 2.0, 0.0), vec2(screen_width() / 2.0, 200.0), 2.0, 8, 250.0, 6.0, Box::new(Bomb::pellet_spawner)))
+            accum.obst(Bomb::new(anchored(Anchor::Top, Vec2::ZERO), anchored(Anchor::Top, vec2(0.0, 200.0)), 2.0, 8, 250.0, 6.0, Box::new(Bomb::pellet_spawner)))
         }, 4, 6.0, 1.0))
 
         .chain(repeat_periodic(|accum: &mut UpdateAccumulator, _| {
-            accum.obst(Bomb::new(vec2(screen_width() / 2.0, screen_height()), vec2(screen_width() / 2.0, screen_height() - 200.0), 2.0, 8, 250.0, 6.0, Box::new(Bomb::pellet_spawner)))
+            accum.obst(Bomb::new(anchored(Anchor::Bottom, Vec2::ZERO), anchored(Anchor::Bottom, vec2(0.0, -200.0)), 2.0, 8, 250.0, 6.0, Box::new(Bomb::pellet_spawner)))
         }, 4, 10.0, 1.0))
 
         .chain(repeat_periodic(BombSideSpawner::new(16, 300.0, 10.0, 2.0), 24, 14.0, 1.0))
@@ -120,8 +123,10 @@ pub fn moonlight_sonata(state: &mut GameState) -> (f32, f32, &'static str) {
 
     // Lasers
     state.add_events(repeat_periodic(|accum: &mut UpdateAccumulator, _| {
-        accum.obst(SlamLaser::new(vec2(gen_range(0.0, screen_width()), -50.0), vec2(gen_range(0.0, screen_width()), screen_height() + 50.0), 25.0, 4.0, 2.0, 0.2, vec2(0.0, 20.0), 0.0));
-        accum.obst(SlamLaser::new(vec2(gen_range(0.0, screen_width()), -50.0), vec2(gen_range(0.0, screen_width()), screen_height() + 50.0), 25.0, 4.0, 2.0, 0.2, vec2(0.0, 20.0), 0.0));
+        for _ in 0..2 {
+            let (top, bottom) = (accum.rng().range(0.0, screen_width()), accum.rng().range(0.0, screen_width()));
+            accum.obst(SlamLaser::new(vec2(top, -50.0), vec2(bottom, screen_height() + 50.0), 25.0, 4.0, 2.0, 0.2, vec2(0.0, 20.0), 0.0));
+        }
     }, 24, 12.0, 1.0));
     state.add_events([
         GSEvent::new(36.0, |accum: &mut UpdateAccumulator, _| {
@@ -136,7 +141,8 @@ pub fn moonlight_sonata(state: &mut GameState) -> (f32, f32, &'static str) {
     ]);
     
     let mut quick_slam = repeat_periodic(|accum: &mut UpdateAccumulator, _| {
-        accum.obst(SlamLaser::new(vec2(gen_range(0.0, screen_width()), -50.0), vec2(gen_range(0.0, screen_width()), screen_height() + 50.0), 50.0, 4.0, 2.0, 0.2, vec2(0.0, 20.0), 0.0));
+        let (top, bottom) = (accum.rng().range(0.0, screen_width()), accum.rng().range(0.0, screen_width()));
+        accum.obst(SlamLaser::new(vec2(top, -50.0), vec2(bottom, screen_height() + 50.0), 50.0, 4.0, 2.0, 0.2, vec2(0.0, 20.0), 0.0));
     }, 4, 26.0, 0.5);
     state.add_events(clone_offset(&quick_slam, 8.0));
     state.add_events(clone_offset(&quick_slam, 4.0));
@@ -158,8 +164,8 @@ pub fn moonlight_sonata(state: &mut GameState) -> (f32, f32, &'static str) {
         })),
         GSEvent(74.0, Box::new(|accum: &mut UpdateAccumulator, _| {
             accum.obst(SlamLaser::new(vec2(100.0, -250.0), vec2(100.0, screen_height() + 250.0), 200.0, 4.0, 1.0, 0.2, vec2(10.0, 20.0), 100.0).leave_time(1.0));
-            accum.obst(SlamLaser::new(vec2(screen_width() - 100.0, -250.0), vec2(screen_width() - 100.0, screen_height() + 250.0), 200.0, 4.0, 1.0, 0.2, vec2(10.0, 20.0), 0.0).leave_time(1.0));
-            accum.obst(SlamLaser::new(vec2(-250.0, screen_height() / 2.0), vec2(screen_width() + 250.0, screen_height() / 2.0), 200.0, 4.0, 1.0, 0.2, vec2(10.0, 20.0), 0.0).leave_time(1.0));
+            accum.obst(SlamLaser::new(anchored(Anchor::TopRight, vec2(-100.0, -250.0)), anchored(Anchor::BottomRight, vec2(-100.0, 250.0)), 200.0, 4.0, 1.0, 0.2, vec2(10.0, 20.0), 0.0).leave_time(1.0));
+            accum.obst(SlamLaser::new(anchored(Anchor::Left, vec2(-250.0, 0.0)), anchored(Anchor::Right, vec2(250.0, 0.0)), 200.0, 4.0, 1.0, 0.2, vec2(10.0, 20.0), 0.0).leave_time(1.0));
         })),
         GSEvent(76.0, Box::new(|accum: &mut UpdateAccumulator, _| {
             accum.obst(SlamLaser::new(vec2(screen_width() / 2.0, -250.0), vec2(screen_width() / 2.0, screen_height() + 250.0), 400.0, 4.0, 4.0, 0.2, vec2(10.0, 20.0), 150.0).leave_time(1.0));
@@ -184,7 +190,8 @@ pub fn moonlight_sonata(state: &mut GameState) -> (f32, f32, &'static str) {
             current_time: 0.0,
             ease_time: 0.0,
             grow_time: 1.0,
-            rpb: 0.05
+            rpb: 0.05,
+            warning_style: WarningStyle::Fill,
         });
         accum.obst(RotatingRect {
             center: screen_center(),
@@ -195,7 +202,8 @@ pub fn moonlight_sonata(state: &mut GameState) -> (f32, f32, &'static str) {
             current_time: 0.0,
             ease_time: 0.0,
             grow_time: 1.0,
-            rpb: 0.05
+            rpb: 0.05,
+            warning_style: WarningStyle::Fill,
         });
         accum.obst(RotatingRect {
             center: screen_center(),
@@ -206,10 +214,11 @@ pub fn moonlight_sonata(state: &mut GameState) -> (f32, f32, &'static str) {
             current_time: 0.0,
             ease_time: 0.0,
             grow_time: 1.0,
-            rpb: 0.05
+            rpb: 0.05,
+            warning_style: WarningStyle::Fill,
         });
         accum.obst(SlamLaser::new(vec2(100.0, -50.0), vec2(100.0, screen_height() + 50.0), 200.0, 8.0, 24.0, 0.2, Vec2::ZERO, 25.0));
-        accum.obst(SlamLaser::new(vec2(screen_width() - 100.0, -50.0), vec2(screen_width() - 100.0, screen_height() + 50.0), 200.0, 8.0, 24.0, 0.2, Vec2::ZERO, 25.0));
+        accum.obst(SlamLaser::new(anchored(Anchor::TopRight, vec2(-100.0, -50.0)), anchored(Anchor::BottomRight, vec2(-100.0, 50.0)), 200.0, 8.0, 24.0, 0.2, Vec2::ZERO, 25.0));
     })));
 
     // Trails
@@ -222,16 +231,18 @@ pub fn moonlight_sonata(state: &mut GameState) -> (f32, f32, &'static str) {
 
     // chiptune blips
     state.add_event(GSEvent(-23.1, Box::new(|accum: &mut UpdateAccumulator, _| {
-        accum.obst(Periodic::new(28, 0.375, Box::new(|ac: &mut UpdateAccumulator, _| {
+        accum.obst(Periodic::new(28, 0.375, Box::new(|ac: &mut UpdateAccumulator, sm: ModifyArgs| {
+            let mut rng = ac.rng_at(sm.time, sm.step);
             for i in 0..8 {
                 ac.obst(RotatableRect {
-                    center: floor_vec(rand_vec(Vec2::ZERO, screen_size()), vec2(20.0, 20.0)),
+                    center: floor_vec(rng.vec(Vec2::ZERO, screen_size()), vec2(20.0, 20.0)),
                     size: vec2(20.0, 20.0),
                     rot: 0.0,
                     warning_time: 4.0,
                     show_time: 2.0,
                     current_time: 0.0,
-                    grow_time: 0.25
+                    grow_time: 0.25,
+                    warning_style: WarningStyle::Fill,
                 });
             }
         })));
@@ -273,9 +284,10 @@ pub fn friendly_faith_plate(state: &mut GameState) -> (f32, f32, &'static str) {
             GSEvent::new(n - 2.0, move |accum: &mut UpdateAccumulator, _| {
                 let w = screen_width();
                 for _ in 0..1 {
+                    let (top, bottom) = (accum.rng().range(w, w * 3.0), accum.rng().range(-w * 2.0, 0.0));
                     accum.obst(GrowLaser::new(
-                        vec2(gen_range(w, w * 3.0), -20.0 - screen_height()),
-                        vec2(gen_range(-w * 2.0, 0.0), screen_height() * 2.0 + 20.0),
+                        vec2(top, -20.0 - screen_height()),
+                        vec2(bottom, screen_height() * 2.0 + 20.0),
                         50.0, 2.0, 1.0, Vec2::ZERO)
                             .grow_time(0.125)
                             .fade_in(0.125)
@@ -286,9 +298,10 @@ pub fn friendly_faith_plate(state: &mut GameState) -> (f32, f32, &'static str) {
         ))
         .chain(repeat_periodic(|accum: &mut UpdateAccumulator, _| {
             for i in 0..2 {
-                let pos = vec2(screen_width(), gen_range(screen_height() * 0.1, screen_height() * 0.9));
+                let pos = vec2(screen_width(), accum.rng().range(screen_height() * 0.1, screen_height() * 0.9));
+                let drift = accum.rng().range(-50.0, 50.0);
                 accum.obst(Bomb::new(
-                    pos, pos + vec2(-80.0, gen_range(-50.0, 50.0)),
+                    pos, pos + vec2(-80.0, drift),
                     1.0, 20, 400.0, 5.0, Box::new(Bomb::pellet_spawner)
                 ))
             }
@@ -505,13 +518,13 @@ pub fn smoke(state: &mut GameState) -> (f32, f32, &'static str) {
 pub fn granite(state: &mut GameState) -> (f32, f32, &'static str) {
     let bpm = 128.0;
     state.add_event(GSEvent(62.0, Box::new(|accum: &mut UpdateAccumulator, _| {
-        accum.obst(GOLGrid::default()
+        let grid = GOLGrid::default()
             .dims(64, 36)
             .first_warning_time(2.0)
             .period(0.5)
             .max(64)
-            .populate(400)
-        );
+            .populate(400, accum.rng());
+        accum.obst(grid);
     })));
     state.add_event(GSEvent(64.0, Box::new(|accum: &mut UpdateAccumulator, _| {
         accum.bg(cmul(SKYBLUE, 0.1));
@@ -591,13 +604,14 @@ pub fn kocmoc(state: &mut GameState) -> (f32, f32, &'static str) {
         for i in 0..10 {
             let rad = i as f32 * 50.0;
             let sign = (i % 2) as f32 * 2.0 - 1.0;
-            let rot_off = gen_range(0.0, TAU);
+            let rot_off = accum.rng().range(0.0, TAU);
+            let rpb = accum.rng().range(0.75, 1.25) * sign;
             accum.obst(
                 SpinningArc::new()
                     .center(screen_center())
                     .inner_rad(rad + 600.0)
                     .outer_rad(rad + 640.0)
-                    .rpb(gen_range(0.75, 1.25) * sign)
+                    .rpb(rpb)
                     .left_angle(-PI)
                     .right_angle(FRAC_PI_2)
                     .show_time(32.0)
@@ -636,13 +650,14 @@ pub fn kocmoc(state: &mut GameState) -> (f32, f32, &'static str) {
         for i in 0..11 {
             let rad = i as f32 * 25.0;
             let sign = (i % 2) as f32 * 2.0 - 1.0;
-            let rot_off = gen_range(0.0, TAU);
+            let rot_off = accum.rng().range(0.0, TAU);
+            let rpb = accum.rng().range(0.75, 1.25) * sign;
             accum.obst(
                 SpinningArc::new()
                     .center(screen_center())
                     .inner_rad(rad + 600.0)
                     .outer_rad(rad + 620.0)
-                    .rpb(gen_range(0.75, 1.25) * sign)
+                    .rpb(rpb)
                     .left_angle(-PI)
                     .right_angle(FRAC_PI_2)
                     .show_time(32.0)
@@ -759,4 +774,17 @@ pub fn firestarter(state: &mut GameState) -> (f32, f32, &'static str) {
         accum.obst(SlamLaser::new(screen(0.5, -0.1), screen(0.5, 1.1), 200.0, 2.0, 2.0, 0.2, vec2(0.0, 0.0), 80.0));
     });
     (-1.978 * bpm / 60.0, bpm, "music/firestarter.mp3")
-}
\ No newline at end of file
+}
+/// Patterns picked a bar at a time for as long as the player survives, to the Inferno loop.
+pub fn endless(state: &mut GameState) -> (f32, f32, &'static str) {
+    let bpm = 170.0;
+    state.loop_music = true;
+    state.scoring = ScoringConfig::survival();
+    state.set_background(BackgroundKind::Grid.create(), None);
+    state.instantly(|accum: &mut UpdateAccumulator, _| {
+        accum.bg(cmul(RED, 0.1));
+        accum.fg(ORANGE);
+        accum.obstacle(Obst::new(Box::new(Endless::new(default_pool(), 4.0)), accum.time()).essential());
+    });
+    (0.0, bpm, "music/inferno.mp3")
+}
@@ -5,24 +5,58 @@
 #![allow(unused)]
 
 use core::time;
-use std::{sync::{Arc, Mutex}, error::Error};
+use std::{sync::{Arc, Mutex}, error::Error, path::Path};
 
-use macroquad::{window::next_frame, prelude::*, time::get_frame_time};
+use macroquad::{prelude::*, time::get_frame_time};
 use soloud::{Soloud, SoloudFlag, Backend, Wav, AudioExt, LoadExt};
 use strum::{IntoEnumIterator, EnumCount};
 
-use sound::Music;
+use sound::{Music, SfxCreator, SoundBank};
 use game::{GameState, LevelState};
 use state_control::{EparState, EparLevel};
-use utils::{screen_size, cmul};
+use input::Action;
+use calibration::Calibration;
+use results::ResultsOption;
+use save::{SaveData, level_key};
+use modifiers::PlayerSize;
+use chart_select::ChartSelect;
+use editor::Editor;
+use utils::{acmul, present, mouse_position, screen_width, screen_height, bench_glow, bench_arcs, compare_smooth_edges};
+use palette::PALETTE_NAMES;
 
 mod sound;
+mod input;
 mod utils;
 mod levels;
 mod game_objects;
 mod spawners;
 mod generators;
 mod game;
+mod chart;
+mod tempo;
+mod settings;
+mod calibration;
+mod beat;
+mod scoring;
+mod results;
+mod save;
+mod modifiers;
+mod chart_select;
+mod endless;
+mod patterns;
+mod background;
+mod palette;
+mod overlay;
+mod debug;
+mod timeline;
+mod editor;
+mod rewind;
+mod inspector;
+mod spatial;
+mod parallel;
+mod camera;
+mod particles;
+mod indicators;
 mod state_control;
 
 type AnyErr = Box<dyn Error>;
@@ -35,19 +69,72 @@ type CanErr = Possibly<()>;
 //#[inline]
 //fn vec2((x, y): (f32, f32)) -> Vec2 { Vec2::new(x, y) }
 
+/// The option after `current` in `options`, wrapping around. Starts over if `current` isn't one of them.
+fn next_in<T: Copy + PartialEq>(options: &[T], current: T) -> T {
+    let idx = options.iter().position(|&o| o == current).map_or(0, |i| (i + 1) % options.len());
+    options[idx]
+}
+
 #[macroquad::main("Exclusively Polygons Alonside Rhythms")]
 async fn main() -> CanErr {
     let start = 0.0;
     let speed = 1.0;
 
     request_new_screen_size(1600.0, 900.0);
-    next_frame().await;
+    present().await;
     let sl = Arc::new(Mutex::new(Soloud::new(SoloudFlag::empty(), Backend::Auto, 44100, 1024, 2)?));
-    //let sfx = SfxCreator::new(sl.clone());
+    let sfx = SfxCreator::new(sl.clone());
     let mut state = GameState::new(Music::new(sl.clone()));
+    state.sounds = SoundBank::load(sl.clone());
+    // closing the window is left to the loop, so the save gets written first
+    prevent_quit();
+    state.save = SaveData::load_or_default();
+    state.input.bindings = state.save.bindings.clone();
+    state.mus.set_audio_offset_ms(state.save.settings.audio_offset_ms);
+    // `--chart <path>` plays a chart file, `--dev` reloads it whenever it changes, `--seed <n>` fixes the randomness,
+    // `--practice` enables seeking around with the seek keys, `--mods "speed1.5 onehp"` picks difficulty modifiers,
+    // `--bench-glow` times glows against plain circles and `--bench-arcs` cached arcs against `draw_arc`, quitting after,
+    // `--compare-smooth-edges` renders shapes with and without soft edges to two images and quits
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.iter().any(|a| a == "--bench-glow") {
+        bench_glow(5000, 120).await;
+        return Ok(());
+    }
+    if args.iter().any(|a| a == "--bench-arcs") {
+        bench_arcs(50, 120).await;
+        return Ok(());
+    }
+    if args.iter().any(|a| a == "--compare-smooth-edges") {
+        compare_smooth_edges().await;
+        return Ok(());
+    }
+    state.hot_reload = args.iter().any(|a| a == "--dev");
+    state.practice = args.iter().any(|a| a == "--practice");
+    if let Some(mods) = args.iter().position(|a| a == "--mods").and_then(|i| args.get(i + 1)) {
+        match mods.parse() {
+            Ok(mods) => state.modifiers = mods,
+            Err(e) => println!("couldn't read the modifiers: {e}"),
+        }
+    }
+    state.seed = args.iter().position(|a| a == "--seed").and_then(|i| args.get(i + 1)).and_then(|s| s.parse().ok());
+    if let Some(path) = args.iter().position(|a| a == "--chart").and_then(|i| args.get(i + 1)) {
+        state.state = EparState::InGame(Box::new(LevelState::new()));
+        state.reset();
+        if let Err(e) = state.load_chart_file(path, start, speed) {
+            println!("couldn't load chart: {e}");
+            state.state = EparState::MainMenu;
+        }
+    }
     loop {
+        if is_quit_requested() {
+            state.save.persist();
+            break;
+        }
         match &mut state.state {
             EparState::MainMenu => {
+                let palette = state.save.settings.palette();
+                clear_background(palette.background);
+                // a dev key, like the setting toggles below it isn't bound, see `Bindings`
                 let show_unfinished = is_key_down(KeyCode::U);
                 let lvls = EparLevel::iter().filter(move |lvl| show_unfinished || lvl.finished()).collect::<Vec<_>>();
                 let length = lvls.len();
@@ -65,28 +152,117 @@ async fn main() -> CanErr {
     
                     let color;
                     if r.contains(mouse_pos){
-                        color = cmul(WHITE, 0.3);
+                        color = acmul(palette.text, 0.3);
                         if is_mouse_button_pressed(MouseButton::Left) {
                             macroquad::rand::srand((get_time() * 1_000_000.0) as u64);
-                            state.state = EparState::InGame(LevelState::new());
+                            state.state = EparState::InGame(Box::new(LevelState::new()));
                             state.reset();
                             state.load_level(lvl, start, speed);
                             break 'elit;
                         }
                     } else {
-                        color = cmul(WHITE, 0.1);
+                        color = acmul(palette.text, 0.1);
                     }
                     draw_rectangle(x_offset - rsize.x / 2.0, y_offset - rsize.y / 2.0, rsize.x, rsize.y, color);
                     let fsize = 40;
-                    let txt = &format!("{lvl}");
+                    let txt = &match state.save.best(&level_key(lvl)) {
+                        Some(best) => format!("{lvl}  -  best {} ({})", best.score, best.grade),
+                        None => format!("{lvl}"),
+                    };
                     let dims = measure_text(txt, None, fsize, 1.0);
-                    draw_text(txt, x_offset - dims.width / 2.0, y_offset + dims.offset_y / 2.0, fsize as f32, if lvl.finished() { WHITE } else { RED });
+                    draw_text(txt, x_offset - dims.width / 2.0, y_offset + dims.offset_y / 2.0, fsize as f32, if lvl.finished() { palette.text } else { palette.warning });
+                }
+                draw_text(&format!("C: calibrate audio offset ({:.0} ms)", state.save.settings.audio_offset_ms), 20.0, screen_height() - 20.0, 24.0, acmul(palette.text, 0.6));
+                let mods = &mut state.modifiers;
+                if is_key_pressed(KeyCode::Key1) { mods.speed_rate = next_in(&[1.0, 1.25, 1.5, 0.75], mods.speed_rate); }
+                if is_key_pressed(KeyCode::Key2) { mods.density = next_in(&[1.0, 1.5, 2.0, 0.5], mods.density); }
+                if is_key_pressed(KeyCode::Key3) { mods.player_size = next_in(&[PlayerSize::Normal, PlayerSize::Tiny, PlayerSize::Big], mods.player_size); }
+                if is_key_pressed(KeyCode::Key4) { mods.no_focus = !mods.no_focus; }
+                if is_key_pressed(KeyCode::Key5) { mods.one_hp = !mods.one_hp; }
+                let mods_text = format!("1-5: modifiers {} (score x{:.2})", mods, mods.score_multiplier());
+                let width = measure_text(&mods_text, None, 24, 1.0).width;
+                draw_text(&mods_text, screen_width() - width - 20.0, screen_height() - 20.0, 24.0, acmul(palette.text, 0.6));
+                draw_text("Tab: charts", 20.0, screen_height() - 48.0, 24.0, acmul(palette.text, 0.6));
+                let palette_text = format!("P: palette {}", state.save.settings.palette);
+                let width = measure_text(&palette_text, None, 24, 1.0).width;
+                draw_text(&palette_text, screen_width() - width - 20.0, screen_height() - 48.0, 24.0, acmul(palette.text, 0.6));
+                let flashing_text = format!("F: reduce flashing {}", if state.save.settings.reduce_flashing { "on" } else { "off" });
+                let width = measure_text(&flashing_text, None, 24, 1.0).width;
+                draw_text(&flashing_text, screen_width() - width - 20.0, screen_height() - 76.0, 24.0, acmul(palette.text, 0.6));
+                if is_key_pressed(KeyCode::P) {
+                    state.save.settings.palette = next_in(&PALETTE_NAMES, state.save.settings.palette.as_str()).to_string();
+                    state.save.persist();
+                }
+                let hitstop_text = format!("H: hit-stop {}", if state.save.settings.hitstop { "on" } else { "off" });
+                let width = measure_text(&hitstop_text, None, 24, 1.0).width;
+                draw_text(&hitstop_text, screen_width() - width - 20.0, screen_height() - 104.0, 24.0, acmul(palette.text, 0.6));
+                let rewind_text = format!("R: rewind on death {}", if state.save.settings.rewind { "on" } else { "off" });
+                let width = measure_text(&rewind_text, None, 24, 1.0).width;
+                draw_text(&rewind_text, screen_width() - width - 20.0, screen_height() - 132.0, 24.0, acmul(palette.text, 0.6));
+                let sfx_text = format!("V: sound effects {:.0}%", state.save.settings.sfx_volume * 100.0);
+                let width = measure_text(&sfx_text, None, 24, 1.0).width;
+                draw_text(&sfx_text, screen_width() - width - 20.0, screen_height() - 160.0, 24.0, acmul(palette.text, 0.6));
+                let rainbow_text = format!("B: rainbow combo {}", if state.save.settings.rainbow_combo { "on" } else { "off" });
+                let width = measure_text(&rainbow_text, None, 24, 1.0).width;
+                draw_text(&rainbow_text, screen_width() - width - 20.0, screen_height() - 188.0, 24.0, acmul(palette.text, 0.6));
+                let shake_text = format!("K: screen shake {:.0}%", state.save.settings.shake_intensity * 100.0);
+                let width = measure_text(&shake_text, None, 24, 1.0).width;
+                draw_text(&shake_text, screen_width() - width - 20.0, screen_height() - 216.0, 24.0, acmul(palette.text, 0.6));
+                let glow_text = format!("G: glow {}", if state.save.settings.glow { "on" } else { "off" });
+                let width = measure_text(&glow_text, None, 24, 1.0).width;
+                draw_text(&glow_text, screen_width() - width - 20.0, screen_height() - 244.0, 24.0, acmul(palette.text, 0.6));
+                let smooth_text = format!("S: smooth edges {}", if state.save.settings.smooth_edges { "on" } else { "off" });
+                let width = measure_text(&smooth_text, None, 24, 1.0).width;
+                draw_text(&smooth_text, screen_width() - width - 20.0, screen_height() - 272.0, 24.0, acmul(palette.text, 0.6));
+                if is_key_pressed(KeyCode::S) {
+                    state.save.settings.smooth_edges = !state.save.settings.smooth_edges;
+                    state.save.persist();
+                }
+                if is_key_pressed(KeyCode::G) {
+                    state.save.settings.glow = !state.save.settings.glow;
+                    state.save.persist();
+                }
+                if is_key_pressed(KeyCode::K) {
+                    state.save.settings.shake_intensity = next_in(&[1.0, 0.5, 0.0, 1.5], state.save.settings.shake_intensity);
+                    state.save.persist();
+                }
+                if is_key_pressed(KeyCode::B) {
+                    state.save.settings.rainbow_combo = !state.save.settings.rainbow_combo;
+                    state.save.persist();
+                }
+                if is_key_pressed(KeyCode::V) {
+                    state.save.settings.sfx_volume = next_in(&[0.7, 1.0, 0.0, 0.4], state.save.settings.sfx_volume);
+                    state.save.persist();
+                }
+                if is_key_pressed(KeyCode::R) {
+                    state.save.settings.rewind = !state.save.settings.rewind;
+                    state.save.persist();
                 }
-                next_frame().await;
+                if is_key_pressed(KeyCode::H) {
+                    state.save.settings.hitstop = !state.save.settings.hitstop;
+                    state.save.persist();
+                }
+                if is_key_pressed(KeyCode::F) {
+                    state.save.settings.reduce_flashing = !state.save.settings.reduce_flashing;
+                    state.save.persist();
+                }
+                if is_key_pressed(KeyCode::C) {
+                    state.state = EparState::Calibrating(Calibration::new());
+                } else if is_key_pressed(KeyCode::Tab) {
+                    state.state = EparState::ChartSelect(ChartSelect::open(state.save.settings.last_chart.as_deref()));
+                }
+                present().await;
             }
-            EparState::InGame(ls) => {
-                ls.player.pos = vec2(0.125, 0.5) * screen_size();
+            EparState::InGame(_) => {
                 while state.mus.is_playing() {
+                    // dropping the run, it's not finished
+                    if is_quit_requested() {
+                        state.exit();
+                        break;
+                    }
+                    // dev keys, not bound
+                    if is_key_pressed(KeyCode::F3) { state.debug.toggle(); }
+                    if state.debug.enabled && is_key_pressed(KeyCode::F4) { state.debug.toggle_hitboxes(); }
                     state.mus.check();
                     if let Some(f) = state.mus.current_beat() {
                         let ft = get_frame_time();
@@ -94,9 +270,101 @@ async fn main() -> CanErr {
                         //println!("{f:.2}");
                     }
                     state.draw();
-                    next_frame().await;
+                    present().await;
+                }
+                // the song ran out with the run still going
+                if let EparState::InGame(_) = state.state {
+                    state.finish(true);
+                }
+            }
+            EparState::Results(results) => {
+                state.input.update();
+                state.coop_input.update();
+                let pressed = |action| state.input.is_pressed(action) || state.coop_input.is_pressed(action);
+                if pressed(Action::MoveLeft) || pressed(Action::MoveUp) { results.selected = results.selected.step(-1); }
+                if pressed(Action::MoveRight) || pressed(Action::MoveDown) { results.selected = results.selected.step(1); }
+                // the first press only skips the count-up
+                let chosen = if pressed(Action::Pause) {
+                    Some(ResultsOption::Back)
+                } else if pressed(Action::Dash) && results.skip_count_up() {
+                    Some(results.selected)
+                } else {
+                    None
+                };
+                results.draw(&state.save.settings.palette());
+                match chosen {
+                    Some(ResultsOption::Retry) => if let Err(e) = state.restart() {
+                        println!("couldn't restart: {e}");
+                        state.state = EparState::MainMenu;
+                    },
+                    Some(ResultsOption::Back) => state.state = match state.current_chart {
+                        Some(_) => EparState::ChartSelect(ChartSelect::open(state.save.settings.last_chart.as_deref())),
+                        None => EparState::MainMenu,
+                    },
+                    None => {}
+                }
+                present().await;
+            }
+            EparState::ChartSelect(select) => {
+                state.input.update();
+                state.coop_input.update();
+                let pressed = |action| state.input.is_pressed(action) || state.coop_input.is_pressed(action);
+                if pressed(Action::MoveUp) { select.step(-1); }
+                if pressed(Action::MoveDown) { select.step(1); }
+                let back = pressed(Action::Pause);
+                let chosen = if pressed(Action::Dash) { select.chosen().map(Path::to_path_buf) } else { None };
+                // the editor's key, not bound
+                let edit = if is_key_pressed(KeyCode::E) { select.selected_path().map(Path::to_path_buf) } else { None };
+                select.draw(&state.save, &state.save.settings.palette());
+                if back {
+                    state.state = EparState::MainMenu;
+                } else if let Some(path) = edit {
+                    match Editor::open(&path) {
+                        Ok(editor) => state.state = EparState::Editing(Box::new(editor)),
+                        Err(e) => println!("couldn't open {} for editing: {e}", path.display()),
+                    }
+                } else if let Some(path) = chosen {
+                    state.save.settings.last_chart = Some(path.display().to_string());
+                    state.save.persist();
+                    state.state = EparState::InGame(Box::new(LevelState::new()));
+                    state.reset();
+                    if let Err(e) = state.load_chart_file(&path, start, speed) {
+                        println!("couldn't load chart: {e}");
+                        state.state = EparState::ChartSelect(ChartSelect::open(state.save.settings.last_chart.as_deref()));
+                    }
+                }
+                present().await;
+            }
+            EparState::Calibrating(calibration) => {
+                state.input.update();
+                let cancelled = state.input.is_pressed(Action::Pause);
+                let result = calibration.update(&sfx, state.input.is_pressed(Action::Dash));
+                calibration.draw(&state.save.settings.palette());
+                match result {
+                    Some(Ok(ms)) => {
+                        state.save.settings.audio_offset_ms = ms;
+                        state.mus.set_audio_offset_ms(ms);
+                        state.save.persist();
+                        state.state = EparState::MainMenu;
+                    }
+                    Some(Err(e)) => {
+                        println!("calibration failed: {e}");
+                        state.state = EparState::MainMenu;
+                    }
+                    None if cancelled => state.state = EparState::MainMenu,
+                    None => {}
+                }
+                present().await;
+            }
+            EparState::Editing(editor) => {
+                let leave = editor.update();
+                editor.draw(&state.save.settings.palette());
+                if leave {
+                    // rescanned, so saved edits show in the list
+                    let path = editor.path.display().to_string();
+                    state.state = EparState::ChartSelect(ChartSelect::open(Some(&path)));
                 }
-                state.state = EparState::MainMenu;
+                present().await;
             }
         }
     }
@@ -0,0 +1,95 @@
+use std::{fmt::Display, str::FromStr};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerSize {
+    #[default]
+    Normal,
+    Tiny,
+    Big,
+}
+impl PlayerSize {
+    /// Multiplier of the player's radius
+    pub fn scale(self) -> f32 {
+        match self {
+            PlayerSize::Normal => 1.0,
+            PlayerSize::Tiny => 0.6,
+            PlayerSize::Big => 1.5,
+        }
+    }
+}
+
+/// Difficulty modifiers picked before a run. Each makes the run easier or harder and scales the score to match.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Modifiers {
+    /// Multiplies the tempo, the music and obstacle timing together
+    pub speed_rate: f32,
+    /// Steps of `Periodic`s that happen: below 1 skips some, above 1 adds extra ones between them
+    pub density: f32,
+    pub player_size: PlayerSize,
+    pub no_focus: bool,
+    /// Every player has a single hit point
+    pub one_hp: bool,
+}
+impl Default for Modifiers {
+    fn default() -> Self {
+        Modifiers { speed_rate: 1.0, density: 1.0, player_size: PlayerSize::Normal, no_focus: false, one_hp: false }
+    }
+}
+impl Modifiers {
+    /// The product of every modifier's multiplier.
+    pub fn score_multiplier(&self) -> f32 {
+        let size = match self.player_size {
+            PlayerSize::Normal => 1.0,
+            PlayerSize::Tiny => 0.8,
+            PlayerSize::Big => 1.3,
+        };
+        self.speed_rate * self.density.sqrt() * size
+            * if self.no_focus { 1.2 } else { 1.0 }
+            * if self.one_hp { 1.5 } else { 1.0 }
+    }
+    /// How many times step `step` of a `Periodic` runs. Spreads the extra and skipped steps evenly, without randomness.
+    pub fn density_copies(&self, step: usize) -> usize {
+        let d = self.density.max(0.0);
+        ((step + 1) as f32 * d).floor() as usize - (step as f32 * d).floor() as usize
+    }
+}
+/// Space-separated, e.g. `speed1.5 density0.5 tiny nofocus onehp`, or `none`.
+impl Display for Modifiers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = vec![];
+        if self.speed_rate != 1.0 { parts.push(format!("speed{}", self.speed_rate)); }
+        if self.density != 1.0 { parts.push(format!("density{}", self.density)); }
+        match self.player_size {
+            PlayerSize::Normal => {}
+            PlayerSize::Tiny => parts.push("tiny".to_string()),
+            PlayerSize::Big => parts.push("big".to_string()),
+        }
+        if self.no_focus { parts.push("nofocus".to_string()); }
+        if self.one_hp { parts.push("onehp".to_string()); }
+        if parts.is_empty() { write!(f, "none") } else { write!(f, "{}", parts.join(" ")) }
+    }
+}
+impl FromStr for Modifiers {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mods = Self::default();
+        for word in s.split_whitespace() {
+            let number = |prefix: &str| word.strip_prefix(prefix).and_then(|n| n.parse::<f32>().ok()).filter(|n| *n > 0.0);
+            match word {
+                "none" => {}
+                "tiny" => mods.player_size = PlayerSize::Tiny,
+                "big" => mods.player_size = PlayerSize::Big,
+                "nofocus" => mods.no_focus = true,
+                "onehp" => mods.one_hp = true,
+                _ => if let Some(rate) = number("speed") {
+                    mods.speed_rate = rate;
+                } else if let Some(density) = number("density") {
+                    mods.density = density;
+                } else {
+                    return Err(format!("unknown modifier `{word}`"));
+                }
+            }
+        }
+        Ok(mods)
+    }
+}
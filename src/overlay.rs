@@ -0,0 +1,67 @@
+//! Full-screen flashes and fades, drawn over the obstacles and under the HUD.
+
+use macroquad::prelude::{Color, WHITE};
+
+/// Most a flash, or flashes stacked up, can cover the screen with
+pub const MAX_FLASH_ALPHA: f32 = 0.6;
+/// What every flash is scaled by with reduced flashing on
+pub const REDUCED_FLASH_SCALE: f32 = 0.25;
+/// Beats the flashes of slam lasers and bombs fade over
+pub const IMPACT_FLASH_BEATS: f32 = 0.5;
+
+/// A color over the whole screen, fading out over `decay` beats from `start`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Flash {
+    pub color: Color,
+    /// Opacity at the start, from 0 to 1
+    pub intensity: f32,
+    pub start: f32,
+    pub decay: f32,
+}
+impl Flash {
+    pub fn new(color: Color, intensity: f32, start: f32, decay: f32) -> Self {
+        Flash { color, intensity: intensity.clamp(0.0, 1.0), start, decay }
+    }
+    /// A small white flash, for impacts.
+    pub fn impact(intensity: f32, start: f32) -> Self {
+        Self::new(WHITE, intensity, start, IMPACT_FLASH_BEATS)
+    }
+    pub fn alpha(&self, time: f32) -> f32 {
+        if self.decay <= 0.0 { return 0.0; }
+        self.intensity * (1.0 - (time - self.start) / self.decay).clamp(0.0, 1.0)
+    }
+    pub fn done(&self, time: f32) -> bool {
+        time >= self.start + self.decay
+    }
+}
+
+/// The screen easing to or from black, holding at `to` once done.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Fade {
+    pub from: f32,
+    pub to: f32,
+    pub start: f32,
+    pub beats: f32,
+}
+impl Fade {
+    pub fn alpha(&self, time: f32) -> f32 {
+        if self.beats <= 0.0 { return self.to; }
+        let t = ((time - self.start) / self.beats).clamp(0.0, 1.0);
+        self.from + (self.to - self.from) * t
+    }
+}
+
+/// Every flash at `time` stacked up, scaled down to fit under `MAX_FLASH_ALPHA`: (color, alpha).
+pub fn composite(flashes: &[Flash], time: f32) -> Option<(Color, f32)> {
+    let total = flashes.iter().map(|f| f.alpha(time)).sum::<f32>();
+    if total <= 0.0 { return None; }
+    // the color is each flash's weighted by how much it shows
+    let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+    for flash in flashes {
+        let weight = flash.alpha(time) / total;
+        r += flash.color.r * weight;
+        g += flash.color.g * weight;
+        b += flash.color.b * weight;
+    }
+    Some((Color::new(r, g, b, 1.0), total.min(MAX_FLASH_ALPHA)))
+}
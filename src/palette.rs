@@ -0,0 +1,114 @@
+use macroquad::prelude::Color;
+
+use crate::utils::mix;
+
+/// Names of the built-in palettes, in the order the menu cycles through them
+pub const PALETTE_NAMES: [&str; 5] = ["default", "high_contrast", "deuteranopia", "dark", "light"];
+
+/// The colors everything is drawn in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub background: Color,
+    /// What obstacles are drawn in. Warnings are this at a lower alpha.
+    pub obstacle: Color,
+    /// Hits, errors and anything else that's bad news
+    pub warning: Color,
+    /// The first player, the others keep their co-op colors
+    pub player: Color,
+    /// Highlights like the combo and bests
+    pub accent: Color,
+    pub text: Color,
+    /// Whether a level's own colors replace `obstacle` and `background`.\
+    /// Off for the palettes made to be readable, so they apply everywhere.
+    pub level_colors: bool,
+}
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            background: Color::new(0.0, 0.0, 0.0, 1.0),
+            obstacle: Color::new(1.0, 0.0, 0.5, 1.0),
+            warning: Color::new(1.0, 0.2, 0.2, 1.0),
+            player: Color::new(1.0, 0.5, 0.8, 1.0),
+            accent: Color::new(1.0, 0.85, 0.3, 1.0),
+            text: Color::new(1.0, 1.0, 1.0, 1.0),
+            level_colors: true,
+        }
+    }
+}
+impl Palette {
+    /// A built-in palette from `PALETTE_NAMES`.
+    pub fn named(name: &str) -> Option<Self> {
+        Some(match name {
+            "default" => Self::default(),
+            "high_contrast" => Palette {
+                background: Color::new(0.0, 0.0, 0.0, 1.0),
+                obstacle: Color::new(1.0, 1.0, 1.0, 1.0),
+                warning: Color::new(1.0, 0.1, 0.1, 1.0),
+                player: Color::new(1.0, 1.0, 0.0, 1.0),
+                accent: Color::new(0.0, 1.0, 1.0, 1.0),
+                text: Color::new(1.0, 1.0, 1.0, 1.0),
+                level_colors: false,
+            },
+            // blue and orange, which stay apart without telling red from green
+            "deuteranopia" => Palette {
+                background: Color::new(0.04, 0.05, 0.1, 1.0),
+                obstacle: Color::new(0.95, 0.55, 0.0, 1.0),
+                warning: Color::new(1.0, 0.85, 0.25, 1.0),
+                player: Color::new(0.35, 0.7, 1.0, 1.0),
+                accent: Color::new(0.8, 0.9, 1.0, 1.0),
+                text: Color::new(1.0, 1.0, 1.0, 1.0),
+                level_colors: false,
+            },
+            "dark" => Palette {
+                background: Color::new(0.06, 0.06, 0.08, 1.0),
+                obstacle: Color::new(0.8, 0.3, 0.5, 1.0),
+                warning: Color::new(0.9, 0.3, 0.3, 1.0),
+                player: Color::new(0.9, 0.6, 0.8, 1.0),
+                accent: Color::new(0.9, 0.75, 0.35, 1.0),
+                text: Color::new(0.85, 0.85, 0.85, 1.0),
+                level_colors: true,
+            },
+            "light" => Palette {
+                background: Color::new(0.95, 0.94, 0.9, 1.0),
+                obstacle: Color::new(0.8, 0.0, 0.35, 1.0),
+                warning: Color::new(0.85, 0.1, 0.1, 1.0),
+                player: Color::new(0.2, 0.3, 0.85, 1.0),
+                accent: Color::new(0.7, 0.45, 0.0, 1.0),
+                text: Color::new(0.1, 0.1, 0.1, 1.0),
+                level_colors: false,
+            },
+            _ => return None,
+        })
+    }
+    /// Every color `t` of the way from `a` to `b`. `level_colors` switches halfway.
+    pub fn lerp(a: &Palette, b: &Palette, t: f32) -> Palette {
+        let t = t.clamp(0.0, 1.0);
+        Palette {
+            background: mix(a.background, b.background, t),
+            obstacle: mix(a.obstacle, b.obstacle, t),
+            warning: mix(a.warning, b.warning, t),
+            player: mix(a.player, b.player, t),
+            accent: mix(a.accent, b.accent, t),
+            text: mix(a.text, b.text, t),
+            level_colors: if t < 0.5 { a.level_colors } else { b.level_colors },
+        }
+    }
+}
+
+/// A palette easing into another over `beats` from `start`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaletteShift {
+    pub from: Palette,
+    pub to: Palette,
+    pub start: f32,
+    pub beats: f32,
+}
+impl PaletteShift {
+    pub fn at(&self, time: f32) -> Palette {
+        if self.beats <= 0.0 { return self.to; }
+        Palette::lerp(&self.from, &self.to, (time - self.start) / self.beats)
+    }
+    pub fn done(&self, time: f32) -> bool {
+        time >= self.start + self.beats
+    }
+}
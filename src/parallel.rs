@@ -0,0 +1,173 @@
+//! Updating `Mover`s on several threads at once, for levels with thousands of them.\
+//! What they do to the game is logged by obstacle and replayed afterwards in obstacle order, so it comes out the same as updating them one by one.
+
+use macroquad::prelude::Vec2;
+
+use crate::{game_objects::{Obst, Mover, Effects}, sound::SoundId};
+
+/// Fewest movers worth starting threads for, below that they're updated in order with everything else
+pub const PARALLEL_MIN_MOVERS: usize = 4096;
+
+/// One of the `Effects`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Effect {
+    Jerk(Vec2),
+    Shake(f32),
+    ShakeDirectional(Vec2),
+    ImpactFlash(f32),
+    Hitstop(f32),
+    /// (sound, volume, pitch)
+    Sound(SoundId, f32, f32),
+}
+impl Effect {
+    pub fn apply(self, to: &mut dyn Effects) {
+        match self {
+            Effect::Jerk(jerk) => to.jerk(jerk),
+            Effect::Shake(shake) => to.shake(shake),
+            Effect::ShakeDirectional(shake) => to.shake_directional(shake),
+            Effect::ImpactFlash(intensity) => to.impact_flash(intensity),
+            Effect::Hitstop(secs) => to.hitstop(secs),
+            Effect::Sound(id, volume, pitch) => to.sound(id, volume, pitch),
+        }
+    }
+}
+
+/// (obstacle index, effect) of what movers had, in the order they had them.
+#[derive(Default)]
+struct EffectLog {
+    /// Index of the obstacle being stepped
+    current: usize,
+    effects: Vec<(usize, Effect)>,
+}
+impl Effects for EffectLog {
+    fn jerk(&mut self, jerk: Vec2) { self.effects.push((self.current, Effect::Jerk(jerk))) }
+    fn shake(&mut self, shake: f32) { self.effects.push((self.current, Effect::Shake(shake))) }
+    fn shake_directional(&mut self, shake: Vec2) { self.effects.push((self.current, Effect::ShakeDirectional(shake))) }
+    fn impact_flash(&mut self, intensity: f32) { self.effects.push((self.current, Effect::ImpactFlash(intensity))) }
+    fn hitstop(&mut self, secs: f32) { self.effects.push((self.current, Effect::Hitstop(secs))) }
+    fn sound(&mut self, id: SoundId, volume: f32, pitch: f32) { self.effects.push((self.current, Effect::Sound(id, volume, pitch))) }
+}
+
+/// Steps every mover in `obsts` as of `time`, spread over the available threads.\
+/// Returns whether it did, there being too few or no threads to spare otherwise, along with their effects in obstacle order.
+pub fn update_movers(obsts: &mut [Obst], time: f32, beat_dt: f32) -> Option<Vec<(usize, Effect)>> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    // tests go through the threads even on one core, so that's what they check
+    let threads = if cfg!(test) { threads.max(4) } else { threads };
+    if cfg!(target_arch = "wasm32") || threads < 2 { return None; }
+    // the obstacles themselves can't go to other threads, only the movers in them
+    let mut movers = obsts.iter_mut().enumerate()
+        .filter(|(_, obst)| !obst.frozen)
+        .filter_map(|(i, obst)| {
+            let (t, dt) = (obst.age(time), obst.frame_beats(beat_dt));
+            obst.obstacle.parallel().map(|mover| (i, t, dt, mover))
+        })
+        .collect::<Vec<(usize, f32, f32, &mut dyn Mover)>>();
+    if movers.len() < PARALLEL_MIN_MOVERS { return None; }
+    let step = |chunk: &mut [(usize, f32, f32, &mut dyn Mover)]| {
+        let mut log = EffectLog::default();
+        for (i, t, dt, mover) in chunk {
+            log.current = *i;
+            mover.step(&mut log, *dt, *t, *dt, *t);
+        }
+        log.effects
+    };
+    let per_thread = movers.len().div_ceil(threads);
+    let mut chunks = movers.chunks_mut(per_thread);
+    let first = chunks.next()?;
+    Some(std::thread::scope(|scope| {
+        let others = chunks.map(|chunk| scope.spawn(move || step(chunk))).collect::<Vec<_>>();
+        // this thread takes the first chunk instead of waiting
+        let mut effects = step(first);
+        for other in others {
+            effects.extend(other.join().expect("a mover panicked"));
+        }
+        effects
+    }))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use macroquad::prelude::vec2;
+
+    use super::*;
+    use crate::{game_objects::{Obstacle, Pellet, GrowLaser, SlamLaser, RotatingRect, SpinningArc}, utils::{GameRng, screen_size, best_of}};
+
+    /// `n` movers of every kind, the lasers firing (and jerking, shaking and playing sounds) at all different times.
+    pub fn mixed_movers(n: usize, seed: u64) -> Vec<Obst> {
+        let mut rng = GameRng::new(seed);
+        (0..n).map(|i| {
+            let (a, b) = (rng.vec(Vec2::ZERO, screen_size()), rng.vec(Vec2::ZERO, screen_size()));
+            let warning = rng.range(0.0, 2.0);
+            let obstacle: Box<dyn Obstacle> = match i % 8 {
+                0 => Box::new(GrowLaser::new(a, b, 20.0, warning, 2.0, rng.vec(vec2(-5.0, -5.0), vec2(5.0, 5.0)))),
+                1 => Box::new(SlamLaser::new(a, b, 20.0, warning, 2.0, 0.2, rng.vec(vec2(-5.0, -5.0), vec2(5.0, 5.0)), rng.range(0.0, 10.0))),
+                2 => Box::new(RotatingRect::default().center(a).size(vec2(200.0, 20.0)).rpb(rng.range(-0.5, 0.5)).warning_time(warning).show_time(2.0)),
+                3 => Box::new(SpinningArc::new().center(a).inner_rad(50.0).outer_rad(80.0).rpb(rng.range(-0.5, 0.5)).show_time(2.0)),
+                _ => Box::new(Pellet::new(a, rng.vec(vec2(-100.0, -100.0), vec2(100.0, 100.0)), rng.range(2.0, 20.0))),
+            };
+            Obst::new(obstacle, rng.range(-1.0, 0.0))
+        }).collect()
+    }
+
+    /// Everything that tells two obstacles apart.
+    fn states(obsts: &[Obst]) -> Vec<Vec<(String, String)>> {
+        obsts.iter().map(|o| o.obstacle.debug_fields()).collect()
+    }
+
+    /// Steps the movers of `obsts` one by one, logging what they did like `update_movers` does.
+    fn update_serially(obsts: &mut [Obst], time: f32, beat_dt: f32) -> Vec<(usize, Effect)> {
+        let mut log = EffectLog::default();
+        for (i, obst) in obsts.iter_mut().enumerate().filter(|(_, o)| !o.frozen) {
+            let t = obst.age(time);
+            if let Some(mover) = obst.obstacle.parallel() {
+                log.current = i;
+                mover.step(&mut log, beat_dt, t, beat_dt, t);
+            }
+        }
+        log.effects
+    }
+
+    #[test]
+    fn threads_do_what_one_by_one_does() {
+        let mut serial = mixed_movers(PARALLEL_MIN_MOVERS * 2, 945);
+        let mut parallel = serial.clone();
+        let mut fired = false;
+        // frames long enough that every laser fires on one of them
+        for frame in 0..40 {
+            let time = frame as f32 * 0.1;
+            let expected = update_serially(&mut serial, time, 0.1);
+            let effects = update_movers(&mut parallel, time, 0.1).expect("not stepped on threads");
+            assert_eq!(effects, expected, "frame {frame}");
+            assert!(states(&parallel) == states(&serial), "frame {frame}");
+            fired |= !effects.is_empty();
+        }
+        assert!(fired, "no laser fired");
+    }
+
+    #[test]
+    fn too_few_movers_are_left_alone() {
+        let mut obsts = mixed_movers(PARALLEL_MIN_MOVERS - 1, 9450);
+        let before = states(&obsts);
+        assert!(update_movers(&mut obsts, 1.0, 0.1).is_none());
+        assert!(states(&obsts) == before);
+    }
+
+    #[test]
+    fn threads_pay_for_themselves() {
+        let mut serial = mixed_movers(10_000, 10_000);
+        let mut parallel = serial.clone();
+        let mut frame = 0;
+        let serial_secs = best_of(|| { update_serially(&mut serial, frame as f32 / 60.0, 1.0 / 60.0); frame += 1; });
+        let mut frame = 0;
+        let parallel_secs = best_of(|| { update_movers(&mut parallel, frame as f32 / 60.0, 1.0 / 60.0); frame += 1; });
+        assert!(states(&serial) == states(&parallel));
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        if cores >= 2 {
+            assert!(parallel_secs < serial_secs, "{parallel_secs}s on {cores} cores, {serial_secs}s in order");
+        } else {
+            // with one core the game never starts them, but what they cost taking turns on it is still well under a frame
+            assert!(parallel_secs < serial_secs + 0.004, "{parallel_secs}s on threads, {serial_secs}s in order");
+        }
+    }
+}
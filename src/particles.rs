@@ -0,0 +1,221 @@
+//! Sparks, dust and debris: little things that fly off and fade, kept apart from the obstacles so they're never collided with.\
+//! They live in a pool made once at the start of a level, the oldest making way for new ones once it's full.
+
+use std::{collections::VecDeque, ops::Range};
+
+use macroquad::prelude::{Vec2, Color, vec2};
+
+use crate::utils::{CircleBatch, GameRng, acmul, rotate};
+
+/// Most particles alive at once
+pub const MAX_PARTICLES: usize = 2048;
+/// Pixels across the particles the spawn helpers make
+pub const PARTICLE_SIZE: f32 = 3.0;
+/// Share of a particle's speed lost per beat
+const PARTICLE_DRAG: f32 = 0.9;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    pub pos: Vec2,
+    /// Pixels per beat
+    pub vel: Vec2,
+    /// Beats since it was spawned
+    pub age: f32,
+    /// Beats it lasts
+    pub life: f32,
+    /// Radius in pixels
+    pub size: f32,
+    pub color: Color,
+    /// Whether it fades out over its life
+    pub fade: bool,
+    /// Whether it shrinks away over its life
+    pub shrink: bool,
+}
+impl Particle {
+    pub fn new(pos: Vec2, vel: Vec2, life: f32, color: Color) -> Self {
+        Particle { pos, vel, age: 0.0, life, size: PARTICLE_SIZE, color, fade: true, shrink: true }
+    }
+    fn alive(&self) -> bool {
+        self.age < self.life
+    }
+}
+
+/// Every particle of a level, oldest first. Empty by default, without room for any, so it can be taken out of the level for free.
+#[derive(Default)]
+pub struct ParticleSystem {
+    particles: VecDeque<Particle>,
+    capacity: usize,
+    /// Particles only show, so they have their own randomness and leave the run's alone
+    rng: GameRng,
+    /// Beats the last update covered, for `trail`
+    frame_beats: f32,
+}
+impl ParticleSystem {
+    /// Room for `capacity` particles, made now so spawning never has to.
+    pub fn new(capacity: usize, seed: u64) -> Self {
+        ParticleSystem { particles: VecDeque::with_capacity(capacity), capacity, rng: GameRng::new(seed), frame_beats: 0.0 }
+    }
+    /// Adds a particle, pushing out the oldest if there's no room.
+    pub fn spawn(&mut self, particle: Particle) {
+        if self.capacity == 0 { return; }
+        if self.particles.len() >= self.capacity { self.particles.pop_front(); }
+        self.particles.push_back(particle);
+    }
+    /// `count` particles flying out every which way.
+    pub fn burst(&mut self, pos: Vec2, count: usize, speed: Range<f32>, life: Range<f32>, color: Color) {
+        self.directional(pos, Vec2::Y, std::f32::consts::PI, count, speed, life, color);
+    }
+    /// `count` particles flying out along `dir`, up to `spread` radians to either side of it.
+    #[allow(clippy::too_many_arguments)]
+    pub fn directional(&mut self, pos: Vec2, dir: Vec2, spread: f32, count: usize, speed: Range<f32>, life: Range<f32>, color: Color) {
+        let dir = dir.try_normalize().unwrap_or(Vec2::Y);
+        for _ in 0..count {
+            let angle = self.rng.range(-spread, spread);
+            let vel = rotate(dir, angle) * self.rng.range(speed.start, speed.end);
+            let life = self.rng.range(life.start, life.end);
+            self.spawn(Particle::new(pos, vel, life, color));
+        }
+    }
+    /// Leaves about `rate` particles a beat behind something at `pos` going `vel`, for calling every frame.
+    pub fn trail(&mut self, pos: Vec2, vel: Vec2, rate: f32, color: Color) {
+        let due = rate * self.frame_beats;
+        // the fraction of one is left to chance, so slow trails still leave some
+        let count = due as usize + self.rng.chance(due.fract().clamp(0.0, 1.0) as f64) as usize;
+        for _ in 0..count {
+            let jitter = vec2(self.rng.range(-1.0, 1.0), self.rng.range(-1.0, 1.0)) * PARTICLE_SIZE;
+            let life = self.rng.range(0.25, 0.5);
+            self.spawn(Particle::new(pos + jitter, vel * -0.1, life, color));
+        }
+    }
+    /// Moves everything along by `beat_dt` beats, dropping what's run its course.
+    pub fn update(&mut self, beat_dt: f32) {
+        self.frame_beats = beat_dt;
+        let drag = (1.0 - PARTICLE_DRAG).powf(beat_dt);
+        for particle in &mut self.particles {
+            particle.age += beat_dt;
+            particle.pos += particle.vel * beat_dt;
+            particle.vel *= drag;
+        }
+        self.particles.retain(Particle::alive);
+    }
+    pub fn draw(&self, offset: Vec2, batch: &mut CircleBatch) {
+        for particle in &self.particles {
+            let left = 1.0 - (particle.age / particle.life).clamp(0.0, 1.0);
+            let size = if particle.shrink { particle.size * left } else { particle.size };
+            let color = if particle.fade { acmul(particle.color, left) } else { particle.color };
+            batch.circle(particle.pos + offset, size, color);
+        }
+        batch.flush();
+    }
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Particle> {
+        self.particles.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macroquad::prelude::WHITE;
+
+    fn numbered(n: usize) -> Particle {
+        Particle::new(vec2(n as f32, 0.0), Vec2::ZERO, 1.0, WHITE)
+    }
+
+    #[test]
+    fn full_pools_push_out_the_oldest() {
+        let mut particles = ParticleSystem::new(8, 0);
+        let room = particles.particles.capacity();
+        for n in 0..20 {
+            particles.spawn(numbered(n));
+            assert_eq!(particles.len(), (n + 1).min(8));
+        }
+        // the newest eight, still oldest first
+        let left = particles.iter().map(|p| p.pos.x as usize).collect::<Vec<_>>();
+        assert_eq!(left, (12..20).collect::<Vec<_>>());
+        // and never grown past what it was made with
+        assert_eq!(particles.particles.capacity(), room);
+        // nor by bursts many times too big for it
+        particles.burst(Vec2::ZERO, 1000, 10.0..20.0, 0.5..1.0, WHITE);
+        assert_eq!(particles.len(), 8);
+        assert_eq!(particles.particles.capacity(), room);
+    }
+
+    #[test]
+    fn pools_without_room_take_nothing() {
+        let mut particles = ParticleSystem::default();
+        particles.spawn(numbered(0));
+        particles.burst(Vec2::ZERO, 10, 10.0..20.0, 0.5..1.0, WHITE);
+        assert!(particles.is_empty());
+    }
+
+    #[test]
+    fn particles_run_their_course() {
+        let mut particles = ParticleSystem::new(8, 0);
+        particles.spawn(Particle::new(Vec2::ZERO, vec2(100.0, 0.0), 0.5, WHITE));
+        particles.spawn(Particle::new(Vec2::ZERO, vec2(100.0, 0.0), 1.0, WHITE));
+        particles.update(0.25);
+        let moved = particles.iter().next().unwrap().pos.x;
+        assert!(moved > 0.0 && moved <= 25.0);
+        particles.update(0.25);
+        assert_eq!(particles.len(), 1);
+        particles.update(0.5);
+        assert!(particles.is_empty());
+    }
+
+    /// Everything the pool holds after a few frames of every kind of spawn.
+    fn run(seed: u64) -> Vec<Particle> {
+        let mut particles = ParticleSystem::new(256, seed);
+        for frame in 0..60 {
+            if frame % 10 == 0 {
+                particles.burst(vec2(frame as f32, 0.0), 20, 80.0..320.0, 0.3..0.7, WHITE);
+            }
+            particles.directional(Vec2::ZERO, Vec2::X, 0.6, 3, 60.0..160.0, 0.15..0.35, WHITE);
+            particles.trail(vec2(0.0, frame as f32), Vec2::Y, 30.0, WHITE);
+            particles.update(1.0 / 30.0);
+        }
+        particles.iter().copied().collect()
+    }
+
+    #[test]
+    fn seeded_pools_do_the_same_every_time() {
+        let first = run(7);
+        assert!(!first.is_empty());
+        assert_eq!(first, run(7));
+        assert_ne!(first, run(8));
+    }
+
+    #[test]
+    fn directional_particles_stay_in_their_spread() {
+        let mut particles = ParticleSystem::new(256, 3);
+        particles.directional(Vec2::ZERO, vec2(0.0, -2.0), 0.5, 200, 50.0..100.0, 0.5..1.0, WHITE);
+        for particle in particles.iter() {
+            let speed = particle.vel.length();
+            assert!((50.0..=100.0).contains(&speed), "{speed}");
+            assert!(particle.vel.angle_between(-Vec2::Y).abs() <= 0.5 + 1e-4);
+            assert!((0.5..=1.0).contains(&particle.life));
+        }
+    }
+
+    #[test]
+    fn trails_leave_about_their_rate() {
+        let mut particles = ParticleSystem::new(MAX_PARTICLES, 5);
+        let mut left = 0;
+        for _ in 0..120 {
+            particles.update(1.0 / 60.0);
+            let before = particles.len();
+            particles.trail(Vec2::ZERO, Vec2::X, 90.0, WHITE);
+            left += particles.len() - before;
+        }
+        // 2 beats at 90 a beat
+        assert!((150..=210).contains(&left), "{left}");
+    }
+}
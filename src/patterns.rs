@@ -0,0 +1,327 @@
+//! Common attack patterns, so levels, charts and endless mode share one take on the trig.\
+//! Each pattern has an option struct with every knob, and a function for the usual case.
+
+use std::f32::consts::{TAU, PI};
+
+use macroquad::prelude::{Vec2, Rect, vec2};
+
+use crate::{
+    game::{UpdateAccumulator, ModifyArgs},
+    game_objects::{Obst, Pellet, Ease, Periodic, GrowLaser, Bomb},
+    utils::{recip_ease_fn, screen_width, screen_height},
+    indicators::IndicatorStyle
+};
+
+macro_rules! builder {
+    ($name:ident: $type:ty) => {
+        pub fn $name(mut self, $name: $type) -> Self { self.$name = $name; self }
+    };
+}
+
+/// Pellets flying out of `center` evenly spread around a circle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ring {
+    pub center: Vec2,
+    pub count: usize,
+    pub speed: f32,
+    pub rad: f32,
+    /// Turns the ring is rotated by, 1 being all the way around
+    pub phase: f32,
+    /// Distance from `center` the pellets start at
+    pub offset: f32,
+    /// Pellets burst out fast and slow down to `speed`
+    pub strong: bool,
+}
+impl Default for Ring {
+    fn default() -> Self {
+        Ring { center: Vec2::ZERO, count: 16, speed: 200.0, rad: 10.0, phase: 0.0, offset: 0.0, strong: false }
+    }
+}
+impl Ring {
+    builder!(center: Vec2);
+    builder!(count: usize);
+    builder!(speed: f32);
+    builder!(rad: f32);
+    builder!(phase: f32);
+    builder!(offset: f32);
+    builder!(strong: bool);
+    pub fn spawn(&self, accum: &mut UpdateAccumulator) {
+        for i in 0..self.count {
+            let angle = (i as f32 / self.count as f32 + self.phase) * TAU;
+            let dir = vec2(angle.cos(), angle.sin());
+            let (pos, vel) = (self.center + dir * self.offset, dir * self.speed);
+            if self.strong {
+                accum.obst(Ease::anon(Pellet::new(pos, vel, self.rad), recip_ease_fn(3.0)));
+            } else {
+                accum.pellet(pos, vel, self.rad);
+            }
+        }
+    }
+}
+/// A ring of `count` pellets, see `Ring`.
+/// ```
+/// patterns::ring(accum, screen_center(), 24, 250.0, 10.0, 0.5 / 24.0);
+/// ```
+pub fn ring(accum: &mut UpdateAccumulator, center: Vec2, count: usize, speed: f32, rad: f32, phase: f32) {
+    Ring::default().center(center).count(count).speed(speed).rad(rad).phase(phase).spawn(accum);
+}
+
+/// Pellets flying out of `origin` in an arc centered on `dir`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fan {
+    pub origin: Vec2,
+    /// Direction of the middle of the fan, doesn't need to be normalized
+    pub dir: Vec2,
+    /// Radians between the outermost pellets
+    pub spread: f32,
+    pub count: usize,
+    pub speed: f32,
+    pub rad: f32,
+}
+impl Default for Fan {
+    fn default() -> Self {
+        Fan { origin: Vec2::ZERO, dir: Vec2::X, spread: PI / 3.0, count: 5, speed: 250.0, rad: 10.0 }
+    }
+}
+impl Fan {
+    builder!(origin: Vec2);
+    builder!(dir: Vec2);
+    builder!(spread: f32);
+    builder!(count: usize);
+    builder!(speed: f32);
+    builder!(rad: f32);
+    /// Aims the fan from `origin` at `target`.
+    pub fn aim(self, target: Vec2) -> Self {
+        let origin = self.origin;
+        self.dir(target - origin)
+    }
+    pub fn spawn(&self, accum: &mut UpdateAccumulator) {
+        let base = self.dir.y.atan2(self.dir.x);
+        for i in 0..self.count {
+            // a lone pellet goes straight down the middle
+            let t = if self.count > 1 { i as f32 / (self.count - 1) as f32 - 0.5 } else { 0.0 };
+            let angle = base + t * self.spread;
+            accum.pellet(self.origin, vec2(angle.cos(), angle.sin()) * self.speed, self.rad);
+        }
+    }
+}
+/// A fan of `count` pellets, see `Fan`.
+/// ```
+/// let origin = vec2(screen_width() / 2.0, 0.0);
+/// patterns::fan(accum, origin, accum.player_pos() - origin, PI / 4.0, 7, 300.0);
+/// ```
+pub fn fan(accum: &mut UpdateAccumulator, origin: Vec2, dir: Vec2, spread: f32, count: usize, speed: f32) {
+    Fan::default().origin(origin).dir(dir).spread(spread).count(count).speed(speed).spawn(accum);
+}
+
+/// Walls covering the screen except for a gap, the gap switching sides with each wall.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WallsAlternating {
+    /// Beats between walls
+    pub interval: f32,
+    pub gap_width: f32,
+    pub count: usize,
+    pub warning: f32,
+    /// Beats each wall stays up for
+    pub show: f32,
+    pub grow: f32,
+    /// The gap goes at the top and bottom instead of left and right
+    pub vertical: bool,
+    /// Which side the first gap is on, left or top if unset
+    pub first_far: bool,
+}
+impl Default for WallsAlternating {
+    fn default() -> Self {
+        WallsAlternating { interval: 2.0, gap_width: 200.0, count: 4, warning: 1.0, show: 0.5, grow: 0.25, vertical: false, first_far: false }
+    }
+}
+impl WallsAlternating {
+    builder!(interval: f32);
+    builder!(gap_width: f32);
+    builder!(count: usize);
+    builder!(warning: f32);
+    builder!(show: f32);
+    builder!(grow: f32);
+    builder!(vertical: bool);
+    builder!(first_far: bool);
+    /// (center, size) of the wall at `step`.
+    fn wall(&self, step: usize) -> (Vec2, Vec2) {
+        let WallsAlternating { gap_width, vertical, first_far, .. } = *self;
+        let size = vec2(screen_width(), screen_height());
+        let (along, across) = if vertical { (size.y, size.x) } else { (size.x, size.y) };
+        let far = (step % 2 == 1) != first_far;
+        let center = if far { (along - gap_width) / 2.0 } else { (along + gap_width) / 2.0 };
+        if vertical {
+            (vec2(across / 2.0, center), vec2(across, along - gap_width))
+        } else {
+            (vec2(center, across / 2.0), vec2(along - gap_width, across))
+        }
+    }
+    pub fn spawn(&self, accum: &mut UpdateAccumulator) {
+        // the walls fill the screen, so they only need pointing out while the camera's zoomed or turned away from them
+        for step in 0..self.count {
+            accum.indicate(self.wall(step).0, IndicatorStyle::Double, step as f32 * self.interval + self.warning);
+        }
+        let walls = *self;
+        accum.obst(Periodic::new(self.count, self.interval, Periodic::rect_trail(self.show, self.warning, self.grow, move |step| {
+            let (center, size) = walls.wall(step);
+            (center, size, 0.0)
+        })));
+    }
+}
+/// `count` walls `interval` beats apart, see `WallsAlternating`.
+/// ```
+/// patterns::walls_alternating(accum, 2.0, 250.0, 8);
+/// ```
+pub fn walls_alternating(accum: &mut UpdateAccumulator, interval: f32, gap_width: f32, count: usize) {
+    WallsAlternating::default().interval(interval).gap_width(gap_width).count(count).spawn(accum);
+}
+
+/// Lasers along the sides of a regular polygon around `center`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LaserCage {
+    pub center: Vec2,
+    /// Sides of the polygon
+    pub n: usize,
+    /// Length of each side
+    pub length: f32,
+    pub warning: f32,
+    pub show: f32,
+    pub thickness: f32,
+    /// Radians the cage is rotated by
+    pub rot: f32,
+}
+impl Default for LaserCage {
+    fn default() -> Self {
+        LaserCage { center: Vec2::ZERO, n: 4, length: 400.0, warning: 1.0, show: 1.0, thickness: 20.0, rot: 0.0 }
+    }
+}
+impl LaserCage {
+    builder!(center: Vec2);
+    builder!(n: usize);
+    builder!(length: f32);
+    builder!(warning: f32);
+    builder!(show: f32);
+    builder!(thickness: f32);
+    builder!(rot: f32);
+    /// Distance from the center to the middle of each side.
+    pub fn apothem(&self) -> f32 {
+        self.length / (2.0 * (PI / self.n.max(3) as f32).tan())
+    }
+    pub fn spawn(&self, accum: &mut UpdateAccumulator) {
+        let corner = self.length / 2.0 / (PI / self.n.max(3) as f32).sin();
+        for i in 0..self.n {
+            let a = self.rot + i as f32 / self.n as f32 * TAU;
+            let b = self.rot + (i + 1) as f32 / self.n as f32 * TAU;
+            let start = self.center + vec2(a.cos(), a.sin()) * corner;
+            let end = self.center + vec2(b.cos(), b.sin()) * corner;
+            accum.obst(GrowLaser::new(start, end, self.thickness, self.warning, self.show, Vec2::ZERO));
+        }
+    }
+}
+/// A cage of `n` lasers `length` long, see `LaserCage`.
+/// ```
+/// patterns::laser_cage(accum, accum.player_pos(), 6, 300.0, 2.0, 4.0);
+/// ```
+pub fn laser_cage(accum: &mut UpdateAccumulator, center: Vec2, n: usize, length: f32, warning: f32, show: f32) {
+    LaserCage::default().center(center).n(n).length(length).warning(warning).show(show).spawn(accum);
+}
+
+/// Bombs dropping in from above `area` one after another, each bursting somewhere random inside it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BombRain {
+    pub count: usize,
+    pub area: Rect,
+    /// Beats between bombs
+    pub stagger: f32,
+    /// Beats each bomb takes to land
+    pub fuse: f32,
+    pub pellets: usize,
+    pub pellet_speed: f32,
+    pub pellet_rad: f32,
+}
+impl Default for BombRain {
+    fn default() -> Self {
+        BombRain { count: 6, area: Rect::new(0.0, 0.0, screen_width(), screen_height()), stagger: 0.5, fuse: 2.0, pellets: 12, pellet_speed: 200.0, pellet_rad: 10.0 }
+    }
+}
+impl BombRain {
+    builder!(count: usize);
+    builder!(area: Rect);
+    builder!(stagger: f32);
+    builder!(fuse: f32);
+    builder!(pellets: usize);
+    builder!(pellet_speed: f32);
+    builder!(pellet_rad: f32);
+    pub fn spawn(&self, accum: &mut UpdateAccumulator) {
+        let BombRain { area, fuse, pellets, pellet_speed, pellet_rad, .. } = *self;
+        accum.obst(Periodic::new(self.count, self.stagger, Box::new(move |gs: &mut UpdateAccumulator, args: ModifyArgs| {
+            let target = gs.rng_at(args.time, args.step).vec(area.point(), area.point() + area.size());
+            let start = vec2(target.x, area.y - 100.0);
+            let bomb = Bomb::new(start, target, fuse, pellets, pellet_speed, pellet_rad, Box::new(Bomb::pellet_spawner));
+            gs.obstacle(Obst::new(Box::new(bomb), args.time).indicated(IndicatorStyle::Chevron, fuse));
+        })));
+    }
+}
+/// `count` bombs `stagger` beats apart, see `BombRain`.
+/// ```
+/// patterns::bomb_rain(accum, 8, Rect::new(0.0, 0.0, screen_width(), screen_height()), 0.25);
+/// ```
+pub fn bomb_rain(accum: &mut UpdateAccumulator, count: usize, area: Rect, stagger: f32) {
+    BombRain::default().count(count).area(area).stagger(stagger).spawn(accum);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game::{GSEvent, simulate}, utils::{GameRng, screen_center}};
+
+    /// How many of each kind of obstacle are alive at `beat` after `pattern` ran at beat 0.
+    fn alive_at(beat: f32, pattern: impl Fn(&mut UpdateAccumulator) + Clone + 'static) -> Vec<(&'static str, usize)> {
+        let events = [GSEvent::new(0.0, move |ac: &mut UpdateAccumulator, _| pattern(ac))];
+        let mut counts: Vec<(&'static str, usize)> = vec![];
+        for obst in simulate(&events, 0.0, beat, GameRng::new(921)) {
+            match counts.iter_mut().find(|(name, _)| *name == obst.obstacle.name()) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((obst.obstacle.name(), 1)),
+            }
+        }
+        counts
+    }
+
+    #[test]
+    fn ring_spawns_count_pellets() {
+        assert_eq!(alive_at(0.0, |ac| ring(ac, screen_center(), 24, 250.0, 10.0, 0.0)), [("Pellet", 24)]);
+        assert_eq!(alive_at(0.0, |ac| Ring::default().center(screen_center()).strong(true).spawn(ac)), [("Pellet", 16)]);
+    }
+
+    #[test]
+    fn fan_spawns_count_pellets() {
+        assert_eq!(alive_at(0.0, |ac| fan(ac, screen_center(), Vec2::Y, PI / 4.0, 7, 300.0)), [("Pellet", 7)]);
+        assert_eq!(alive_at(0.0, |ac| fan(ac, screen_center(), Vec2::Y, PI / 4.0, 1, 300.0)), [("Pellet", 1)]);
+    }
+
+    #[test]
+    fn laser_cage_spawns_a_laser_a_side() {
+        assert_eq!(alive_at(0.0, |ac| laser_cage(ac, screen_center(), 6, 300.0, 2.0, 4.0)), [("GrowLaser", 6)]);
+    }
+
+    #[test]
+    fn walls_alternating_spawns_a_wall_each_interval() {
+        let walls = |ac: &mut UpdateAccumulator| walls_alternating(ac, 2.0, 250.0, 4);
+        // the first one comes an interval in, like any `Periodic`
+        assert_eq!(alive_at(0.1, walls), [("Periodic", 1)]);
+        for beat in [2.1, 4.1, 6.1] {
+            assert_eq!(alive_at(beat, walls), [("Periodic", 1), ("RotatableRect", 1)], "at {beat}");
+        }
+        assert_eq!(alive_at(8.1, walls), [("RotatableRect", 1)]);
+        assert!(alive_at(11.0, walls).is_empty());
+    }
+
+    #[test]
+    fn bomb_rain_drops_count_bombs() {
+        let rain = |ac: &mut UpdateAccumulator| BombRain::default().count(5).stagger(0.5).fuse(10.0).spawn(ac);
+        assert_eq!(alive_at(1.1, rain), [("Periodic", 1), ("Bomb", 2)]);
+        assert_eq!(alive_at(2.6, rain), [("Bomb", 5)]);
+    }
+}
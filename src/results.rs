@@ -0,0 +1,124 @@
+use macroquad::{prelude::*, time::get_time};
+use strum::{IntoEnumIterator, EnumCount};
+
+use crate::{scoring::Score, utils::{centered_text_draw, acmul, screen_width, screen_height}, palette::Palette};
+
+/// Seconds the numbers on the results screen take to count up.
+pub const COUNT_UP_SECS: f32 = 1.5;
+
+/// How a run ended, for the results screen and personal bests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunResult {
+    pub cleared: bool,
+    pub seconds_survived: f32,
+    /// Length of the song, so the survived time can be put in proportion
+    pub song_seconds: f32,
+    pub score: Score,
+    /// (section, deaths) of the sections died in, to know what to practice
+    pub sections: Vec<(String, usize)>,
+}
+impl RunResult {
+    /// Fraction of the song survived, 1 for a clear.
+    pub fn survived(&self) -> f32 {
+        if self.cleared || self.song_seconds <= 0.0 { 1.0 } else { (self.seconds_survived / self.song_seconds).clamp(0.0, 1.0) }
+    }
+}
+
+/// Turns a run into a letter grade: `formula` rates it, and the first grade it reaches is given.
+#[derive(Debug, Clone)]
+pub struct Grading {
+    pub formula: fn(&RunResult) -> f32,
+    /// (lowest rating, grade), from best to worst
+    pub grades: Vec<(f32, &'static str)>,
+}
+impl Default for Grading {
+    fn default() -> Self {
+        Grading {
+            formula: default_rating,
+            grades: vec![(1.0, "S"), (0.85, "A"), (0.7, "B"), (0.5, "C"), (0.0, "D")],
+        }
+    }
+}
+impl Grading {
+    pub fn grade(&self, result: &RunResult) -> &'static str {
+        let rating = (self.formula)(result);
+        self.grades.iter().find(|&&(min, _)| rating >= min).or(self.grades.last()).map_or("?", |&(_, grade)| grade)
+    }
+}
+
+/// The fraction of the song survived, losing 10% for every hit point lost. A flawless clear rates 1.
+pub fn default_rating(result: &RunResult) -> f32 {
+    result.survived() * 0.9f32.powi(result.score.stats.damage_taken as i32)
+}
+
+#[derive(strum_macros::EnumIter, strum_macros::EnumCount, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultsOption {
+    Retry,
+    Back,
+}
+impl ResultsOption {
+    /// The option `by` columns away, wrapping around.
+    pub fn step(self, by: isize) -> Self {
+        let idx = (self as isize + by).rem_euclid(Self::COUNT as isize);
+        Self::iter().nth(idx as usize).unwrap()
+    }
+}
+
+/// The screen after a run, counting up its stats.
+pub struct Results {
+    pub result: RunResult,
+    pub grade: &'static str,
+    pub new_best: bool,
+    pub selected: ResultsOption,
+    shown_at: f64,
+}
+impl Results {
+    pub fn new(result: RunResult, grade: &'static str, new_best: bool) -> Self {
+        Results { result, grade, new_best, selected: ResultsOption::Retry, shown_at: get_time() }
+    }
+    /// How far the count-up is, from 0 to 1.
+    fn progress(&self) -> f32 {
+        let t = ((get_time() - self.shown_at) as f32 / COUNT_UP_SECS).clamp(0.0, 1.0);
+        1.0 - (1.0 - t) * (1.0 - t)
+    }
+    /// Skips the count-up, or returns whether it was already done.
+    pub fn skip_count_up(&mut self) -> bool {
+        let done = self.progress() >= 1.0;
+        self.shown_at = f64::NEG_INFINITY;
+        done
+    }
+    pub fn draw(&self, palette: &Palette) {
+        clear_background(palette.background);
+        let center = vec2(screen_width(), screen_height()) / 2.0;
+        let p = self.progress();
+        let (title, title_color) = if self.result.cleared { ("CLEAR", palette.text) } else { ("FAILED", palette.warning) };
+        centered_text_draw(title, center - vec2(0.0, 300.0), 80.0, title_color);
+        let stats = self.result.score.stats;
+        let secs = self.result.seconds_survived * p;
+        let lines = [
+            format!("time survived  {}:{:02}", (secs / 60.0) as u32, secs as u32 % 60),
+            format!("damage taken  {}", (stats.damage_taken as f32 * p).round()),
+            format!("max combo  {}", (stats.max_combo as f32 * p).round()),
+            format!("grazes  {}", (stats.grazes as f32 * p).round()),
+            format!("score  {}", (self.result.score.points as f64 * p as f64).round()),
+            format!("modifiers  {}  x{:.2}", stats.modifiers, stats.modifiers.score_multiplier()),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            centered_text_draw(line, center + vec2(0.0, -180.0 + i as f32 * 40.0), 32.0, acmul(palette.text, 0.85));
+        }
+        if p >= 1.0 {
+            centered_text_draw(self.grade, center + vec2(0.0, 130.0), 120.0, palette.accent);
+            if self.new_best { centered_text_draw("new best!", center + vec2(0.0, 200.0), 28.0, palette.accent); }
+        }
+        if !self.result.sections.is_empty() {
+            draw_text("deaths per section", 40.0, center.y - 180.0, 28.0, acmul(palette.text, 0.6));
+            for (i, (name, deaths)) in self.result.sections.iter().enumerate() {
+                draw_text(&format!("{name}  {deaths}"), 40.0, center.y - 140.0 + i as f32 * 30.0, 24.0, acmul(palette.text, 0.85));
+            }
+        }
+        for (i, (option, label)) in [(ResultsOption::Retry, "Retry"), (ResultsOption::Back, "Back to charts")].into_iter().enumerate() {
+            let color = if option == self.selected { palette.text } else { acmul(palette.text, 0.4) };
+            centered_text_draw(label, center + vec2((i as f32 - 0.5) * 400.0, 280.0), 36.0, color);
+        }
+    }
+}
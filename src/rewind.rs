@@ -0,0 +1,98 @@
+//! The last few seconds of the world as it looked, played backwards after dying.\
+//! Snapshots only keep what's needed to draw a ghost of each obstacle, so recording stays cheap.
+
+use std::collections::VecDeque;
+
+use macroquad::prelude::*;
+
+use crate::{game_objects::{Obst, Player}, utils::{acmul, screen_width, screen_height}};
+
+/// Seconds of history kept
+pub const REWIND_HISTORY_SECS: f32 = 3.0;
+/// Snapshots taken per second
+pub const SNAPSHOT_HZ: f32 = 20.0;
+/// Seconds the history takes to play back
+pub const REWIND_SECS: f32 = 1.0;
+/// Most obstacles a snapshot holds, so crowded moments don't blow up the buffer
+pub const MAX_SNAPSHOT_OBSTACLES: usize = 512;
+const SNAPSHOTS: usize = (REWIND_HISTORY_SECS * SNAPSHOT_HZ) as usize;
+
+/// How an obstacle is drawn in the rewind.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSnapshot {
+    pub pos: Vec2,
+    /// The obstacle's type, from `Obstacle::name`
+    pub kind: &'static str,
+    pub color: Color,
+}
+impl RenderSnapshot {
+    /// The obstacle's ghost, if it has an anchor to draw it at.
+    pub fn of(obst: &Obst, color: Color) -> Option<Self> {
+        Some(RenderSnapshot { pos: obst.obstacle.anchor()?, kind: obst.obstacle.name(), color })
+    }
+    fn draw(&self, alpha: f32) {
+        let color = acmul(self.color, alpha);
+        match self.kind {
+            "Pellet" | "ScoreOrb" => draw_circle(self.pos.x, self.pos.y, 8.0, color),
+            "RotatableRect" | "RotatingRect" => draw_rectangle_lines(self.pos.x - 20.0, self.pos.y - 20.0, 40.0, 40.0, 2.0, color),
+            _ => draw_circle_lines(self.pos.x, self.pos.y, 14.0, 2.0, color),
+        }
+    }
+}
+
+/// The world at one moment.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct WorldSnapshot {
+    /// (position, color) of each player
+    pub players: Vec<(Vec2, Color)>,
+    pub obsts: Vec<RenderSnapshot>,
+}
+
+/// A bounded history of snapshots, oldest first.
+#[derive(Default)]
+pub struct RewindBuffer {
+    snapshots: VecDeque<WorldSnapshot>,
+    since_snapshot: f32,
+}
+impl RewindBuffer {
+    /// Takes a snapshot if one is due, dropping the oldest once the history is full.
+    pub fn record(&mut self, frame_time: f32, players: &[Player], obsts: &[Obst], color: Color) {
+        self.since_snapshot += frame_time;
+        if self.since_snapshot < 1.0 / SNAPSHOT_HZ && !self.snapshots.is_empty() { return; }
+        self.since_snapshot = 0.0;
+        if self.snapshots.len() >= SNAPSHOTS { self.snapshots.pop_front(); }
+        self.snapshots.push_back(WorldSnapshot {
+            players: players.iter().map(|p| (p.pos, p.color)).collect(),
+            obsts: obsts.iter().filter_map(|o| RenderSnapshot::of(o, color)).take(MAX_SNAPSHOT_OBSTACLES).collect(),
+        });
+    }
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.since_snapshot = 0.0;
+    }
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+    /// The snapshot `progress` of the way back through the history, the newest at 0 and the oldest at 1.
+    pub fn frame(&self, progress: f32) -> Option<&WorldSnapshot> {
+        let last = self.snapshots.len().checked_sub(1)?;
+        let back = (progress.clamp(0.0, 1.0) * last as f32).round() as usize;
+        self.snapshots.get(last - back)
+    }
+    /// Draws the world `progress` of the way through the rewind, over an already cleared screen.
+    pub fn draw(&self, progress: f32, offset: Vec2) {
+        let Some(frame) = self.frame(progress) else { return };
+        // ghostly, flickering a little like a tape
+        let alpha = 0.45 + 0.1 * (progress * 40.0).sin();
+        for obst in &frame.obsts {
+            RenderSnapshot { pos: obst.pos + offset, ..*obst }.draw(alpha);
+        }
+        for &(pos, color) in &frame.players {
+            draw_circle(pos.x + offset.x, pos.y + offset.y, 6.0, acmul(color, alpha + 0.3));
+        }
+        for i in 0..3 {
+            let y = (progress * 3.0 + i as f32 / 3.0).fract() * screen_height();
+            draw_rectangle(0.0, y, screen_width(), 3.0, acmul(WHITE, 0.08));
+        }
+    }
+}
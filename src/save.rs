@@ -0,0 +1,351 @@
+use std::{fs, path::{Path, PathBuf}, collections::BTreeMap};
+
+use crate::{Possibly, CanErr, results::RunResult, state_control::EparLevel, chart::Chart, modifiers::Modifiers, settings::Settings, input::Bindings};
+
+/// Version of the save format `SaveData::serialize` writes. Version 1 was the bests alone, without sections.
+pub const SAVE_VERSION: u32 = 2;
+/// Folder of the save in the platform's data directory
+const APP_DIR: &str = "epar";
+
+/// The folder next to the executable, where everything was kept before the save.
+fn exe_dir() -> PathBuf {
+    std::env::current_exe().ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        .unwrap_or_default()
+}
+
+/// Where the save goes: the platform's data directory, else next to the executable.
+pub fn data_dir() -> PathBuf {
+    let var = |name| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    let base = if cfg!(target_os = "windows") {
+        var("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        var("XDG_DATA_HOME").or_else(|| var("HOME").map(|home| home.join(".local/share")))
+    };
+    base.map_or_else(exe_dir, |base| base.join(APP_DIR))
+}
+
+pub fn level_key(lvl: EparLevel) -> String {
+    format!("level:{lvl:?}")
+}
+/// Charts are told apart by their title and difficulty, as the difficulties of a song share its audio.
+/// Untitled ones go by their audio file instead.
+pub fn chart_key(chart: &Chart) -> String {
+    let name = chart.meta.title.as_deref().unwrap_or(&chart.audio);
+    match &chart.meta.difficulty {
+        Some(difficulty) => format!("chart:{name} ({difficulty})"),
+        None => format!("chart:{name}"),
+    }
+}
+
+/// The best run of a level or chart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Best {
+    pub score: u64,
+    pub grade: String,
+    pub cleared: bool,
+    /// What the run was played with, so bests with different modifiers can be told apart
+    pub modifiers: Modifiers,
+}
+
+/// Totals over every run played.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PlayStats {
+    pub runs: u64,
+    pub clears: u64,
+    pub deaths: u64,
+    pub grazes: u64,
+    pub seconds_played: f64,
+}
+impl PlayStats {
+    pub fn record(&mut self, result: &RunResult) {
+        self.runs += 1;
+        if result.cleared { self.clears += 1; }
+        self.deaths += result.sections.iter().map(|&(_, deaths)| deaths as u64).sum::<u64>();
+        self.grazes += result.score.stats.grazes as u64;
+        self.seconds_played += result.seconds_survived as f64;
+    }
+    /// One total per line, e.g. `runs = 12`. Unknown totals are skipped.
+    fn parse(text: &str) -> Possibly<Self> {
+        let mut stats = Self::default();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            let (key, value) = line.split_once('=').ok_or_else(|| format!("line {}: expected `total = value`", idx + 1))?;
+            let (key, value) = (key.trim(), value.trim());
+            let count = || value.parse::<u64>().map_err(|_| format!("line {}: expected a count, found `{value}`", idx + 1));
+            match key {
+                "runs" => stats.runs = count()?,
+                "clears" => stats.clears = count()?,
+                "deaths" => stats.deaths = count()?,
+                "grazes" => stats.grazes = count()?,
+                "seconds_played" => stats.seconds_played = value.parse()
+                    .map_err(|_| format!("line {}: expected a number of seconds, found `{value}`", idx + 1))?,
+                _ => {}
+            }
+        }
+        Ok(stats)
+    }
+    fn serialize(&self) -> String {
+        format!(
+            "runs = {}\nclears = {}\ndeaths = {}\ngrazes = {}\nseconds_played = {}\n",
+            self.runs, self.clears, self.deaths, self.grazes, self.seconds_played
+        )
+    }
+}
+
+/// Everything that persists between runs, in one file: the settings, the bindings,
+/// personal bests keyed by `level_key` and `chart_key`, and the play totals.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SaveData {
+    pub settings: Settings,
+    /// Copied into the inputs at startup
+    pub bindings: Bindings,
+    pub bests: BTreeMap<String, Best>,
+    pub stats: PlayStats,
+}
+impl SaveData {
+    pub fn default_path() -> PathBuf {
+        data_dir().join("save.txt")
+    }
+    /// A `version = n` line, then a `[section]` header before each part:
+    /// ```text
+    /// version = 2
+    /// [settings]
+    /// palette = dark
+    /// [bindings]
+    /// Dash = Space X
+    /// [bests]
+    /// level:Hallway = 12345 A clear speed1.5 onehp
+    /// [stats]
+    /// runs = 12
+    /// ```
+    /// Unknown sections and settings are skipped and missing ones keep their defaults, so newer saves still load.
+    /// Lines before the first section are bests, as in version 1.
+    pub fn parse(text: &str) -> Possibly<Self> {
+        let mut sections = vec![];
+        let mut section = "bests";
+        let mut version = 1;
+        for (idx, line) in text.lines().enumerate() {
+            let trimmed = line.trim();
+            if let Some(name) = trimmed.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                section = name.trim();
+                sections.push((section, ""));
+                continue;
+            }
+            if let Some(value) = trimmed.strip_prefix("version").and_then(|s| s.trim_start().strip_prefix('=')) {
+                version = value.trim().parse::<u32>().map_err(|_| format!("line {}: expected a version, found `{}`", idx + 1, value.trim()))?;
+                sections.push((section, ""));
+                continue;
+            }
+            sections.push((section, line));
+        }
+        if version > SAVE_VERSION {
+            println!("the save is from a newer version ({version}), some of it may be skipped");
+        }
+        // the other sections' lines are left blank, so line numbers in errors match the file
+        let section = |name: &str| sections.iter().map(|&(s, line)| if s == name { line } else { "" }).collect::<Vec<_>>().join("\n");
+        Ok(SaveData {
+            settings: Settings::parse(&section("settings"))?,
+            bindings: Bindings::parse(&section("bindings"))?,
+            bests: Self::parse_bests(&section("bests"))?,
+            stats: PlayStats::parse(&section("stats"))?,
+        })
+    }
+    /// One best per line, e.g. `level:Hallway = 12345 A clear speed1.5 onehp`.
+    fn parse_bests(text: &str) -> Possibly<BTreeMap<String, Best>> {
+        let mut bests = BTreeMap::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            let (key, best) = line.rsplit_once('=').ok_or_else(|| format!("line {}: expected `key = score grade clear|fail modifiers`", idx + 1))?;
+            let mut fields = best.split_whitespace();
+            let (Some(score), Some(grade), Some(outcome)) = (fields.next(), fields.next(), fields.next()) else {
+                return Err(format!("line {}: expected `score grade clear|fail modifiers`", idx + 1).into());
+            };
+            let modifiers = fields.collect::<Vec<_>>().join(" ").parse::<Modifiers>().map_err(|e| format!("line {}: {e}", idx + 1))?;
+            let score = score.parse().map_err(|_| format!("line {}: expected a score, found `{score}`", idx + 1))?;
+            let cleared = match outcome {
+                "clear" => true,
+                "fail" => false,
+                _ => return Err(format!("line {}: expected `clear` or `fail`, found `{outcome}`", idx + 1).into()),
+            };
+            bests.insert(key.trim().to_string(), Best { score, grade: grade.to_string(), cleared, modifiers });
+        }
+        Ok(bests)
+    }
+    pub fn serialize(&self) -> String {
+        let bests = self.bests.iter().map(|(key, best)| format!(
+            "{key} = {} {} {} {}\n",
+            best.score, best.grade, if best.cleared { "clear" } else { "fail" }, best.modifiers
+        )).collect::<String>();
+        format!(
+            "version = {SAVE_VERSION}\n[settings]\n{}[bindings]\n{}[bests]\n{bests}[stats]\n{}",
+            self.settings.serialize(), self.bindings.serialize(), self.stats.serialize()
+        )
+    }
+    pub fn load(path: impl AsRef<Path>) -> Possibly<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> CanErr {
+        if let Some(dir) = path.as_ref().parent() { fs::create_dir_all(dir)?; }
+        fs::write(path, self.serialize())?;
+        Ok(())
+    }
+    /// Saves to `default_path`, printing why if it couldn't.
+    pub fn persist(&self) {
+        if let Err(e) = self.save(Self::default_path()) { println!("couldn't save: {e}"); }
+    }
+    /// Loads the save at `default_path`. Without one, the files from before the save are moved into a new one.\
+    /// A broken save is renamed to `save.txt.corrupt` and started over, rather than overwritten.
+    pub fn load_or_default() -> Self {
+        Self::load_or_default_at(&Self::default_path(), &exe_dir())
+    }
+    /// `load_or_default` with the save at `path`, migrating from the old files in `old_dir`.
+    fn load_or_default_at(path: &Path, old_dir: &Path) -> Self {
+        if !path.exists() {
+            let save = Self::migrate(old_dir);
+            if let Err(e) = save.save(path) { println!("couldn't save: {e}"); }
+            return save;
+        }
+        Self::load(path).unwrap_or_else(|e| {
+            let aside = path.with_extension("txt.corrupt");
+            println!("couldn't load the save, moving it to {} and starting over: {e}", aside.display());
+            if let Err(e) = fs::rename(path, &aside) { println!("couldn't move the broken save: {e}"); }
+            Self::default()
+        })
+    }
+    /// A save from the `settings.txt`, `bindings.txt` and `save.txt` in `dir`, each left at its defaults if it's missing or broken.
+    pub fn migrate(dir: &Path) -> Self {
+        fn read<T: Default>(path: PathBuf, parse: fn(&str) -> Possibly<T>) -> T {
+            let Ok(text) = fs::read_to_string(&path) else { return T::default() };
+            parse(&text).unwrap_or_else(|e| {
+                println!("couldn't migrate {}, using defaults: {e}", path.display());
+                T::default()
+            })
+        }
+        SaveData {
+            settings: read(dir.join("settings.txt"), Settings::parse),
+            bindings: read(dir.join("bindings.txt"), Bindings::parse),
+            bests: read(dir.join("save.txt"), Self::parse_bests),
+            stats: PlayStats::default(),
+        }
+    }
+    pub fn best(&self, key: &str) -> Option<&Best> {
+        self.bests.get(key)
+    }
+    /// Records `result` if it beats the best under `key`. Returns whether it did.
+    pub fn record(&mut self, key: &str, result: &RunResult, grade: &str) -> bool {
+        if self.best(key).is_some_and(|best| best.score >= result.score.points) { return false; }
+        self.bests.insert(key.to_string(), Best {
+            score: result.score.points,
+            grade: grade.to_string(),
+            cleared: result.cleared,
+            modifiers: result.score.stats.modifiers,
+        });
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::input::KeyCode;
+
+    use super::*;
+    use crate::{chart::ChartMeta, input::Action, modifiers::PlayerSize};
+
+    /// An empty folder of its own under the system's temp folder.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("epar-test-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn populated() -> SaveData {
+        let mut save = SaveData::default();
+        save.settings.audio_offset_ms = 35.5;
+        save.settings.last_chart = Some("charts/song.txt".to_string());
+        save.settings.reduce_flashing = true;
+        save.settings.sfx_volume = 0.25;
+        save.bindings.set(Action::Dash, KeyCode::X);
+        save.bindings.add(Action::Dash, KeyCode::Space);
+        save.bests.insert("level:Hallway".to_string(), Best { score: 12345, grade: "A".to_string(), cleared: true, modifiers: Modifiers { speed_rate: 1.5, one_hp: true, ..Modifiers::default() } });
+        save.bests.insert("chart:Song (Hard)".to_string(), Best { score: 99, grade: "D".to_string(), cleared: false, modifiers: Modifiers { player_size: PlayerSize::Tiny, ..Modifiers::default() } });
+        save.stats = PlayStats { runs: 12, clears: 3, deaths: 40, grazes: 512, seconds_played: 1234.5 };
+        save
+    }
+
+    #[test]
+    fn round_trips() {
+        let save = populated();
+        assert_eq!(SaveData::parse(&save.serialize()).unwrap(), save);
+        let dir = temp_dir("round-trip");
+        let path = dir.join("save.txt");
+        save.save(&path).unwrap();
+        assert_eq!(SaveData::load_or_default_at(&path, &dir), save);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reads_version_1() {
+        // just the bests, no version or sections
+        let save = SaveData::parse("level:Hallway = 12345 A clear speed1.5 onehp\nchart:Song = 99 D fail\n").unwrap();
+        assert_eq!(save.bests.len(), 2);
+        assert_eq!(save.best("level:Hallway").map(|b| (b.score, b.cleared, b.modifiers.one_hp)), Some((12345, true, true)));
+        assert_eq!(save.best("chart:Song").map(|b| (b.grade.as_str(), b.cleared)), Some(("D", false)));
+        assert_eq!(save.settings, Settings::default());
+    }
+
+    #[test]
+    fn skips_what_it_doesnt_know() {
+        let text = "version = 99\n[settings]\nsfx_volume = 0.5\nfrom_the_future = 1\n[unlocks]\neverything\n[stats]\nruns = 4\n";
+        let save = SaveData::parse(text).unwrap();
+        assert_eq!(save.settings.sfx_volume, 0.5);
+        assert_eq!(save.stats.runs, 4);
+        assert!(save.bests.is_empty());
+    }
+
+    #[test]
+    fn broken_save_is_moved_aside() {
+        let dir = temp_dir("corrupt");
+        let path = dir.join("save.txt");
+        fs::write(&path, "version = 2\n[bests]\nlevel:Hallway = lots\n").unwrap();
+        assert_eq!(SaveData::load_or_default_at(&path, &dir), SaveData::default());
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(dir.join("save.txt.corrupt")).unwrap(), "version = 2\n[bests]\nlevel:Hallway = lots\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_save_migrates_the_old_files() {
+        let dir = temp_dir("migrate");
+        let old = dir.join("old");
+        fs::create_dir_all(&old).unwrap();
+        fs::write(old.join("settings.txt"), "sfx_volume = 0.5\n").unwrap();
+        fs::write(old.join("save.txt"), "level:Hallway = 10 C clear\n").unwrap();
+        let path = dir.join("data").join("save.txt");
+        let save = SaveData::load_or_default_at(&path, &old);
+        assert_eq!(save.settings.sfx_volume, 0.5);
+        assert_eq!(save.best("level:Hallway").map(|b| b.score), Some(10));
+        // and written out as a new save
+        assert_eq!(SaveData::load(&path).unwrap(), save);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn chart(title: Option<&str>, difficulty: Option<&str>) -> Chart {
+        let meta = ChartMeta { title: title.map(str::to_string), difficulty: difficulty.map(str::to_string), ..ChartMeta::default() };
+        Chart { meta, audio: "assets/song.wav".to_string(), ..Chart::default() }
+    }
+
+    #[test]
+    fn difficulties_of_a_song_have_their_own_bests() {
+        let (easy, hard) = (chart(Some("Song"), Some("Easy")), chart(Some("Song"), Some("Hard")));
+        assert_ne!(chart_key(&easy), chart_key(&hard));
+        assert_eq!(chart_key(&hard), "chart:Song (Hard)");
+        assert_ne!(chart_key(&chart(Some("Other song"), Some("Hard"))), chart_key(&hard));
+        assert_eq!(chart_key(&chart(None, None)), "chart:assets/song.wav");
+    }
+}
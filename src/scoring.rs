@@ -0,0 +1,195 @@
+use crate::modifiers::Modifiers;
+
+/// Point values and combo rules, so difficulties can tune them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoringConfig {
+    /// Points for each beat survived, before the multiplier
+    pub per_beat: f32,
+    pub per_graze: u64,
+    /// Points for each point of a score orb's value
+    pub per_orb: u64,
+    /// Combo it takes for each extra multiple, e.g. 10 gives x2 at 10 in a row and x3 at 20
+    pub combo_step: u32,
+    /// Beats without a graze or pickup before the combo drops. `None` only drops it on hits.
+    pub combo_timeout: Option<f32>,
+}
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        ScoringConfig { per_beat: 10.0, per_graze: 50, per_orb: 1, combo_step: 10, combo_timeout: None }
+    }
+}
+impl ScoringConfig {
+    /// A point per beat survived and nothing else, for endless mode.
+    pub fn survival() -> Self {
+        ScoringConfig { per_beat: 1.0, per_graze: 0, per_orb: 0, combo_step: u32::MAX, combo_timeout: None }
+    }
+}
+
+/// Grazes and pickups in a row. Goes up on either, and back to 0 on a hit or after `ScoringConfig::combo_timeout`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Combo {
+    pub count: u32,
+    /// Beat of the last graze or pickup
+    last_bump: f32,
+}
+impl Default for Combo {
+    fn default() -> Self {
+        Combo { count: 0, last_bump: f32::NEG_INFINITY }
+    }
+}
+impl Combo {
+    pub fn bump(&mut self, time: f32) {
+        self.count += 1;
+        self.last_bump = time;
+    }
+    pub fn reset(&mut self) {
+        self.count = 0;
+    }
+    /// Drops the combo if it timed out by `time`.
+    pub fn tick(&mut self, time: f32, config: &ScoringConfig) {
+        if config.combo_timeout.is_some_and(|timeout| time - self.last_bump > timeout) {
+            self.reset();
+        }
+    }
+    pub fn multiplier(&self, config: &ScoringConfig) -> u64 {
+        1 + (self.count / config.combo_step.max(1)) as u64
+    }
+}
+
+/// Tallies of a run, for the results screen.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RunStats {
+    pub max_combo: u32,
+    pub grazes: usize,
+    pub orbs: usize,
+    /// Hit points lost, shields not included
+    pub damage_taken: u32,
+    pub beats_survived: f32,
+    /// Modifiers the run was played with, their multiplier applies to every point
+    pub modifiers: Modifiers,
+}
+
+/// The score of a run and everything that goes into it.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Score {
+    pub points: u64,
+    pub combo: Combo,
+    pub stats: RunStats,
+    /// Points that don't add up to a whole one yet
+    fraction: f32,
+    /// Beat the combo last went up at, for the HUD's pop
+    pub bumped_at: f32,
+}
+impl Score {
+    /// Adds the points for surviving `beats` more, up to `time`.
+    pub fn survive(&mut self, beats: f32, time: f32, config: &ScoringConfig) {
+        self.combo.tick(time, config);
+        self.stats.beats_survived += beats;
+        self.add(beats * config.per_beat * self.combo.multiplier(config) as f32);
+    }
+    pub fn graze(&mut self, time: f32, config: &ScoringConfig) {
+        self.add((config.per_graze * self.combo.multiplier(config)) as f32);
+        self.stats.grazes += 1;
+        self.bump(time);
+    }
+    pub fn orb(&mut self, value: u32, time: f32, config: &ScoringConfig) {
+        self.add((value as u64 * config.per_orb * self.combo.multiplier(config)) as f32);
+        self.stats.orbs += 1;
+        self.bump(time);
+    }
+    /// A hit that cost a hit point.
+    pub fn hit(&mut self) {
+        self.combo.reset();
+        self.stats.damage_taken += 1;
+    }
+    /// Adds `points` times the modifiers' multiplier.
+    fn add(&mut self, points: f32) {
+        self.fraction += points * self.stats.modifiers.score_multiplier();
+        let whole = self.fraction.floor();
+        self.points += whole as u64;
+        self.fraction -= whole;
+    }
+    fn bump(&mut self, time: f32) {
+        self.combo.bump(time);
+        self.stats.max_combo = self.stats.max_combo.max(self.combo.count);
+        self.bumped_at = time;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combo_counts_up_and_resets_on_hits() {
+        let config = ScoringConfig::default();
+        let mut combo = Combo::default();
+        for i in 0..25 {
+            combo.bump(i as f32);
+            combo.tick(i as f32 + 100.0, &config);
+        }
+        // no timeout, so only a hit drops it
+        assert_eq!(combo.count, 25);
+        assert_eq!(combo.multiplier(&config), 3);
+        combo.reset();
+        assert_eq!(combo.count, 0);
+        assert_eq!(combo.multiplier(&config), 1);
+    }
+
+    #[test]
+    fn combo_times_out() {
+        let config = ScoringConfig { combo_timeout: Some(2.0), ..ScoringConfig::default() };
+        let mut combo = Combo::default();
+        combo.bump(0.0);
+        combo.bump(1.0);
+        combo.tick(3.0, &config);
+        assert_eq!(combo.count, 2);
+        combo.tick(3.5, &config);
+        assert_eq!(combo.count, 0);
+        // a fresh combo doesn't time out before it starts
+        combo.tick(10.0, &config);
+        assert_eq!(combo.count, 0);
+    }
+
+    #[test]
+    fn multiplier_steps() {
+        let config = ScoringConfig { combo_step: 10, ..ScoringConfig::default() };
+        let at = |count| Combo { count, ..Combo::default() }.multiplier(&config);
+        assert_eq!([at(0), at(9), at(10), at(19), at(20)], [1, 1, 2, 2, 3]);
+        // a step of 0 is taken as 1 rather than dividing by it
+        assert_eq!(Combo { count: 4, ..Combo::default() }.multiplier(&ScoringConfig { combo_step: 0, ..config }), 5);
+        assert_eq!(Combo { count: 1000, ..Combo::default() }.multiplier(&ScoringConfig::survival()), 1);
+    }
+
+    #[test]
+    fn score_tallies_the_run() {
+        let config = ScoringConfig::default();
+        let mut score = Score::default();
+        // fractions of a point add up
+        for i in 0..40 {
+            score.survive(0.025, i as f32 * 0.025, &config);
+        }
+        assert_eq!(score.points, 10);
+        for i in 0..10 {
+            score.graze(1.0 + i as f32, &config);
+        }
+        // each is worth what the combo was before it, so it's the orb after that gets doubled
+        assert_eq!(score.points, 10 + 10 * 50);
+        score.orb(5, 11.0, &config);
+        assert_eq!(score.points, 510 + 5 * 2);
+        score.hit();
+        assert_eq!(score.combo.count, 0);
+        assert_eq!(score.stats, RunStats { max_combo: 11, grazes: 10, orbs: 1, damage_taken: 1, beats_survived: score.stats.beats_survived, ..RunStats::default() });
+        assert!((score.stats.beats_survived - 1.0).abs() < 1e-4);
+        assert_eq!(score.bumped_at, 11.0);
+    }
+
+    #[test]
+    fn modifiers_multiply_every_point() {
+        let config = ScoringConfig::default();
+        let mut score = Score::default();
+        score.stats.modifiers = Modifiers { one_hp: true, ..Modifiers::default() };
+        score.graze(0.0, &config);
+        assert_eq!(score.points, 75);
+    }
+}
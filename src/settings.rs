@@ -0,0 +1,83 @@
+use crate::{Possibly, palette::{Palette, PALETTE_NAMES}};
+
+/// Settings that persist between runs, kept in the save.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    /// How late the player perceives the audio, in milliseconds. Set by calibration.
+    pub audio_offset_ms: f32,
+    /// Path of the chart last picked in the chart list
+    pub last_chart: Option<String>,
+    /// Name of the palette from `PALETTE_NAMES`, unless a chart picks its own
+    pub palette: String,
+    /// Tones down flashes for players sensitive to them
+    pub reduce_flashing: bool,
+    /// Whether slams and close calls briefly freeze the game
+    pub hitstop: bool,
+    /// Whether the last seconds play backwards after dying, before respawning
+    pub rewind: bool,
+    /// Volume of obstacles' sound effects out of 1, the music staying as it is
+    pub sfx_volume: f32,
+    /// Whether the combo counter cycles through the rainbow instead of flashing the accent color
+    pub rainbow_combo: bool,
+    /// How much the screen shakes, 1 as designed and 0 not at all
+    pub shake_intensity: f32,
+    /// Whether pellets and orbs glow softly
+    pub glow: bool,
+    /// Whether circles and lines get soft edges
+    pub smooth_edges: bool,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Settings { audio_offset_ms: 0.0, last_chart: None, palette: PALETTE_NAMES[0].to_string(), reduce_flashing: false, hitstop: true, rewind: true, sfx_volume: 0.7, rainbow_combo: false, shake_intensity: 1.0, glow: false, smooth_edges: false }
+    }
+}
+impl Settings {
+    /// One setting per line, e.g. `audio_offset_ms = 35`. Settings that aren't listed keep their defaults, unknown ones are skipped.
+    pub fn parse(text: &str) -> Possibly<Self> {
+        let mut settings = Self::default();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            let (key, value) = line.split_once('=').ok_or_else(|| format!("line {}: expected `setting = value`", idx + 1))?;
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "audio_offset_ms" => settings.audio_offset_ms = value.parse()
+                    .map_err(|_| format!("line {}: expected a number of milliseconds, found `{value}`", idx + 1))?,
+                "reduce_flashing" => settings.reduce_flashing = value.parse()
+                    .map_err(|_| format!("line {}: expected `true` or `false`, found `{value}`", idx + 1))?,
+                "hitstop" => settings.hitstop = value.parse()
+                    .map_err(|_| format!("line {}: expected `true` or `false`, found `{value}`", idx + 1))?,
+                "rewind" => settings.rewind = value.parse()
+                    .map_err(|_| format!("line {}: expected `true` or `false`, found `{value}`", idx + 1))?,
+                "shake_intensity" => settings.shake_intensity = value.parse::<f32>()
+                    .map_err(|_| format!("line {}: expected a number from 0 to 2, found `{value}`", idx + 1))?.clamp(0.0, 2.0),
+                "glow" => settings.glow = value.parse()
+                    .map_err(|_| format!("line {}: expected `true` or `false`, found `{value}`", idx + 1))?,
+                "smooth_edges" => settings.smooth_edges = value.parse()
+                    .map_err(|_| format!("line {}: expected `true` or `false`, found `{value}`", idx + 1))?,
+                "rainbow_combo" => settings.rainbow_combo = value.parse()
+                    .map_err(|_| format!("line {}: expected `true` or `false`, found `{value}`", idx + 1))?,
+                "sfx_volume" => settings.sfx_volume = value.parse::<f32>()
+                    .map_err(|_| format!("line {}: expected a volume from 0 to 1, found `{value}`", idx + 1))?.clamp(0.0, 1.0),
+                "last_chart" => settings.last_chart = Some(value.to_string()),
+                "palette" if Palette::named(value).is_some() => settings.palette = value.to_string(),
+                // palettes from newer versions fall back to the default
+                "palette" => println!("line {}: unknown palette `{value}`, expected one of {}", idx + 1, PALETTE_NAMES.join(", ")),
+                _ => {}
+            }
+        }
+        Ok(settings)
+    }
+    pub fn serialize(&self) -> String {
+        let mut text = format!("audio_offset_ms = {}\npalette = {}\nreduce_flashing = {}\nhitstop = {}\nrewind = {}\nsfx_volume = {}\nrainbow_combo = {}\nshake_intensity = {}\nglow = {}\nsmooth_edges = {}\n",
+            self.audio_offset_ms, self.palette, self.reduce_flashing, self.hitstop, self.rewind, self.sfx_volume, self.rainbow_combo, self.shake_intensity, self.glow, self.smooth_edges);
+        if let Some(path) = &self.last_chart {
+            text += &format!("last_chart = {path}\n");
+        }
+        text
+    }
+    /// The chosen palette, the default one if it's unknown.
+    pub fn palette(&self) -> Palette {
+        Palette::named(&self.palette).unwrap_or_default()
+    }
+}
@@ -1,10 +1,12 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
-use std::sync::{Arc, Mutex};
+use std::{collections::HashMap, sync::{Arc, Mutex}};
 
 type ThreadSafe<T> = Arc<Mutex<T>>;
 
-use soloud::{Soloud, AudioExt, Handle, SoloudError};
+use soloud::{Soloud, AudioExt, LoadExt, Handle, SoloudError, Sfxr, SfxrPreset, Wav};
+
+use crate::tempo::TempoMap;
 
 pub struct SfxCreator {
     sl: ThreadSafe<Soloud>
@@ -14,24 +16,158 @@ impl SfxCreator {
     pub fn spawn_sfx(&self, sfx: &impl AudioExt) -> Handle { self.sl.lock().unwrap().play(sfx) }
 }
 
+/// Different pitches of one sound that play in the same frame at most, the loudest ones if there are more
+pub const SOUND_POLYPHONY: usize = 3;
+/// Pitches closer than this are the same, as far as playing them together goes
+const SAME_PITCH: f32 = 0.02;
+/// Where `SoundId::Custom` sounds are loaded from, `<id>.wav` in it
+pub const CUSTOM_SOUNDS_DIR: &str = "sounds";
+
+/// A sound effect obstacles can ask for, see `UpdateAccumulator::sound`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundId {
+    LaserFire,
+    BombExplode,
+    Warning,
+    Graze,
+    Pickup,
+    /// `<id>.wav` in `CUSTOM_SOUNDS_DIR`
+    Custom(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SoundRequest {
+    pub id: SoundId,
+    /// Out of 1, before the sound effects volume
+    pub volume: f32,
+    /// Playback speed, 1 being as recorded
+    pub pitch: f32,
+}
+
+/// The sounds asked for over a frame.\
+/// Asking for a sound that's already there at the same pitch only makes it as loud as the loudest ask,
+/// so a ring of 40 pellets plays its sound once rather than 40 times over.
+#[derive(Debug, Clone, Default)]
+pub struct SoundQueue(Vec<SoundRequest>);
+impl SoundQueue {
+    pub fn request(&mut self, request: SoundRequest) {
+        let mut same = self.0.iter_mut().filter(|r| r.id == request.id).peekable();
+        if same.peek().is_none() {
+            self.0.push(request);
+            return;
+        }
+        let mut playing = 0;
+        let mut quietest: Option<&mut SoundRequest> = None;
+        for r in same {
+            if (r.pitch - request.pitch).abs() < SAME_PITCH {
+                r.volume = r.volume.max(request.volume);
+                return;
+            }
+            playing += 1;
+            if quietest.as_ref().is_none_or(|q| r.volume < q.volume) { quietest = Some(r); }
+        }
+        if playing < SOUND_POLYPHONY {
+            self.0.push(request);
+        } else if let Some(quietest) = quietest.filter(|q| q.volume < request.volume) {
+            *quietest = request;
+        }
+    }
+    /// Takes out everything asked for, in the order first asked.
+    pub fn drain(&mut self) -> std::vec::Drain<'_, SoundRequest> {
+        self.0.drain(..)
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+enum Sfx {
+    Synth(Sfxr),
+    File(Wav),
+}
+
+/// What each `SoundId` plays, made at startup. Sounds that couldn't be made, and all of them without audio, are skipped.
+#[derive(Default)]
+pub struct SoundBank {
+    sl: Option<ThreadSafe<Soloud>>,
+    sounds: HashMap<SoundId, Sfx>,
+}
+impl SoundBank {
+    /// Synthesizes the built-in sounds and loads the custom ones from `CUSTOM_SOUNDS_DIR`.
+    pub fn load(sl: ThreadSafe<Soloud>) -> Self {
+        let mut sounds = HashMap::new();
+        let presets = [
+            (SoundId::LaserFire, SfxrPreset::Laser),
+            (SoundId::BombExplode, SfxrPreset::Explosion),
+            (SoundId::Warning, SfxrPreset::Blip),
+            (SoundId::Graze, SfxrPreset::Hurt),
+            (SoundId::Pickup, SfxrPreset::Coin),
+        ];
+        for (id, preset) in presets {
+            let mut sfx = Sfxr::default();
+            match sfx.load_preset(preset, 0) {
+                Ok(()) => { sounds.insert(id, Sfx::Synth(sfx)); }
+                Err(e) => println!("couldn't make the {id:?} sound: {e}"),
+            }
+        }
+        // no custom sounds is fine
+        for entry in std::fs::read_dir(CUSTOM_SOUNDS_DIR).into_iter().flatten().flatten() {
+            let path = entry.path();
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()).and_then(|s| s.parse().ok()) else { continue };
+            let mut wav = Wav::default();
+            match wav.load(&path) {
+                Ok(()) => { sounds.insert(SoundId::Custom(id), Sfx::File(wav)); }
+                Err(e) => println!("couldn't load {}: {e}", path.display()),
+            }
+        }
+        SoundBank { sl: Some(sl), sounds }
+    }
+    /// Plays `requests` at `volume` times their own.
+    pub fn play(&self, requests: impl Iterator<Item = SoundRequest>, volume: f32) {
+        let Some(sl) = &self.sl else { return };
+        if volume <= 0.0 { return; }
+        let mut sl = sl.lock().unwrap();
+        for request in requests {
+            let volume = request.volume * volume;
+            let handle = match self.sounds.get(&request.id) {
+                Some(Sfx::Synth(sfx)) => sl.play_ex(sfx, volume, 0.0, false, Handle::PRIMARY),
+                Some(Sfx::File(sfx)) => sl.play_ex(sfx, volume, 0.0, false, Handle::PRIMARY),
+                None => continue,
+            };
+            if request.pitch != 1.0 {
+                // a sound too short to still be playing is fine
+                let _ = sl.set_relative_play_speed(handle, request.pitch);
+            }
+        }
+    }
+}
+
 pub struct Music {
     sl: ThreadSafe<Soloud>,
     handle: Option<Handle>,
-    bpm: f32,
+    tempo: TempoMap,
     offset: f32,
+    /// Seconds the song position is ahead of the time played, from seeking
     sought: f32,
     speed: f32,
+    /// The game's time scale, on top of `speed` but not counted in the beat
+    time_scale: f32,
+    /// Seconds the player hears the audio late by, from calibration. The beat lags the audio by this much.
+    audio_offset: f32,
 }
 impl Music {
     pub fn new(sl: ThreadSafe<Soloud>) -> Self {
-        Music { sl, handle: None, bpm: 0.0, offset: 0.0, sought: 0.0, speed: 1.0 }
+        Music { sl, handle: None, tempo: TempoMap::default(), offset: 0.0, sought: 0.0, speed: 1.0, time_scale: 1.0, audio_offset: 0.0 }
     }
-    pub fn replace(&mut self, new_music: &impl AudioExt, bpm: f32, offset: f32) -> Handle {
+    pub fn replace(&mut self, new_music: &impl AudioExt, tempo: TempoMap, offset: f32) -> Handle {
         if let Some(handle) = self.handle { self.sl.lock().unwrap().stop(handle); }
         let handle = self.sl.lock().unwrap().play(new_music);
         //self.sl.lock().unwrap().seek(handle, offset as f64 * self.bpm as f64 / 60.0);
         self.handle = Some(handle);
-        self.bpm = bpm;
+        self.tempo = tempo;
         self.offset = offset;
         self.sought = 0.0;
         handle
@@ -40,14 +176,50 @@ impl Music {
         self.speed = speed;
         if let Some(handle) = self.handle {
             let mut guard = self.sl.lock().unwrap();
-            Some(guard.set_relative_play_speed(handle, speed))
+            Some(guard.set_relative_play_speed(handle, speed * self.time_scale))
         } else {
             None
         }
     }
+    /// Plays the music `scale` times faster on top of the speed. The beat follows, as it comes from the song position.
+    pub fn set_time_scale(&mut self, scale: f32) {
+        if scale == self.time_scale { return; }
+        self.time_scale = scale;
+        if let Some(handle) = self.handle {
+            if let Err(e) = self.sl.lock().unwrap().set_relative_play_speed(handle, self.speed * scale) {
+                println!("couldn't change the music's speed: {e}");
+            }
+        }
+    }
     pub fn get_speed(&self) -> f32 { self.speed }
+    pub fn set_audio_offset_ms(&mut self, ms: f32) { self.audio_offset = ms / 1000.0; }
+    pub fn audio_offset_ms(&self) -> f32 { self.audio_offset * 1000.0 }
+    pub fn tempo(&self) -> &TempoMap { &self.tempo }
+    /// Seconds into the song, seeks included.
+    fn song_time(&self, sl: &Soloud, h: Handle) -> f32 {
+        sl.stream_time(h) as f32 + self.sought
+    }
+    /// The tempo at the current position of the song.
+    pub fn bpm(&self) -> f32 {
+        match self.handle {
+            Some(h) => self.tempo.bpm_at_seconds(self.song_time(&self.sl.lock().unwrap(), h)),
+            None => self.tempo.bpm_at_seconds(0.0)
+        }
+    }
+    /// Pauses or resumes the music. The beat stays where it is while paused.
+    pub fn pause(&mut self, paused: bool) {
+        if let Some(handle) = self.handle {
+            self.sl.lock().unwrap().set_pause(handle, paused);
+        }
+    }
+    /// Stops the song and forgets it, returning its handle.
     pub fn stop(&mut self) -> Option<Handle> {
-        if let Some(handle) = self.handle { self.handle = None; Some(handle) }
+        if let Some(handle) = self.handle {
+            // only forgetting the handle left the song playing on under whatever played next, e.g. a restart
+            self.sl.lock().unwrap().stop(handle);
+            self.handle = None;
+            Some(handle)
+        }
         else { None }
     }
     pub fn current_beat(&self) -> Option<f32> {
@@ -58,7 +230,7 @@ impl Music {
                 let buf_size = sl.backend_buffer_size() as f32;
                 let offset = buf_size / sr;
                 
-                let beat = ((sl.stream_time(h) as f32 + offset) * self.bpm / 60.0 + self.offset + self.sought) * self.speed;
+                let beat = (self.tempo.seconds_to_beats(self.song_time(&sl, h) + offset - self.audio_offset) + self.offset) * self.speed;
                 Some(beat)
             }
             None => None
@@ -78,12 +250,71 @@ impl Music {
             false
         }
     }
+    /// Seeks to `beats` from the start of the track, regardless of earlier seeks.
+    /// The audio goes to `audio_offset` past it, so the beat lands on `beats`.\
+    /// Takes the same units as `seek`, which is only correct once right after `replace`.
+    pub fn seek_to(&mut self, beats: f32) -> Result<(), SoloudError> {
+        if let Some(h) = self.handle {
+            let sl = self.sl.lock().unwrap();
+            let target = (self.tempo.beats_to_seconds(beats) + self.audio_offset).max(0.0);
+            sl.seek(h, target as f64)?;
+            self.sought = target - sl.stream_time(h) as f32;
+        }
+        Ok(())
+    }
     pub fn seek(&mut self, beats: f32) -> Result<(), SoloudError> {
         if let Some(h) = self.handle {
             let sl = self.sl.lock().unwrap();
-            sl.seek(h, (beats * 60.0 / self.bpm) as f64)?;
-            self.sought += beats;
+            let target = self.tempo.beats_to_seconds(beats);
+            sl.seek(h, target as f64)?;
+            self.sought += target;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ask(queue: &mut SoundQueue, id: SoundId, volume: f32, pitch: f32) {
+        queue.request(SoundRequest { id, volume, pitch });
+    }
+
+    #[test]
+    fn the_same_sound_plays_once_as_loud_as_asked() {
+        let mut queue = SoundQueue::default();
+        // a ring of 40 pellets
+        for i in 0..40 {
+            ask(&mut queue, SoundId::Graze, 0.2 + i as f32 / 100.0, 1.0 + (i % 2) as f32 * SAME_PITCH / 2.0);
+        }
+        let played = queue.drain().collect::<Vec<_>>();
+        assert_eq!(played, [SoundRequest { id: SoundId::Graze, volume: 0.59, pitch: 1.0 }]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn different_sounds_all_play_in_the_order_asked() {
+        let mut queue = SoundQueue::default();
+        for id in [SoundId::Pickup, SoundId::LaserFire, SoundId::Custom(3), SoundId::Custom(4), SoundId::LaserFire, SoundId::Pickup] {
+            ask(&mut queue, id, 1.0, 1.0);
+        }
+        let ids = queue.drain().map(|r| r.id).collect::<Vec<_>>();
+        assert_eq!(ids, [SoundId::Pickup, SoundId::LaserFire, SoundId::Custom(3), SoundId::Custom(4)]);
+    }
+
+    #[test]
+    fn pitches_of_a_sound_are_capped_keeping_the_loudest() {
+        let mut queue = SoundQueue::default();
+        for (volume, pitch) in [(0.5, 1.0), (0.3, 1.5), (0.8, 2.0)] {
+            ask(&mut queue, SoundId::LaserFire, volume, pitch);
+        }
+        assert_eq!(queue.len(), SOUND_POLYPHONY);
+        // quieter than all of them, dropped
+        ask(&mut queue, SoundId::LaserFire, 0.1, 0.5);
+        // louder than the quietest, taking its place
+        ask(&mut queue, SoundId::LaserFire, 0.9, 3.0);
+        let played = queue.drain().map(|r| (r.volume, r.pitch)).collect::<Vec<_>>();
+        assert_eq!(played, [(0.5, 1.0), (0.9, 3.0), (0.8, 2.0)]);
+    }
+}
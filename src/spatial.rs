@@ -0,0 +1,200 @@
+//! A uniform grid over the screen for finding the obstacles near a player without going through all of them.\
+//! Rebuilt every frame from the obstacles' `bounds`, keeping its lists so nothing is allocated once it's warmed up.
+
+use macroquad::prelude::{Rect, Vec2, vec2};
+
+use crate::{game_objects::Obst, utils::{screen_width, screen_height}};
+
+/// Pixels per cell, a couple of pellets across. Anything smaller goes in a single cell
+pub const CELL_SIZE: f32 = 48.0;
+/// Obstacles covering more cells than this are checked every time instead, it's cheaper than filling the grid
+pub const MAX_CELLS: usize = 512;
+
+/// Obstacle indices by the cells their bounds cover. Anything past the edge of the screen goes in the edge cells.\
+/// The cells are laid out one after another in a single list, counted first and then filled, which beats a list per cell.
+pub struct SpatialHash {
+    cols: usize,
+    rows: usize,
+    /// Where each cell's indices start in `entries`, with where the last one ends after them
+    starts: Vec<usize>,
+    entries: Vec<usize>,
+    /// (obstacle, cells it covers) of everything placed this frame
+    placed: Vec<(usize, Span)>,
+    /// Obstacles without bounds, or with too big ones
+    everywhere: Vec<usize>,
+    /// The last query's results
+    found: Vec<usize>,
+}
+/// (first column, first row, last column, last row) of a group of cells
+type Span = (usize, usize, usize, usize);
+impl Default for SpatialHash {
+    fn default() -> Self {
+        SpatialHash {
+            cols: (screen_width() / CELL_SIZE).ceil() as usize,
+            rows: (screen_height() / CELL_SIZE).ceil() as usize,
+            starts: vec![], entries: vec![], placed: vec![], everywhere: vec![], found: vec![],
+        }
+    }
+}
+impl SpatialHash {
+    fn cell(&self, at: Vec2) -> (usize, usize) {
+        // casting saturates, so anything left of or above the screen ends up in the first cells
+        (((at.x / CELL_SIZE) as usize).min(self.cols - 1), ((at.y / CELL_SIZE) as usize).min(self.rows - 1))
+    }
+    fn span(&self, area: Rect) -> Span {
+        let corner = area.point() + vec2(area.w, area.h);
+        let (x0, y0) = self.cell(area.point().min(corner));
+        let (x1, y1) = self.cell(area.point().max(corner));
+        (x0, y0, x1, y1)
+    }
+    fn cells(span: Span) -> usize {
+        (span.2 - span.0 + 1) * (span.3 - span.1 + 1)
+    }
+    /// Buckets `obsts` by their bounds, forgetting the last frame's.
+    pub fn rebuild(&mut self, obsts: &[Obst]) {
+        self.starts.clear();
+        self.starts.resize(self.cols * self.rows + 1, 0);
+        self.placed.clear();
+        self.everywhere.clear();
+        for (i, obst) in obsts.iter().enumerate() {
+            // bounds that aren't numbers end up in the first cell, where nothing ever overlaps them anyway
+            let Some(bounds) = obst.obstacle.bounds() else {
+                self.everywhere.push(i);
+                continue;
+            };
+            let span = if bounds.w.abs() <= CELL_SIZE && bounds.h.abs() <= CELL_SIZE {
+                // small ones only go in the cell of their center, queries look half a cell further for them
+                let (x, y) = self.cell(bounds.center());
+                (x, y, x, y)
+            } else {
+                self.span(bounds)
+            };
+            if Self::cells(span) > MAX_CELLS {
+                self.everywhere.push(i);
+                continue;
+            }
+            for y in span.1..=span.3 {
+                for x in span.0..=span.2 {
+                    self.starts[y * self.cols + x] += 1;
+                }
+            }
+            self.placed.push((i, span));
+        }
+        for cell in 1..self.starts.len() {
+            self.starts[cell] += self.starts[cell - 1];
+        }
+        self.entries.clear();
+        self.entries.resize(self.starts[self.starts.len() - 1], 0);
+        // each cell counted up to its end, and is filled back to front so `starts` ends up at the fronts
+        for &(i, span) in self.placed.iter().rev() {
+            for y in span.1..=span.3 {
+                for x in span.0..=span.2 {
+                    let end = &mut self.starts[y * self.cols + x];
+                    *end -= 1;
+                    self.entries[*end] = i;
+                }
+            }
+        }
+    }
+    /// Indices of the obstacles that might reach `area`, in order and each once.
+    pub fn query(&mut self, area: Rect) -> &[usize] {
+        self.found.clear();
+        self.found.extend_from_slice(&self.everywhere);
+        // nothing's placed before the first rebuild
+        if !self.starts.is_empty() {
+            let half = CELL_SIZE / 2.0;
+            let span = self.span(Rect::new(area.x - half, area.y - half, area.w + CELL_SIZE, area.h + CELL_SIZE));
+            for y in span.1..=span.3 {
+                for x in span.0..=span.2 {
+                    let cell = y * self.cols + x;
+                    self.found.extend_from_slice(&self.entries[self.starts[cell]..self.starts[cell + 1]]);
+                }
+            }
+        }
+        self.found.sort_unstable();
+        self.found.dedup();
+        &self.found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::prelude::Vec2;
+
+    use super::*;
+    use crate::{game_objects::{Pellet, GrowLaser, Periodic, Player}, game::UpdateAccumulator, utils::{GameRng, circle_bounds, screen_size, best_of}};
+
+    /// Pellets of every size, some off the screen, lasers across it and something without bounds.
+    fn random_scene(rng: &mut GameRng, pellets: usize) -> Vec<Obst> {
+        let margin = vec2(200.0, 200.0);
+        let mut obsts = (0..pellets).map(|_| {
+            let rad = if rng.chance(0.9) { rng.range(2.0, 20.0) } else { rng.range(20.0, 400.0) };
+            Obst::new(Box::new(Pellet::new(rng.vec(-margin, screen_size() + margin), Vec2::ZERO, rad)), 0.0)
+        }).collect::<Vec<_>>();
+        for _ in 0..pellets / 50 {
+            let (start, end) = (rng.vec(-margin, screen_size() + margin), rng.vec(-margin, screen_size() + margin));
+            let mut laser = GrowLaser::new(start, end, rng.range(5.0, 60.0), 0.0, 4.0, Vec2::ZERO);
+            laser.current_time = 1.0;
+            obsts.push(Obst::new(Box::new(laser), 0.0));
+        }
+        obsts.push(Obst::new(Box::new(Periodic::new(1, 1.0, Box::new(|_: &mut UpdateAccumulator, _| {}))), 0.0));
+        obsts
+    }
+
+    #[test]
+    fn finds_what_a_linear_scan_does() {
+        let mut rng = GameRng::new(936);
+        let mut grid = SpatialHash::default();
+        for round in 0..50 {
+            let obsts = random_scene(&mut rng, 500);
+            grid.rebuild(&obsts);
+            for _ in 0..200 {
+                let player = Player { pos: rng.vec(vec2(-300.0, -300.0), screen_size() + 300.0), rad: rng.range(1.0, 60.0), ..Player::default() };
+                let linear = (0..obsts.len()).filter(|&i| obsts[i].collides(player)).collect::<Vec<_>>();
+                let near = grid.query(circle_bounds(player.pos, player.rad));
+                assert!(near.windows(2).all(|w| w[0] < w[1]), "query results out of order or repeated");
+                let hashed = near.iter().copied().filter(|&i| obsts[i].collides(player)).collect::<Vec<_>>();
+                assert_eq!(hashed, linear, "round {round} at {:?} radius {}", player.pos, player.rad);
+            }
+        }
+    }
+
+    #[test]
+    fn queries_before_a_rebuild_find_nothing() {
+        assert!(SpatialHash::default().query(Rect::new(0.0, 0.0, 100.0, 100.0)).is_empty());
+    }
+
+    #[test]
+    fn rebuilding_keeps_its_allocations() {
+        let mut rng = GameRng::new(9360);
+        let obsts = random_scene(&mut rng, 2000);
+        let mut grid = SpatialHash::default();
+        grid.rebuild(&obsts);
+        let capacity = (grid.starts.capacity(), grid.entries.capacity(), grid.placed.capacity());
+        for _ in 0..10 {
+            grid.rebuild(&obsts);
+        }
+        assert_eq!((grid.starts.capacity(), grid.entries.capacity(), grid.placed.capacity()), capacity);
+    }
+
+    #[test]
+    fn grid_checks_a_fraction_of_the_pairs_and_beats_a_linear_scan() {
+        let mut rng = GameRng::new(5000);
+        let obsts = random_scene(&mut rng, 2000);
+        let players = (0..500).map(|_| Player { pos: rng.vec(Vec2::ZERO, screen_size()), rad: 8.0, ..Player::default() }).collect::<Vec<_>>();
+        let mut grid = SpatialHash::default();
+        grid.rebuild(&obsts);
+        let checked = players.iter().map(|p| grid.query(circle_bounds(p.pos, p.rad)).len()).sum::<usize>();
+        assert!(checked * 10 < obsts.len() * players.len(), "{checked} of {} pairs checked", obsts.len() * players.len());
+
+        let (mut linear, mut hashed) = (0, 0);
+        let linear_secs = best_of(|| linear = players.iter().map(|&p| obsts.iter().filter(|o| o.collides(p)).count()).sum::<usize>());
+        // with the rebuild, which is done every frame
+        let hashed_secs = best_of(|| {
+            grid.rebuild(&obsts);
+            hashed = players.iter().map(|&p| grid.query(circle_bounds(p.pos, p.rad)).iter().filter(|&&i| obsts[i].collides(p)).count()).sum::<usize>();
+        });
+        assert_eq!(linear, hashed);
+        assert!(hashed_secs < linear_secs, "{hashed_secs}s with the grid, {linear_secs}s linear");
+    }
+}
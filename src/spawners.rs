@@ -1,8 +1,10 @@
 
-use macroquad::{window::{screen_width, screen_height}, prelude::{Vec2, rand::gen_range, vec2}};
-use rand::{thread_rng, Rng};
+use std::f32::consts::TAU;
 
-use crate::game::{ModifyArgs, UpdateAccumulator};
+use macroquad::prelude::{Vec2, vec2};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{game::{ModifyArgs, UpdateAccumulator}, utils::{rotate, hash_seed, screen_width, screen_height, anchored, Anchor}};
 
 use super::{game::{GameState, Accumulatee}, game_objects::{Bomb, Obst, GrowLaser}};
 
@@ -41,9 +43,10 @@ impl Accumulatee for BombSideSpawner {
         Box::new(self.clone())
     }
     fn run(&self, gs: &mut UpdateAccumulator, _: ModifyArgs) {
+        let (start_y, target_y) = (gs.rng().range(0.0, screen_height()), gs.rng().range(0.0, screen_height()));
         gs.obst(Bomb::new(
-            Vec2 { x: screen_width(), y: gen_range(0.0, screen_height()) },
-            Vec2 { x: screen_width() - 100.0, y: gen_range(0.0, screen_height()) },
+            anchored(Anchor::TopRight, vec2(0.0, start_y)),
+            anchored(Anchor::TopRight, vec2(-100.0, target_y)),
             self.bomb_life, self.pellets, self.pellet_vel, self.pellet_rad, self.spawner.box_clone()
         ))
     }
@@ -83,9 +86,10 @@ impl HorLaserSpawner {
 impl Accumulatee for HorLaserSpawner {
     fn box_clone(&self) -> Box<dyn Accumulatee> { Box::new(self.clone()) }
     fn run(&self, gs: &mut UpdateAccumulator, _: ModifyArgs) {
-        let y = gen_range(0.0, screen_height());
+        let y = gs.rng().range(0.0, screen_height());
+        let jerk = gs.rng().range(-self.jerk, self.jerk);
         gs.obst(
-            GrowLaser::new(vec2(-100.0, y), vec2(screen_width() + 100.0, y), self.thickness, self.warning_time, self.show_time, vec2(gen_range(-self.jerk, self.jerk), 0.0))
+            GrowLaser::new(vec2(-100.0, y), vec2(screen_width() + 100.0, y), self.thickness, self.warning_time, self.show_time, vec2(jerk, 0.0))
         );
     }
 }
@@ -104,9 +108,10 @@ impl VertLaserSpawner {
 impl Accumulatee for VertLaserSpawner {
     fn box_clone(&self) -> Box<dyn Accumulatee> { Box::new(self.clone()) }
     fn run(&self, gs: &mut UpdateAccumulator, _: ModifyArgs) {
-        let x = gen_range(0.0, screen_width());
+        let x = gs.rng().range(0.0, screen_width());
+        let jerk = gs.rng().range(-self.jerk, self.jerk);
         gs.obst(
-            GrowLaser::new(vec2(x, -100.0), vec2(x, screen_height() + 100.0), self.thickness, self.warning_time, self.show_time, vec2(0.0, gen_range(-self.jerk, self.jerk)))
+            GrowLaser::new(vec2(x, -100.0), vec2(x, screen_height() + 100.0), self.thickness, self.warning_time, self.show_time, vec2(0.0, jerk))
         );
     }
 }
@@ -125,7 +130,7 @@ impl LaserSpawner {
 impl Accumulatee for LaserSpawner {
     fn box_clone(&self) -> Box<dyn Accumulatee> { Box::new(self.clone()) }
     fn run(&self, gs: &mut UpdateAccumulator, sm: ModifyArgs) {
-        if thread_rng().gen_bool(0.5) {
+        if gs.rng().chance(0.5) {
             HorLaserSpawner::new(self.warning_time, self.show_time, self.thickness, self.jerk).run(gs, sm)
         } else {
             VertLaserSpawner::new(self.warning_time, self.show_time, self.thickness, self.jerk).run(gs, sm)
@@ -133,7 +138,153 @@ impl Accumulatee for LaserSpawner {
     }
 }
 
+/// Bounds for `jitter`. All perturbations are uniform within `-x..x`, except `pos` which is a disc.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct JitterSpec {
+    /// Radius of the disc positions are moved within
+    pub pos: f32,
+    /// Maximum velocity rotation in radians
+    pub vel_angle: f32,
+    /// Maximum velocity magnitude change, as a fraction of the original
+    pub vel_mag: f32,
+    /// Maximum radius change
+    pub rad: f32,
+    /// If set, the jitter is the same every run instead of following the run's seed.\
+    /// Either way it's deterministic per (`args.time`, `args.step`).
+    pub seed: Option<u64>
+}
+impl JitterSpec {
+    fn apply(&self, mut args: ModifyArgs, mut rand: impl FnMut() -> f32) -> ModifyArgs {
+        let mut signed = || rand() * 2.0 - 1.0;
+        let dist = self.pos * (signed() * 0.5 + 0.5).sqrt();
+        let ang = (signed() * 0.5 + 0.5) * TAU;
+        args.pos += vec2(ang.sin(), ang.cos()) * dist;
+        args.vel = rotate(args.vel, signed() * self.vel_angle) * (1.0 + signed() * self.vel_mag);
+        args.rad = (args.rad + signed() * self.rad).max(0.0);
+        args
+    }
+}
+
+pub struct Jitter {
+    pub inner: Box<dyn Accumulatee>,
+    pub spec: JitterSpec
+}
+impl Clone for Jitter {
+    fn clone(&self) -> Self {
+        Jitter { inner: self.inner.box_clone(), spec: self.spec }
+    }
+}
+impl Accumulatee for Jitter {
+    fn box_clone(&self) -> Box<dyn Accumulatee> { Box::new(self.clone()) }
+    fn run(&self, gs: &mut UpdateAccumulator, args: ModifyArgs) {
+        let args = match self.spec.seed {
+            Some(seed) => {
+                let mut rng = StdRng::seed_from_u64(hash_seed(seed, args.time, args.step));
+                self.spec.apply(args, || rng.gen())
+            }
+            None => {
+                let mut rng = gs.rng_at(args.time, args.step);
+                self.spec.apply(args, || rng.range(0.0, 1.0))
+            }
+        };
+        self.inner.run(gs, args)
+    }
+}
+/// Randomly perturbs the `ModifyArgs` passed to `inner` (e.g. `Bomb::pellet_spawner`) within `spec`.
+pub fn jitter(inner: Box<dyn Accumulatee>, spec: JitterSpec) -> Box<dyn Accumulatee> {
+    Box::new(Jitter { inner, spec })
+}
+
 pub struct LaserArray {
     pub warning_time: f32,
     
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::utils::GameRng;
+
+    const SPEC: JitterSpec = JitterSpec { pos: 20.0, vel_angle: 0.3, vel_mag: 0.25, rad: 4.0, seed: Some(884) };
+
+    /// The args `spec` hands on for each (time, step) of `calls`, in the order asked, through an accumulator seeded with `run_seed`.
+    fn jittered(spec: JitterSpec, run_seed: u64, calls: &[(f32, usize)]) -> Vec<(Vec2, Vec2, f32)> {
+        let seen = Rc::new(RefCell::new(vec![]));
+        let record = {
+            let seen = seen.clone();
+            move |_: &mut UpdateAccumulator, args: ModifyArgs| seen.borrow_mut().push((args.pos, args.vel, args.rad))
+        };
+        let spawner = jitter(Box::new(record), spec);
+        let mut accum = UpdateAccumulator::new();
+        *accum.rng() = GameRng::new(run_seed);
+        for &(time, step) in calls {
+            // what's drawn from the run's generator in between mustn't matter
+            accum.rng().range(0.0, 1.0);
+            spawner.run(&mut accum, ModifyArgs::new(time).step(step).pos(vec2(100.0, 100.0)).vel(vec2(200.0, 0.0)).rad(10.0));
+        }
+        let seen = seen.borrow().clone();
+        seen
+    }
+
+    fn calls() -> Vec<(f32, usize)> {
+        (0..32).map(|i| (i as f32 * 0.25, i % 8)).collect()
+    }
+
+    #[test]
+    fn seeded_jitter_is_the_same_every_time_and_in_any_order() {
+        let first = jittered(SPEC, 1, &calls());
+        assert_eq!(first, jittered(SPEC, 1, &calls()));
+        // the seed is the spec's, not the run's
+        assert_eq!(first, jittered(SPEC, 2, &calls()));
+        let mut backwards = calls();
+        backwards.reverse();
+        let mut again = jittered(SPEC, 1, &backwards);
+        again.reverse();
+        assert_eq!(first, again);
+        // and every (time, step) is jittered differently
+        assert!(first.windows(2).all(|w| w[0] != w[1]));
+    }
+
+    #[test]
+    fn different_seeds_jitter_differently() {
+        let first = jittered(SPEC, 1, &calls());
+        let other = jittered(JitterSpec { seed: Some(885), ..SPEC }, 1, &calls());
+        assert!(first.iter().zip(&other).all(|(a, b)| a != b));
+    }
+
+    #[test]
+    fn unseeded_jitter_follows_the_runs_seed() {
+        let spec = JitterSpec { seed: None, ..SPEC };
+        let first = jittered(spec, 7, &calls());
+        assert_eq!(first, jittered(spec, 7, &calls()));
+        let mut backwards = calls();
+        backwards.reverse();
+        let mut again = jittered(spec, 7, &backwards);
+        again.reverse();
+        assert_eq!(first, again);
+        assert_ne!(first, jittered(spec, 8, &calls()));
+    }
+
+    #[test]
+    fn jitter_stays_in_its_bounds() {
+        let many = (0..2000).map(|i| (i as f32 * 0.01, i)).collect::<Vec<_>>();
+        let spec = JitterSpec { rad: 15.0, ..SPEC };
+        let spread = jittered(spec, 1, &many);
+        for &(pos, vel, rad) in &spread {
+            assert!(pos.distance(vec2(100.0, 100.0)) <= spec.pos + 1e-3, "{pos}");
+            assert!(vel.angle_between(Vec2::X).abs() <= spec.vel_angle + 1e-4, "{vel}");
+            let scale = vel.length() / 200.0;
+            assert!((1.0 - spec.vel_mag - 1e-4..=1.0 + spec.vel_mag + 1e-4).contains(&scale), "{scale}");
+            // radii can't go below nothing
+            assert!((0.0..=10.0 + spec.rad + 1e-4).contains(&rad), "{rad}");
+        }
+        // and they're spread through them, not bunched up
+        assert!(spread.iter().any(|(pos, ..)| pos.distance(vec2(100.0, 100.0)) > spec.pos * 0.9));
+        assert!(spread.iter().any(|(_, _, rad)| *rad == 0.0));
+        // nothing to jitter by leaves the args as they were
+        let still = jittered(JitterSpec { seed: Some(1), ..JitterSpec::default() }, 1, &calls());
+        assert!(still.iter().all(|&a| a == (vec2(100.0, 100.0), vec2(200.0, 0.0), 10.0)));
+    }
+}
@@ -3,14 +3,22 @@ use std::{error::Error, fmt::Display};
 use macroquad::color::Color;
 use soloud::{Wav, AudioExt, LoadExt};
 
-use crate::{game::{GameState, LevelState, ColorEase, StateModifier, ModifyArgs}, sound::Music};
+use crate::{calibration::Calibration, results::Results, chart_select::ChartSelect, editor::Editor, game::{GameState, LevelState, ColorEase, StateModifier, ModifyArgs}, sound::Music};
 
 pub type LevelInfo = (f32, f32, &'static str);
 pub type LevelLoader = fn(&mut GameState) -> LevelInfo;
 
 pub enum EparState {
     MainMenu,
-    InGame(LevelState)
+    InGame(Box<LevelState>),
+    /// Measuring the audio offset, see `Calibration`
+    Calibrating(Calibration),
+    /// After a run, see `Results`
+    Results(Results),
+    /// Picking a chart file to play, see `ChartSelect`
+    ChartSelect(ChartSelect),
+    /// Editing a chart file, see `Editor`
+    Editing(Box<Editor>),
 }
 impl EparState {
     pub fn map<R, F: FnOnce(&mut LevelState) -> R>(&mut self, map_fn: F) -> Option<R> {
@@ -39,9 +47,9 @@ impl StateModifier for ColorChange {
     fn run(&self, state: &mut GameState, _: ModifyArgs) {
         state.state.map(|s|
             if self.is_fg {
-                s.fg_color = self.color.box_clone();
+                s.fg_color = Some(self.color.box_clone());
             } else {
-                s.bg_color = self.color.box_clone();
+                s.bg_color = Some(self.color.box_clone());
             }
         ).unwrap_or(())
     }
@@ -66,7 +74,8 @@ pub enum EparLevel {
     Isolation,
     Kocmoc,
     Sparkler,
-    Firestarter
+    Firestarter,
+    Endless
 }
 impl EparLevel {
     pub fn level(&self) -> LevelLoader {
@@ -80,7 +89,8 @@ impl EparLevel {
             EparLevel::Isolation => levels::isolation,
             EparLevel::Kocmoc => levels::kocmoc,
             EparLevel::Sparkler => levels::sparkler,
-            EparLevel::Firestarter => levels::firestarter
+            EparLevel::Firestarter => levels::firestarter,
+            EparLevel::Endless => levels::endless
         }
     }
     pub fn name(&self) -> &'static str {
@@ -94,6 +104,7 @@ impl EparLevel {
             EparLevel::Kocmoc => "KOCMOC (Albee Remix)",
             EparLevel::Sparkler => "Sparkler",
             EparLevel::Firestarter => "Firestarter",
+            EparLevel::Endless => "Endless",
         }
     }
     /// Used to filter out levels that are under development
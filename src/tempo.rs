@@ -0,0 +1,164 @@
+/// A change of tempo, starting at `time` seconds into the song.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TempoPoint {
+    pub time: f32,
+    pub bpm: f32,
+    pub beats_per_bar: f32,
+}
+
+/// Converts between song time and beats for songs that change tempo.\
+/// Beat 0 is the start of the song. Before the first point, its tempo is extrapolated backwards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TempoMap {
+    /// Sorted by time, never empty
+    points: Vec<TempoPoint>,
+    /// The beat each point starts at
+    beats: Vec<f32>,
+}
+impl Default for TempoMap {
+    fn default() -> Self { Self::constant(120.0) }
+}
+impl TempoMap {
+    /// A single tempo in 4/4, the whole song long.
+    pub fn constant(bpm: f32) -> Self {
+        Self::new(vec![TempoPoint { time: 0.0, bpm, beats_per_bar: 4.0 }])
+    }
+    /// Panics if `points` is empty.
+    pub fn new(mut points: Vec<TempoPoint>) -> Self {
+        assert!(!points.is_empty(), "a tempo map needs at least one point");
+        points.sort_by(|a, b| a.time.total_cmp(&b.time));
+        let mut beats = vec![points[0].time * points[0].bpm / 60.0];
+        for pair in points.windows(2) {
+            beats.push(beats[beats.len() - 1] + (pair[1].time - pair[0].time) * pair[0].bpm / 60.0);
+        }
+        TempoMap { points, beats }
+    }
+    /// Adds a tempo change, replacing any other change at the same time.
+    pub fn set(&mut self, point: TempoPoint) {
+        let mut points = self.points.clone();
+        points.retain(|p| p.time != point.time);
+        points.push(point);
+        *self = Self::new(points);
+    }
+    pub fn points(&self) -> &[TempoPoint] {
+        &self.points
+    }
+    /// Index of the segment `key` falls in, the first one for anything before it.
+    fn segment(&self, key: impl Fn(usize) -> f32, at: f32) -> usize {
+        (0..self.points.len()).rev().find(|&i| key(i) <= at).unwrap_or(0)
+    }
+    fn segment_at_seconds(&self, secs: f32) -> usize {
+        self.segment(|i| self.points[i].time, secs)
+    }
+    fn segment_at_beats(&self, beats: f32) -> usize {
+        self.segment(|i| self.beats[i], beats)
+    }
+    pub fn seconds_to_beats(&self, secs: f32) -> f32 {
+        let i = self.segment_at_seconds(secs);
+        self.beats[i] + (secs - self.points[i].time) * self.points[i].bpm / 60.0
+    }
+    pub fn beats_to_seconds(&self, beats: f32) -> f32 {
+        let i = self.segment_at_beats(beats);
+        self.points[i].time + (beats - self.beats[i]) * 60.0 / self.points[i].bpm
+    }
+    pub fn bpm_at_seconds(&self, secs: f32) -> f32 {
+        self.points[self.segment_at_seconds(secs)].bpm
+    }
+    pub fn bpm_at_beats(&self, beats: f32) -> f32 {
+        self.points[self.segment_at_beats(beats)].bpm
+    }
+    pub fn beats_per_bar_at(&self, beats: f32) -> f32 {
+        self.points[self.segment_at_beats(beats)].beats_per_bar
+    }
+    /// How far into its bar `beats` is, from 0 to 1. Bars start over at each tempo change.
+    pub fn bar_phase(&self, beats: f32) -> f32 {
+        let i = self.segment_at_beats(beats);
+        ((beats - self.beats[i]) / self.points[i].beats_per_bar).rem_euclid(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::GameRng;
+
+    /// A map of 2 to 8 random tempo changes within the first few minutes.
+    fn random_map(rng: &mut GameRng) -> TempoMap {
+        let count = rng.range(2, 9);
+        let points = (0..count).map(|i| TempoPoint {
+            time: if i == 0 { rng.range(-2.0, 2.0) } else { rng.range(0.0, 240.0) },
+            bpm: rng.range(40.0, 300.0),
+            beats_per_bar: rng.range(2, 8) as f32,
+        }).collect();
+        TempoMap::new(points)
+    }
+
+    #[test]
+    fn seconds_and_beats_are_inverses() {
+        let mut rng = GameRng::new(912);
+        for _ in 0..200 {
+            let map = random_map(&mut rng);
+            // the boundaries themselves, and either side of them
+            let boundaries = map.points().iter().flat_map(|p| [p.time - 0.01, p.time, p.time + 0.01]);
+            let times = boundaries.chain((0..50).map(|_| rng.range(-10.0, 300.0))).collect::<Vec<f32>>();
+            for secs in times {
+                let back = map.beats_to_seconds(map.seconds_to_beats(secs));
+                assert!((back - secs).abs() < 1e-3, "{secs}s came back as {back}s in {map:?}");
+            }
+            let beats = map.points().iter().map(|p| map.seconds_to_beats(p.time)).chain((0..50).map(|_| rng.range(-10.0, 600.0))).collect::<Vec<f32>>();
+            for beat in beats {
+                let back = map.seconds_to_beats(map.beats_to_seconds(beat));
+                assert!((back - beat).abs() < 1e-3, "beat {beat} came back as {back} in {map:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn beats_are_monotonic_and_continuous() {
+        let mut rng = GameRng::new(913);
+        for _ in 0..200 {
+            let map = random_map(&mut rng);
+            let mut last = map.seconds_to_beats(-10.0);
+            for i in 1..=3000 {
+                let beats = map.seconds_to_beats(-10.0 + i as f32 * 0.1);
+                assert!(beats > last, "beats went back at {}s in {map:?}", -10.0 + i as f32 * 0.1);
+                last = beats;
+            }
+            for (i, p) in map.points().iter().enumerate().skip(1) {
+                let (before, after) = (map.seconds_to_beats(p.time - 1e-3), map.seconds_to_beats(p.time));
+                // no more than a millisecond's worth of the faster tempo apart
+                let most = 1e-3 * map.points()[i - 1].bpm.max(p.bpm) / 60.0 + 1e-3;
+                assert!((after - before).abs() <= most, "jumped from {before} to {after} at {}s in {map:?}", p.time);
+            }
+        }
+    }
+
+    #[test]
+    fn before_the_first_point_extrapolates_its_tempo() {
+        let map = TempoMap::new(vec![
+            TempoPoint { time: 2.0, bpm: 120.0, beats_per_bar: 4.0 },
+            TempoPoint { time: 10.0, bpm: 60.0, beats_per_bar: 3.0 },
+        ]);
+        // beat 0 is the start of the song
+        assert_eq!(map.seconds_to_beats(0.0), 0.0);
+        assert_eq!(map.seconds_to_beats(-1.0), -2.0);
+        assert_eq!(map.beats_to_seconds(-2.0), -1.0);
+        assert_eq!(map.bpm_at_seconds(-5.0), 120.0);
+        assert_eq!(map.bpm_at_beats(-5.0), 120.0);
+        assert_eq!(map.beats_per_bar_at(-5.0), 4.0);
+        // 20 beats in at the change, then a beat a second
+        assert_eq!(map.seconds_to_beats(10.0), 20.0);
+        assert_eq!(map.seconds_to_beats(12.0), 22.0);
+        assert_eq!(map.bpm_at_beats(20.0), 60.0);
+        assert_eq!(map.bar_phase(20.0), 0.0);
+        assert_eq!(map.bar_phase(21.5), 0.5);
+    }
+
+    #[test]
+    fn setting_a_point_replaces_one_at_the_same_time() {
+        let mut map = TempoMap::constant(120.0);
+        map.set(TempoPoint { time: 0.0, bpm: 90.0, beats_per_bar: 4.0 });
+        assert_eq!(map.points().len(), 1);
+        assert_eq!(map.bpm_at_seconds(0.0), 90.0);
+    }
+}
@@ -0,0 +1,79 @@
+//! The dev-mode strip along the bottom of the screen, showing what the chart spawns over the next few measures.
+
+use macroquad::prelude::*;
+
+use crate::utils::{acmul, screen_width, screen_height};
+
+/// Measures the timeline shows, starting from the current one
+pub const TIMELINE_MEASURES: f32 = 8.0;
+const TIMELINE_HEIGHT: f32 = 36.0;
+
+/// What kind of thing an event spawns, for coloring its tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventCategory {
+    Pellets,
+    Lasers,
+    Rects,
+    Grid,
+    /// Flashes, fades, palette shifts and the like, that spawn nothing
+    Effect,
+    /// Events written in code, which can't be looked into
+    Other,
+}
+impl EventCategory {
+    /// The category of an obstacle from a chart entry, by its kind.
+    pub fn of_kind(kind: &str) -> Self {
+        match kind {
+            "Pellet" | "Bomb" | "CenterProj" | "PelletSpinner" => EventCategory::Pellets,
+            "GrowLaser" | "SlamLaser" | "SpinningArc" => EventCategory::Lasers,
+            "RotatableRect" | "RotatingRect" | "Periodic" => EventCategory::Rects,
+            "GOLGrid" => EventCategory::Grid,
+            _ => EventCategory::Other,
+        }
+    }
+    pub fn color(self) -> Color {
+        match self {
+            EventCategory::Pellets => Color::new(1.0, 0.4, 0.7, 1.0),
+            EventCategory::Lasers => Color::new(0.4, 0.8, 1.0, 1.0),
+            EventCategory::Rects => Color::new(1.0, 0.8, 0.3, 1.0),
+            EventCategory::Grid => Color::new(0.5, 1.0, 0.5, 1.0),
+            EventCategory::Effect => Color::new(0.8, 0.6, 1.0, 1.0),
+            EventCategory::Other => Color::new(0.7, 0.7, 0.7, 1.0),
+        }
+    }
+}
+
+/// The beats the timeline spans at `time`: from the start of the current measure, `TIMELINE_MEASURES` on.
+pub fn span(time: f32, bar: f32) -> (f32, f32) {
+    let start = (time / bar).floor() * bar;
+    (start, start + TIMELINE_MEASURES * bar)
+}
+
+pub fn rect() -> Rect {
+    Rect::new(0.0, screen_height() - TIMELINE_HEIGHT, screen_width(), TIMELINE_HEIGHT)
+}
+
+/// The beat under `x` on the timeline at `time`.
+pub fn beat_at(x: f32, time: f32, bar: f32) -> f32 {
+    let (start, end) = span(time, bar);
+    start + (x / screen_width()).clamp(0.0, 1.0) * (end - start)
+}
+
+/// Draws the ticks of `events` (beat, category) in the span, and the playhead at `time`.
+pub fn draw(events: impl Iterator<Item = (f32, EventCategory)>, time: f32, bar: f32) {
+    let area = rect();
+    let (start, end) = span(time, bar);
+    let x = |beat: f32| area.x + (beat - start) / (end - start) * area.w;
+    draw_rectangle(area.x, area.y, area.w, area.h, Color::new(0.0, 0.0, 0.0, 0.6));
+    for measure in 0..TIMELINE_MEASURES as usize {
+        let mx = x(start + measure as f32 * bar);
+        draw_line(mx, area.y, mx, area.bottom(), 1.0, acmul(WHITE, 0.3));
+        draw_text(&format!("{}", (start / bar) as i32 + measure as i32 + 1), mx + 3.0, area.y + 12.0, 14.0, acmul(WHITE, 0.5));
+    }
+    for (beat, category) in events.filter(|&(beat, _)| beat >= start && beat < end) {
+        let tx = x(beat);
+        draw_line(tx, area.y + 14.0, tx, area.bottom() - 2.0, 2.0, category.color());
+    }
+    let head = x(time);
+    draw_line(head, area.y, head, area.bottom(), 2.0, WHITE);
+}
@@ -1,7 +1,8 @@
 #![allow(dead_code)]
 use std::{f32::consts::{TAU, PI}, ops::Add};
 
-use macroquad::{prelude::{Vec2, vec2, Color}, shapes::draw_triangle, text::{draw_text, measure_text}, window::{screen_width, screen_height}, rand::gen_range};
+use macroquad::{prelude::{Vec2, vec2, vec3, Color, Rect, BLACK, WHITE}, models::{Mesh, Vertex, draw_mesh}, shapes::{draw_triangle, draw_rectangle, draw_line, draw_circle}, text::{draw_text, measure_text}, texture::{Texture2D, FilterMode, DrawTextureParams, draw_texture_ex, render_target}, window::{self, next_frame, clear_background}, camera::{Camera2D, set_camera}, input, time::get_time};
+use rand::{Rng, SeedableRng, rngs::StdRng, distributions::uniform::SampleUniform, seq::SliceRandom};
 
 use crate::game::GSEvent;
 
@@ -42,8 +43,17 @@ pub fn iter<T>(obj: T) -> impl Iterator<Item = T> {
 /// Squares a number.
 #[inline]
 pub fn sq(num: f32) -> f32 { num * num }
-pub fn cmul(clr: Color, val: f32) -> Color { Color { r: clr.r * val, g: clr.g * val, b: clr.b * val, a: clr.a } }
-pub fn acmul(clr: Color, val: f32) -> Color { Color { a: clr.a * val, ..clr } }
+/// Scales the color channels of `clr` by `val`, keeping them between 0 and 1.
+pub fn cmul(clr: Color, val: f32) -> Color { clamp_color(Color { r: clr.r * val, g: clr.g * val, b: clr.b * val, a: clr.a }) }
+/// Scales the alpha of `clr` by `val`, keeping every channel between 0 and 1.
+pub fn acmul(clr: Color, val: f32) -> Color { clamp_color(Color { a: clr.a * val, ..clr }) }
+/// `clr` with an alpha of `a`, kept between 0 and 1.
+pub fn with_alpha(clr: Color, a: f32) -> Color { clamp_color(Color { a, ..clr }) }
+/// Every channel of `clr` between 0 and 1, NaN turning into 0.
+pub fn clamp_color(clr: Color) -> Color {
+    let clamp = |c: f32| if c.is_nan() { 0.0 } else { c.clamp(0.0, 1.0) };
+    Color { r: clamp(clr.r), g: clamp(clr.g), b: clamp(clr.b), a: clamp(clr.a) }
+}
 
 /// Tests if two circles collide.\
 /// Fast; no division or square roots
@@ -58,18 +68,55 @@ pub fn collide_chc(cpos: Vec2, crad: f32, hpos: Vec2, hrad: f32, hradin: f32) ->
 }
 
 
+/// Mixes the channels of two colors as they are, `by` of the way from `color1` to `color2`. Every channel is kept between 0 and 1.
 pub fn mix(color1: Color, color2: Color, by: f32) -> Color {
-    Color {
-        r: lerp(color1.r, color2.r, by),
-        g: lerp(color1.g, color2.g, by),
-        b: lerp(color1.b, color2.b, by),
+    // weighted rather than `lerp`, which can miss `color2` by a rounding error at 1
+    let channel = |a: f32, b: f32| a * (1.0 - by) + b * by;
+    clamp_color(Color {
+        r: channel(color1.r, color2.r),
+        g: channel(color1.g, color2.g),
+        b: channel(color1.b, color2.b),
+        a: channel(color1.a, color2.a),
+    })
+}
+
+/// A color channel from sRGB to linear light, squared for speed instead of the exact curve.
+#[inline]
+fn to_linear(c: f32) -> f32 { c * c }
+/// Undoes `to_linear`.
+#[inline]
+fn to_srgb(c: f32) -> f32 { c.max(0.0).sqrt() }
+
+/// Mixes like `mix` but in linear light, so fading between bright colors doesn't dip dark and muddy halfway through.\
+/// Alpha is mixed as it is. `by` is clamped between 0 and 1, landing on either color exactly at the ends.
+pub fn mix_linear(color1: Color, color2: Color, by: f32) -> Color {
+    let (color1, color2) = (clamp_color(color1), clamp_color(color2));
+    if by.is_nan() || by <= 0.0 { return color1; }
+    if by >= 1.0 { return color2; }
+    let channel = |a: f32, b: f32| to_srgb(lerp(to_linear(a), to_linear(b), by));
+    clamp_color(Color {
+        r: channel(color1.r, color2.r),
+        g: channel(color1.g, color2.g),
+        b: channel(color1.b, color2.b),
         a: lerp(color1.a, color2.a, by),
-    }
+    })
 }
 
 #[inline]
 pub fn lerp(a: f32, b: f32, t: f32) -> f32 { a + (b - a) * t }
 
+/// Evaluates a cubic bezier curve at `t` (0-1).
+pub fn cubic_bezier(p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2, t: f32) -> Vec2 {
+    let it = 1.0 - t;
+    p0 * (it * it * it) + p1 * (3.0 * it * it * t) + p2 * (3.0 * it * t * t) + p3 * (t * t * t)
+}
+
+/// Derivative of a cubic bezier curve at `t` (0-1). Not normalized.
+pub fn cubic_bezier_tangent(p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2, t: f32) -> Vec2 {
+    let it = 1.0 - t;
+    (p1 - p0) * (3.0 * it * it) + (p2 - p1) * (6.0 * it * t) + (p3 - p2) * (3.0 * t * t)
+}
+
 // Tests if two rotatable rectangles collide.\
 // TODO: implement
 // pub fn collide_rr(center1: Vec2, size1: Vec2, rot1: f32, center2: Vec2, size2: Vec2, rot2: f32) -> bool { false }
@@ -99,21 +146,230 @@ pub fn collide_ca(rpos: Vec2, rsize: Vec2, cpos: Vec2, rad: f32) -> bool {
     CA_COLL[col_idx](rpos, rsize, cpos, rad)
 }
 
-/// Tests if a circle is colliding with a capsule (point->point).\
-/// This just collides a circle with a rotated rectangle of 0 width.
+/// Tests if a circle is colliding with a capsule (point->point), a line `rad * 2` wide with round ends.\
+/// Works for capsules of no length, testing them as circles.
 pub fn collide_capsule(p1: Vec2, p2: Vec2, rad: f32, other: Vec2, other_rad: f32) -> bool {
-    let (c, s, r) = rectify_line(p1, p2, 0.0);
-    // sneaky sneaky
-    collide_cr(c, s, r, other, rad + other_rad)
+    closest_on_segment(p1, p2, other).distance_squared(other) <= sq(rad + other_rad)
+}
+
+/// Closest point to `point` on the segment from `from` to `to`.
+pub fn closest_on_segment(from: Vec2, to: Vec2, point: Vec2) -> Vec2 {
+    let delta = to - from;
+    let len_sq = delta.length_squared();
+    if len_sq == 0.0 { return from; }
+    from + delta * ((point - from).dot(delta) / len_sq).clamp(0.0, 1.0)
+}
+
+/// Tests if a circle is colliding with a capsule swept from `from` to `to`. `collide_capsule`, named for sweeping.
+pub fn collide_capsule_circle(from: Vec2, to: Vec2, rad: f32, cpos: Vec2, crad: f32) -> bool {
+    collide_capsule(from, to, rad, cpos, crad)
+}
+
+/// Whether the segments from `a1` to `a2` and from `b1` to `b2` cross or touch.
+fn segments_cross(a1: Vec2, a2: Vec2, b1: Vec2, b2: Vec2) -> bool {
+    let side = |from: Vec2, to: Vec2, p: Vec2| (to - from).perp_dot(p - from);
+    let (s1, s2) = (side(a1, a2, b1), side(a1, a2, b2));
+    let (s3, s4) = (side(b1, b2, a1), side(b1, b2, a2));
+    // collinear ones are caught by the endpoint distances instead
+    s1 * s2 < 0.0 && s3 * s4 < 0.0
+}
+
+/// Tests if two capsules are colliding, e.g. a laser and a circle swept from `from` to `to`.
+pub fn collide_capsules(a1: Vec2, a2: Vec2, arad: f32, b1: Vec2, b2: Vec2, brad: f32) -> bool {
+    segments_cross(a1, a2, b1, b2)
+        || collide_capsule(a1, a2, arad + brad, b1, 0.0)
+        || collide_capsule(a1, a2, arad + brad, b2, 0.0)
+        || collide_capsule(b1, b2, arad + brad, a1, 0.0)
+        || collide_capsule(b1, b2, arad + brad, a2, 0.0)
+}
+
+/// Tests if a capsule swept from `from` to `to` is colliding with a rotatable rectangle.\
+/// Takes the rectangle the same way as `collide_cr`.
+pub fn collide_capsule_rect(from: Vec2, to: Vec2, rad: f32, rcenter: Vec2, rsize: Vec2, rot: f32) -> bool {
+    // into the space where the rectangle spans (0, 0)..rsize, like collide_cr
+    let around = rotate(rsize * -0.5, -rot) + rcenter;
+    let a = rotate_around(from, around, rot) - around;
+    let b = rotate_around(to, around, rot) - around;
+    if segment_hits_aabb(a, b, rsize) { return true; }
+    // otherwise the closest pair involves an endpoint or a corner
+    let dist_sq = |p: Vec2| (p - p.clamp(Vec2::ZERO, rsize)).length_squared();
+    let corners = [Vec2::ZERO, vec2(rsize.x, 0.0), vec2(0.0, rsize.y), rsize];
+    dist_sq(a) <= sq(rad)
+        || dist_sq(b) <= sq(rad)
+        || corners.iter().any(|&c| closest_on_segment(a, b, c).distance_squared(c) <= sq(rad))
+}
+
+/// Whether the segment from `a` to `b` touches the rectangle spanning (0, 0)..`size` (Liang-Barsky).
+fn segment_hits_aabb(a: Vec2, b: Vec2, size: Vec2) -> bool {
+    let delta = b - a;
+    let (mut enter, mut exit) = (0.0f32, 1.0f32);
+    for (p, q) in [(-delta.x, a.x), (delta.x, size.x - a.x), (-delta.y, a.y), (delta.y, size.y - a.y)] {
+        if p == 0.0 {
+            if q < 0.0 { return false; }
+        } else {
+            let t = q / p;
+            if p < 0.0 { enter = enter.max(t); } else { exit = exit.min(t); }
+            if enter > exit { return false; }
+        }
+    }
+    true
+}
+
+/// Where a circle touches a shape.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    /// The point of the shape's outline closest to the circle's center, if the shape knows it
+    pub point: Option<Vec2>,
+    /// Out of the shape, towards the circle. Zero if unknown
+    pub normal: Vec2,
+    /// How far the circle reaches into the shape
+    pub depth: f32,
+}
+impl Contact {
+    /// Touching somewhere, for shapes that can only tell whether they're touched.
+    pub fn unknown() -> Self {
+        Contact { point: None, normal: Vec2::ZERO, depth: 0.0 }
+    }
+    /// A circle at `cpos` touching a shape whose outline is closest to it at `point`, `inside` being whether `cpos` is within the shape.
+    pub fn at(point: Vec2, inside: bool, cpos: Vec2, rad: f32) -> Self {
+        let away = (cpos - point).normalize_or_zero();
+        let dist = point.distance(cpos);
+        if inside {
+            Contact { point: Some(point), normal: -away, depth: rad + dist }
+        } else {
+            Contact { point: Some(point), normal: away, depth: rad - dist }
+        }
+    }
+}
+
+/// Where a circle at `cpos` touches a capsule, whether or not they collide. Takes the capsule the same way as `collide_capsule`.
+pub fn contact_capsule(p1: Vec2, p2: Vec2, rad: f32, cpos: Vec2, crad: f32) -> Contact {
+    let closest = closest_on_segment(p1, p2, cpos);
+    // right on the line, pushed out sideways
+    let dir = (cpos - closest).try_normalize().unwrap_or_else(|| (p2 - p1).perp().try_normalize().unwrap_or(Vec2::X));
+    Contact { point: Some(closest + dir * rad.abs()), normal: dir, depth: rad.abs() + crad - cpos.distance(closest) }
+}
+
+/// Where a circle at `cpos` touches the circle at `pos`, whether or not they collide.
+pub fn contact_cc(pos: Vec2, rad: f32, cpos: Vec2, crad: f32) -> Contact {
+    // circles right on top of each other are pushed apart any which way
+    let dir = (cpos - pos).try_normalize().unwrap_or(Vec2::X);
+    let point = pos + dir * rad.abs();
+    Contact { point: Some(point), normal: dir, depth: rad.abs() + crad - cpos.distance(pos) }
+}
+
+/// Closest point to `point` within a rotatable rectangle, `point` itself if it's inside.\
+/// Takes the rectangle the same way as `collide_cr`.
+pub fn closest_point_cr(rcenter: Vec2, rsize: Vec2, rot: f32, point: Vec2) -> Vec2 {
+    let half = rsize.abs() * 0.5;
+    rotate(rotate(point - rcenter, rot).clamp(-half, half), -rot) + rcenter
 }
 
-pub fn rectify_line(start: Vec2, end: Vec2, thickness: f32) -> (Vec2, Vec2, f32) {
+/// Where a circle at `cpos` touches a rotatable rectangle, whether or not they collide.\
+/// Takes the rectangle the same way as `collide_cr`.
+pub fn contact_cr(rcenter: Vec2, rsize: Vec2, rot: f32, cpos: Vec2, rad: f32) -> Contact {
+    let half = rsize.abs() * 0.5;
+    let local = rotate(cpos - rcenter, rot);
+    if local.clamp(-half, half) != local {
+        return Contact::at(closest_point_cr(rcenter, rsize, rot, cpos), false, cpos, rad);
+    }
+    // inside, the closest edge is the one it's least far from
+    let room = half - local.abs();
+    let (edge, out) = if room.x < room.y {
+        (vec2(half.x.copysign(local.x), local.y), vec2(1.0f32.copysign(local.x), 0.0))
+    } else {
+        (vec2(local.x, half.y.copysign(local.y)), vec2(0.0, 1.0f32.copysign(local.y)))
+    };
+    // out through that edge, even from right on it where there's no direction to the circle
+    Contact { point: Some(rotate(edge, -rot) + rcenter), normal: rotate(out, -rot), depth: rad + room.x.min(room.y) }
+}
+
+/// Where a circle touches an arc, whether or not they collide. Takes the arc the same way as `collide_circ_arc`.
+pub fn contact_arc(cpos: Vec2, crad: f32, apos: Vec2, aradout: f32, aradin: f32, ang1: f32, ang2: f32) -> Contact {
+    let (point, inside) = closest_on_arc(cpos, apos, aradout, aradin, ang1, ang2);
+    Contact::at(point, inside, cpos, crad)
+}
+
+/// Twice the signed area of a polygon, positive if it winds counterclockwise (on a y-down screen, clockwise).
+fn poly_area2(verts: &[Vec2]) -> f32 {
+    poly_edges(verts).map(|(a, b)| a.perp_dot(b)).sum()
+}
+
+/// Each edge of a polygon, the last vertex back to the first included.
+fn poly_edges(verts: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    verts.iter().copied().zip(verts.iter().copied().cycle().skip(1))
+}
+
+/// Whether a polygon has an inside at all: 3 vertices or more enclosing some area, none of them NaN or infinite.
+fn poly_valid(verts: &[Vec2]) -> bool {
+    // NaN fails the comparison too
+    verts.len() >= 3 && poly_area2(verts).abs() > 0.0
+}
+
+/// Whether `point` is inside a convex polygon of either winding, its edges included.
+fn inside_convex(verts: &[Vec2], point: Vec2) -> bool {
+    let winding = poly_area2(verts).signum();
+    poly_edges(verts).all(|(a, b)| (b - a).perp_dot(point - a) * winding >= 0.0)
+}
+
+/// Closest point to `point` on a polygon's outline, in the polygon's own space.
+fn closest_on_outline(verts: &[Vec2], point: Vec2) -> Vec2 {
+    poly_edges(verts)
+        .map(|(a, b)| closest_on_segment(a, b, point))
+        .min_by(|a, b| a.distance_squared(point).total_cmp(&b.distance_squared(point)))
+        .unwrap_or(point)
+}
+
+/// Tests if a circle is colliding with a convex polygon of either winding, turned by `rot` and moved to `offset` the way `draw_rrect` places its corners.\
+/// Polygons without an inside (fewer than 3 vertices, no area) never collide.
+pub fn collide_circ_poly(verts: &[Vec2], offset: Vec2, rot: f32, c: Vec2, r: f32) -> bool {
+    if !poly_valid(verts) { return false; }
+    let local = rotate(c - offset, -rot);
+    inside_convex(verts, local) || closest_on_outline(verts, local).distance_squared(local) <= sq(r)
+}
+
+/// Closest point to `point` on a convex polygon's outline, whether `point` is inside or out.\
+/// Takes the polygon the same way as `collide_circ_poly`, and is `None` for ones without an inside.
+pub fn closest_point_poly(verts: &[Vec2], offset: Vec2, rot: f32, point: Vec2) -> Option<Vec2> {
+    if !poly_valid(verts) { return None; }
+    Some(rotate(closest_on_outline(verts, rotate(point - offset, -rot)), rot) + offset)
+}
+
+/// Where a circle at `cpos` touches a convex polygon, whether or not they collide.\
+/// Takes the polygon the same way as `collide_circ_poly`, and is `None` for ones without an inside.
+pub fn contact_poly(verts: &[Vec2], offset: Vec2, rot: f32, cpos: Vec2, rad: f32) -> Option<Contact> {
+    let point = closest_point_poly(verts, offset, rot, cpos)?;
+    Some(Contact::at(point, inside_convex(verts, rotate(cpos - offset, -rot)), cpos, rad))
+}
+
+/// (center, size, rotation) of the rect covered by a line `thickness` wide, its length along the rotation.\
+/// Lines along an axis come out unrotated, so their extents are exact. None if the ends are the same point
+/// or anything isn't a number, there being no line to lie along and nothing to collide with.
+pub fn rectify_line(start: Vec2, end: Vec2, thickness: f32) -> Option<(Vec2, Vec2, f32)> {
+    if !start.is_finite() || !end.is_finite() || !thickness.is_finite() { return None; }
     let delta = end - start;
-    (
-        start.lerp(end, 0.5), // Position (center)
-        vec2(start.distance(end), thickness), // Size
-        delta.y.atan2(delta.x) // Rotation
-    )
+    let length = delta.length();
+    if length == 0.0 || !length.is_finite() { return None; }
+    let center = start.lerp(end, 0.5);
+    Some(if delta.y == 0.0 {
+        (center, vec2(length, thickness), 0.0)
+    } else if delta.x == 0.0 {
+        (center, vec2(thickness, length), 0.0)
+    } else {
+        (center, vec2(length, thickness), delta.y.atan2(delta.x))
+    })
+}
+
+/// The box around a circle.
+pub fn circle_bounds(center: Vec2, rad: f32) -> Rect {
+    Rect::new(center.x - rad, center.y - rad, rad * 2.0, rad * 2.0)
+}
+
+/// A box around a line `thickness` wide, whichever way it points.
+pub fn line_bounds(start: Vec2, end: Vec2, thickness: f32) -> Rect {
+    let half = thickness.abs() / 2.0;
+    let min = start.min(end) - half;
+    Rect::new(min.x, min.y, (start.x - end.x).abs() + half * 2.0, (start.y - end.y).abs() + half * 2.0)
 }
 
 /// Draws a rotated rectangle.
@@ -127,10 +383,107 @@ pub fn draw_rrect(center: Vec2, size: Vec2, rot: f32, color: impl Into<Color>) {
     draw_triangle(br, tr, bl, clr);
 }
 
+/// The border of a rotated rectangle `thickness` wide and inside it, as one quad per side (outer corner, outer corner, inner corner, inner corner).\
+/// Neighbouring quads meet along the corners' diagonals, so they neither overlap nor leave gaps. Rects too thin for the border make it a fill.
+fn rrect_frame(center: Vec2, size: Vec2, rot: f32, thickness: f32) -> [[Vec2; 4]; 4] {
+    let half = size.abs() * 0.5;
+    let inset = (half - Vec2::splat(thickness.max(0.0))).max(Vec2::ZERO);
+    let corners = [vec2(-1.0, -1.0), vec2(1.0, -1.0), vec2(1.0, 1.0), vec2(-1.0, 1.0)];
+    let outer = corners.map(|c| rotate(c * half, rot) + center);
+    let inner = corners.map(|c| rotate(c * inset, rot) + center);
+    [0, 1, 2, 3].map(|i| [outer[i], outer[(i + 1) % 4], inner[(i + 1) % 4], inner[i]])
+}
+
+/// Draws the border of a rotated rectangle, `thickness` wide and inside where `draw_rrect` would draw it.
+pub fn draw_rrect_outline(center: Vec2, size: Vec2, rot: f32, thickness: f32, color: impl Into<Color>) {
+    let clr = color.into();
+    for [a, b, c, d] in rrect_frame(center, size, rot, thickness) {
+        draw_triangle(a, b, c, clr);
+        draw_triangle(a, c, d, clr);
+    }
+}
+
+/// Draws a rotated rectangle filled, outlined (thickness, color), or both.\
+/// With both, the fill stops at the outline so translucent colors don't add up under it.
+pub fn draw_rrect_styled(center: Vec2, size: Vec2, rot: f32, fill: Option<Color>, stroke: Option<(f32, Color)>) {
+    if let Some(fill) = fill {
+        let inset = stroke.map_or(0.0, |(thickness, _)| thickness.max(0.0) * 2.0);
+        draw_rrect(center, (size.abs() - Vec2::splat(inset)).max(Vec2::ZERO), rot, fill);
+    }
+    if let Some((thickness, color)) = stroke {
+        draw_rrect_outline(center, size, rot, thickness, color);
+    }
+}
+
+/// Draws `string` centered on `pos`.
 pub fn centered_text_draw(string: &str, pos: Vec2, font_size: f32, color: Color) {
     let text_dims = measure_text(string, None, font_size as u16, font_size / font_size.floor());
     let text_center = vec2(text_dims.width, text_dims.height) / 2.0;
-    draw_text(string, pos.x + text_center.x, pos.y + text_dims.offset_y / 2.0, font_size, color);
+    // back by half the width; adding it drew the text starting half its width right of `pos`
+    draw_text(string, pos.x - text_center.x, pos.y + text_dims.offset_y / 2.0, font_size, color);
+}
+
+/// A color as hue (degrees in 0..360), saturation, value and alpha.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hsv {
+    pub h: f32,
+    pub s: f32,
+    pub v: f32,
+    pub a: f32,
+}
+
+/// Grays have no hue of their own, they get 0 so converting them back and forth gives the same.
+pub fn rgb_to_hsv(color: Color) -> Hsv {
+    let max = color.r.max(color.g).max(color.b);
+    let min = color.r.min(color.g).min(color.b);
+    let range = max - min;
+    let h = if range <= 0.0 {
+        0.0
+    } else if max == color.r {
+        60.0 * ((color.g - color.b) / range)
+    } else if max == color.g {
+        60.0 * ((color.b - color.r) / range + 2.0)
+    } else {
+        60.0 * ((color.r - color.g) / range + 4.0)
+    };
+    Hsv { h: h.rem_euclid(360.0), s: if max > 0.0 { range / max } else { 0.0 }, v: max, a: color.a }
+}
+
+/// Hues outside 0..360 wrap around.
+pub fn hsv_to_rgb(hsv: Hsv) -> Color {
+    let h = hsv.h.rem_euclid(360.0) / 60.0;
+    let chroma = hsv.v * hsv.s;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = hsv.v - chroma;
+    Color { r: r + m, g: g + m, b: b + m, a: hsv.a }
+}
+
+/// Mixes like `mix` but through hue, going the shorter way around. Grays take the other color's hue, so they don't sweep through red.
+pub fn mix_hsv(color1: Color, color2: Color, by: f32) -> Color {
+    let (mut a, mut b) = (rgb_to_hsv(color1), rgb_to_hsv(color2));
+    if a.s <= 0.0 { a.h = b.h; }
+    if b.s <= 0.0 { b.h = a.h; }
+    let turn = (b.h - a.h + 540.0).rem_euclid(360.0) - 180.0;
+    hsv_to_rgb(Hsv { h: a.h + turn * by, s: lerp(a.s, b.s, by), v: lerp(a.v, b.v, by), a: lerp(a.a, b.a, by) })
+}
+
+/// Turns the hue of `color` by `degrees`, leaving grays as they are.
+pub fn hue_shift(color: Color, degrees: f32) -> Color {
+    let hsv = rgb_to_hsv(color);
+    hsv_to_rgb(Hsv { h: hsv.h + degrees, ..hsv })
+}
+
+/// `base` with its hue turned `speed` times around per beat, at beat `time`.
+pub fn hue_cycle(base: Color, speed: f32, time: f32) -> Color {
+    hue_shift(base, (time * speed).fract() * 360.0)
 }
 
 pub fn gay(phase: f32) -> Color {
@@ -142,6 +495,61 @@ pub fn gay(phase: f32) -> Color {
     }
 }
 
+/// The size everything plays and draws at, whatever the size of the window.\
+/// The window shows it scaled to fit, the rest letterboxed, so every aspect ratio gets the same playfield.
+pub const VIRTUAL_WIDTH: f32 = 1600.0;
+pub const VIRTUAL_HEIGHT: f32 = 900.0;
+
+/// Width of the virtual screen. Stands in for macroquad's, which is the window's.
+pub fn screen_width() -> f32 {
+    VIRTUAL_WIDTH
+}
+pub fn screen_height() -> f32 {
+    VIRTUAL_HEIGHT
+}
+
+/// Where the virtual screen is shown in the window, in window pixels.
+pub fn letterbox() -> Rect {
+    let (width, height) = (window::screen_width(), window::screen_height());
+    let scale = (width / VIRTUAL_WIDTH).min(height / VIRTUAL_HEIGHT);
+    let (w, h) = (VIRTUAL_WIDTH * scale, VIRTUAL_HEIGHT * scale);
+    Rect::new((width - w) / 2.0, (height - h) / 2.0, w, h)
+}
+
+/// A camera showing `rect` of the virtual screen in the letterbox.
+pub fn virtual_camera(rect: Rect) -> Camera2D {
+    let view = letterbox();
+    Camera2D {
+        viewport: Some((view.x as i32, view.y as i32, view.w as i32, view.h as i32)),
+        ..Camera2D::from_display_rect(rect)
+    }
+}
+
+/// Draws in virtual screen coordinates from here on, which is the default every frame.
+pub fn set_screen_camera() {
+    set_camera(&virtual_camera(Rect::new(0.0, 0.0, VIRTUAL_WIDTH, VIRTUAL_HEIGHT)));
+}
+
+/// The mouse on the virtual screen. Outside of the letterbox it's past the edges.
+pub fn mouse_position() -> (f32, f32) {
+    let (x, y) = input::mouse_position();
+    let view = letterbox();
+    ((x - view.x) / view.w * VIRTUAL_WIDTH, (y - view.y) / view.h * VIRTUAL_HEIGHT)
+}
+
+/// Blacks out the bars around the letterbox and ends the frame, starting the next one on the virtual screen.
+pub async fn present() {
+    let (width, height) = (window::screen_width(), window::screen_height());
+    let view = letterbox();
+    set_camera(&Camera2D::from_display_rect(Rect::new(0.0, 0.0, width, height)));
+    draw_rectangle(0.0, 0.0, view.x, height, BLACK);
+    draw_rectangle(view.right(), 0.0, width - view.right(), height, BLACK);
+    draw_rectangle(0.0, 0.0, width, view.y, BLACK);
+    draw_rectangle(0.0, view.bottom(), width, height - view.bottom(), BLACK);
+    next_frame().await;
+    set_screen_camera();
+}
+
 pub fn screen_center() -> Vec2 {
     screen_size() / 2.0
 }
@@ -150,10 +558,6 @@ pub fn screen_size() -> Vec2 {
     vec2(screen_width(), screen_height())
 }
 
-pub fn rand_vec(from: Vec2, to: Vec2) -> Vec2 {
-    vec2(gen_range(from.x, to.x), gen_range(from.y, to.y))
-}
-
 pub fn floor_vec(vec: Vec2, to: Vec2) -> Vec2 {
     vec2(
         (vec.x / to.x).floor() * to.x,
@@ -165,6 +569,60 @@ pub fn screen(xfac: f32, yfac: f32) -> Vec2 {
     screen_size() * vec2(xfac, yfac)
 }
 
+/// One of the nine spots on the screen things get placed relative to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft, Top, TopRight,
+    Left, Center, Right,
+    BottomLeft, Bottom, BottomRight,
+}
+impl Anchor {
+    pub const ALL: [Anchor; 9] = [
+        Anchor::TopLeft, Anchor::Top, Anchor::TopRight,
+        Anchor::Left, Anchor::Center, Anchor::Right,
+        Anchor::BottomLeft, Anchor::Bottom, Anchor::BottomRight,
+    ];
+    /// Where it is as a fraction of the screen, (0, 0) at the top left and (1, 1) at the bottom right.
+    pub fn frac(self) -> Vec2 {
+        let i = self as usize;
+        vec2((i % 3) as f32 / 2.0, (i / 3) as f32 / 2.0)
+    }
+}
+
+/// `offset` pixels from `anchor` on the virtual screen.
+/// ```
+/// // 40 pixels in from the middle of the right edge
+/// let pos = anchored(Anchor::Right, vec2(-40.0, 0.0));
+/// ```
+pub fn anchored(anchor: Anchor, offset: Vec2) -> Vec2 {
+    screen_size() * anchor.frac() + offset
+}
+/// `frac` of the screen's size away from `anchor`, see `anchored`.
+pub fn anchored_frac(anchor: Anchor, frac: Vec2) -> Vec2 {
+    anchored(anchor, screen_size() * frac)
+}
+
+/// A side of the screen, in the order the arena's edges are numbered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Left, Top, Right, Bottom,
+}
+impl Edge {
+    pub const ALL: [Edge; 4] = [Edge::Left, Edge::Top, Edge::Right, Edge::Bottom];
+}
+
+/// The point `t` of the way along `edge` of the virtual screen, left to right along the top and bottom and top to bottom down the sides.\
+/// Past 0 and 1 it carries on past the corners.
+pub fn edge_point(edge: Edge, t: f32) -> Vec2 {
+    let (start, end) = match edge {
+        Edge::Left => (Anchor::TopLeft, Anchor::BottomLeft),
+        Edge::Top => (Anchor::TopLeft, Anchor::TopRight),
+        Edge::Right => (Anchor::TopRight, Anchor::BottomRight),
+        Edge::Bottom => (Anchor::BottomLeft, Anchor::BottomRight),
+    };
+    anchored(start, Vec2::ZERO).lerp(anchored(end, Vec2::ZERO), t)
+}
+
 /// Anonymous event repeater.\
 /// This is meant to work with numeric types (like `f32`) and events that implement `Clone`.\
 /// However, anything that implements `Clone + Copy + Add<O, Output = T>` can be used as `T`,\
@@ -203,6 +661,15 @@ pub fn ease_sineout_rep(x: f32) -> f32 {
 pub fn circ_climb(x: f32) -> f32 {
     ease_sineout_rep(x).sqrt() + x.floor()
 }
+/// `circ_climb` with each climb `period` long instead of 1. Periods that aren't positive leave `x` as it is.
+pub fn circ_climb_period(x: f32, period: f32) -> f32 {
+    if period.is_nan() || period <= 0.0 { return x; }
+    circ_climb(x / period) * period
+}
+/// `circ_climb_period` with `period` baked in, for `Ease::anon` and `chart::easing`.
+pub fn circ_climb_fn(period: f32) -> impl Fn(f32) -> f32 + Clone {
+    move |x| circ_climb_period(x, period)
+}
 
 /// Event zeroer
 pub fn ez<E>(mut ev: Vec<(f32, E)>) -> Vec<(f32, E)> {
@@ -241,32 +708,1879 @@ pub fn rep_off<T: Copy + Add<Output = T>>(iter: impl IntoIterator<Item = T>, cou
 pub fn recip_ease(t: f32) -> f32 {
     1.0 - 1.0 / (t + 1.0)
 }
+/// A burst of `recip_ease` `k` times as hard, carrying on at a steady pace after it.\
+/// recip_ease(t * k) + t, the strong pellets going with k = 3.
+pub fn recip_ease_k(t: f32, k: f32) -> f32 {
+    recip_ease(t * k) + t
+}
+/// `recip_ease_k` with `k` baked in, for `Ease::anon` and `chart::easing`.
+pub fn recip_ease_fn(k: f32) -> impl Fn(f32) -> f32 + Clone {
+    move |t| recip_ease_k(t, k)
+}
+
+/// The start and span of an arc from `ang1` to `ang2`, the start in 0..TAU and the span in 0..=TAU.\
+/// Spans going backwards (`ang2` before `ang1`) are the same arc the other way round, spans of a full turn or more are the whole ring.
+/// Angles that aren't finite make a NaN span, which is no arc at all.
+fn arc_span(ang1: f32, ang2: f32) -> (f32, f32) {
+    if !ang1.is_finite() || !ang2.is_finite() { return (0.0, f32::NAN); }
+    let (start, span) = if ang2 >= ang1 { (ang1, ang2 - ang1) } else { (ang2, ang1 - ang2) };
+    (start.rem_euclid(TAU), span.min(TAU))
+}
+
+/// Whether `angle` is within the arc from `ang1` to `ang2`, ends included. NaN angles are never within.
+fn within_arc(angle: f32, ang1: f32, ang2: f32) -> bool {
+    let (start, span) = arc_span(ang1, ang2);
+    span >= TAU || (angle - start).rem_euclid(TAU) <= span
+}
+
+/// Closest point to `point` of an arc, taken the same way as `collide_circ_arc`, and whether `point` is inside it.
+fn closest_on_arc(point: Vec2, apos: Vec2, aradout: f32, aradin: f32, ang1: f32, ang2: f32) -> (Vec2, bool) {
+    let (inner, outer) = (aradin.abs().min(aradout.abs()), aradin.abs().max(aradout.abs()));
+    let offset = point - apos;
+    let dist = offset.length();
+    let (start, span) = arc_span(ang1, ang2);
+    // angles go clockwise from straight down, like `draw_arc` draws them
+    let dir = |angle: f32| vec2(angle.sin(), angle.cos());
+    let facing = dist > 0.0 && within_arc(offset.x.atan2(offset.y), ang1, ang2);
+    let inside = facing && (inner..=outer).contains(&dist);
+    let rim = |rad: f32| apos + offset / dist * rad;
+    let mut candidates = vec![];
+    if facing {
+        candidates.extend([rim(inner), rim(outer)]);
+    } else if dist == 0.0 {
+        // the center is as close to all of the inner rim
+        candidates.push(apos + dir(start) * inner);
+    }
+    if span < TAU {
+        for side in [dir(start), dir(start + span)] {
+            candidates.push(closest_on_segment(apos + side * inner, apos + side * outer, point));
+        }
+    }
+    let closest = candidates.into_iter()
+        .min_by(|a, b| a.distance_squared(point).total_cmp(&b.distance_squared(point)))
+        .unwrap_or(apos);
+    (closest, inside)
+}
 
+/// Tests if a circle is colliding with an arc: the part of the ring between `aradin` and `aradout` around `apos` from angle `ang1` to `ang2`.\
+/// Angles are as `draw_arc` takes them: radians clockwise from straight down, any number of turns around.
+/// Going from `ang2` back to `ang1` is the same arc, and a span of a full turn or more is the whole ring. Circles just touching an edge collide.\
+/// Arcs with angles that aren't finite numbers never collide.
 pub fn collide_circ_arc(cpos: Vec2, crad: f32, apos: Vec2, aradout: f32, aradin: f32, ang1: f32, ang2: f32) -> bool {
-    collide_chc(cpos, crad, apos, aradout, aradin) &&
-    rotate_around(cpos, apos, ang1).y >= apos.y - crad &&
-    rotate_around(cpos, apos, ang2).y <= apos.y - crad
+    // a NaN span would otherwise come out as a whole ring
+    if !ang1.is_finite() || !ang2.is_finite() { return false; }
+    // cheap rejection first, most circles aren't anywhere near the ring
+    collide_cc(cpos, crad, apos, aradout.abs().max(aradin.abs())) && {
+        let (closest, inside) = closest_on_arc(cpos, apos, aradout, aradin, ang1, ang2);
+        inside || closest.distance_squared(cpos) <= sq(crad)
+    }
+}
+
+/// Sides of each circle in a `CircleBatch`, as many as `draw_circle` gives them so they look the same
+const BATCH_CIRCLE_SIDES: usize = 20;
+/// Indices a `CircleBatch` draws at once, macroquad takes fewer than 5000 at a time
+const BATCH_INDICES: usize = 4999;
+
+/// Filled circles collected and drawn as a few meshes, instead of building and drawing each on its own.\
+/// Keeps its buffers between frames.
+pub struct CircleBatch {
+    mesh: Mesh,
+    /// Points around the unit circle
+    unit: [Vec2; BATCH_CIRCLE_SIDES],
+    circles: usize,
+}
+impl Default for CircleBatch {
+    fn default() -> Self {
+        let unit = std::array::from_fn(|i| {
+            let angle = i as f32 / BATCH_CIRCLE_SIDES as f32 * TAU;
+            vec2(angle.cos(), angle.sin())
+        });
+        CircleBatch { mesh: Mesh { vertices: vec![], indices: vec![], texture: None }, unit, circles: 0 }
+    }
+}
+impl CircleBatch {
+    /// Adds a circle, drawing the ones before it first if there are too many to add more. Gets a soft rim with `smooth_edges` on.
+    pub fn circle(&mut self, center: Vec2, rad: f32, color: Color) {
+        let smooth = smooth_edges();
+        // the rim is two more triangles a side
+        let indices = BATCH_CIRCLE_SIDES * 3 * if smooth { 3 } else { 1 };
+        if self.mesh.indices.len() + indices > BATCH_INDICES { self.flush(); }
+        let first = self.mesh.vertices.len() as u16;
+        let vertex = |pos: Vec2, uv: Vec2, color: Color| Vertex { position: vec3(pos.x, pos.y, 0.0), uv, color };
+        let core = if smooth { (rad - FEATHER / 2.0).max(0.0) } else { rad };
+        self.mesh.vertices.push(vertex(center, Vec2::ZERO, color));
+        for (i, &dir) in self.unit.iter().enumerate() {
+            self.mesh.vertices.push(vertex(center + dir * core, dir, color));
+            let next = (i + 1) % BATCH_CIRCLE_SIDES;
+            self.mesh.indices.extend_from_slice(&[first, first + 1 + i as u16, first + 1 + next as u16]);
+        }
+        if smooth {
+            let clear = Color { a: 0.0, ..color };
+            let rim = first + 1 + BATCH_CIRCLE_SIDES as u16;
+            for (i, &dir) in self.unit.iter().enumerate() {
+                self.mesh.vertices.push(vertex(center + dir * (rad + FEATHER / 2.0), dir, clear));
+                let next = ((i + 1) % BATCH_CIRCLE_SIDES) as u16;
+                let (i, in_first) = (i as u16, first + 1);
+                self.mesh.indices.extend_from_slice(&[in_first + i, rim + i, rim + next, in_first + i, rim + next, in_first + next]);
+            }
+        }
+        self.circles += 1;
+    }
+    /// Draws what's been added since the last flush. Anything drawn afterwards goes over it.
+    pub fn flush(&mut self) {
+        if self.circles == 0 { return; }
+        draw_mesh(&self.mesh);
+        self.mesh.vertices.clear();
+        self.mesh.indices.clear();
+        self.circles = 0;
+    }
+}
+
+/// Pixels across the soft rim of smoothed edges, half inside the shape and half outside
+pub const FEATHER: f32 = 1.5;
+
+thread_local! {
+    /// Whether the helpers that can smooth their edges do, set from the settings every frame
+    static SMOOTH_EDGES: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+/// Turns smoothed edges on or off for `draw_circle_aa`, `draw_line_aa` and `CircleBatch` from here on.
+pub fn set_smooth_edges(on: bool) {
+    SMOOTH_EDGES.with(|cell| cell.set(on));
+}
+pub fn smooth_edges() -> bool {
+    SMOOTH_EDGES.with(|cell| cell.get())
+}
+
+/// (end, outward normal) of points around a capsule `rad` thick from `a` to `b`, around the cap at `b` from one side to the other,
+/// then around the cap at `a` back again.
+fn capsule_normals(a: Vec2, b: Vec2, rad: f32) -> Vec<(Vec2, Vec2)> {
+    let dir = (b - a).try_normalize().unwrap_or(Vec2::Y);
+    // a cap is half a circle, given about as many sides as `draw_circle` gives a whole one
+    let sides = ((rad * PI / 4.0).ceil() as usize).clamp(6, 32);
+    [(b, dir.perp()), (a, -dir.perp())].into_iter()
+        .flat_map(|(end, side)| (0..=sides).map(move |i| (end, rotate(side, PI * i as f32 / sides as f32))))
+        .collect()
+}
+
+/// A capsule `rad` thick from `a` to `b` fading out over `FEATHER` pixels at its edge, a circle if they're the same point.
+fn draw_feathered_capsule(a: Vec2, b: Vec2, rad: f32, color: Color) {
+    draw_mesh(&feathered_capsule(a, b, rad, color));
+}
+
+/// The mesh `draw_feathered_capsule` draws: a fan out to just inside the edge, and a ring fading from there to clear just outside it.
+fn feathered_capsule(a: Vec2, b: Vec2, rad: f32, color: Color) -> Mesh {
+    let (core, rim) = ((rad - FEATHER / 2.0).max(0.0), rad + FEATHER / 2.0);
+    let clear = Color { a: 0.0, ..color };
+    let vertex = |pos: Vec2, color: Color| Vertex { position: vec3(pos.x, pos.y, 0.0), uv: Vec2::ZERO, color };
+    let mut vertices = vec![vertex(a.lerp(b, 0.5), color)];
+    for (end, normal) in capsule_normals(a, b, rad) {
+        vertices.push(vertex(end + normal * core, color));
+        vertices.push(vertex(end + normal * rim, clear));
+    }
+    let around = (vertices.len() as u16 - 1) / 2;
+    let mut indices = Vec::with_capacity(around as usize * 9);
+    for i in 0..around {
+        let next = (i + 1) % around;
+        let (inner, outer, next_inner, next_outer) = (1 + i * 2, 2 + i * 2, 1 + next * 2, 2 + next * 2);
+        indices.extend_from_slice(&[0, inner, next_inner, inner, outer, next_outer, inner, next_outer, next_inner]);
+    }
+    Mesh { vertices, indices, texture: None }
+}
+
+/// Strokes the edge of the capsule `collide_capsule` tests with the same arguments, `thickness` wide. A circle if the ends are the same point.
+pub fn draw_capsule_lines(a: Vec2, b: Vec2, rad: f32, thickness: f32, color: Color) {
+    let points = capsule_normals(a, b, rad).into_iter().map(|(end, normal)| end + normal * rad).collect::<Vec<_>>();
+    for (i, &from) in points.iter().enumerate() {
+        let to = points[(i + 1) % points.len()];
+        draw_line(from.x, from.y, to.x, to.y, thickness, color);
+    }
+}
+
+/// `draw_circle`, with a soft rim while `smooth_edges` is on so small moving circles don't shimmer.
+pub fn draw_circle_aa(x: f32, y: f32, r: f32, color: Color) {
+    if !smooth_edges() {
+        draw_circle(x, y, r, color);
+        return;
+    }
+    draw_feathered_capsule(vec2(x, y), vec2(x, y), r, color);
+}
+
+/// `draw_line`, with round caps and a soft edge while `smooth_edges` is on.
+pub fn draw_line_aa(x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32, color: Color) {
+    if !smooth_edges() {
+        draw_line(x1, y1, x2, y2, thickness, color);
+        return;
+    }
+    // like `draw_line`, a line without any length draws nothing
+    if x1 == x2 && y1 == y2 { return; }
+    draw_feathered_capsule(vec2(x1, y1), vec2(x2, y2), thickness / 2.0, color);
 }
 
-pub fn draw_arc(center: Vec2, inner_rad: f32, outer_rad: f32, ang1: f32, ang2: f32, segments: usize, color: impl Into<Color>) {
+/// Draws the same circles and lines into a texture with and without smoothed edges, saving each as a PNG
+/// and printing how many of its pixels are only partly covered, which the smoothing should raise.
+pub async fn compare_smooth_edges() {
+    let (width, height) = (256, 128);
+    let target = render_target(width, height);
+    target.texture.set_filter(FilterMode::Nearest);
+    let smoothing = smooth_edges();
+    for smooth in [false, true] {
+        set_camera(&Camera2D { render_target: Some(target), ..Camera2D::from_display_rect(Rect::new(0.0, 0.0, width as f32, height as f32)) });
+        clear_background(BLACK);
+        set_smooth_edges(smooth);
+        // off the pixel grid, where hard edges shimmer
+        for i in 0..12 {
+            draw_circle_aa(12.3 + i as f32 * 20.3, 30.25 + i as f32 * 0.37, 4.0 + i as f32 * 0.25, WHITE);
+        }
+        draw_line_aa(10.0, 70.5, 246.0, 110.0, 6.0, WHITE);
+        draw_line_aa(10.0, 120.0, 246.0, 80.3, 2.0, WHITE);
+        present().await;
+        let image = target.texture.get_texture_data();
+        let partial = image.get_image_data().iter().filter(|p| (1..255).contains(&p[0])).count();
+        let covered = image.get_image_data().iter().filter(|p| p[0] > 0).count();
+        let name = if smooth { "smooth_edges_on.png" } else { "smooth_edges_off.png" };
+        image.export_png(name);
+        println!("smooth edges {}: {partial} of {covered} covered pixels partly covered, saved to {name}", if smooth { "on" } else { "off" });
+    }
+    set_smooth_edges(smoothing);
+}
+
+/// Pixels across the glow texture. It's stretched smoothly, so it doesn't need many
+const GLOW_TEXTURE_SIZE: u16 = 64;
+
+thread_local! {
+    /// Made the first time something glows, textures can't be made before the window is
+    static GLOW_TEXTURE: std::cell::Cell<Option<Texture2D>> = const { std::cell::Cell::new(None) };
+}
+
+/// RGBA pixels of a `size` square of white fading out from the middle to nothing at the edge, easing off so there's no visible rim.
+fn glow_pixels(size: usize) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(size * size * 4);
+    for y in 0..size {
+        for x in 0..size {
+            let from_middle = (vec2(x as f32, y as f32) + 0.5) / size as f32 * 2.0 - 1.0;
+            let falloff = sq((1.0 - from_middle.length()).max(0.0));
+            pixels.extend_from_slice(&[255, 255, 255, (falloff * 255.0) as u8]);
+        }
+    }
+    pixels
+}
+
+/// `glow_pixels` as a texture, made once.
+fn glow_texture() -> Texture2D {
+    GLOW_TEXTURE.with(|cell| {
+        if let Some(texture) = cell.get() { return texture; }
+        let pixels = glow_pixels(GLOW_TEXTURE_SIZE as usize);
+        let texture = Texture2D::from_rgba8(GLOW_TEXTURE_SIZE, GLOW_TEXTURE_SIZE, &pixels);
+        texture.set_filter(FilterMode::Linear);
+        cell.set(Some(texture));
+        texture
+    })
+}
+
+/// Draws a soft glow `radius` around `pos`, fading out towards its edge. `intensity` scales its alpha, `color`'s included.\
+/// It's a single stretched texture, and each one drawn after another goes in the same draw call, so it's fine for every pellet.
+pub fn draw_glow_circle(pos: Vec2, radius: f32, color: Color, intensity: f32) {
+    if radius <= 0.0 || intensity <= 0.0 { return; }
+    let params = DrawTextureParams { dest_size: Some(Vec2::splat(radius * 2.0)), ..Default::default() };
+    draw_texture_ex(glow_texture(), pos.x - radius, pos.y - radius, acmul(color, intensity), params);
+}
+
+/// Times drawing `count` glows against as many plain circles for a few frames, printing milliseconds per frame of each. For `--bench-glow`.
+pub async fn bench_glow(count: usize, frames: usize) {
+    let mut times = [0.0; 2];
+    for frame in 0..frames * 2 {
+        let glow = frame % 2 == 1;
+        let start = get_time();
+        for i in 0..count {
+            let pos = vec2((i * 37 % VIRTUAL_WIDTH as usize) as f32, (i * 91 % VIRTUAL_HEIGHT as usize) as f32);
+            if glow { draw_glow_circle(pos, 20.0, WHITE, 0.5); } else { draw_circle(pos.x, pos.y, 20.0, WHITE); }
+        }
+        // the drawing only really happens once the frame ends
+        present().await;
+        times[glow as usize] += get_time() - start;
+    }
+    let per_frame = |secs: f64| secs / frames as f64 * 1000.0;
+    println!("{count} circles: {:.2}ms a frame, {count} glows: {:.2}ms a frame", per_frame(times[0]), per_frame(times[1]));
+}
+
+/// Pixels of rim each segment of an arc covers when `draw_arc` picks the resolution
+const ARC_SEGMENT_LEN: f32 = 12.0;
+const MIN_ARC_SEGMENTS: usize = 4;
+const MAX_ARC_SEGMENTS: usize = 256;
+
+/// Segments an arc spanning `span` radians of a rim `rad` out gets, for `segments`, or enough for its length when None.
+fn arc_segments(span: f32, rad: f32, segments: Option<usize>) -> usize {
+    segments.unwrap_or_else(|| ((span * rad.abs() / ARC_SEGMENT_LEN).ceil() as usize).clamp(MIN_ARC_SEGMENTS, MAX_ARC_SEGMENTS)).max(1)
+}
+
+/// Draws the part of the ring between `inner_rad` and `outer_rad` around `center` from `ang1` to `ang2`, radians clockwise from straight down.\
+/// Spans are taken like `collide_circ_arc` takes them, a full turn or more being the whole ring.
+/// `segments` of None picks enough for the arc's length.
+pub fn draw_arc(center: Vec2, inner_rad: f32, outer_rad: f32, ang1: f32, ang2: f32, segments: Option<usize>, color: impl Into<Color>) {
     let color = color.into();
-    let angdiff = ang2 - ang1;
-    let seglen = angdiff / segments as f32;
+    arc_quads(center, inner_rad, outer_rad, ang1, ang2, segments, |quad| draw_arc_quad(quad, color));
+}
 
+/// Each piece of the arc `draw_arc` draws, as [outer start, outer end, inner start, inner end].
+fn arc_quads(center: Vec2, inner_rad: f32, outer_rad: f32, ang1: f32, ang2: f32, segments: Option<usize>, mut quad: impl FnMut([Vec2; 4])) {
+    let (start, span) = arc_span(ang1, ang2);
+    if span.is_nan() || span <= 0.0 { return; }
+    let segments = arc_segments(span, inner_rad.abs().max(outer_rad.abs()), segments);
+    // every point is worked out from the start so a full ring ends exactly where it began
+    let dir = |i: usize| {
+        let p = start + span * i as f32 / segments as f32;
+        vec2(p.sin(), p.cos())
+    };
     for i in 0..segments {
-        let p1 = i as f32 * seglen + ang1;
-        let p2 = (i + 1) as f32 * seglen + ang1;
-        
-        let tl = vec2(p1.sin(), p1.cos()) * outer_rad + center;
-        let tr = vec2(p2.sin(), p2.cos()) * outer_rad + center;
-        let bl = vec2(p1.sin(), p1.cos()) * inner_rad + center;
-        let br = vec2(p2.sin(), p2.cos()) * inner_rad + center;
+        let (d1, d2) = (dir(i), dir(i + 1));
+        quad([d1 * outer_rad + center, d2 * outer_rad + center, d1 * inner_rad + center, d2 * inner_rad + center]);
+    }
+}
+
+fn draw_arc_quad([tl, tr, bl, br]: [Vec2; 4], color: Color) {
+    draw_triangle(tl, tr, br, color);
+    draw_triangle(tl, bl, br, color);
+}
+
+/// An arc's points worked out once, for arcs that only move and turn. Drawing it looks like `draw_arc`,
+/// but takes one sine and cosine for the turn instead of a pair for every point.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ArcMesh {
+    inner_rad: f32,
+    outer_rad: f32,
+    span: f32,
+    segments: Option<usize>,
+    /// Directions of the points along it, the first straight down
+    dirs: Vec<Vec2>,
+}
+impl ArcMesh {
+    /// The arc between `inner_rad` and `outer_rad` spanning `span` radians, taken like `draw_arc` takes spans, with `segments` like it.
+    pub fn new(inner_rad: f32, outer_rad: f32, span: f32, segments: Option<usize>) -> Self {
+        let mut mesh = ArcMesh { inner_rad, outer_rad, span: arc_span(0.0, span).1, segments, dirs: vec![] };
+        if mesh.span.is_nan() || mesh.span <= 0.0 { return mesh; }
+        let count = arc_segments(mesh.span, inner_rad.abs().max(outer_rad.abs()), segments);
+        mesh.dirs = (0..=count).map(|i| {
+            let p = mesh.span * i as f32 / count as f32;
+            vec2(p.sin(), p.cos())
+        }).collect();
+        mesh
+    }
+    /// Whether it's what `new` makes of these, so it can be kept instead of worked out again.
+    pub fn matches(&self, inner_rad: f32, outer_rad: f32, span: f32, segments: Option<usize>) -> bool {
+        self.inner_rad == inner_rad && self.outer_rad == outer_rad && self.span == arc_span(0.0, span).1 && self.segments == segments
+    }
+    /// Draws it around `center`, starting `rotation` radians clockwise from straight down.
+    pub fn draw(&self, center: Vec2, rotation: f32, color: impl Into<Color>) {
+        let color = color.into();
+        self.quads(center, rotation, |quad| draw_arc_quad(quad, color));
+    }
+    /// Each piece of it as `draw` draws it, like `arc_quads`.
+    fn quads(&self, center: Vec2, rotation: f32, mut quad: impl FnMut([Vec2; 4])) {
+        let (sin, cos) = rotation.sin_cos();
+        let turn = |d: Vec2| vec2(cos * d.x + sin * d.y, cos * d.y - sin * d.x);
+        for pair in self.dirs.windows(2) {
+            let (d1, d2) = (turn(pair[0]), turn(pair[1]));
+            quad([d1 * self.outer_rad + center, d2 * self.outer_rad + center, d1 * self.inner_rad + center, d2 * self.inner_rad + center]);
+        }
+    }
+}
+
+/// Times drawing `count` arcs with `draw_arc` against drawing them from `ArcMesh`es over `frames` frames each, printing the average frame of each.
+pub async fn bench_arcs(count: usize, frames: usize) {
+    let meshes = (0..count).map(|i| ArcMesh::new(80.0 + i as f32, 120.0 + i as f32, 3.0, None)).collect::<Vec<_>>();
+    let mut times = [0.0; 2];
+    for frame in 0..frames * 2 {
+        let cached = frame % 2 == 1;
+        let start = get_time();
+        for (i, mesh) in meshes.iter().enumerate() {
+            let center = vec2((i * 37 % VIRTUAL_WIDTH as usize) as f32, (i * 91 % VIRTUAL_HEIGHT as usize) as f32);
+            let rot = frame as f32 * 0.01 + i as f32;
+            if cached {
+                mesh.draw(center, rot, WHITE);
+            } else {
+                draw_arc(center, 80.0 + i as f32, 120.0 + i as f32, rot, rot + 3.0, None, WHITE);
+            }
+        }
+        present().await;
+        times[cached as usize] += get_time() - start;
+    }
+    let per_frame = |secs: f64| secs / frames as f64 * 1000.0;
+    println!("{count} arcs: {:.2}ms a frame with draw_arc, {:.2}ms a frame from meshes", per_frame(times[0]), per_frame(times[1]));
+}
+
+/// Strokes the border of the arc `draw_arc` would draw, `thickness` wide and inside the arc.\
+/// A whole ring has no sides, and arcs too thin to hold an outline are filled instead.
+pub fn draw_arc_outline(center: Vec2, inner_rad: f32, outer_rad: f32, ang1: f32, ang2: f32, thickness: f32, color: impl Into<Color>) {
+    let color = color.into();
+    let (inner, outer) = (inner_rad.abs().min(outer_rad.abs()), inner_rad.abs().max(outer_rad.abs()));
+    let (start, span) = arc_span(ang1, ang2);
+    if span.is_nan() || span <= 0.0 || thickness <= 0.0 { return; }
+    let ring = span >= TAU;
+    if outer - inner <= thickness * 2.0 || (!ring && span * outer <= thickness * 2.0) {
+        draw_arc(center, inner, outer, start, start + span, None, color);
+        return;
+    }
+    draw_arc(center, outer - thickness, outer, start, start + span, None, color);
+    // a pie has no inner rim, its sides meet at the center
+    let sides_from = if inner > 0.0 {
+        draw_arc(center, inner, inner + thickness, start, start + span, None, color);
+        inner + thickness
+    } else { 0.0 };
+    if ring { return; }
+    // the sides go between the rims, each leaning into the arc so they don't poke out past the ends
+    for (angle, into) in [(start, 1.0), (start + span, -1.0)] {
+        let dir = vec2(angle.sin(), angle.cos());
+        let across = vec2(dir.y, -dir.x) * thickness * into;
+        let (a, b) = (center + dir * sides_from, center + dir * (outer - thickness));
+        draw_triangle(a, b, b + across, color);
+        draw_triangle(a, a + across, b + across, color);
+    }
+}
+
+/// The stretches of a path `length` long that dashes cover, as (from, to) distances along it.\
+/// Dashes repeat every `dash_len + gap_len`, pushed along by `phase` of a repeat, so feeding it beats marches them along once a beat.
+fn dash_spans(length: f32, dash_len: f32, gap_len: f32, phase: f32) -> Vec<(f32, f32)> {
+    let period = dash_len + gap_len.max(0.0);
+    if !(length > 0.0 && dash_len > 0.0 && period.is_finite()) { return vec![]; }
+    if gap_len <= 0.0 { return vec![(0.0, length)]; }
+    // where the dash covering or coming up to the start of the path starts
+    let mut at = phase.rem_euclid(1.0) * period - period;
+    let mut spans = vec![];
+    while at < length {
+        let (from, to) = (at.max(0.0), (at + dash_len).min(length));
+        if to > from { spans.push((from, to)); }
+        at += period;
+    }
+    spans
+}
+
+/// Draws a dashed line through `points`, back to the first if `closed`.\
+/// Dashes are laid out along the whole path, so they run on around corners instead of starting over at each one.
+pub fn draw_dashed_poly(points: &[Vec2], closed: bool, thickness: f32, dash_len: f32, gap_len: f32, phase: f32, color: impl Into<Color>) {
+    let color = color.into();
+    let mut path = points.to_vec();
+    if closed && points.len() > 2 { path.push(points[0]); }
+    // how far along the path each point is
+    let mut along = vec![0.0];
+    for pair in path.windows(2) {
+        along.push(along[along.len() - 1] + pair[0].distance(pair[1]));
+    }
+    let point_at = |s: f32, seg: usize| {
+        let len = along[seg + 1] - along[seg];
+        path[seg].lerp(path[seg + 1], if len > 0.0 { (s - along[seg]) / len } else { 0.0 })
+    };
+    let mut seg = 0;
+    for (from, to) in dash_spans(along[along.len() - 1], dash_len, gap_len, phase) {
+        while seg + 2 < along.len() && along[seg + 1] <= from { seg += 1; }
+        let mut last = point_at(from, seg);
+        // bend with the path if the dash goes past a corner
+        while seg + 2 < along.len() && along[seg + 1] < to {
+            seg += 1;
+            draw_line(last.x, last.y, path[seg].x, path[seg].y, thickness, color);
+            last = path[seg];
+        }
+        let end = point_at(to, seg);
+        draw_line(last.x, last.y, end.x, end.y, thickness, color);
+    }
+}
+
+/// Draws a dashed line from `a` to `b`, as `draw_dashed_poly` would.
+pub fn draw_dashed_line(a: Vec2, b: Vec2, thickness: f32, dash_len: f32, gap_len: f32, phase: f32, color: impl Into<Color>) {
+    draw_dashed_poly(&[a, b], false, thickness, dash_len, gap_len, phase, color)
+}
+
+/// Draws a dashed circle, as `draw_dashed_poly` would.\
+/// The dashes and gaps are stretched a little so a whole number of them goes around, leaving no odd one where the circle closes.
+pub fn draw_dashed_circle(center: Vec2, radius: f32, thickness: f32, dash_len: f32, gap_len: f32, phase: f32, color: impl Into<Color>) {
+    let color = color.into();
+    let length = TAU * radius.abs();
+    let period = dash_len + gap_len.max(0.0);
+    if !(length > 0.0 && dash_len > 0.0 && period.is_finite()) { return; }
+    let repeats = (length / period).round().max(1.0);
+    let stretch = length / (repeats * period);
+    let (inner, outer) = (radius.abs() - thickness / 2.0, radius.abs() + thickness / 2.0);
+    for (from, to) in dash_spans(length, dash_len * stretch, gap_len * stretch, phase) {
+        draw_arc(center, inner.max(0.0), outer, from / radius.abs(), to / radius.abs(), None, color);
+    }
+}
+
+/// Fixed-capacity ring buffer. Pushing while full overwrites the oldest element.
+#[derive(Clone, Copy)]
+pub struct RingBuffer<T: Copy + Default, const N: usize> {
+    buf: [T; N],
+    start: usize,
+    len: usize,
+}
+impl<T: Copy + Default, const N: usize> Default for RingBuffer<T, N> {
+    fn default() -> Self {
+        RingBuffer { buf: [T::default(); N], start: 0, len: 0 }
+    }
+}
+impl<T: Copy + Default, const N: usize> RingBuffer<T, N> {
+    pub fn new() -> Self { Self::default() }
+    pub fn push(&mut self, val: T) {
+        if self.len < N {
+            self.buf[(self.start + self.len) % N] = val;
+            self.len += 1;
+        } else {
+            self.buf[self.start] = val;
+            self.start = (self.start + 1) % N;
+        }
+    }
+    pub fn clear(&mut self) {
+        self.start = 0;
+        self.len = 0;
+    }
+    pub fn len(&self) -> usize { self.len }
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+    /// Iterates from oldest to newest.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = T> + '_ {
+        (0..self.len).map(move |i| self.buf[(self.start + i) % N])
+    }
+    pub fn front(&self) -> Option<T> {
+        (self.len > 0).then(|| self.buf[self.start])
+    }
+    /// Takes out the oldest element.
+    pub fn pop_front(&mut self) -> Option<T> {
+        let val = self.front()?;
+        self.start = (self.start + 1) % N;
+        self.len -= 1;
+        Some(val)
+    }
+    /// Takes out the newest element.
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.len == 0 { return None; }
+        self.len -= 1;
+        Some(self.buf[(self.start + self.len) % N])
+    }
+}
+
+/// Most points a `TrailBuffer` holds.
+pub const TRAIL_CAPACITY: usize = 128;
+
+/// Recent positions of something with the time each was at, for drawing trails behind it.\
+/// Pruned by age, so a trail covers all of its beats at any frame rate. Points closer together than the capacity allows are thinned out,
+/// so it never holds more than `TRAIL_CAPACITY` and never allocates.
+#[derive(Clone, Copy, Default)]
+pub struct TrailBuffer {
+    points: RingBuffer<(Vec2, f32), TRAIL_CAPACITY>,
+}
+impl TrailBuffer {
+    pub fn new() -> Self { Self::default() }
+    /// Adds where it is at `time`, forgetting the points more than `max_age` behind. Points from after `time`, e.g. before a seek back, go too.
+    pub fn advance(&mut self, pos: Vec2, time: f32, max_age: f32) {
+        // only the head may be closer than this to the point before it, so what's within `max_age` fits with room to spare
+        let spacing = max_age / (TRAIL_CAPACITY - 4) as f32;
+        if self.points.iter().rev().nth(1).is_some_and(|(_, t)| t <= time && time - t < spacing) {
+            // the head moves along until it's far enough from the point before it
+            self.points.pop_back();
+        }
+        self.points.push((pos, time));
+        while let Some((_, t)) = self.points.front() {
+            if time - t <= max_age && t <= time { break; }
+            self.points.pop_front();
+        }
+    }
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+    pub fn len(&self) -> usize { self.points.len() }
+    pub fn is_empty(&self) -> bool { self.points.is_empty() }
+    /// (position, time) from the newest to the oldest.
+    pub fn iter(&self) -> impl Iterator<Item = (Vec2, f32)> + '_ {
+        self.points.iter().rev()
+    }
+    /// Replaces `out` with the positions moved by `offset`, from the newest to the oldest as `draw_ribbon` takes them.
+    pub fn positions_into(&self, offset: Vec2, out: &mut Vec<Vec2>) {
+        out.clear();
+        out.extend(self.iter().map(|(pos, _)| pos + offset));
+    }
+}
+
+/// How far a ribbon's corners can reach past its sides, as a multiple of its half width, before they're cut off
+const RIBBON_MITER_LIMIT: f32 = 2.0;
+
+/// Triangles of a ribbon through `points`, the head first, as (position, how far along from head to tail in 0..=1) vertices and indices.\
+/// Each piece of the path is a quad, meeting the next at a shared miter, or with a bevel out of the corner where a miter would reach past `RIBBON_MITER_LIMIT` or cut too far into a short piece.
+/// Inside the corner the quads overlap a little instead of pinching into a bowtie.
+fn ribbon_geometry(points: &[Vec2], width_head: f32, width_tail: f32) -> (Vec<(Vec2, f32)>, Vec<u16>) {
+    let mut path = points.to_vec();
+    path.dedup_by(|a, b| a.distance_squared(*b) < 1e-6);
+    let (mut verts, mut indices) = (vec![], vec![]);
+    if path.len() < 2 { return (verts, indices); }
+    let along = |i: usize| i as f32 / (path.len() - 1) as f32;
+    let half = |i: usize| lerp(width_head, width_tail, along(i)).max(0.0) / 2.0;
+    let normals = path.windows(2).map(|w| (w[1] - w[0]).normalize().perp()).collect::<Vec<_>>();
+    let lengths = path.windows(2).map(|w| w[0].distance(w[1])).collect::<Vec<_>>();
+    // (left, right) where each piece starts and ends, shared through the miters
+    let mut ends = vec![];
+    for i in 0..path.len() {
+        let (p, w) = (path[i], half(i));
+        let incoming = if i > 0 { normals[i - 1] } else { normals[0] };
+        let outgoing = if i < normals.len() { normals[i] } else { incoming };
+        let miter = (incoming + outgoing).normalize_or_zero();
+        let reach = miter.dot(incoming);
+        // inside the corner a miter cuts into the pieces either side, and one cutting past the middle of a short piece twists it
+        let inset = w * (1.0 - sq(reach)).max(0.0).sqrt() / reach;
+        let shortest = lengths[i.saturating_sub(1)].min(lengths[i.min(lengths.len() - 1)]);
+        if reach >= 1.0 / RIBBON_MITER_LIMIT && inset <= shortest / 2.0 {
+            let corner = miter * w / reach;
+            ends.push(((p + corner, p - corner), (p + corner, p - corner)));
+        } else {
+            ends.push(((p + incoming * w, p - incoming * w), (p + outgoing * w, p - outgoing * w)));
+            // the bevel fills the outside of the corner, which is the right side when turning left
+            let left_turn = incoming.perp_dot(outgoing) > 0.0;
+            let (from, to) = if left_turn { (p - incoming * w, p - outgoing * w) } else { (p + incoming * w, p + outgoing * w) };
+            let first = verts.len() as u16;
+            verts.extend([(p, along(i)), (from, along(i)), (to, along(i))]);
+            indices.extend([first, first + 1, first + 2]);
+        }
+    }
+    for i in 0..path.len() - 1 {
+        let ((sl, sr), (el, er)) = (ends[i].1, ends[i + 1].0);
+        let first = verts.len() as u16;
+        verts.extend([(sl, along(i)), (sr, along(i)), (er, along(i + 1)), (el, along(i + 1))]);
+        indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+    (verts, indices)
+}
 
-        draw_triangle(tl, tr, br, color);
-        draw_triangle(tl, bl, br, color);
+/// Draws a strip through `points`, the head first, going from `width_head` and `color_head` there to `width_tail` and `color_tail` at the end.\
+/// It's one mesh, split up only for very long paths.
+pub fn draw_ribbon(points: &[Vec2], width_head: f32, width_tail: f32, color_head: Color, color_tail: Color) {
+    // macroquad takes fewer than 5000 indices at a time, and the longest paths have to go in a few meshes
+    const CHUNK: usize = 400;
+    let mut start = 0;
+    while start + 1 < points.len() {
+        let end = (start + CHUNK).min(points.len() - 1);
+        let along = |t: f32| (start as f32 + t * (end - start) as f32) / (points.len() - 1) as f32;
+        let (w1, w2) = (lerp(width_head, width_tail, along(0.0)), lerp(width_head, width_tail, along(1.0)));
+        let (verts, indices) = ribbon_geometry(&points[start..=end], w1, w2);
+        let vertices = verts.into_iter().map(|(pos, t)| Vertex {
+            position: vec3(pos.x, pos.y, 0.0), uv: Vec2::ZERO, color: mix(color_head, color_tail, along(t)),
+        }).collect();
+        draw_mesh(&Mesh { vertices, indices, texture: None });
+        start = end;
     }
 }
 
-pub fn gen_sign() -> f32 {
-    gen_range(0, 2) as f32 * 2.0 - 1.0
+/// Mixes a seed with a time and step into a new seed (splitmix64).\
+/// Used to keep randomness deterministic regardless of call order.
+pub fn hash_seed(seed: u64, time: f32, step: usize) -> u64 {
+    let mut x = seed ^ (time.to_bits() as u64).rotate_left(32) ^ (step as u64).wrapping_mul(0x9E3779B97F4A7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D049BB133111EB);
+    x ^ (x >> 31)
+}
+
+/// The run's random number generator, so the same seed always spawns the same obstacles.\
+/// Purely visual randomness (camera shake, particles) stays on macroquad's generator so it can't shift the run.
+#[derive(Clone)]
+pub struct GameRng {
+    seed: u64,
+    rng: StdRng,
+}
+impl Default for GameRng {
+    fn default() -> Self { Self::new(0) }
+}
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng { seed, rng: StdRng::seed_from_u64(seed) }
+    }
+    /// The seed of the run, not of the current sequence.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// A generator for `(time, step)` of the run, for closures that shouldn't depend on call order.
+    pub fn derive(&self, time: f32, step: usize) -> Self {
+        GameRng { seed: self.seed, rng: StdRng::seed_from_u64(hash_seed(self.seed, time, step)) }
+    }
+    /// Restarts the sequence as it would be at `time`, e.g. after rewinding to a checkpoint.
+    pub fn rewind(&mut self, time: f32) {
+        *self = self.derive(time, 0);
+    }
+    /// A value in `low..high`, or `low` if the range is empty.
+    pub fn range<T: SampleUniform + PartialOrd>(&mut self, low: T, high: T) -> T {
+        if low < high { self.rng.gen_range(low..high) } else { low }
+    }
+    pub fn chance(&mut self, p: f64) -> bool {
+        self.rng.gen_bool(p)
+    }
+    pub fn sign(&mut self) -> f32 {
+        if self.chance(0.5) { 1.0 } else { -1.0 }
+    }
+    pub fn vec(&mut self, from: Vec2, to: Vec2) -> Vec2 {
+        vec2(self.range(from.x, to.x), self.range(from.y, to.y))
+    }
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        items.shuffle(&mut self.rng);
+    }
+}
+
+/// The fastest of five runs of `f`, in seconds, for tests that check one way of doing something beats another.
+#[cfg(test)]
+pub(crate) fn best_of(mut f: impl FnMut()) -> f64 {
+    (0..5).map(|_| {
+        let start = std::time::Instant::now();
+        f();
+        start.elapsed().as_secs_f64()
+    }).fold(f64::INFINITY, f64::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vec2, b: Vec2) -> bool {
+        a.distance(b) < 1e-3
+    }
+
+    #[test]
+    fn bezier_ends_on_its_endpoints() {
+        let [p0, p1, p2, p3] = [vec2(0.0, 0.0), vec2(100.0, 300.0), vec2(400.0, -200.0), vec2(500.0, 100.0)];
+        assert!(close(cubic_bezier(p0, p1, p2, p3, 0.0), p0));
+        assert!(close(cubic_bezier(p0, p1, p2, p3, 1.0), p3));
+    }
+
+    #[test]
+    fn straight_bezier_is_a_line() {
+        let (from, to) = (vec2(-50.0, 20.0), vec2(250.0, 320.0));
+        for i in 0..=10 {
+            let t = i as f32 / 10.0;
+            assert!(close(cubic_bezier(from, from.lerp(to, 1.0 / 3.0), from.lerp(to, 2.0 / 3.0), to, t), from.lerp(to, t)));
+            assert!(close(cubic_bezier_tangent(from, from.lerp(to, 1.0 / 3.0), from.lerp(to, 2.0 / 3.0), to, t), to - from));
+        }
+    }
+
+    #[test]
+    fn bezier_tangent_matches_the_curve() {
+        let [p0, p1, p2, p3] = [vec2(0.0, 0.0), vec2(100.0, 300.0), vec2(400.0, -200.0), vec2(500.0, 100.0)];
+        let h = 1e-3;
+        for i in 1..10 {
+            let t = i as f32 / 10.0;
+            let numeric = (cubic_bezier(p0, p1, p2, p3, t + h) - cubic_bezier(p0, p1, p2, p3, t - h)) / (2.0 * h);
+            assert!(numeric.distance(cubic_bezier_tangent(p0, p1, p2, p3, t)) < 1.0, "at {t}");
+        }
+        // the ends point at the neighbouring control points
+        assert!(close(cubic_bezier_tangent(p0, p1, p2, p3, 0.0), (p1 - p0) * 3.0));
+        assert!(close(cubic_bezier_tangent(p0, p1, p2, p3, 1.0), (p3 - p2) * 3.0));
+    }
+
+    #[test]
+    fn trail_covers_its_beats_at_any_frame_rate() {
+        let mut trail = TrailBuffer::new();
+        // a thousand frames a beat, far more than the capacity holds unthinned
+        for frame in 0..=2000 {
+            trail.advance(vec2(frame as f32, 0.0), frame as f32 / 1000.0, 0.5);
+            assert!(trail.len() <= TRAIL_CAPACITY);
+        }
+        let (newest, oldest) = (trail.iter().next().unwrap(), trail.iter().last().unwrap());
+        assert_eq!(newest, (vec2(2000.0, 0.0), 2.0));
+        assert!((newest.1 - oldest.1 - 0.5).abs() < 0.01, "covers {} beats", newest.1 - oldest.1);
+        assert!(trail.len() > TRAIL_CAPACITY / 2);
+        // and each point is where it was at its time
+        assert!(trail.iter().all(|(pos, t)| (pos.x - t * 1000.0).abs() < 1e-2));
+    }
+
+    #[test]
+    fn trail_forgets_old_and_future_points() {
+        let mut trail = TrailBuffer::new();
+        for frame in 0..100 {
+            trail.advance(Vec2::ZERO, frame as f32 * 0.1, 1.0);
+        }
+        assert!(trail.iter().all(|(_, t)| t >= 8.9 - 1e-4));
+        // seeking back leaves nothing from after the new time
+        trail.advance(Vec2::ONE, 2.0, 1.0);
+        let mut points = vec![Vec2::ZERO; 3];
+        trail.positions_into(Vec2::ONE, &mut points);
+        assert_eq!(points, vec![Vec2::splat(2.0)]);
+        trail.clear();
+        assert!(trail.is_empty());
+    }
+
+
+    #[test]
+    fn segment_hits_aabb_cases() {
+        let size = vec2(100.0, 50.0);
+        // through, inside, and missing
+        assert!(segment_hits_aabb(vec2(-10.0, 25.0), vec2(110.0, 25.0), size));
+        assert!(segment_hits_aabb(vec2(10.0, 10.0), vec2(90.0, 40.0), size));
+        assert!(!segment_hits_aabb(vec2(-10.0, -10.0), vec2(110.0, -10.0), size));
+        // parallel to an edge, outside and along it
+        assert!(!segment_hits_aabb(vec2(-20.0, 60.0), vec2(120.0, 60.0), size));
+        assert!(segment_hits_aabb(vec2(-20.0, 50.0), vec2(120.0, 50.0), size));
+        // ending short of it
+        assert!(!segment_hits_aabb(vec2(-50.0, 25.0), vec2(-1.0, 25.0), size));
+        // a point
+        assert!(segment_hits_aabb(vec2(50.0, 25.0), vec2(50.0, 25.0), size));
+    }
+
+    #[test]
+    fn capsule_circle_cases() {
+        let (from, to) = (vec2(0.0, 0.0), vec2(100.0, 0.0));
+        assert!(collide_capsule_circle(from, to, 10.0, vec2(50.0, 14.0), 5.0));
+        assert!(!collide_capsule_circle(from, to, 10.0, vec2(50.0, 16.0), 5.0));
+        // past the ends, only the caps count
+        assert!(collide_capsule_circle(from, to, 10.0, vec2(110.0, 8.0), 5.0));
+        assert!(!collide_capsule_circle(from, to, 10.0, vec2(112.0, 12.0), 5.0));
+        // a small circle well inside
+        assert!(collide_capsule_circle(from, to, 10.0, vec2(30.0, 1.0), 1.0));
+        // a zero-length sweep is just a circle
+        assert_eq!(collide_capsule_circle(from, from, 10.0, vec2(14.0, 0.0), 5.0), collide_cc(from, 10.0, vec2(14.0, 0.0), 5.0));
+        assert_eq!(collide_capsule_circle(from, from, 10.0, vec2(16.0, 0.0), 5.0), collide_cc(from, 10.0, vec2(16.0, 0.0), 5.0));
+    }
+
+    #[test]
+    fn capsule_rect_parallel_to_an_edge() {
+        let (center, size) = (vec2(50.0, 25.0), vec2(100.0, 50.0));
+        // running along the top, just out of reach and just in it
+        assert!(!collide_capsule_rect(vec2(-50.0, -11.0), vec2(150.0, -11.0), 10.0, center, size, 0.0));
+        assert!(collide_capsule_rect(vec2(-50.0, -9.0), vec2(150.0, -9.0), 10.0, center, size, 0.0));
+        // and right down the middle
+        assert!(collide_capsule_rect(vec2(-50.0, 25.0), vec2(150.0, 25.0), 1.0, center, size, 0.0));
+    }
+
+    #[test]
+    fn capsule_rect_inside() {
+        let (center, size) = (vec2(50.0, 25.0), vec2(100.0, 50.0));
+        assert!(collide_capsule_rect(vec2(20.0, 20.0), vec2(80.0, 30.0), 1.0, center, size, 0.0));
+        assert!(collide_capsule_rect(vec2(20.0, 20.0), vec2(80.0, 30.0), 1.0, center, size, 1.0));
+    }
+
+    #[test]
+    fn capsule_rect_grazes_a_corner() {
+        let (center, size) = (vec2(50.0, 25.0), vec2(100.0, 50.0));
+        // diagonal past the (100, 0) corner, along x - y = 119 (13.4 away) and x - y = 121 (14.8 away)
+        assert!(collide_capsule_rect(vec2(99.5, -19.5), vec2(119.5, 0.5), 14.0, center, size, 0.0));
+        assert!(!collide_capsule_rect(vec2(100.5, -20.5), vec2(120.5, -0.5), 14.0, center, size, 0.0));
+        // neither end is in reach, only the middle
+        assert!(!collide_cr(center, size, 0.0, vec2(99.5, -19.5), 14.0));
+        assert!(!collide_cr(center, size, 0.0, vec2(119.5, 0.5), 14.0));
+    }
+
+    #[test]
+    fn capsule_rect_matches_collide_cr_at_the_ends() {
+        let mut rng = GameRng::new(902);
+        for _ in 0..2000 {
+            let center = rng.vec(vec2(-100.0, -100.0), vec2(100.0, 100.0));
+            let size = rng.vec(vec2(1.0, 1.0), vec2(150.0, 150.0));
+            let rot = rng.range(-TAU, TAU);
+            let rad = rng.range(0.5, 30.0);
+            let from = rng.vec(vec2(-200.0, -200.0), vec2(200.0, 200.0));
+            let to = rng.vec(vec2(-200.0, -200.0), vec2(200.0, 200.0));
+            // a sweep that doesn't move is the circle check
+            assert_eq!(collide_capsule_rect(from, from, rad, center, size, rot), collide_cr(center, size, rot, from, rad));
+            // and one that does hits whatever either end, or anything along it, hits
+            let sampled = (0..=64).any(|i| collide_cr(center, size, rot, from.lerp(to, i as f32 / 64.0), rad));
+            if sampled {
+                assert!(collide_capsule_rect(from, to, rad, center, size, rot), "{from} -> {to} r{rad} vs {center} {size} {rot}");
+            }
+        }
+    }
+
+
+    /// (distance from the center, alpha) of each vertex of the only circle in `batch`, after the center.
+    fn rings(batch: &CircleBatch, center: Vec2) -> Vec<(f32, f32)> {
+        batch.mesh.vertices[1..].iter().map(|v| (vec2(v.position.x, v.position.y).distance(center), v.color.a)).collect()
+    }
+
+    #[test]
+    fn batched_circles_are_the_circles_draw_circle_draws() {
+        set_smooth_edges(false);
+        let mut batch = CircleBatch::default();
+        let (center, color) = (vec2(300.0, 200.0), Color::new(0.2, 0.4, 0.6, 0.8));
+        batch.circle(center, 12.0, color);
+        assert_eq!(batch.mesh.vertices.len(), BATCH_CIRCLE_SIDES + 1);
+        assert_eq!(batch.mesh.vertices[0].position, vec3(center.x, center.y, 0.0));
+        assert!(rings(&batch, center).iter().all(|&(dist, alpha)| (dist - 12.0).abs() < 1e-3 && alpha == 0.8));
+        assert!(batch.mesh.vertices.iter().all(|v| v.color == color));
+        // a fan around the center covering each side once
+        assert_eq!(batch.mesh.indices.len(), BATCH_CIRCLE_SIDES * 3);
+        assert!(batch.mesh.indices.chunks(3).all(|tri| tri[0] == 0));
+    }
+
+    #[test]
+    fn smoothed_batched_circles_fade_across_the_edge() {
+        set_smooth_edges(true);
+        let mut batch = CircleBatch::default();
+        batch.circle(Vec2::ZERO, 12.0, Color::new(1.0, 1.0, 1.0, 0.5));
+        set_smooth_edges(false);
+        let rings = rings(&batch, Vec2::ZERO);
+        let (core, rim) = rings.split_at(BATCH_CIRCLE_SIDES);
+        assert!(core.iter().all(|&(dist, alpha)| (dist - (12.0 - FEATHER / 2.0)).abs() < 1e-3 && alpha == 0.5));
+        assert!(rim.iter().all(|&(dist, alpha)| (dist - (12.0 + FEATHER / 2.0)).abs() < 1e-3 && alpha == 0.0));
+        assert_eq!(batch.mesh.indices.len(), BATCH_CIRCLE_SIDES * 9);
+    }
+
+    #[test]
+    fn batches_stay_under_the_index_limit() {
+        for smooth in [false, true] {
+            set_smooth_edges(smooth);
+            let mut batch = CircleBatch::default();
+            let per = BATCH_CIRCLE_SIDES * 3 * if smooth { 3 } else { 1 };
+            // as many as fit without flushing, which would need a window
+            for i in 0..BATCH_INDICES / per {
+                batch.circle(vec2(i as f32, 0.0), 5.0, WHITE);
+            }
+            assert!(batch.mesh.indices.len() + per > BATCH_INDICES);
+            assert!(batch.mesh.indices.iter().all(|&i| (i as usize) < batch.mesh.vertices.len()));
+        }
+        set_smooth_edges(false);
+    }
+
+    #[test]
+    fn batches_refill_without_reallocating() {
+        let mut batch = CircleBatch::default();
+        let mut capacity = None;
+        for i in 0..10_000 {
+            if batch.mesh.indices.len() + BATCH_CIRCLE_SIDES * 3 > BATCH_INDICES {
+                // what flushing does, besides drawing
+                let filled = (batch.mesh.vertices.capacity(), batch.mesh.indices.capacity());
+                assert_eq!(*capacity.get_or_insert(filled), filled, "after {i} circles");
+                batch.mesh.vertices.clear();
+                batch.mesh.indices.clear();
+                batch.circles = 0;
+            }
+            batch.circle(vec2((i % 1600) as f32, (i / 1600) as f32), 5.0, WHITE);
+        }
+        assert!(capacity.is_some());
+    }
+
+
+    /// 80 by 40 around (100, 100), unrotated
+    const RC: Vec2 = vec2(100.0, 100.0);
+    const RS: Vec2 = vec2(80.0, 40.0);
+
+    #[test]
+    fn closest_point_on_rect_edges_and_corners() {
+        // beside each edge, straight across
+        assert!(close(closest_point_cr(RC, RS, 0.0, vec2(200.0, 110.0)), vec2(140.0, 110.0)));
+        assert!(close(closest_point_cr(RC, RS, 0.0, vec2(0.0, 90.0)), vec2(60.0, 90.0)));
+        assert!(close(closest_point_cr(RC, RS, 0.0, vec2(70.0, 0.0)), vec2(70.0, 80.0)));
+        assert!(close(closest_point_cr(RC, RS, 0.0, vec2(130.0, 300.0)), vec2(130.0, 120.0)));
+        // off each corner, the corner
+        for (point, corner) in [((200.0, 200.0), (140.0, 120.0)), ((0.0, 0.0), (60.0, 80.0)), ((200.0, 0.0), (140.0, 80.0)), ((0.0, 200.0), (60.0, 120.0))] {
+            assert!(close(closest_point_cr(RC, RS, 0.0, point.into()), corner.into()), "{point:?}");
+        }
+        // inside and on the outline, itself
+        for point in [vec2(110.0, 105.0), vec2(140.0, 100.0), vec2(140.0, 120.0), RC] {
+            assert!(close(closest_point_cr(RC, RS, 0.0, point), point), "{point}");
+        }
+        // flipped sizes are the same rect, and a rect without size is its center
+        assert!(close(closest_point_cr(RC, -RS, 0.0, vec2(200.0, 200.0)), vec2(140.0, 120.0)));
+        assert!(close(closest_point_cr(RC, Vec2::ZERO, 1.0, vec2(200.0, 200.0)), RC));
+    }
+
+    #[test]
+    fn rect_contacts_at_edges_and_corners() {
+        let beside = contact_cr(RC, RS, 0.0, vec2(150.0, 110.0), 15.0);
+        assert!(close(beside.point.unwrap(), vec2(140.0, 110.0)) && close(beside.normal, Vec2::X));
+        assert!((beside.depth - 5.0).abs() < 1e-4);
+
+        let corner = contact_cr(RC, RS, 0.0, vec2(143.0, 124.0), 10.0);
+        assert!(close(corner.point.unwrap(), vec2(140.0, 120.0)) && close(corner.normal, vec2(0.6, 0.8)));
+        assert!((corner.depth - 5.0).abs() < 1e-4);
+
+        // inside, out through the nearest edge
+        let inside = contact_cr(RC, RS, 0.0, vec2(110.0, 105.0), 10.0);
+        assert!(close(inside.point.unwrap(), vec2(110.0, 120.0)) && close(inside.normal, Vec2::Y));
+        assert!((inside.depth - 25.0).abs() < 1e-4);
+
+        // right on an edge or a corner, still pushed out
+        let edge = contact_cr(RC, RS, 0.0, vec2(60.0, 100.0), 10.0);
+        assert!(close(edge.normal, -Vec2::X) && (edge.depth - 10.0).abs() < 1e-4);
+        let on_corner = contact_cr(RC, RS, 0.0, vec2(140.0, 120.0), 10.0);
+        assert!((on_corner.normal.length() - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn rect_contacts_turn_with_the_rect() {
+        let mut rng = GameRng::new(943);
+        for _ in 0..500 {
+            let (rot, point) = (rng.range(-TAU, TAU), rng.vec(vec2(0.0, 0.0), vec2(200.0, 200.0)));
+            let flat = contact_cr(RC, RS, 0.0, point, 10.0);
+            // the same point, turned with the rect
+            let turned = contact_cr(RC, RS, rot, rotate(point - RC, -rot) + RC, 10.0);
+            assert!(close(turned.point.unwrap(), rotate(flat.point.unwrap() - RC, -rot) + RC), "{point} at {rot}");
+            assert!(close(turned.normal, rotate(flat.normal, -rot)) && (turned.depth - flat.depth).abs() < 1e-3, "{point} at {rot}");
+        }
+    }
+
+    #[test]
+    fn closest_point_on_rect_is_closest() {
+        let mut rng = GameRng::new(9430);
+        for _ in 0..300 {
+            let (center, size, rot) = (rng.vec(vec2(-200.0, -200.0), vec2(200.0, 200.0)), rng.vec(vec2(-300.0, -300.0), vec2(300.0, 300.0)), rng.range(-TAU, TAU));
+            let point = rng.vec(vec2(-500.0, -500.0), vec2(500.0, 500.0));
+            let rad = rng.range(1.0, 100.0);
+            let closest = closest_point_cr(center, size, rot, point);
+            let local = rotate(closest - center, rot);
+            assert!(local.abs().cmple(size.abs() / 2.0 + 1e-3).all(), "closest point {closest} outside the rect");
+            // no point of the rect is any closer
+            for _ in 0..50 {
+                let other = rotate(rng.vec(-size.abs() / 2.0, size.abs() / 2.0), -rot) + center;
+                assert!(other.distance(point) >= closest.distance(point) - 1e-3);
+            }
+            // and contacts only reach in when the circle collides
+            let contact = contact_cr(center, size.abs(), rot, point, rad);
+            if contact.depth.abs() > 1e-2 {
+                assert_eq!(contact.depth > 0.0, collide_cr(center, size.abs(), rot, point, rad), "{point} against {center} {size} {rot}");
+            }
+            assert!((contact.normal.length() - 1.0).abs() < 1e-3, "no way out from {point}");
+        }
+    }
+
+    #[test]
+    fn circle_and_capsule_contacts_right_on_top() {
+        let stacked = contact_cc(Vec2::ZERO, 10.0, Vec2::ZERO, 5.0);
+        assert!(close(stacked.normal, Vec2::X) && stacked.depth == 15.0);
+        // on the line, pushed out sideways
+        let on_line = contact_capsule(Vec2::ZERO, vec2(100.0, 0.0), 10.0, vec2(50.0, 0.0), 5.0);
+        assert!((on_line.normal.dot(Vec2::X)).abs() < 1e-6 && (on_line.normal.length() - 1.0).abs() < 1e-6 && on_line.depth == 15.0);
+        // a capsule with no length is a circle
+        let point = contact_capsule(Vec2::ONE, Vec2::ONE, 10.0, vec2(1.0, 21.0), 5.0);
+        assert!(close(point.point.unwrap(), vec2(1.0, 11.0)) && close(point.normal, Vec2::Y) && point.depth == -5.0);
+    }
+
+    #[test]
+    fn arc_contacts_agree_with_collide_circ_arc() {
+        let mut rng = GameRng::new(9431);
+        for _ in 0..2000 {
+            let (inner, outer) = (rng.range(0.0, 150.0), rng.range(50.0, 300.0));
+            let (ang1, ang2) = (rng.range(-3.0 * TAU, 3.0 * TAU), rng.range(-3.0 * TAU, 3.0 * TAU));
+            let (cpos, crad) = (rng.vec(vec2(-400.0, -400.0), vec2(400.0, 400.0)), rng.range(1.0, 60.0));
+            let contact = contact_arc(cpos, crad, Vec2::ZERO, outer, inner, ang1, ang2);
+            if contact.depth.abs() > 1e-2 {
+                assert_eq!(contact.depth > 0.0, collide_circ_arc(cpos, crad, Vec2::ZERO, outer, inner, ang1, ang2), "{cpos} {crad} against {inner}..{outer} from {ang1} to {ang2}");
+            }
+        }
+    }
+
+
+    const SQUARE: [Vec2; 4] = [vec2(0.0, 0.0), vec2(100.0, 0.0), vec2(100.0, 100.0), vec2(0.0, 100.0)];
+
+    #[test]
+    fn polygon_edges_and_vertices() {
+        let reversed = SQUARE.iter().rev().copied().collect::<Vec<_>>();
+        for verts in [&SQUARE[..], &reversed] {
+            // just touching an edge collides, a little further doesn't
+            assert!(collide_circ_poly(verts, Vec2::ZERO, 0.0, vec2(150.0, 50.0), 50.0));
+            assert!(!collide_circ_poly(verts, Vec2::ZERO, 0.0, vec2(150.0, 50.0), 49.9));
+            // off a corner, it's the corner's distance that counts
+            assert!(collide_circ_poly(verts, Vec2::ZERO, 0.0, vec2(130.0, 140.0), 50.0));
+            assert!(!collide_circ_poly(verts, Vec2::ZERO, 0.0, vec2(130.0, 140.0), 49.9));
+            assert!(!collide_circ_poly(verts, Vec2::ZERO, 0.0, vec2(120.0, 120.0), 25.0));
+            // inside, however small, and right on a vertex
+            assert!(collide_circ_poly(verts, Vec2::ZERO, 0.0, vec2(50.0, 50.0), 0.0));
+            assert!(collide_circ_poly(verts, Vec2::ZERO, 0.0, vec2(100.0, 100.0), 0.0));
+
+            assert!(close(closest_point_poly(verts, Vec2::ZERO, 0.0, vec2(150.0, 50.0)).unwrap(), vec2(100.0, 50.0)));
+            assert!(close(closest_point_poly(verts, Vec2::ZERO, 0.0, vec2(130.0, 140.0)).unwrap(), vec2(100.0, 100.0)));
+            assert!(close(closest_point_poly(verts, Vec2::ZERO, 0.0, vec2(50.0, 10.0)).unwrap(), vec2(50.0, 0.0)));
+        }
+        // turned an eighth around its first corner and moved, the same cases turned with it
+        let (offset, rot) = (vec2(500.0, 500.0), PI / 4.0);
+        let place = |local: Vec2| rotate(local, rot) + offset;
+        assert!(close(closest_point_poly(&SQUARE, offset, rot, place(vec2(130.0, -40.0))).unwrap(), place(vec2(100.0, 0.0))));
+        assert!(collide_circ_poly(&SQUARE, offset, rot, place(vec2(130.0, -40.0)), 50.1));
+        assert!(!collide_circ_poly(&SQUARE, offset, rot, place(vec2(130.0, -40.0)), 49.9));
+        assert!(collide_circ_poly(&SQUARE, offset, rot, place(vec2(50.0, 50.0)), 0.0));
+        assert!(!collide_circ_poly(&SQUARE, offset, rot, place(vec2(150.0, 50.0)), 49.0));
+    }
+
+    #[test]
+    fn polygons_without_an_inside_never_collide() {
+        let degenerate: [&[Vec2]; 6] = [
+            &[],
+            &[vec2(1.0, 1.0)],
+            &[vec2(0.0, 0.0), vec2(100.0, 0.0)],
+            // in a line
+            &[vec2(0.0, 0.0), vec2(50.0, 50.0), vec2(100.0, 100.0)],
+            &[vec2(5.0, 5.0); 4],
+            &[vec2(0.0, 0.0), vec2(f32::NAN, 0.0), vec2(0.0, 100.0)],
+        ];
+        for verts in degenerate {
+            assert!(!collide_circ_poly(verts, Vec2::ZERO, 0.0, vec2(5.0, 5.0), 1000.0), "{verts:?}");
+            assert!(closest_point_poly(verts, Vec2::ZERO, 0.0, vec2(5.0, 5.0)).is_none(), "{verts:?}");
+            assert!(contact_poly(verts, Vec2::ZERO, 0.0, vec2(5.0, 5.0), 10.0).is_none(), "{verts:?}");
+        }
+    }
+
+    /// Whether `point` is inside the polygon, by counting the edges a ray to its right crosses.
+    fn crossings_inside(verts: &[Vec2], point: Vec2) -> bool {
+        poly_edges(verts).filter(|&(a, b)| {
+            (a.y > point.y) != (b.y > point.y) && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        }).count() % 2 == 1
+    }
+
+    #[test]
+    fn polygons_match_dense_sampling() {
+        let mut rng = GameRng::new(950);
+        for _ in 0..300 {
+            // corners around an ellipse are always convex
+            let (center, radii) = (rng.vec(vec2(-100.0, -100.0), vec2(100.0, 100.0)), rng.vec(vec2(10.0, 10.0), vec2(200.0, 200.0)));
+            let mut angles = (0..rng.range(3.0, 12.0) as usize).map(|_| rng.range(0.0, TAU)).collect::<Vec<_>>();
+            angles.sort_by(f32::total_cmp);
+            let verts = angles.iter().map(|&a| center + vec2(a.cos(), a.sin()) * radii).collect::<Vec<_>>();
+            if poly_area2(&verts).abs() < 1.0 { continue; }
+            let (offset, rot) = (rng.vec(vec2(-300.0, -300.0), vec2(300.0, 300.0)), rng.range(-TAU, TAU));
+            let outline = poly_edges(&verts).flat_map(|(a, b)| (0..=200).map(move |i| a.lerp(b, i as f32 / 200.0))).collect::<Vec<_>>();
+            for _ in 0..50 {
+                let (c, r) = (rng.vec(vec2(-700.0, -700.0), vec2(700.0, 700.0)), rng.range(0.0, 150.0));
+                let local = rotate(c - offset, -rot);
+                let nearest = outline.iter().map(|p| p.distance(local)).fold(f32::INFINITY, f32::min);
+                let closest = closest_point_poly(&verts, offset, rot, c).unwrap();
+                // nothing on the outline is closer, give or take the sampling
+                assert!(closest.distance(c) <= nearest + 1e-2);
+                if (nearest - r).abs() < 1.0 { continue; }
+                let expected = crossings_inside(&verts, local) || nearest <= r;
+                assert_eq!(collide_circ_poly(&verts, offset, rot, c, r), expected, "{c} radius {r} against {verts:?} at {offset} turned {rot}");
+            }
+        }
+    }
+
+
+    #[test]
+    fn capsules_with_no_length_are_circles() {
+        let mut rng = GameRng::new(951);
+        for _ in 0..500 {
+            let (at, rad, other, other_rad) = (rng.vec(Vec2::ZERO, vec2(100.0, 100.0)), rng.range(0.0, 30.0), rng.vec(Vec2::ZERO, vec2(100.0, 100.0)), rng.range(0.0, 30.0));
+            assert_eq!(collide_capsule(at, at, rad, other, other_rad), collide_cc(at, rad, other, other_rad));
+        }
+        // where rectify_line finds no line at all
+        assert!(rectify_line(Vec2::ONE, Vec2::ONE, 10.0).is_none());
+        assert!(collide_capsule(Vec2::ONE, Vec2::ONE, 10.0, vec2(1.0, 12.0), 1.0));
+        let contact = contact_capsule(Vec2::ONE, Vec2::ONE, 10.0, Vec2::ONE, 1.0);
+        assert!(contact.normal.is_finite() && contact.point.is_some_and(Vec2::is_finite) && contact.depth.is_finite());
+    }
+
+    #[test]
+    fn capsule_ends_are_round() {
+        let (a, b) = (vec2(0.0, 0.0), vec2(100.0, 0.0));
+        // off the corner of the square end a rect would have, clear of the rounded one
+        let corner = vec2(108.0, 8.0);
+        assert!(collide_cr(vec2(50.0, 0.0), vec2(100.0 + 20.0, 20.0), 0.0, corner, 1.0));
+        assert!(!collide_capsule(a, b, 10.0, corner, 1.0));
+        // straight past the tip
+        assert!(collide_capsule(a, b, 10.0, vec2(110.5, 0.0), 1.0));
+        assert!(!collide_capsule(a, b, 10.0, vec2(111.5, 0.0), 1.0));
+    }
+
+    #[test]
+    fn capsules_match_circles_along_them() {
+        let mut rng = GameRng::new(9510);
+        for _ in 0..500 {
+            let (a, b, rad) = (rng.vec(Vec2::ZERO, vec2(400.0, 400.0)), rng.vec(Vec2::ZERO, vec2(400.0, 400.0)), rng.range(0.0, 40.0));
+            let (other, other_rad) = (rng.vec(Vec2::ZERO, vec2(400.0, 400.0)), rng.range(0.0, 40.0));
+            // the nearest of a dense row of circles along the capsule
+            let nearest = (0..=1000).map(|i| a.lerp(b, i as f32 / 1000.0).distance(other)).fold(f32::INFINITY, f32::min);
+            if (nearest - rad - other_rad).abs() < 0.5 { continue; }
+            assert_eq!(collide_capsule(a, b, rad, other, other_rad), nearest <= rad + other_rad, "{other} radius {other_rad} against {a} to {b} radius {rad}");
+        }
+        assert!(!collide_capsule(vec2(f32::NAN, 0.0), Vec2::ONE, 10.0, Vec2::ONE, 10.0));
+    }
+
+
+    /// Whether a point circle `angle` round, halfway through the ring, is in the arc from 100 to 150 around the origin.
+    fn in_arc_at(angle: f32, ang1: f32, ang2: f32) -> bool {
+        collide_circ_arc(vec2(angle.sin(), angle.cos()) * 125.0, 0.0, Vec2::ZERO, 150.0, 100.0, ang1, ang2)
+    }
+
+    #[test]
+    fn arcs_cover_their_span_at_any_number_of_turns() {
+        for turns in -3..=3 {
+            for start_deg in (0..360i32).step_by(15) {
+                for span_deg in [10, 90, 200, 350] {
+                    let ang1 = (start_deg as f32).to_radians() + turns as f32 * TAU;
+                    let ang2 = ang1 + (span_deg as f32).to_radians();
+                    for deg in 0..360 {
+                        // how far round from the start, clockwise
+                        let along = (deg - start_deg).rem_euclid(360);
+                        let off = along.min(360 - along).min((along - span_deg).abs());
+                        // close to an end, where rounding decides
+                        if off < 1 { continue; }
+                        let angle = (deg as f32).to_radians();
+                        let expected = along < span_deg;
+                        assert_eq!(in_arc_at(angle, ang1, ang2), expected, "{deg} degrees against {start_deg}+{span_deg} at {turns} turns");
+                        // given backwards, it's the same arc
+                        assert_eq!(in_arc_at(angle, ang2, ang1), expected, "{deg} degrees against {start_deg}+{span_deg} backwards at {turns} turns");
+                        // and the angle itself can be any number of turns round
+                        assert_eq!(in_arc_at(angle - 2.0 * TAU, ang1, ang2), expected);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn arc_ends_and_whole_rings() {
+        let (ang1, ang2) = (1.0, 2.0);
+        // ends included
+        assert!(in_arc_at(ang1, ang1, ang2) && in_arc_at(ang2, ang1, ang2));
+        // a turn or more is the whole ring, whichever way
+        for (ang1, ang2) in [(0.0, TAU), (0.5, 0.5 + 3.0 * TAU), (2.0, 2.0 - 1.5 * TAU)] {
+            assert!((0..36).all(|i| in_arc_at(i as f32 * TAU / 36.0, ang1, ang2)), "{ang1} to {ang2}");
+        }
+        // no span is just the one angle
+        assert!(in_arc_at(1.0, 1.0, 1.0) && !in_arc_at(1.1, 1.0, 1.0));
+        // arcs with angles that aren't numbers are nowhere
+        assert!(!in_arc_at(1.5, f32::NAN, 2.0) && !in_arc_at(1.5, 1.0, f32::NAN) && !in_arc_at(1.5, 1.0, f32::INFINITY));
+        // and circles at angles that aren't are in none
+        assert!(!collide_circ_arc(vec2(f32::NAN, 125.0), 0.0, Vec2::ZERO, 150.0, 100.0, 0.0, TAU));
+        // the ring's radii either way round
+        assert!(collide_circ_arc(vec2(1.5f32.sin(), 1.5f32.cos()) * 125.0, 0.0, Vec2::ZERO, 100.0, 150.0, ang1, ang2));
+    }
+
+
+    /// Area of a simple polygon either way round.
+    fn area(verts: &[Vec2]) -> f32 {
+        poly_area2(verts).abs() / 2.0
+    }
+
+    #[test]
+    fn frames_meet_at_mitered_corners() {
+        let mut rng = GameRng::new(954);
+        for _ in 0..200 {
+            let (center, size, rot) = (rng.vec(vec2(-500.0, -500.0), vec2(500.0, 500.0)), rng.vec(vec2(-300.0, -300.0), vec2(300.0, 300.0)), rng.range(-TAU, TAU));
+            let thickness = rng.range(0.0, 40.0);
+            let frame = rrect_frame(center, size, rot, thickness);
+            for i in 0..4 {
+                // each side ends on the diagonal the next one starts on
+                let (side, next) = (frame[i], frame[(i + 1) % 4]);
+                assert!(side[1] == next[0] && side[2] == next[3]);
+            }
+            // no gaps and no overlaps: the sides add up to the rect less its inside
+            let inset = (size.abs() - Vec2::splat(thickness * 2.0)).max(Vec2::ZERO);
+            let expected = size.x.abs() * size.y.abs() - inset.x * inset.y;
+            let total = frame.iter().map(|quad| area(quad)).sum::<f32>();
+            assert!((total - expected).abs() <= expected.max(1.0) * 1e-3, "{total} against {expected}");
+            // and every point of the border is in exactly one of them
+            for _ in 0..50 {
+                let local = rng.vec(-size.abs() / 2.0, size.abs() / 2.0);
+                let room = size.abs() / 2.0 - local.abs();
+                let depth = room.x.min(room.y);
+                if (depth - thickness).abs() < 0.1 || (room.x - room.y).abs() < 0.1 || depth < 0.1 { continue; }
+                let point = rotate(local, rot) + center;
+                let within = frame.iter().filter(|quad| inside_convex(&quad[..], point)).count();
+                assert_eq!(within, usize::from(depth < thickness), "{local} in {size} {thickness} thick");
+            }
+        }
+    }
+
+    #[test]
+    fn frames_of_degenerate_rects() {
+        // no width, no frame to speak of, and nothing that isn't a number
+        for (size, thickness) in [(vec2(0.0, 100.0), 5.0), (Vec2::ZERO, 5.0), (vec2(100.0, 50.0), 0.0), (vec2(100.0, 50.0), -3.0)] {
+            let frame = rrect_frame(vec2(10.0, 10.0), size, 0.7, thickness);
+            assert!(frame.iter().flatten().all(|p| p.is_finite()));
+            assert!(frame.iter().map(|quad| area(quad)).sum::<f32>() < 1e-3, "{size} {thickness} thick");
+        }
+        // thicker than the rect is the whole rect
+        let frame = rrect_frame(Vec2::ZERO, vec2(100.0, 50.0), 0.3, 40.0);
+        assert!((frame.iter().map(|quad| area(quad)).sum::<f32>() - 5000.0).abs() < 0.5);
+        // a thin border around a thin rect fills across where it's thin and not along
+        let frame = rrect_frame(Vec2::ZERO, vec2(100.0, 10.0), 0.0, 8.0);
+        assert!((frame.iter().map(|quad| area(quad)).sum::<f32>() - 1000.0).abs() < 0.5);
+    }
+
+
+    fn same_color(a: Color, b: Color) -> bool {
+        [a.r - b.r, a.g - b.g, a.b - b.b, a.a - b.a].iter().all(|d| d.abs() < 1e-4)
+    }
+
+    #[test]
+    fn rgb_to_hsv_and_back() {
+        let steps = (0..=10).map(|i| i as f32 / 10.0).collect::<Vec<_>>();
+        for &r in &steps {
+            for &g in &steps {
+                for &b in &steps {
+                    let color = Color::new(r, g, b, r * 0.5 + 0.25);
+                    let hsv = rgb_to_hsv(color);
+                    assert!((0.0..360.0).contains(&hsv.h) && (0.0..=1.0).contains(&hsv.s) && (0.0..=1.0).contains(&hsv.v), "{hsv:?}");
+                    assert!(same_color(hsv_to_rgb(hsv), color), "{color:?} came back as {:?}", hsv_to_rgb(hsv));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn primaries_and_grays_in_hsv() {
+        for (color, h) in [(Color::new(1.0, 0.0, 0.0, 1.0), 0.0), (Color::new(1.0, 1.0, 0.0, 1.0), 60.0), (Color::new(0.0, 1.0, 0.0, 1.0), 120.0), (Color::new(0.0, 1.0, 1.0, 1.0), 180.0), (Color::new(0.0, 0.0, 1.0, 1.0), 240.0), (Color::new(1.0, 0.0, 1.0, 1.0), 300.0)] {
+            let hsv = rgb_to_hsv(color);
+            assert!((hsv.h - h).abs() < 1e-3 && hsv.s == 1.0 && hsv.v == 1.0, "{color:?} is {hsv:?}");
+        }
+        // grays keep their value, with a hue that's always the same
+        for v in [0.0, 0.3, 1.0] {
+            assert_eq!(rgb_to_hsv(Color::new(v, v, v, 1.0)), Hsv { h: 0.0, s: 0.0, v, a: 1.0 });
+        }
+    }
+
+    #[test]
+    fn hues_wrap_around() {
+        let hsv = |h: f32| hsv_to_rgb(Hsv { h, s: 0.8, v: 0.9, a: 1.0 });
+        assert!(same_color(hsv(360.0), hsv(0.0)) && same_color(hsv(-120.0), hsv(240.0)) && same_color(hsv(720.0 + 30.0), hsv(30.0)));
+        // right before the wrap is still red, not the other end of the wheel
+        assert!(same_color(hsv(-1e-7), hsv(0.0)));
+        let red = Color::new(1.0, 0.0, 0.0, 1.0);
+        assert!(same_color(hue_shift(red, 360.0), red) && same_color(hue_shift(red, -240.0), hue_shift(red, 120.0)));
+        // grays don't change
+        let gray = Color::new(0.4, 0.4, 0.4, 0.5);
+        assert!(same_color(hue_shift(gray, 77.0), gray));
+    }
+
+    #[test]
+    fn hsv_mixes_go_the_short_way_round() {
+        let red = Color::new(1.0, 0.0, 0.0, 1.0);
+        let magenta = Color::new(1.0, 0.0, 1.0, 1.0);
+        // halfway from 0 to 300 is 330, not 150
+        assert!((rgb_to_hsv(mix_hsv(red, magenta, 0.5)).h - 330.0).abs() < 1e-2);
+        assert!((rgb_to_hsv(mix_hsv(magenta, red, 0.5)).h - 330.0).abs() < 1e-2);
+        assert!(same_color(mix_hsv(red, magenta, 0.0), red) && same_color(mix_hsv(red, magenta, 1.0), magenta));
+        // from gray, the hue is the other color's the whole way
+        let gray = Color::new(0.5, 0.5, 0.5, 1.0);
+        let green = Color::new(0.0, 1.0, 0.0, 1.0);
+        assert!((0..=10).all(|i| { let hsv = rgb_to_hsv(mix_hsv(gray, green, i as f32 / 10.0)); hsv.s == 0.0 || (hsv.h - 120.0).abs() < 1e-2 }));
+    }
+
+    #[test]
+    fn hue_cycles_come_back_round() {
+        let base = Color::new(0.9, 0.3, 0.1, 1.0);
+        for time in [0.0, 0.37, 5.5, -2.25] {
+            // a turn every two beats
+            assert!(same_color(hue_cycle(base, 0.5, time), hue_cycle(base, 0.5, time + 2.0)), "at {time}");
+        }
+        assert!(same_color(hue_cycle(base, 0.5, 0.0), base));
+        assert!(same_color(hue_cycle(base, 0.5, 0.5), hue_shift(base, 90.0)));
+    }
+
+
+    #[test]
+    fn glow_fades_out_to_nothing_at_its_edge() {
+        let size = GLOW_TEXTURE_SIZE as usize;
+        let pixels = glow_pixels(size);
+        assert_eq!(pixels.len(), size * size * 4);
+        let alpha = |x: usize, y: usize| pixels[(y * size + x) * 4 + 3];
+        // white all over, only the alpha changes
+        assert!(pixels.chunks(4).all(|p| p[..3] == [255, 255, 255]));
+        // nearly opaque in the middle, clear along the edges and in the corners
+        assert!(alpha(size / 2, size / 2) > 240);
+        assert!((0..size).all(|i| alpha(i, 0) == 0 && alpha(0, i) == 0 && alpha(i, size - 1) == 0 && alpha(size - 1, i) == 0));
+        // fading the whole way out, the same every way round
+        for x in size / 2..size - 1 {
+            assert!(alpha(x + 1, size / 2) <= alpha(x, size / 2));
+            assert_eq!(alpha(x, size / 2), alpha(size - 1 - x, size / 2));
+            assert_eq!(alpha(x, size / 2), alpha(size / 2, x));
+        }
+        // easing off, so the last bit before the edge is faint rather than a rim
+        assert!(alpha(size - 2, size / 2) < 4);
+    }
+
+    /// Signed doubled areas of a ribbon's triangles.
+    fn ribbon_areas(verts: &[(Vec2, f32)], indices: &[u16]) -> Vec<f32> {
+        assert!(indices.len().is_multiple_of(3) && indices.iter().all(|&i| (i as usize) < verts.len()));
+        indices.chunks(3).map(|tri| {
+            let [a, b, c] = [0, 1, 2].map(|k| verts[tri[k] as usize].0);
+            (b - a).perp_dot(c - a)
+        }).collect()
+    }
+
+    /// Every piece's two triangles wind the same way, so none of them is a bowtie.
+    fn assert_no_bowties(points: &[Vec2]) {
+        let (verts, indices) = ribbon_geometry(points, 10.0, 10.0);
+        let areas = ribbon_areas(&verts, &indices);
+        // the pieces are the last 2 triangles per segment
+        let pieces = &areas[areas.len() - 2 * (points.len() - 1)..];
+        for quad in pieces.chunks(2) {
+            assert!(quad[0] * quad[1] > 0.0, "bowtie {quad:?} in {points:?}");
+        }
+    }
+
+    #[test]
+    fn straight_ribbons_are_their_width_all_along() {
+        let points = [vec2(0.0, 0.0), vec2(10.0, 0.0), vec2(30.0, 0.0), vec2(60.0, 0.0)];
+        let (verts, indices) = ribbon_geometry(&points, 10.0, 10.0);
+        // a quad per piece and nothing else
+        assert_eq!((verts.len(), indices.len()), (12, 18));
+        assert!(verts.iter().all(|(pos, _)| (pos.y.abs() - 5.0).abs() < 1e-4));
+        assert!(verts.iter().all(|(pos, _)| (0.0..=60.0).contains(&pos.x)));
+        let area = ribbon_areas(&verts, &indices).iter().map(|a| a.abs() / 2.0).sum::<f32>();
+        assert!((area - 600.0).abs() < 1e-2, "{area}");
+        // tapering goes from the head's width to the tail's
+        let (verts, _) = ribbon_geometry(&points, 10.0, 2.0);
+        for (pos, along) in verts {
+            assert!((pos.y.abs() - lerp(10.0, 2.0, along) / 2.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn right_angles_meet_at_a_miter() {
+        let points = [vec2(0.0, 0.0), vec2(20.0, 0.0), vec2(20.0, 20.0)];
+        let (verts, indices) = ribbon_geometry(&points, 10.0, 10.0);
+        // no bevel, the pieces share their corners
+        assert_eq!((verts.len(), indices.len()), (8, 12));
+        let corners = verts.iter().filter(|(_, along)| *along == 0.5).map(|(pos, _)| *pos).collect::<Vec<_>>();
+        for corner in [vec2(15.0, 5.0), vec2(25.0, -5.0)] {
+            assert_eq!(corners.iter().filter(|c| c.distance(corner) < 1e-4).count(), 2, "{corners:?}");
+        }
+        assert_no_bowties(&points);
+    }
+
+    #[test]
+    fn hairpins_are_bevelled() {
+        let points = [vec2(0.0, 0.0), vec2(50.0, 0.0), vec2(0.0, 1.0)];
+        let (verts, indices) = ribbon_geometry(&points, 10.0, 10.0);
+        // a bevel and two quads
+        assert_eq!((verts.len(), indices.len()), (11, 15));
+        // nothing reaches far past the turn, as a miter would
+        assert!(verts.iter().all(|(pos, _)| pos.x <= 50.0 + 5.0 + 1e-3), "{verts:?}");
+        assert_no_bowties(&points);
+        // the bevel is on the outside of the corner, turning either way
+        let mirrored = points.map(|p| vec2(p.x, -p.y));
+        assert_no_bowties(&mirrored);
+        let (verts, _) = ribbon_geometry(&mirrored, 10.0, 10.0);
+        assert!(verts.iter().all(|(pos, _)| pos.x <= 55.0 + 1e-3));
+        // and doubling straight back is the same
+        assert_no_bowties(&[vec2(0.0, 0.0), vec2(50.0, 0.0), vec2(0.0, 0.0)]);
+    }
+
+    #[test]
+    fn ribbons_through_too_few_points_are_empty() {
+        let (verts, indices) = ribbon_geometry(&[], 10.0, 10.0);
+        assert!(verts.is_empty() && indices.is_empty());
+        let (verts, _) = ribbon_geometry(&[Vec2::ONE], 10.0, 10.0);
+        assert!(verts.is_empty());
+        // repeated points count once
+        let (verts, _) = ribbon_geometry(&[Vec2::ONE; 10], 10.0, 10.0);
+        assert!(verts.is_empty());
+        let (_, indices) = ribbon_geometry(&[Vec2::ZERO, Vec2::ZERO, vec2(10.0, 0.0), vec2(10.0, 0.0)], 10.0, 10.0);
+        assert_eq!(indices.len(), 6);
+    }
+
+    #[test]
+    fn random_ribbons_never_bowtie() {
+        let mut rng = GameRng::new(11);
+        for _ in 0..200 {
+            let mut points = vec![Vec2::ZERO];
+            for _ in 0..8 {
+                points.push(*points.last().unwrap() + vec2(rng.range(-50.0, 50.0), rng.range(-50.0, 50.0)));
+            }
+            assert_no_bowties(&points);
+        }
+    }
+
+
+    fn channels(c: Color) -> [f32; 4] {
+        [c.r, c.g, c.b, c.a]
+    }
+
+    #[test]
+    fn color_arithmetic_stays_in_range() {
+        let color = Color::new(0.8, 0.5, 0.2, 0.9);
+        let in_range = |c: Color| channels(c).iter().all(|c| (0.0..=1.0).contains(c));
+        for val in [-2.0, -0.1, 0.0, 0.5, 1.0, 1.01, 1.5, 100.0, f32::INFINITY, f32::NAN] {
+            assert!(in_range(cmul(color, val)), "cmul by {val}");
+            assert!(in_range(acmul(color, val)), "acmul by {val}");
+            assert!(in_range(with_alpha(color, val)), "with_alpha {val}");
+            assert!(in_range(mix(color, Color::new(1.0, 1.0, 1.0, 1.0), val)), "mix by {val}");
+            assert!(in_range(mix_linear(color, Color::new(1.0, 1.0, 1.0, 1.0), val)), "mix_linear by {val}");
+        }
+        assert_eq!(channels(cmul(color, 1.5)), [1.0, 0.75, 0.3, 0.9]);
+        assert_eq!(channels(acmul(color, 2.0)), [0.8, 0.5, 0.2, 1.0]);
+        assert_eq!(channels(with_alpha(color, 0.25)), [0.8, 0.5, 0.2, 0.25]);
+        assert_eq!(channels(clamp_color(Color::new(f32::NAN, -1.0, 2.0, 0.5))), [0.0, 0.0, 1.0, 0.5]);
+        // inputs already out of range come back in too
+        assert!(in_range(cmul(Color::new(3.0, -1.0, 0.5, 2.0), 1.0)));
+    }
+
+    #[test]
+    fn mixes_land_on_their_ends() {
+        let (a, b) = (Color::new(0.1, 0.7, 0.3, 1.0), Color::new(0.9, 0.2, 0.6, 0.5));
+        assert_eq!(channels(mix(a, b, 0.0)), channels(a));
+        assert_eq!(channels(mix(a, b, 1.0)), channels(b));
+        assert_eq!(channels(mix_linear(a, b, 0.0)), channels(a));
+        assert_eq!(channels(mix_linear(a, b, 1.0)), channels(b));
+        // past the ends stays on them
+        assert_eq!(channels(mix_linear(a, b, -1.0)), channels(a));
+        assert_eq!(channels(mix_linear(a, b, 2.0)), channels(b));
+        assert_eq!(channels(mix_linear(a, b, f32::NAN)), channels(a));
+        // plain mixing is a straight line through every channel
+        assert!(same_color(mix(a, b, 0.25), Color::new(0.3, 0.575, 0.375, 0.875)));
+    }
+
+    #[test]
+    fn linear_mixes_go_one_way_and_stay_bright() {
+        let (white, red, black) = (Color::new(1.0, 1.0, 1.0, 1.0), Color::new(1.0, 0.0, 0.0, 1.0), Color::new(0.0, 0.0, 0.0, 1.0));
+        for (from, to) in [(white, red), (red, white), (black, white), (Color::new(0.2, 0.9, 0.4, 1.0), Color::new(0.7, 0.1, 0.4, 0.0))] {
+            let mut last = channels(from);
+            for i in 1..=100 {
+                let now = channels(mix_linear(from, to, i as f32 / 100.0));
+                for c in 0..4 {
+                    // every channel heads toward where it's going, never back
+                    let toward = channels(to)[c] - channels(from)[c];
+                    assert!((now[c] - last[c]) * toward.signum() >= -1e-6, "channel {c} at {i}");
+                }
+                last = now;
+            }
+        }
+        // halfway from white to red is brighter than the plain mix, not a muddy pink
+        let (linear, plain) = (mix_linear(white, red, 0.5), mix(white, red, 0.5));
+        assert!(linear.g > plain.g && linear.r == 1.0);
+        assert!((linear.g - 0.5f32.sqrt()).abs() < 1e-4);
+    }
+
+
+    #[test]
+    fn lines_with_no_length_are_nothing() {
+        assert_eq!(rectify_line(vec2(3.0, 4.0), vec2(3.0, 4.0), 10.0), None);
+        assert_eq!(rectify_line(Vec2::ZERO, Vec2::ZERO, 0.0), None);
+        assert_eq!(rectify_line(Vec2::ZERO, -Vec2::ZERO, 10.0), None);
+        // nor is anything that isn't a number
+        assert_eq!(rectify_line(vec2(f32::NAN, 0.0), Vec2::ONE, 10.0), None);
+        assert_eq!(rectify_line(Vec2::ZERO, vec2(f32::INFINITY, 0.0), 10.0), None);
+        assert_eq!(rectify_line(Vec2::ZERO, Vec2::ONE, f32::NAN), None);
+        // or that's too long to measure
+        assert_eq!(rectify_line(vec2(-f32::MAX, 0.0), vec2(f32::MAX, 0.0), 10.0), None);
+    }
+
+    #[test]
+    fn straight_lines_are_unrotated() {
+        // horizontal either way
+        for (start, end) in [(vec2(10.0, 50.0), vec2(110.0, 50.0)), (vec2(110.0, 50.0), vec2(10.0, 50.0))] {
+            assert_eq!(rectify_line(start, end, 8.0), Some((vec2(60.0, 50.0), vec2(100.0, 8.0), 0.0)));
+        }
+        // vertical either way, standing up rather than turned a quarter
+        for (start, end) in [(vec2(-20.0, 0.0), vec2(-20.0, 40.0)), (vec2(-20.0, 40.0), vec2(-20.0, 0.0))] {
+            assert_eq!(rectify_line(start, end, 6.0), Some((vec2(-20.0, 20.0), vec2(6.0, 40.0), 0.0)));
+        }
+        // and what collides with them is exactly what's within their extents
+        let (center, size, rot) = rectify_line(vec2(-20.0, 0.0), vec2(-20.0, 40.0), 6.0).unwrap();
+        assert!(collide_cr(center, size, rot, vec2(-17.5, 39.5), 0.0));
+        assert!(!collide_cr(center, size, rot, vec2(-16.5, 20.0), 0.0));
+        assert!(!collide_cr(center, size, rot, vec2(-20.0, 40.5), 0.0));
+    }
+
+    #[test]
+    fn slanted_lines_turn_to_meet_their_ends() {
+        let (center, size, rot) = rectify_line(Vec2::ZERO, vec2(30.0, 40.0), 2.0).unwrap();
+        assert!(close(center, vec2(15.0, 20.0)));
+        assert!((size.x - 50.0).abs() < 1e-4 && size.y == 2.0);
+        assert!((rot - (40.0f32).atan2(30.0)).abs() < 1e-6);
+        assert!(collide_cr(center, size, rot, vec2(29.0, 38.6), 0.0));
+        assert!(!collide_cr(center, size, rot, vec2(31.0, 41.5), 0.0));
+    }
+
+    #[test]
+    fn rectified_lines_are_never_nan() {
+        let mut rng = GameRng::new(964);
+        let scales = [1e-30, 1e-6, 1.0, 1e3, 1e18, 1e37];
+        for i in 0..20000 {
+            let scale = scales[i % scales.len()];
+            let start = rng.vec(-Vec2::ONE, Vec2::ONE) * scale;
+            let end = match i % 4 {
+                // the same point, or right next to it
+                0 => start,
+                1 => start + rng.vec(-Vec2::ONE, Vec2::ONE) * f32::EPSILON * start.abs().max_element(),
+                2 => vec2(start.x, rng.range(-1.0, 1.0) * scale),
+                _ => rng.vec(-Vec2::ONE, Vec2::ONE) * scale,
+            };
+            let thickness = rng.range(-10.0, 100.0);
+            if let Some((center, size, rot)) = rectify_line(start, end, thickness) {
+                assert!(center.is_finite() && size.is_finite() && rot.is_finite(), "{start} {end} {thickness}");
+                assert!(size.x.max(size.y) > 0.0);
+                // and every test against it has an answer
+                let _ = collide_cr(center, size, rot, start, 1.0);
+            }
+        }
+    }
+
+
+    /// Each of `xs` into `f` going up (or staying), and by how much at most.
+    fn rises_steadily(f: impl Fn(f32) -> f32, xs: impl Iterator<Item = f32>) -> bool {
+        let values = xs.map(f).collect::<Vec<_>>();
+        values.windows(2).all(|w| w[1] >= w[0])
+    }
+
+    #[test]
+    fn recip_ease_rises_to_one() {
+        assert_eq!(recip_ease(0.0), 0.0);
+        assert_eq!(recip_ease(1.0), 0.5);
+        assert_eq!(recip_ease(3.0), 0.75);
+        assert!(recip_ease(1e6) < 1.0 && recip_ease(1e6) > 1.0 - 1e-5);
+        assert!(rises_steadily(recip_ease, (0..10000).map(|i| i as f32 * 0.01)));
+        // fast out of the start, slow toward the end
+        assert!(recip_ease(0.1) - recip_ease(0.0) > recip_ease(10.1) - recip_ease(10.0));
+    }
+
+    #[test]
+    fn recip_ease_k_bursts_then_keeps_going() {
+        for k in [0.5, 1.0, 3.0, 10.0] {
+            assert_eq!(recip_ease_k(0.0, k), 0.0);
+            assert!(rises_steadily(|t| recip_ease_k(t, k), (0..10000).map(|i| i as f32 * 0.01)));
+            // the burst is spent, leaving one beat a beat
+            let late = recip_ease_k(1001.0, k) - recip_ease_k(1000.0, k);
+            assert!((late - 1.0).abs() < 1e-3, "{k}: {late}");
+            assert!((recip_ease_k(1e4, k) - (1e4 + 1.0)).abs() < 1e-2);
+        }
+        // harder bursts get further sooner
+        assert!(recip_ease_k(0.2, 10.0) > recip_ease_k(0.2, 3.0) && recip_ease_k(0.2, 3.0) > recip_ease_k(0.2, 1.0));
+        // the strong pellets' curve, which every chart's strong pellets move along
+        for i in 0..100 {
+            let t = i as f32 * 0.05;
+            assert_eq!(recip_ease_k(t, 3.0), recip_ease(t * 3.0) + t);
+            assert_eq!(recip_ease_fn(3.0)(t), recip_ease_k(t, 3.0));
+        }
+        // no burst at all is just time going by
+        assert_eq!(recip_ease_k(2.5, 0.0), 2.5);
+    }
+
+    #[test]
+    fn circ_climb_climbs_beside_the_diagonal() {
+        assert_eq!(circ_climb(0.0), 0.0);
+        // landing on every whole beat, and never straying a beat from y = x
+        for n in 0..20 {
+            assert!((circ_climb(n as f32) - n as f32).abs() < 1e-4, "{n}");
+        }
+        // coming into them smoothly from below
+        for n in 1..20 {
+            assert!((circ_climb(n as f32 - 1e-4) - n as f32).abs() < 1e-2, "{n}");
+        }
+        assert!((0..2000).all(|i| { let x = i as f32 * 0.01; (circ_climb(x) - x).abs() <= 1.0 }));
+        assert!(rises_steadily(circ_climb, (0..2000).map(|i| i as f32 * 0.01)));
+        // ahead of the diagonal through each beat, climbing fast out of it
+        assert!(circ_climb(0.25) > 0.25 && circ_climb(0.5) > 0.5);
+    }
+
+    #[test]
+    fn circ_climb_period_stretches_the_climbs() {
+        for i in 0..100 {
+            let x = i as f32 * 0.07;
+            assert!((circ_climb_period(x, 1.0) - circ_climb(x)).abs() < 1e-5);
+            assert_eq!(circ_climb_fn(2.0)(x), circ_climb_period(x, 2.0));
+        }
+        for period in [0.5, 2.0, 4.0] {
+            assert_eq!(circ_climb_period(0.0, period), 0.0);
+            for n in 0..10 {
+                let x = n as f32 * period;
+                assert!((circ_climb_period(x, period) - x).abs() < 1e-3, "{period} at {x}");
+            }
+            assert!(rises_steadily(|x| circ_climb_period(x, period), (0..2000).map(|i| i as f32 * 0.01)));
+            assert!((0..2000).all(|i| { let x = i as f32 * 0.01; (circ_climb_period(x, period) - x).abs() <= period }));
+        }
+        // periods that can't be are no climb at all
+        for period in [0.0, -1.0, f32::NAN] {
+            assert_eq!(circ_climb_period(1.3, period), 1.3);
+        }
+    }
+
+
+    #[test]
+    fn the_nine_anchors() {
+        assert_eq!((VIRTUAL_WIDTH, VIRTUAL_HEIGHT), (1600.0, 900.0));
+        let at = Anchor::ALL.map(|anchor| anchored(anchor, Vec2::ZERO));
+        assert_eq!(at, [
+            vec2(0.0, 0.0), vec2(800.0, 0.0), vec2(1600.0, 0.0),
+            vec2(0.0, 450.0), vec2(800.0, 450.0), vec2(1600.0, 450.0),
+            vec2(0.0, 900.0), vec2(800.0, 900.0), vec2(1600.0, 900.0),
+        ]);
+        assert_eq!(anchored(Anchor::Center, Vec2::ZERO), screen_center());
+        // offsets in pixels, or in screens
+        assert_eq!(anchored(Anchor::Right, vec2(-40.0, 0.0)), vec2(1560.0, 450.0));
+        assert_eq!(anchored(Anchor::BottomLeft, vec2(10.0, -20.0)), vec2(10.0, 880.0));
+        assert_eq!(anchored_frac(Anchor::TopLeft, vec2(0.25, 0.5)), vec2(400.0, 450.0));
+        assert_eq!(anchored_frac(Anchor::Top, vec2(0.0, 0.1)), vec2(800.0, 90.0));
+        for anchor in Anchor::ALL {
+            assert_eq!(anchored_frac(anchor, Vec2::ZERO), anchored(anchor, Vec2::ZERO));
+        }
+    }
+
+    #[test]
+    fn edges_run_corner_to_corner() {
+        let corner = |anchor| anchored(anchor, Vec2::ZERO);
+        let ends = [
+            (Edge::Left, Anchor::TopLeft, Anchor::Left, Anchor::BottomLeft),
+            (Edge::Top, Anchor::TopLeft, Anchor::Top, Anchor::TopRight),
+            (Edge::Right, Anchor::TopRight, Anchor::Right, Anchor::BottomRight),
+            (Edge::Bottom, Anchor::BottomLeft, Anchor::Bottom, Anchor::BottomRight),
+        ];
+        for (edge, start, middle, end) in ends {
+            assert_eq!(edge_point(edge, 0.0), corner(start));
+            assert_eq!(edge_point(edge, 0.5), corner(middle));
+            assert_eq!(edge_point(edge, 1.0), corner(end));
+        }
+        assert_eq!(edge_point(Edge::Top, 0.25), vec2(400.0, 0.0));
+        assert_eq!(edge_point(Edge::Right, 0.75), vec2(1600.0, 675.0));
+        // and on past the corners
+        assert_eq!(edge_point(Edge::Bottom, -0.1), vec2(-160.0, 900.0));
+        assert_eq!(edge_point(Edge::Left, 1.5), vec2(0.0, 1350.0));
+        assert_eq!(Edge::ALL.len(), 4);
+    }
+
+
+    fn arc_pieces(center: Vec2, inner_rad: f32, outer_rad: f32, ang1: f32, ang2: f32, segments: Option<usize>) -> Vec<[Vec2; 4]> {
+        let mut pieces = vec![];
+        arc_quads(center, inner_rad, outer_rad, ang1, ang2, segments, |quad| pieces.push(quad));
+        pieces
+    }
+
+    fn mesh_pieces(mesh: &ArcMesh, center: Vec2, rotation: f32) -> Vec<[Vec2; 4]> {
+        let mut pieces = vec![];
+        mesh.quads(center, rotation, |quad| pieces.push(quad));
+        pieces
+    }
+
+    #[test]
+    fn arc_meshes_draw_what_draw_arc_draws() {
+        let mut rng = GameRng::new(967);
+        for i in 0..500 {
+            let (inner, outer) = (rng.range(0.0, 150.0), rng.range(150.0, 400.0));
+            let span = [rng.range(0.01, TAU), TAU, 2.5 * TAU, -1.0][i % 4];
+            let segments = [None, Some(1), Some(7), Some(100)][i / 4 % 4];
+            let (center, rotation) = (rng.vec(Vec2::ZERO, screen_size()), rng.range(-100.0, 100.0));
+            let mesh = ArcMesh::new(inner, outer, span, segments);
+            assert!(mesh.matches(inner, outer, span, segments));
+            // draw_arc taking the span forwards from where the mesh is turned to
+            let (start, end) = if span >= 0.0 { (rotation, rotation + span) } else { (rotation + span, rotation) };
+            let expected = arc_pieces(center, inner, outer, start, end, segments);
+            let drawn = mesh_pieces(&mesh, center, if span >= 0.0 { rotation } else { start });
+            assert_eq!(drawn.len(), expected.len(), "{span} {segments:?}");
+            for (a, b) in drawn.iter().zip(&expected) {
+                // the turn comes out of one sine and cosine instead of one for every point, so it's off by rounding
+                assert!((0..4).all(|k| a[k].distance(b[k]) < 0.01 + rotation.abs() * 1e-4), "{a:?} {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn arc_meshes_are_remade_for_new_spans() {
+        let mesh = ArcMesh::new(50.0, 80.0, 1.0, None);
+        assert!(mesh.matches(50.0, 80.0, 1.0, None));
+        // spans past a whole turn are the whole ring, which is what the mesh keeps
+        assert!(ArcMesh::new(50.0, 80.0, 10.0, None).matches(50.0, 80.0, 7.0, None));
+        for (inner, outer, span, segments) in [(50.0, 80.0, 1.1, None), (51.0, 80.0, 1.0, None), (50.0, 81.0, 1.0, None), (50.0, 80.0, 1.0, Some(8))] {
+            assert!(!mesh.matches(inner, outer, span, segments));
+        }
+        // arcs that aren't any are empty, like draw_arc draws nothing for them
+        for span in [0.0, f32::NAN, f32::INFINITY] {
+            assert!(mesh_pieces(&ArcMesh::new(50.0, 80.0, span, None), Vec2::ZERO, 0.0).is_empty());
+            assert!(arc_pieces(Vec2::ZERO, 50.0, 80.0, 0.0, span, None).is_empty());
+        }
+        assert!(mesh_pieces(&ArcMesh::default(), Vec2::ZERO, 0.0).is_empty());
+    }
+
+    #[test]
+    fn arc_meshes_beat_working_the_points_out() {
+        use std::hint::black_box;
+        let meshes = &(0..50).map(|i| ArcMesh::new(80.0 + i as f32, 120.0 + i as f32, 3.0, None)).collect::<Vec<_>>();
+        let frames = |cached: bool| move || for frame in 0..50 {
+            for (i, mesh) in meshes.iter().enumerate() {
+                let rot = frame as f32 * 0.01 + i as f32;
+                let sink = |quad: [Vec2; 4]| { black_box(quad); };
+                if cached {
+                    mesh.quads(vec2(800.0, 450.0), rot, sink);
+                } else {
+                    arc_quads(vec2(800.0, 450.0), 80.0 + i as f32, 120.0 + i as f32, rot, rot + 3.0, None, sink);
+                }
+            }
+        };
+        let (trig, cached) = (best_of(frames(false)), best_of(frames(true)));
+        assert!(cached < trig, "{cached}s from meshes, {trig}s with draw_arc's trig");
+    }
+
+
+    /// How much of each pixel of a `size` square `mesh` covers, found at the pixel's center and taken from the vertices' alpha
+    /// the way the GPU blends them across a triangle. Stands in for rendering to a texture, which takes a window.
+    fn rasterize(mesh: &Mesh, size: usize) -> Vec<f32> {
+        let mut coverage = vec![0.0; size * size];
+        let at = |i: u16| { let v = &mesh.vertices[i as usize]; (v.position.truncate(), v.color.a) };
+        for tri in mesh.indices.chunks(3) {
+            let [(a, aa), (b, ba), (c, ca)] = [at(tri[0]), at(tri[1]), at(tri[2])];
+            let area = (b - a).perp_dot(c - a);
+            if area == 0.0 { continue; }
+            let (min, max) = (a.min(b).min(c).floor().max(Vec2::ZERO), a.max(b).max(c).ceil().min(Vec2::splat(size as f32)));
+            for y in min.y as usize..max.y as usize {
+                for x in min.x as usize..max.x as usize {
+                    // nudged off the exact center, so a point can't land on an edge two triangles share
+                    let p = vec2(x as f32 + 0.5013, y as f32 + 0.4987);
+                    let (wa, wb) = ((b - p).perp_dot(c - p) / area, (c - p).perp_dot(a - p) / area);
+                    let wc = 1.0 - wa - wb;
+                    if wa >= 0.0 && wb >= 0.0 && wc >= 0.0 {
+                        coverage[y * size + x] += wa * aa + wb * ba + wc * ca;
+                    }
+                }
+            }
+        }
+        coverage
+    }
+
+    /// `rasterize` for what `draw_circle` and `draw_line` draw: whole pixels in, or out.
+    fn rasterize_hard(size: usize, inside: impl Fn(Vec2) -> bool) -> Vec<f32> {
+        (0..size * size).map(|i| inside(vec2((i % size) as f32 + 0.5, (i / size) as f32 + 0.5)) as u8 as f32).collect()
+    }
+
+    fn partly_covered(coverage: &[f32]) -> usize {
+        coverage.iter().filter(|&&c| c > 0.02 && c < 0.98).count()
+    }
+
+    #[test]
+    fn feathered_circles_cover_what_hard_ones_do_with_soft_edges() {
+        for (center, rad) in [(vec2(16.3, 16.7), 4.0), (vec2(32.5, 31.25), 12.0), (vec2(31.9, 32.1), 25.5)] {
+            let mesh = feathered_capsule(center, center, rad, WHITE);
+            assert!(mesh.indices.iter().all(|&i| (i as usize) < mesh.vertices.len()));
+            let (smooth, hard) = (rasterize(&mesh, 64), rasterize_hard(64, |p| p.distance(center) <= rad));
+            // nothing drawn twice over, and nothing left out of the middle
+            assert!(smooth.iter().all(|&c| c <= 1.0 + 1e-3), "{rad}");
+            let middle = center.floor();
+            assert!((smooth[middle.y as usize * 64 + middle.x as usize] - 1.0).abs() < 1e-3);
+            // the same amount drawn, the edges fading out instead of stepping
+            let area = PI * sq(rad);
+            let (smooth_area, hard_area) = (smooth.iter().sum::<f32>(), hard.iter().sum::<f32>());
+            assert!((smooth_area - area).abs() < area * 0.05 + 2.0, "{rad}: {smooth_area} of {area}");
+            assert!((hard_area - area).abs() < area * 0.1 + 4.0, "{rad}: {hard_area} of {area}");
+            assert_eq!(partly_covered(&hard), 0);
+            assert!(partly_covered(&smooth) as f32 >= TAU * rad * 0.8, "{rad}: {}", partly_covered(&smooth));
+            // and nothing past the rim
+            for (i, &c) in smooth.iter().enumerate() {
+                let p = vec2((i % 64) as f32 + 0.5, (i / 64) as f32 + 0.5);
+                if p.distance(center) > rad + FEATHER { assert_eq!(c, 0.0); }
+            }
+        }
+    }
+
+    #[test]
+    fn feathered_lines_are_round_capped_capsules() {
+        let (a, b, thickness) = (vec2(10.3, 20.1), vec2(52.7, 40.4), 6.0);
+        let smooth = rasterize(&feathered_capsule(a, b, thickness / 2.0, WHITE), 64);
+        assert!(smooth.iter().all(|&c| c <= 1.0 + 1e-3));
+        let area = a.distance(b) * thickness + PI * sq(thickness / 2.0);
+        assert!((smooth.iter().sum::<f32>() - area).abs() < area * 0.05, "{} of {area}", smooth.iter().sum::<f32>());
+        for (i, &c) in smooth.iter().enumerate() {
+            let p = vec2((i % 64) as f32 + 0.5, (i / 64) as f32 + 0.5);
+            let from_line = closest_on_segment(a, b, p).distance(p);
+            if from_line < thickness / 2.0 - FEATHER { assert!((c - 1.0).abs() < 1e-3, "{p}"); }
+            if from_line > thickness / 2.0 + FEATHER { assert_eq!(c, 0.0, "{p}"); }
+        }
+        assert!(partly_covered(&smooth) > partly_covered(&rasterize_hard(64, |p| closest_on_segment(a, b, p).distance(p) <= thickness / 2.0)));
+    }
+
+    #[test]
+    fn feathering_keeps_the_color() {
+        let color = Color::new(0.2, 0.4, 0.6, 0.5);
+        let mesh = feathered_capsule(Vec2::ZERO, vec2(10.0, 0.0), 3.0, color);
+        assert!(mesh.vertices.iter().all(|v| v.color == color || v.color == Color { a: 0.0, ..color }));
+        // tiny circles still fade rather than vanish
+        let tiny = rasterize(&feathered_capsule(vec2(8.4, 8.6), vec2(8.4, 8.6), 0.5, WHITE), 16);
+        assert!(tiny.iter().sum::<f32>() > 0.1);
+    }
+
+    #[test]
+    fn feathering_adds_two_triangles_a_side_at_any_size() {
+        for rad in (1..=400).map(|r| r as f32 * 0.5) {
+            let mesh = feathered_capsule(vec2(100.0, 100.0), vec2(100.0, 100.0), rad, WHITE);
+            let sides = (mesh.vertices.len() - 1) / 2;
+            // the fan's one triangle a side, and the rim's two
+            assert_eq!(mesh.indices.len(), sides * 9, "{rad}");
+            // capped however big it gets, as `capsule_normals` caps the sides
+            assert!(sides <= 2 * 33, "{rad}: {sides} sides");
+        }
+    }
 }